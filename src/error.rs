@@ -1,10 +1,21 @@
 use std::error::Error;
 use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
 
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey::Pubkey;
+
+/// Why an [`RpcClientExt`](crate::RpcClientExt) call failed.
 #[derive(Debug)]
 pub enum SolanaClientExtError {
     RpcError(String),
     ComputeUnitsError(String),
+    /// The transaction already sets a compute unit limit, below the units it was estimated
+    /// to consume without it.
+    ComputeUnitLimitTooLow {
+        limit: u32,
+        estimate: u32,
+    },
 }
 
 impl Display for SolanaClientExtError {
@@ -14,8 +25,195 @@ impl Display for SolanaClientExtError {
             SolanaClientExtError::ComputeUnitsError(ref err) => {
                 write!(f, "Compute Units error: {}", err)
             }
+            SolanaClientExtError::ComputeUnitLimitTooLow { limit, estimate } => write!(
+                f,
+                "Compute Units error: the transaction's compute unit limit ({} CU) is below \
+                 the estimate ({} CU)",
+                limit, estimate
+            ),
         }
     }
 }
 
 impl Error for SolanaClientExtError {}
+
+/// Why a [`RollUpAccountLoader`](crate::RollUpAccountLoader) couldn't provide an account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccountLoadError {
+    /// The account doesn't exist and the loader's policy is
+    /// [`MissingAccountPolicy::Error`](crate::MissingAccountPolicy::Error).
+    Missing(Pubkey),
+    /// The source failed to answer, so whether the account exists is unknown.
+    Transport { pubkey: Pubkey, message: String },
+    /// The node couldn't serve the account at or after `slot`, the slot the rest of the
+    /// batch was read at, within the [`SlotConsistency`](crate::SlotConsistency) deadline.
+    Inconsistent { pubkey: Pubkey, slot: Slot },
+}
+
+impl Display for AccountLoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccountLoadError::Missing(pubkey) => write!(f, "account {} does not exist", pubkey),
+            AccountLoadError::Transport { pubkey, message } => {
+                write!(f, "account fetch failed: {}: {}", pubkey, message)
+            }
+            AccountLoadError::Inconsistent { pubkey, slot } => {
+                write!(
+                    f,
+                    "account {} could not be read at slot {} or later",
+                    pubkey, slot
+                )
+            }
+        }
+    }
+}
+
+impl AccountLoadError {
+    /// Whether the source, rather than the account, is at fault: the account may well
+    /// exist, but couldn't be read.
+    pub fn is_infrastructure(&self) -> bool {
+        match self {
+            AccountLoadError::Missing(_) => false,
+            AccountLoadError::Transport { .. } | AccountLoadError::Inconsistent { .. } => true,
+        }
+    }
+}
+
+impl Error for AccountLoadError {}
+
+/// Why an [`AccountFetcher`](crate::AccountFetcher) couldn't answer a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceError {
+    /// The backend is rate limiting; the loader retries the request after a backoff, see
+    /// [`RequestLimits`](crate::RequestLimits).
+    Throttled(String),
+    /// Any other failure, described by its message.
+    Failed(String),
+}
+
+impl Display for SourceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SourceError::Throttled(message) => write!(f, "rate limited: {}", message),
+            SourceError::Failed(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for SourceError {}
+
+/// Why [`RollUpAccountLoader::prefetch_program_accounts`](crate::RollUpAccountLoader::prefetch_program_accounts)
+/// cached nothing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProgramAccountsError {
+    /// The loader's source has no RPC client to send the query to.
+    Unsupported(Pubkey),
+    /// The node refused the query, e.g. because it disables `getProgramAccounts` or
+    /// doesn't index the program's accounts.
+    Refused { program_id: Pubkey, message: String },
+    /// The query matched more accounts than the loader accepts, see
+    /// [`RollUpAccountLoader::with_max_program_accounts`](crate::RollUpAccountLoader::with_max_program_accounts).
+    TooManyAccounts {
+        program_id: Pubkey,
+        count: usize,
+        limit: usize,
+    },
+    /// The request failed for any other reason, e.g. a network error.
+    Transport { program_id: Pubkey, message: String },
+}
+
+impl Display for ProgramAccountsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProgramAccountsError::Unsupported(program_id) => write!(
+                f,
+                "cannot list the accounts of program {}: the source has no RPC client",
+                program_id
+            ),
+            ProgramAccountsError::Refused {
+                program_id,
+                message,
+            } => write!(
+                f,
+                "node refused to list the accounts of program {}: {}",
+                program_id, message
+            ),
+            ProgramAccountsError::TooManyAccounts {
+                program_id,
+                count,
+                limit,
+            } => write!(
+                f,
+                "program {} has {} matching accounts, more than the limit of {}",
+                program_id, count, limit
+            ),
+            ProgramAccountsError::Transport {
+                program_id,
+                message,
+            } => write!(
+                f,
+                "listing the accounts of program {} failed: {}",
+                program_id, message
+            ),
+        }
+    }
+}
+
+impl Error for ProgramAccountsError {}
+
+/// Why a program couldn't be added to a [`RollUpChannel`](crate::RollUpChannel), see
+/// [`RollUpChannel::add_program_from_file`](crate::RollUpChannel::add_program_from_file),
+/// or compiled ahead of time, see [`warm_up_programs`](crate::warm_up_programs).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProgramLoadError {
+    /// The ELF file couldn't be read.
+    Io { path: PathBuf, message: String },
+    /// The ELF doesn't load or fails verification, e.g. it isn't an SBF program or calls
+    /// an unknown syscall.
+    InvalidElf { program_id: Pubkey, message: String },
+    /// The program account doesn't exist.
+    NotFound { program_id: Pubkey },
+    /// The account isn't a program deployed with one of the BPF loaders.
+    NotAProgram { program_id: Pubkey },
+    /// The channel's [`ProgramFilter`](crate::ProgramFilter) refuses the program.
+    Denied { program_id: Pubkey },
+    /// The processing environment the program would be compiled for couldn't be set up,
+    /// e.g. the cluster's features couldn't be fetched.
+    Environment { message: String },
+    /// A transaction invokes the ZK ElGamal proof program, but it isn't registered, see
+    /// `Builtin::zk_elgamal_proof` with the `zk-elgamal-proof` feature.
+    ZkProofProgramNotEnabled,
+}
+
+impl Display for ProgramLoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProgramLoadError::Io { path, message } => {
+                write!(f, "cannot read program {}: {}", path.display(), message)
+            }
+            ProgramLoadError::InvalidElf {
+                program_id,
+                message,
+            } => write!(f, "invalid ELF for program {}: {}", program_id, message),
+            ProgramLoadError::NotFound { program_id } => {
+                write!(f, "program {} not found", program_id)
+            }
+            ProgramLoadError::NotAProgram { program_id } => {
+                write!(f, "account {} is not a program", program_id)
+            }
+            ProgramLoadError::Denied { program_id } => {
+                write!(f, "program {} is denied by the program filter", program_id)
+            }
+            ProgramLoadError::Environment { message } => {
+                write!(f, "cannot set up the processing environment: {}", message)
+            }
+            ProgramLoadError::ZkProofProgramNotEnabled => write!(
+                f,
+                "zk proof program not enabled, register `Builtin::zk_elgamal_proof` \
+                 (`zk-elgamal-proof` feature)"
+            ),
+        }
+    }
+}
+
+impl Error for ProgramLoadError {}
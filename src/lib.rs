@@ -79,7 +79,7 @@
 ///    for (i, result) in results.iter().enumerate() {
 ///        println!(
 ///            "Transaction {}: Success={}, CU={}, Result: {}",
-///            i, result.success, result.cu, result.result
+///            i, result.is_success(), result.cu, result.result
 ///        );
 ///    }
 ///
@@ -102,17 +102,79 @@
 ///
 /// }
 /// ```
-use error::SolanaClientExtError;
 use solana_client::rpc_config::RpcSimulateTransactionConfig;
+use solana_compute_budget::compute_budget::ComputeBudget;
+use solana_compute_budget::compute_budget_limits::MAX_COMPUTE_UNIT_LIMIT;
 use solana_sdk::compute_budget::ComputeBudgetInstruction;
-use solana_sdk::{message::Message, signers::Signers, transaction::Transaction};
+use solana_sdk::{
+    message::Message,
+    reserved_account_keys::ReservedAccountKeys,
+    signers::Signers,
+    transaction::{SanitizedTransaction, Transaction},
+};
 mod error;
 pub mod state;
 mod utils;
 
-use crate::state::fork_rollup_graph::ForkRollUpGraph;
+use crate::utils::helpers::{compute_unit_limit_instruction, get_compute_unit_limit};
+use agave_feature_set::FeatureSet;
 
-pub use state::{return_struct::ReturnStruct, rollup_channel::RollUpChannel};
+pub use error::{
+    AccountLoadError, ProgramAccountsError, ProgramLoadError, SolanaClientExtError, SourceError,
+};
+pub use state::{
+    account_cache::{AccountCache, PinnedAccounts},
+    account_change::{AccountChange, AccountChangeKind},
+    account_fetcher::AccountFetcher,
+    account_fixtures::{load_account_fixtures, parse_account_fixture},
+    async_account_loader::AsyncAccountLoader,
+    batch_summary::BatchSummary,
+    block_result::BlockResult,
+    cache_inspection::{AccountLayer, CachedAccountInfo},
+    error_decoder::{DecodedError, ErrorDecoder},
+    execution_timings::{ExecutionTimings, ProgramTimings},
+    execution_trace::{ExecutionTrace, TraceStatus, TracedInstruction},
+    fetch_latency::{FetchLatency, LATENCY_BUCKET_BOUNDS},
+    fork_rollup_graph::ForkRollUpGraph,
+    inner_instructions::{InnerInstructionGroup, InvokedInstruction},
+    local_program::LoaderKind,
+    lock_analysis::{LockAnalysis, TransactionLocks},
+    middleware::{Reject, RollUpMiddleware},
+    processing_environment::ProcessingEnvironmentBuilder,
+    processor_builder::{Builtin, ProcessorBuilder},
+    program_cache::{warm_up_programs, ProgramCacheLimits, ProgramCacheStats, ProgramWarmUp},
+    request_limiter::RequestLimits,
+    result_json::{results_to_json, RESULT_JSON_SCHEMA_VERSION},
+    return_struct::{ConversionOptions, ReturnStruct, TxStatus},
+    rollup_account_loader::{
+        AccessedAccounts, AccountSource, CacheStats, DataEncoding, FetchStats, LoaderSnapshot,
+        MissingAccountPolicy, PrefetchReport, RollUpAccountLoader, SlotConsistency, SysvarDefaults,
+    },
+    rollup_channel::RollUpChannel,
+    rollup_channel_builder::RollUpChannelBuilder,
+    rollup_channel_config::{
+        BlockhashSource, FeatureSetSource, ProgramFilter, RentSource, RollUpChannelConfig,
+        SanitizationMode, SysvarSource,
+    },
+    simulation_context::SimulationContext,
+    snapshot_accounts::SnapshotAccounts,
+};
+// The building blocks channels process batches with, for callers running their own
+// processing loop on a processor from `ProcessorBuilder`. They follow semver like the
+// rest of the public API; the other helpers are internal and may change at any time.
+pub use utils::helpers::{
+    check_transactions, create_transaction_batch_processor, feature_set_from_cluster,
+    feature_set_from_cluster_pending_at, populate_sysvars, sanitize_transactions,
+    verify_precompiles, CheckConfig,
+};
+
+#[cfg(feature = "pubsub")]
+pub use state::account_refresher::AccountRefresher;
+
+#[cfg(feature = "embedded-spl")]
+pub use state::embedded_spl::{
+    embedded_spl_accounts, SPL_ASSOCIATED_TOKEN_ACCOUNT_ID, SPL_TOKEN_2022_ID, SPL_TOKEN_ID,
+};
 
 pub trait RpcClientExt {
     /// Estimates compute units for an **unsigned transaction**.
@@ -147,6 +209,15 @@ pub trait RpcClientExt {
     /// using CU estimation as guidance.
     ///
     /// This modifies the transaction **in-place**.
+    ///
+    /// Fails if the simulation consumed its entire compute unit limit, since such a
+    /// number is truncated and would produce a limit that's too low.
+    ///
+    /// A `SetComputeUnitLimit` instruction the transaction already holds is updated
+    /// rather than duplicated. Since its limit would cap the simulation, the estimate is
+    /// made without it; if the limit is below the estimate,
+    /// [`SolanaClientExtError::ComputeUnitLimitTooLow`] is returned and the transaction
+    /// left untouched.
     fn optimize_compute_units_unsigned_tx<'a, I: Signers + ?Sized>(
         &self,
         unsigned_transaction: &mut Transaction,
@@ -157,6 +228,8 @@ pub trait RpcClientExt {
     /// Same as `optimize_compute_units_unsigned_tx`, but works at the message level.
     ///
     /// Useful when constructing a transaction later.
+    ///
+    /// Like the transaction variant, refuses estimates that hit the compute unit limit.
     fn optimize_compute_units_msg<'a, I: Signers + ?Sized>(
         &self,
         message: &mut Message,
@@ -170,29 +243,7 @@ impl RpcClientExt for solana_client::rpc_client::RpcClient {
         transaction: &Transaction,
         _signers: &'a I,
     ) -> Result<Vec<u64>, Box<dyn std::error::Error + 'static>> {
-        // GET SVM MESSAGE
-
-        let accounts = transaction.message.account_keys.clone();
-        // Build the rollup simulation context
-        let rollup_c = RollUpChannel::new(accounts, self);
-        // Process the transaction via rollup
-        let results = rollup_c.process_rollup_transfers(&[transaction.clone()]);
-
-        // Check if all transactions were successful
-        let failures: Vec<&ReturnStruct> = results.iter().filter(|r| !r.success).collect();
-
-        if !failures.is_empty() {
-            let error_messages = failures
-                .iter()
-                .map(|r| r.result.clone())
-                .collect::<Vec<String>>()
-                .join("\n");
-
-            return Err(Box::new(SolanaClientExtError::ComputeUnitsError(format!(
-                "Transaction simulation failed:\n{}",
-                error_messages
-            ))));
-        }
+        let results = simulate_unsigned_tx(self, transaction, None)?;
 
         // Return compute units for each successful transaction
         Ok(results.iter().map(|r| r.cu).collect())
@@ -234,15 +285,39 @@ impl RpcClientExt for solana_client::rpc_client::RpcClient {
     fn optimize_compute_units_unsigned_tx<'a, I: Signers + ?Sized>(
         &self,
         transaction: &mut Transaction,
-        signers: &'a I,
+        _signers: &'a I,
     ) -> Result<u32, Box<dyn std::error::Error + 'static>> {
-        // Estimate optimal CU
-        let optimal_cu_vec = self.estimate_compute_units_unsigned_tx(transaction, signers)?;
-        let optimal_cu = *optimal_cu_vec.get(0).unwrap() as u32;
+        // Estimate optimal CU, refusing numbers truncated by the compute limit. A limit
+        // already present caps the simulation, so it is lifted for a fresh estimate.
+        let present = compute_unit_limit_instruction(&transaction.message);
+        let compute_budget = present.map(|_| ComputeBudget {
+            compute_unit_limit: u64::from(MAX_COMPUTE_UNIT_LIMIT),
+            ..ComputeBudget::default()
+        });
+        let results = simulate_unsigned_tx(self, transaction, compute_budget)?;
+        let result = results
+            .first()
+            .ok_or(SolanaClientExtError::ComputeUnitsError(
+                "No simulation result returned.".into(),
+            ))?;
+        if result.hit_compute_limit {
+            return Err(Box::new(truncated_estimate_error(result.cu)));
+        }
+        let optimal_cu = result.cu as u32;
+        if let Some((_, limit)) = present.filter(|(_, limit)| *limit < optimal_cu) {
+            return Err(Box::new(SolanaClientExtError::ComputeUnitLimitTooLow {
+                limit,
+                estimate: optimal_cu,
+            }));
+        }
 
         // Add buffer (doubling for safety)
         let optimize_ix =
             ComputeBudgetInstruction::set_compute_unit_limit(optimal_cu.saturating_add(optimal_cu));
+        if let Some((index, _)) = present {
+            transaction.message.instructions[index].data = optimize_ix.data;
+            return Ok(optimal_cu);
+        }
 
         // Add compute budget account key
         transaction
@@ -266,6 +341,19 @@ impl RpcClientExt for solana_client::rpc_client::RpcClient {
         // Estimate optimal CU from simulation
         let optimal_cu = u32::try_from(self.estimate_compute_units_msg(message, signers)?)?;
 
+        // A simulation that used up the whole limit was cut short; its number is meaningless
+        let sanitized = SanitizedTransaction::try_from_legacy_transaction(
+            Transaction::new_unsigned(message.clone()),
+            &ReservedAccountKeys::empty_key_set(),
+        )
+        .map_err(|err| {
+            SolanaClientExtError::ComputeUnitsError(format!("Invalid message: {err}"))
+        })?;
+        let limit = get_compute_unit_limit(&sanitized, &FeatureSet::all_enabled());
+        if limit.is_some_and(|limit| optimal_cu >= limit) {
+            return Err(Box::new(truncated_estimate_error(u64::from(optimal_cu))));
+        }
+
         // Add buffer
         let optimize_ix = ComputeBudgetInstruction::set_compute_unit_limit(
             optimal_cu.saturating_add(150 /*optimal_cu.saturating_div(100)*100*/),
@@ -280,3 +368,53 @@ impl RpcClientExt for solana_client::rpc_client::RpcClient {
         Ok(optimal_cu)
     }
 }
+
+/// Runs `transaction` through a local rollup channel, failing if any result failed.
+fn simulate_unsigned_tx(
+    rpc_client: &solana_client::rpc_client::RpcClient,
+    transaction: &Transaction,
+    compute_budget: Option<ComputeBudget>,
+) -> Result<Vec<ReturnStruct>, Box<dyn std::error::Error + 'static>> {
+    let accounts = transaction.message.account_keys.clone();
+    // Build the rollup simulation context, recording logs to explain failures.
+    let config = RollUpChannelConfig {
+        record_trace: true,
+        compute_budget,
+        ..RollUpChannelConfig::default()
+    };
+    let rollup_c = RollUpChannel::with_config(accounts, rpc_client, config);
+    // Process the transaction via rollup
+    let results = rollup_c.process_rollup_transfers(&[transaction.clone()]);
+
+    // Check if all transactions were successful
+    let failures: Vec<&ReturnStruct> = results.iter().filter(|r| !r.is_success()).collect();
+
+    if !failures.is_empty() {
+        let error_messages = failures
+            .iter()
+            .map(|r| match &r.logs {
+                Some(logs) if !logs.is_empty() => {
+                    format!("{}\nLogs:\n{}", r.result, logs.join("\n"))
+                }
+                _ => r.result.clone(),
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        return Err(Box::new(SolanaClientExtError::ComputeUnitsError(format!(
+            "Transaction simulation failed:\n{}",
+            error_messages
+        ))));
+    }
+
+    Ok(results)
+}
+
+/// Error returned when a simulation consumed its entire compute unit limit.
+fn truncated_estimate_error(cu: u64) -> SolanaClientExtError {
+    SolanaClientExtError::ComputeUnitsError(format!(
+        "Transaction consumed its entire compute unit limit ({} CU); the estimate is truncated. \
+         Raise the limit and estimate again.",
+        cu
+    ))
+}
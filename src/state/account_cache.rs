@@ -0,0 +1,470 @@
+use lru::LruCache;
+use solana_sdk::account::{AccountSharedData, ReadableAccount};
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::state::single_flight::SingleFlight;
+
+/// Outcome of a fetch shared by the loaders waiting on it: the account, or `None` if the
+/// source doesn't have it, or why the source failed.
+pub(crate) type SharedFetch = Result<Option<AccountSharedData>, String>;
+
+/// Change of the set of keys an [`AccountCache`] holds, reported to its listener.
+#[cfg_attr(not(feature = "pubsub"), allow(dead_code))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CacheChange {
+    Inserted(Pubkey),
+    Removed(Pubkey),
+}
+
+/// Called with every [`CacheChange`], under the cache's lock, so it must not block.
+pub(crate) type CacheListener = Arc<dyn Fn(CacheChange) + Send + Sync>;
+
+/// Accounts fetched from a source, shareable by several [`RollUpAccountLoader`](crate::RollUpAccountLoader)s.
+///
+/// Cloning an `AccountCache` yields a handle to the same cache, so channels created per
+/// request can pass one clone each to their loader and stop refetching hot accounts
+/// (programs, common mints, config PDAs). Every loader sharing the cache applies the
+/// cache's TTL.
+///
+/// Only fetched accounts are shared: state a channel commits or synthesizes stays
+/// private to its loader. The cache's lock is never held while a source is queried, so
+/// loaders used concurrently, or reentrantly by the SVM, can't deadlock on it. Concurrent
+/// lookups of the same uncached account through the cache's loaders send a single request.
+///
+/// A cache bounded with [`with_max_bytes`](Self::with_max_bytes) evicts its least
+/// recently used accounts once their data exceeds the bound, except those
+/// [pinned](Self::pin) by a batch in flight.
+#[derive(Clone, Default)]
+pub struct AccountCache {
+    inner: Arc<CacheInner>,
+}
+
+struct CacheInner {
+    /// Shared with loader snapshots until either side changes.
+    entries: RwLock<Arc<HashMap<Pubkey, CacheEntry>>>,
+    /// How long a fetched account is served, `None` for ever.
+    ttl: Option<Duration>,
+    /// Account data bytes currently held by `entries`.
+    bytes: AtomicU64,
+    /// Account data bytes `entries` may hold, `u64::MAX` for no bound.
+    max_bytes: AtomicU64,
+    /// Keys of `entries`, least recently used last. Only maintained for a bounded cache,
+    /// and always locked after `entries`.
+    recency: Mutex<LruCache<Pubkey, ()>>,
+    /// Number of live [`PinnedAccounts`] guards holding each key.
+    pins: Mutex<HashMap<Pubkey, usize>>,
+    /// Single-key fetches in progress, awaited by concurrent lookups of the same key.
+    in_flight: SingleFlight<SharedFetch>,
+    /// Told about keys entering and leaving `entries`, e.g. by an `AccountRefresher`.
+    listener: RwLock<Option<CacheListener>>,
+}
+
+impl Default for CacheInner {
+    fn default() -> Self {
+        Self {
+            entries: RwLock::default(),
+            ttl: None,
+            bytes: AtomicU64::new(0),
+            max_bytes: AtomicU64::new(u64::MAX),
+            recency: Mutex::new(LruCache::unbounded()),
+            pins: Mutex::default(),
+            in_flight: SingleFlight::default(),
+            listener: RwLock::default(),
+        }
+    }
+}
+
+/// A cached account, along with the moment it stops being served.
+#[derive(Clone)]
+pub(crate) struct CacheEntry {
+    pub(crate) account: AccountSharedData,
+    /// `None` for entries that never expire.
+    pub(crate) expires_at: Option<Instant>,
+    /// The account was fetched without its data, see
+    /// [`RollUpAccountLoader::with_data_slicing`](crate::RollUpAccountLoader::with_data_slicing).
+    pub(crate) data_sliced: bool,
+    /// Newest slot the loader had seen when it fetched the account, if its source
+    /// reports slots.
+    pub(crate) slot: Option<Slot>,
+    pub(crate) fetched_at: SystemTime,
+}
+
+impl CacheEntry {
+    pub(crate) fn is_fresh(&self, now: Instant) -> bool {
+        self.expires_at.is_none_or(|expires_at| now < expires_at)
+    }
+}
+
+impl AccountCache {
+    /// An empty cache whose accounts never expire.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An empty cache expiring accounts `ttl` after they were fetched.
+    pub fn with_ttl(ttl: Duration) -> Self {
+        Self {
+            inner: Arc::new(CacheInner {
+                ttl: Some(ttl),
+                ..CacheInner::default()
+            }),
+        }
+    }
+
+    /// Bounds the account data the cache holds to `max_bytes`, evicting the least
+    /// recently used accounts beyond it.
+    ///
+    /// The bound applies to every handle of the cache, including those already cloned.
+    pub fn with_max_bytes(self, max_bytes: u64) -> Self {
+        self.inner.max_bytes.store(max_bytes, Ordering::Relaxed);
+        {
+            let mut entries = self.inner.entries.write().unwrap();
+            let mut recency = self.inner.recency.lock().unwrap();
+            recency.clear();
+            for pubkey in entries.keys() {
+                recency.put(*pubkey, ());
+            }
+            self.shrink(&mut entries, &mut recency);
+        }
+        self
+    }
+
+    /// How long a fetched account is served, `None` if accounts never expire.
+    pub fn ttl(&self) -> Option<Duration> {
+        self.inner.ttl
+    }
+
+    /// Account data bytes the cache may hold, `None` if it is unbounded.
+    pub fn max_bytes(&self) -> Option<u64> {
+        match self.inner.max_bytes.load(Ordering::Relaxed) {
+            u64::MAX => None,
+            max_bytes => Some(max_bytes),
+        }
+    }
+
+    /// Keeps `pubkeys` from being evicted for the size bound until the returned guard is
+    /// dropped, e.g. the accounts of a batch being executed.
+    ///
+    /// Pinned accounts can keep the cache above its bound; it shrinks back on a later
+    /// insertion. Expiration and invalidation still apply to them.
+    pub fn pin(&self, pubkeys: impl IntoIterator<Item = Pubkey>) -> PinnedAccounts {
+        let pubkeys: Vec<Pubkey> = pubkeys.into_iter().collect();
+        let mut pins = self.inner.pins.lock().unwrap();
+        for pubkey in &pubkeys {
+            *pins.entry(*pubkey).or_default() += 1;
+        }
+        PinnedAccounts {
+            cache: self.clone(),
+            pubkeys,
+        }
+    }
+
+    /// Number of cached accounts, expired ones included until they are replaced.
+    pub fn len(&self) -> usize {
+        self.inner.entries.read().unwrap().len()
+    }
+
+    /// Whether the cache holds no account.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Account data bytes currently held by the cache.
+    pub fn bytes_cached(&self) -> u64 {
+        self.inner.bytes.load(Ordering::Relaxed)
+    }
+
+    /// Read access to the entries; the guard must not outlive the lookup.
+    pub(crate) fn entries(&self) -> RwLockReadGuard<'_, Arc<HashMap<Pubkey, CacheEntry>>> {
+        self.inner.entries.read().unwrap()
+    }
+
+    /// Runs `fetch` for `pubkey`, or waits for the fetch of `pubkey` another lookup already
+    /// runs and shares its outcome.
+    ///
+    /// Returns the outcome, and whether this call ran `fetch`.
+    pub(crate) fn fetch_once(
+        &self,
+        pubkey: Pubkey,
+        fetch: impl FnOnce() -> SharedFetch,
+    ) -> (SharedFetch, bool) {
+        self.inner.in_flight.run(pubkey, fetch)
+    }
+
+    /// Marks `pubkey` as just used, if the cache is bounded.
+    pub(crate) fn touch(&self, pubkey: &Pubkey) {
+        if self.max_bytes().is_some() {
+            self.inner.recency.lock().unwrap().promote(pubkey);
+        }
+    }
+
+    /// Caches an account fetched from the source at `slot`, expiring it after the TTL.
+    ///
+    /// Returns the number of entries evicted: an expired entry it replaced, and those
+    /// dropped to stay under the size bound.
+    pub(crate) fn insert(
+        &self,
+        pubkey: Pubkey,
+        account: AccountSharedData,
+        data_sliced: bool,
+        slot: Option<Slot>,
+    ) -> u64 {
+        let entry = CacheEntry {
+            account,
+            expires_at: self.inner.ttl.map(|ttl| Instant::now() + ttl),
+            data_sliced,
+            slot,
+            fetched_at: SystemTime::now(),
+        };
+        self.insert_entry(pubkey, entry)
+    }
+
+    /// Caches an account fetched at `fetched_at` by an earlier run, expiring it when its
+    /// TTL would have.
+    ///
+    /// Returns `None`, caching nothing, if the account already expired; otherwise the
+    /// number of entries evicted, like [`insert`](Self::insert).
+    pub(crate) fn insert_persisted(
+        &self,
+        pubkey: Pubkey,
+        account: AccountSharedData,
+        slot: Option<Slot>,
+        fetched_at: SystemTime,
+    ) -> Option<u64> {
+        // A fetch time in the future, e.g. after a clock change, counts as just now.
+        let age = fetched_at.elapsed().unwrap_or_default();
+        let expires_at = match self.inner.ttl {
+            Some(ttl) => {
+                Some(Instant::now() + ttl.checked_sub(age).filter(|left| !left.is_zero())?)
+            }
+            None => None,
+        };
+        let entry = CacheEntry {
+            account,
+            expires_at,
+            data_sliced: false,
+            slot,
+            fetched_at,
+        };
+        Some(self.insert_entry(pubkey, entry))
+    }
+
+    fn insert_entry(&self, pubkey: Pubkey, entry: CacheEntry) -> u64 {
+        let now = Instant::now();
+        // The size is updated under the lock so it can't drift from the cache's content.
+        let mut entries = self.inner.entries.write().unwrap();
+        self.inner
+            .bytes
+            .fetch_add(entry.account.data().len() as u64, Ordering::Relaxed);
+        let expired = match Arc::make_mut(&mut entries).insert(pubkey, entry) {
+            Some(replaced) => {
+                self.inner
+                    .bytes
+                    .fetch_sub(replaced.account.data().len() as u64, Ordering::Relaxed);
+                u64::from(!replaced.is_fresh(now))
+            }
+            None => {
+                self.notify(CacheChange::Inserted(pubkey));
+                0
+            }
+        };
+        if self.max_bytes().is_none() {
+            return expired;
+        }
+        let mut recency = self.inner.recency.lock().unwrap();
+        recency.put(pubkey, ());
+        expired + self.shrink(&mut entries, &mut recency)
+    }
+
+    /// Evicts the least recently used unpinned entries until the cache fits its bound,
+    /// returning how many it evicted.
+    fn shrink(
+        &self,
+        entries: &mut Arc<HashMap<Pubkey, CacheEntry>>,
+        recency: &mut LruCache<Pubkey, ()>,
+    ) -> u64 {
+        let max_bytes = self.inner.max_bytes.load(Ordering::Relaxed);
+        let mut excess = self
+            .inner
+            .bytes
+            .load(Ordering::Relaxed)
+            .saturating_sub(max_bytes);
+        if excess == 0 {
+            return 0;
+        }
+
+        let pins = self.inner.pins.lock().unwrap();
+        let mut evicted = Vec::new();
+        for (pubkey, _) in recency.iter().rev() {
+            if excess == 0 {
+                break;
+            }
+            if pins.contains_key(pubkey) {
+                continue;
+            }
+            let size = entries
+                .get(pubkey)
+                .map_or(0, |entry| entry.account.data().len() as u64);
+            excess = excess.saturating_sub(size);
+            evicted.push(*pubkey);
+        }
+
+        let entries = Arc::make_mut(entries);
+        for pubkey in &evicted {
+            recency.pop(pubkey);
+            if let Some(entry) = entries.remove(pubkey) {
+                self.inner
+                    .bytes
+                    .fetch_sub(entry.account.data().len() as u64, Ordering::Relaxed);
+                self.notify(CacheChange::Removed(*pubkey));
+            }
+        }
+        evicted.len() as u64
+    }
+
+    /// Replaces the account of `pubkey` with a newer state pushed by the source, keeping
+    /// its expiry, if the cache still holds it and didn't fetch it at a later slot.
+    ///
+    /// Returns whether the entry was replaced.
+    #[cfg(feature = "pubsub")]
+    pub(crate) fn refresh(&self, pubkey: &Pubkey, account: AccountSharedData, slot: Slot) -> bool {
+        let mut entries = self.inner.entries.write().unwrap();
+        let Some(entry) = Arc::make_mut(&mut entries).get_mut(pubkey) else {
+            return false;
+        };
+        if entry.slot.is_some_and(|fetched_slot| fetched_slot > slot) {
+            return false;
+        }
+        let (old_len, new_len) = (entry.account.data().len(), account.data().len());
+        entry.account = account;
+        entry.data_sliced = false;
+        entry.slot = Some(slot);
+        entry.fetched_at = SystemTime::now();
+        self.inner
+            .bytes
+            .fetch_add(new_len as u64, Ordering::Relaxed);
+        self.inner
+            .bytes
+            .fetch_sub(old_len as u64, Ordering::Relaxed);
+        true
+    }
+
+    /// Reports every change of the cache's keys to `listener`, replacing any previous
+    /// listener, or stops reporting them with `None`.
+    #[cfg(feature = "pubsub")]
+    pub(crate) fn set_listener(&self, listener: Option<CacheListener>) {
+        *self.inner.listener.write().unwrap() = listener;
+    }
+
+    fn notify(&self, change: CacheChange) {
+        if let Some(listener) = &*self.inner.listener.read().unwrap() {
+            listener(change);
+        }
+    }
+
+    /// Drops the entry of `pubkey`, returning `true` if there was one.
+    pub(crate) fn remove(&self, pubkey: &Pubkey) -> bool {
+        let mut entries = self.inner.entries.write().unwrap();
+        if !entries.contains_key(pubkey) {
+            return false;
+        }
+        self.inner.recency.lock().unwrap().pop(pubkey);
+        match Arc::make_mut(&mut entries).remove(pubkey) {
+            Some(removed) => {
+                self.inner
+                    .bytes
+                    .fetch_sub(removed.account.data().len() as u64, Ordering::Relaxed);
+                self.notify(CacheChange::Removed(*pubkey));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drops every entry, returning how many there were.
+    pub(crate) fn clear(&self) -> usize {
+        let mut entries = self.inner.entries.write().unwrap();
+        self.inner.bytes.store(0, Ordering::Relaxed);
+        self.inner.recency.lock().unwrap().clear();
+        let cleared = std::mem::take(&mut *entries);
+        for pubkey in cleared.keys() {
+            self.notify(CacheChange::Removed(*pubkey));
+        }
+        cleared.len()
+    }
+
+    /// The current entries, shared until either side changes.
+    pub(crate) fn snapshot(&self) -> Arc<HashMap<Pubkey, CacheEntry>> {
+        Arc::clone(&self.inner.entries.read().unwrap())
+    }
+
+    /// Replaces every entry with those of a [`snapshot`](Self::snapshot).
+    ///
+    /// The restored entries count as equally recent, so any of them may be evicted first.
+    pub(crate) fn restore(&self, entries: Arc<HashMap<Pubkey, CacheEntry>>) {
+        let mut current = self.inner.entries.write().unwrap();
+        let bytes = entries
+            .values()
+            .map(|entry| entry.account.data().len() as u64)
+            .sum();
+        self.inner.bytes.store(bytes, Ordering::Relaxed);
+        let mut recency = self.inner.recency.lock().unwrap();
+        recency.clear();
+        if self.max_bytes().is_some() {
+            for pubkey in entries.keys() {
+                recency.put(*pubkey, ());
+            }
+        }
+        for pubkey in current
+            .keys()
+            .filter(|pubkey| !entries.contains_key(pubkey))
+        {
+            self.notify(CacheChange::Removed(*pubkey));
+        }
+        for pubkey in entries
+            .keys()
+            .filter(|pubkey| !current.contains_key(pubkey))
+        {
+            self.notify(CacheChange::Inserted(*pubkey));
+        }
+        *current = entries;
+    }
+
+    /// Keys of every entry, expired ones included.
+    #[cfg(feature = "pubsub")]
+    pub(crate) fn keys(&self) -> Vec<Pubkey> {
+        self.inner.entries.read().unwrap().keys().copied().collect()
+    }
+}
+
+/// Accounts of an [`AccountCache`] kept from being evicted for the size bound, until
+/// dropped. Returned by [`AccountCache::pin`].
+pub struct PinnedAccounts {
+    cache: AccountCache,
+    pubkeys: Vec<Pubkey>,
+}
+
+impl Drop for PinnedAccounts {
+    fn drop(&mut self) {
+        // Unpin even if a panic poisoned the lock, so the keys don't stay pinned forever.
+        let mut pins = self
+            .cache
+            .inner
+            .pins
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for pubkey in &self.pubkeys {
+            if let Some(count) = pins.get_mut(pubkey) {
+                *count -= 1;
+                if *count == 0 {
+                    pins.remove(pubkey);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use solana_sdk::account::{AccountSharedData, ReadableAccount};
+use solana_sdk::hash::{hash, Hash};
+use solana_sdk::pubkey::Pubkey;
+
+/// What a transaction did to an account, see [`AccountChange`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AccountChangeKind {
+    /// The account had no lamports before the transaction and has some after.
+    Created,
+    /// The account had lamports before the transaction and none after, so the runtime
+    /// deletes it.
+    Closed,
+    /// The account existed before and after, with a different state.
+    Modified,
+}
+
+/// An account whose state a transaction changed, as listed in
+/// [`ReturnStruct::account_changes`](crate::ReturnStruct::account_changes).
+///
+/// Accounts that didn't exist are described with no lamports, no data and the default
+/// owner.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AccountChange {
+    pub pubkey: Pubkey,
+    pub kind: AccountChangeKind,
+    pub old_lamports: u64,
+    pub new_lamports: u64,
+    pub old_data_len: usize,
+    pub new_data_len: usize,
+    pub old_owner: Pubkey,
+    pub new_owner: Pubkey,
+    /// SHA-256 of the account data before the transaction.
+    pub old_data_hash: Hash,
+    /// SHA-256 of the account data after the transaction.
+    pub new_data_hash: Hash,
+    /// The account data before the transaction, when the channel records it (see
+    /// [`RollUpChannelConfig::record_account_data`](crate::RollUpChannelConfig::record_account_data)).
+    pub old_data: Option<Vec<u8>>,
+    /// The account data after the transaction, when the channel records it.
+    pub new_data: Option<Vec<u8>>,
+}
+
+impl AccountChange {
+    /// The change from `old` to `new`, `None` if the account is left as it was.
+    pub(crate) fn between(
+        pubkey: Pubkey,
+        old: Option<&AccountSharedData>,
+        new: &AccountSharedData,
+        keep_data: bool,
+    ) -> Option<Self> {
+        let empty = AccountSharedData::default();
+        let old = old.unwrap_or(&empty);
+        // The rent epoch isn't compared: the runtime may bump it on any writable account.
+        if old.lamports() == new.lamports()
+            && old.owner() == new.owner()
+            && old.executable() == new.executable()
+            && old.data() == new.data()
+        {
+            return None;
+        }
+
+        let kind = match (old.lamports(), new.lamports()) {
+            (0, _) => AccountChangeKind::Created,
+            (_, 0) => AccountChangeKind::Closed,
+            _ => AccountChangeKind::Modified,
+        };
+        Some(Self {
+            pubkey,
+            kind,
+            old_lamports: old.lamports(),
+            new_lamports: new.lamports(),
+            old_data_len: old.data().len(),
+            new_data_len: new.data().len(),
+            old_owner: *old.owner(),
+            new_owner: *new.owner(),
+            old_data_hash: hash(old.data()),
+            new_data_hash: hash(new.data()),
+            old_data: keep_data.then(|| old.data().to_vec()),
+            new_data: keep_data.then(|| new.data().to_vec()),
+        })
+    }
+}
@@ -0,0 +1,42 @@
+use solana_sdk::account::AccountSharedData;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::error::SourceError;
+
+/// A custom backend a [`RollUpAccountLoader`](crate::RollUpAccountLoader) reads accounts
+/// from, e.g. an indexer's database, plugged in through [`AccountSource::custom`](crate::AccountSource::custom).
+///
+/// Only the fetching is delegated: the loader keeps caching, overriding, negative
+/// caching, pacing and counting the requests, exactly as it does for an RPC source.
+///
+/// Fetchers must be `Send + Sync` so a loader stays usable from several threads.
+pub trait AccountFetcher: Send + Sync {
+    /// Fetches a single account.
+    ///
+    /// `Ok(None)` means the account doesn't exist; `Err` means the backend couldn't tell.
+    fn fetch(&self, pubkey: &Pubkey) -> Result<Option<AccountSharedData>, SourceError>;
+
+    /// Fetches up to 100 accounts in one go, returning one entry per key, in order.
+    ///
+    /// Defaults to one [`fetch`](Self::fetch) per key; backends able to answer several
+    /// keys at once should override it.
+    fn fetch_multiple(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> Result<Vec<Option<AccountSharedData>>, SourceError> {
+        pubkeys.iter().map(|pubkey| self.fetch(pubkey)).collect()
+    }
+}
+
+impl<T: AccountFetcher + ?Sized> AccountFetcher for &T {
+    fn fetch(&self, pubkey: &Pubkey) -> Result<Option<AccountSharedData>, SourceError> {
+        (**self).fetch(pubkey)
+    }
+
+    fn fetch_multiple(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> Result<Vec<Option<AccountSharedData>>, SourceError> {
+        (**self).fetch_multiple(pubkeys)
+    }
+}
@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde_json::Value;
+use solana_account_decoder_client_types::UiAccount;
+use solana_sdk::account::AccountSharedData;
+use solana_sdk::pubkey::Pubkey;
+
+/// Parses one account fixture, as printed by `solana account <PUBKEY> --output json`.
+pub fn parse_account_fixture(json: &str) -> io::Result<(Pubkey, AccountSharedData)> {
+    let mut fixture: Value = serde_json::from_str(json)?;
+    let pubkey = fixture["pubkey"]
+        .as_str()
+        .ok_or_else(|| invalid_data("missing pubkey".to_string()))?;
+    let pubkey = Pubkey::from_str(pubkey).map_err(|err| invalid_data(err.to_string()))?;
+    let account: UiAccount = serde_json::from_value(fixture["account"].take())?;
+    let account = account
+        .decode()
+        .ok_or_else(|| invalid_data(format!("undecodable data for account {}", pubkey)))?;
+    Ok((pubkey, account))
+}
+
+/// Parses every `*.json` file of `dir` as an account fixture.
+pub fn load_account_fixtures(
+    dir: impl AsRef<Path>,
+) -> io::Result<HashMap<Pubkey, AccountSharedData>> {
+    let mut accounts = HashMap::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            let (pubkey, account) = parse_account_fixture(&fs::read_to_string(&path)?)
+                .map_err(|err| invalid_data(format!("{}: {}", path.display(), err)))?;
+            accounts.insert(pubkey, account);
+        }
+    }
+    Ok(accounts)
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
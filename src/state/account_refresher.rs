@@ -0,0 +1,255 @@
+use futures::stream::{self, FuturesUnordered, SelectAll, StreamExt};
+use solana_account_decoder_client_types::{UiAccount, UiAccountEncoding};
+use solana_client::nonblocking::pubsub_client::{PubsubClient, PubsubClientError};
+use solana_client::rpc_config::RpcAccountInfoConfig;
+use solana_client::rpc_response::Response as RpcResponse;
+use solana_sdk::account::{Account, AccountSharedData};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+use crate::state::account_cache::{AccountCache, CacheChange};
+
+/// Keeps the accounts of an [`AccountCache`] up to date through `accountSubscribe`
+/// notifications of an RPC node's websocket, for channels living long enough for TTLs
+/// to serve stale state.
+///
+/// Every key the cache holds is subscribed to, on a single connection, and the
+/// subscriptions follow the cache as accounts are fetched, evicted and invalidated. A
+/// notification replaces the cached account, so later lookups see it without going to
+/// the source; overrides and committed state still win over it.
+///
+/// The refresher is best effort: if the connection can't be established or drops, or a
+/// subscription fails, the accounts concerned keep being refreshed by the cache's TTL
+/// alone. A cache has at most one refresher, the latest started; dropping it stops the
+/// notifications.
+///
+/// Only available with the `pubsub` feature.
+pub struct AccountRefresher {
+    events: UnboundedSender<Event>,
+    cache: AccountCache,
+    stats: Arc<RefresherStats>,
+    thread: Option<JoinHandle<()>>,
+}
+
+#[derive(Default)]
+struct RefresherStats {
+    connected: AtomicBool,
+    subscriptions: AtomicU64,
+    failed_subscriptions: AtomicU64,
+    notifications: AtomicU64,
+}
+
+enum Event {
+    Change(CacheChange),
+    Shutdown,
+}
+
+type Unsubscribe = Box<dyn FnOnce() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send>;
+
+impl AccountRefresher {
+    /// Subscribes to every account of `cache`, and to those it caches later, through the
+    /// websocket endpoint at `url`, e.g. `wss://api.mainnet-beta.solana.com`.
+    ///
+    /// Notifications are requested at `commitment`, which should match the loaders'.
+    pub fn start(
+        url: impl Into<String>,
+        cache: &AccountCache,
+        commitment: CommitmentConfig,
+    ) -> Self {
+        let url = url.into();
+        let (events, receiver) = mpsc::unbounded_channel();
+        let stats = Arc::new(RefresherStats::default());
+
+        let sender = events.clone();
+        cache.set_listener(Some(Arc::new(move |change| {
+            let _ = sender.send(Event::Change(change));
+        })));
+        for pubkey in cache.keys() {
+            let _ = events.send(Event::Change(CacheChange::Inserted(pubkey)));
+        }
+
+        let thread = {
+            let cache = cache.clone();
+            let stats = Arc::clone(&stats);
+            thread::Builder::new()
+                .name("account-refresher".to_string())
+                .spawn(move || {
+                    let Ok(runtime) = tokio::runtime::Builder::new_current_thread()
+                        .enable_all()
+                        .build()
+                    else {
+                        return;
+                    };
+                    runtime.block_on(refresh(&url, commitment, &cache, &stats, receiver));
+                })
+                .ok()
+        };
+        Self {
+            events,
+            cache: cache.clone(),
+            stats,
+            thread,
+        }
+    }
+
+    /// Whether notifications are currently being received; `false` before the connection
+    /// is established and once it failed, in which case only TTLs refresh accounts.
+    pub fn is_connected(&self) -> bool {
+        self.stats.connected.load(Ordering::Relaxed)
+    }
+
+    /// Number of accounts currently subscribed to.
+    pub fn subscriptions(&self) -> u64 {
+        self.stats.subscriptions.load(Ordering::Relaxed)
+    }
+
+    /// Subscriptions the node refused or that couldn't be sent.
+    pub fn failed_subscriptions(&self) -> u64 {
+        self.stats.failed_subscriptions.load(Ordering::Relaxed)
+    }
+
+    /// Notifications applied to the cache so far.
+    pub fn notifications(&self) -> u64 {
+        self.stats.notifications.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for AccountRefresher {
+    fn drop(&mut self) {
+        self.cache.set_listener(None);
+        let _ = self.events.send(Event::Shutdown);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Applies notifications to `cache` and follows its changes until shut down or
+/// disconnected.
+async fn refresh(
+    url: &str,
+    commitment: CommitmentConfig,
+    cache: &AccountCache,
+    stats: &RefresherStats,
+    mut events: UnboundedReceiver<Event>,
+) {
+    let client = match PubsubClient::new(url).await {
+        Ok(client) => client,
+        Err(_) => return,
+    };
+    stats.connected.store(true, Ordering::Relaxed);
+    let config = RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64Zstd),
+        commitment: Some(commitment),
+        ..RpcAccountInfoConfig::default()
+    };
+
+    // Every subscription gets an id, so the end of a replaced one isn't mistaken for the
+    // end of its replacement. Subscribing and unsubscribing wait for the node, so both
+    // are queued rather than awaited, and a burst of cache changes doesn't hold back
+    // notifications; a key removed while its subscription is pending is unsubscribed
+    // once the node confirms it.
+    let mut subscriptions: HashMap<Pubkey, (u64, Unsubscribe)> = HashMap::new();
+    let mut pending: HashMap<Pubkey, u64> = HashMap::new();
+    let mut subscribing = FuturesUnordered::new();
+    let mut unsubscribing = FuturesUnordered::new();
+    let mut next_id = 0;
+    let mut notifications = SelectAll::new();
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Some(Event::Change(CacheChange::Inserted(pubkey))) => {
+                    if subscriptions.contains_key(&pubkey) || pending.contains_key(&pubkey) {
+                        continue;
+                    }
+                    let id = next_id;
+                    next_id += 1;
+                    pending.insert(pubkey, id);
+                    let (client, config) = (&client, config.clone());
+                    subscribing.push(async move {
+                        (pubkey, id, client.account_subscribe(&pubkey, Some(config)).await)
+                    });
+                }
+                Some(Event::Change(CacheChange::Removed(pubkey))) => {
+                    if pending.remove(&pubkey).is_none() {
+                        if let Some((_, unsubscribe)) = subscriptions.remove(&pubkey) {
+                            unsubscribing.push(unsubscribe());
+                        }
+                    }
+                }
+                Some(Event::Shutdown) | None => break,
+            },
+            Some((pubkey, id, subscribed)) = subscribing.next(), if !subscribing.is_empty() => {
+                let current = pending.get(&pubkey) == Some(&id);
+                match subscribed {
+                    Ok((notified, unsubscribe)) if current => {
+                        pending.remove(&pubkey);
+                        subscriptions.insert(pubkey, (id, unsubscribe));
+                        notifications.push(
+                            notified
+                                .map(move |response| (pubkey, id, Some(response)))
+                                .chain(stream::once(async move { (pubkey, id, None) })),
+                        );
+                    }
+                    // The account left the cache meanwhile.
+                    Ok((_, unsubscribe)) => unsubscribing.push(unsubscribe()),
+                    Err(PubsubClientError::ConnectionClosed(_)) => break,
+                    Err(_) => {
+                        if current {
+                            pending.remove(&pubkey);
+                        }
+                        stats.failed_subscriptions.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            }
+            Some(()) = unsubscribing.next(), if !unsubscribing.is_empty() => {}
+            Some((pubkey, id, response)) = notifications.next(), if !notifications.is_empty() => {
+                let current = subscriptions
+                    .get(&pubkey)
+                    .is_some_and(|(current_id, _)| *current_id == id);
+                match response {
+                    Some(response) if current => {
+                        if apply(cache, &pubkey, response) {
+                            stats.notifications.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                    // The node ended the subscription, or the connection dropped: the
+                    // account is left to its TTL.
+                    None if current => {
+                        subscriptions.remove(&pubkey);
+                        stats.failed_subscriptions.fetch_add(1, Ordering::Relaxed);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        stats
+            .subscriptions
+            .store(subscriptions.len() as u64, Ordering::Relaxed);
+    }
+
+    stats.connected.store(false, Ordering::Relaxed);
+    stats.subscriptions.store(0, Ordering::Relaxed);
+    drop((subscribing, unsubscribing, notifications));
+    let _ = client.shutdown().await;
+}
+
+/// Replaces the cached account of `pubkey` with the notified state, returning whether
+/// the cache took it.
+fn apply(cache: &AccountCache, pubkey: &Pubkey, response: RpcResponse<UiAccount>) -> bool {
+    let Some(account) = response.value.decode::<Account>() else {
+        return false;
+    };
+    cache.refresh(
+        pubkey,
+        AccountSharedData::from(account),
+        response.context.slot,
+    )
+}
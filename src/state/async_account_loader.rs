@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+
+use futures::stream::{self, StreamExt, TryStreamExt};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::account::AccountSharedData;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::Transaction;
+
+use crate::error::AccountLoadError;
+use crate::state::rollup_account_loader::{programdata_address, MAX_MULTIPLE_ACCOUNTS};
+use crate::utils::helpers::SYSVARS;
+
+/// Fetches every account a batch needs through the nonblocking [`RpcClient`], for use
+/// inside an async runtime.
+///
+/// The SVM itself is synchronous, so a [`RollUpAccountLoader`](crate::RollUpAccountLoader)
+/// backed by the blocking client would stall the executor on each lookup. Instead, fetch
+/// the batch's accounts up front and hand them to an offline channel:
+///
+/// ```no_run
+/// # use solana_client::nonblocking::rpc_client::RpcClient;
+/// # use solana_client_ext::{AsyncAccountLoader, RollUpChannel};
+/// # async fn run(rpc_client: &RpcClient, transactions: &[solana_sdk::transaction::Transaction]) {
+/// let accounts = AsyncAccountLoader::new(rpc_client)
+///     .prefetch(transactions)
+///     .await
+///     .unwrap();
+/// let results = RollUpChannel::new_offline(vec![], accounts).process_rollup_transfers(transactions);
+/// # }
+/// ```
+///
+/// Accounts the SVM only discovers mid-execution are included as long as they can be
+/// derived from the batch (the ProgramData of upgradeable programs and the sysvars);
+/// anything else is seen as missing.
+pub struct AsyncAccountLoader<'a> {
+    rpc_client: &'a RpcClient,
+    /// Maximum number of `getMultipleAccounts` requests in flight at once.
+    max_concurrent_requests: usize,
+}
+
+impl<'a> AsyncAccountLoader<'a> {
+    /// Creates a loader sending up to 8 concurrent requests through `rpc_client`.
+    pub fn new(rpc_client: &'a RpcClient) -> Self {
+        Self {
+            rpc_client,
+            max_concurrent_requests: 8,
+        }
+    }
+
+    /// Limits how many requests are in flight at once. A limit of 0 is treated as 1.
+    pub fn with_max_concurrent_requests(mut self, max_concurrent_requests: usize) -> Self {
+        self.max_concurrent_requests = max_concurrent_requests.max(1);
+        self
+    }
+
+    /// Fetches every account referenced by `transactions`, the ProgramData accounts of
+    /// the upgradeable programs among them, and the sysvars.
+    ///
+    /// Accounts that don't exist are left out of the returned map.
+    pub async fn prefetch(
+        &self,
+        transactions: &[Transaction],
+    ) -> Result<HashMap<Pubkey, AccountSharedData>, AccountLoadError> {
+        let keys = transactions
+            .iter()
+            .flat_map(|tx| tx.message.account_keys.iter().copied())
+            .chain(SYSVARS);
+        let mut accounts = self.prefetch_keys(keys).await?;
+
+        let programdata_keys: Vec<Pubkey> =
+            accounts.values().filter_map(programdata_address).collect();
+        accounts.extend(self.prefetch_keys(programdata_keys).await?);
+
+        Ok(accounts)
+    }
+
+    /// Fetches `keys` in batches of up to 100, running batches concurrently.
+    ///
+    /// Accounts that don't exist are left out of the returned map. A failed request
+    /// fails the whole prefetch with [`AccountLoadError::Transport`], naming the first
+    /// key of the failed batch.
+    pub async fn prefetch_keys(
+        &self,
+        keys: impl IntoIterator<Item = Pubkey>,
+    ) -> Result<HashMap<Pubkey, AccountSharedData>, AccountLoadError> {
+        let mut keys: Vec<Pubkey> = keys.into_iter().collect();
+        keys.sort_unstable();
+        keys.dedup();
+
+        let batches: Vec<Vec<(Pubkey, AccountSharedData)>> =
+            stream::iter(keys.chunks(MAX_MULTIPLE_ACCOUNTS))
+                .map(|chunk| async move {
+                    let accounts =
+                        self.rpc_client
+                            .get_multiple_accounts(chunk)
+                            .await
+                            .map_err(|err| AccountLoadError::Transport {
+                                pubkey: chunk[0],
+                                message: err.to_string(),
+                            })?;
+                    Ok::<_, AccountLoadError>(
+                        chunk
+                            .iter()
+                            .zip(accounts)
+                            .filter_map(|(pubkey, account)| {
+                                Some((*pubkey, AccountSharedData::from(account?)))
+                            })
+                            .collect::<Vec<_>>(),
+                    )
+                })
+                .buffer_unordered(self.max_concurrent_requests)
+                .try_collect()
+                .await?;
+
+        Ok(batches.into_iter().flatten().collect())
+    }
+}
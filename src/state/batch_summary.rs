@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use crate::ReturnStruct;
+
+/// Aggregate numbers for a batch of transaction results.
+///
+/// Can be computed from any slice of [`ReturnStruct`]s, so it works the same for
+/// results coming out of a single batch or several batches stitched together.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BatchSummary {
+    /// Number of transactions in the batch.
+    pub transactions: u64,
+    /// Transactions that succeeded.
+    pub succeeded: u64,
+    /// Transactions that failed, for any reason.
+    pub failed: u64,
+    /// Positions of the failed transactions in the summarized results.
+    pub failed_indexes: Vec<usize>,
+    /// Sum of `cu` over every transaction in the batch, failed ones included.
+    pub total_cu: u64,
+    /// Sum of `fee_lamports` over every transaction in the batch.
+    pub total_fees: u64,
+    /// Sum of `loaded_accounts_count` over every transaction in the batch.
+    pub loaded_accounts_count: u64,
+    /// Sum of `loaded_accounts_data_size` over every transaction in the batch, in bytes.
+    ///
+    /// Useful to compare against the cluster's block limits when packing transactions.
+    pub loaded_accounts_data_size: u64,
+    /// Transactions that failed with an
+    /// [`infrastructure_error`](ReturnStruct::infrastructure_error).
+    pub infrastructure_errors: u64,
+    /// Sum of the timed transactions' `execution_time`, `None` if none was timed.
+    pub execution_time: Option<Duration>,
+}
+
+impl BatchSummary {
+    /// Fold a slice of results into batch totals.
+    pub fn from_results(results: &[ReturnStruct]) -> Self {
+        results
+            .iter()
+            .enumerate()
+            .fold(Self::default(), |mut summary, (index, result)| {
+                summary.transactions += 1;
+                if result.is_success() {
+                    summary.succeeded += 1;
+                } else {
+                    summary.failed += 1;
+                    summary.failed_indexes.push(index);
+                }
+                summary.total_cu += result.cu;
+                summary.total_fees += result.fee_lamports;
+                summary.loaded_accounts_count += u64::from(result.loaded_accounts_count);
+                summary.loaded_accounts_data_size += u64::from(result.loaded_accounts_data_size);
+                summary.infrastructure_errors += u64::from(result.infrastructure_error);
+                if let Some(execution_time) = result.execution_time {
+                    summary.execution_time =
+                        Some(summary.execution_time.unwrap_or_default() + execution_time);
+                }
+                summary
+            })
+    }
+
+    /// Compute units an average transaction of the batch consumed, `0` for an empty batch.
+    pub fn average_cu(&self) -> u64 {
+        self.total_cu
+            .checked_div(self.transactions)
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,31 @@
+use solana_sdk::clock::Slot;
+
+use crate::state::execution_timings::ExecutionTimings;
+use crate::ReturnStruct;
+
+/// The outcome of one block simulated through [`RollUpChannel::process_blocks`](crate::RollUpChannel::process_blocks).
+pub struct BlockResult {
+    /// The processor slot the block executed in.
+    pub slot: Slot,
+    /// Per-transaction results, in block order.
+    pub results: Vec<ReturnStruct>,
+    /// Compute units consumed by every transaction of the block.
+    pub total_cu: u64,
+    /// Fees (base plus prioritization) charged across the block, in lamports.
+    ///
+    /// Includes fees from failed and fees-only transactions, since those are still charged.
+    pub total_fees: u64,
+    /// Index of the first transaction left out because the block reached
+    /// [`RollUpChannelConfig::batch_cu_limit`](crate::RollUpChannelConfig::batch_cu_limit).
+    ///
+    /// Transactions from this index on didn't commit anything, and those after it weren't
+    /// executed, so they can be carried over into the next block. `None` if the whole
+    /// block fit.
+    pub cutoff: Option<usize>,
+    /// Some transaction of the block failed with an
+    /// [`infrastructure_error`](ReturnStruct::infrastructure_error), so the block's
+    /// results don't reflect what the cluster would do.
+    pub infrastructure_error: bool,
+    /// Where the SVM spent its time executing the block.
+    pub timings: ExecutionTimings,
+}
@@ -0,0 +1,103 @@
+use serde_json::{json, Value};
+use solana_sdk::clock::Slot;
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+/// Layer of a [`RollUpAccountLoader`](crate::RollUpAccountLoader) an account is served from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountLayer {
+    /// Set with [`insert_override`](crate::RollUpAccountLoader::insert_override).
+    Override,
+    /// Committed by a channel after executing a transaction, or synthesized by it.
+    Committed,
+    /// Fetched from the source and held by the [`AccountCache`](crate::AccountCache).
+    Fetched,
+}
+
+impl AccountLayer {
+    fn as_str(&self) -> &'static str {
+        match self {
+            AccountLayer::Override => "override",
+            AccountLayer::Committed => "committed",
+            AccountLayer::Fetched => "fetched",
+        }
+    }
+}
+
+/// State of one account held by a loader, as returned by
+/// [`RollUpAccountLoader::inspect`](crate::RollUpAccountLoader::inspect).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedAccountInfo {
+    pub pubkey: Pubkey,
+    /// The layer the account is served from; an account held by several layers is
+    /// reported for the one lookups see.
+    pub layer: AccountLayer,
+    pub lamports: u64,
+    pub owner: Pubkey,
+    pub executable: bool,
+    pub data_len: usize,
+    /// SHA-256 of the account data, to compare states without dumping the data.
+    pub data_hash: Hash,
+    /// The account was fetched without its data, so `data_len` and `data_hash` describe
+    /// empty data.
+    pub data_sliced: bool,
+    /// Newest slot the loader had seen when it fetched the account, for fetched
+    /// accounts whose source reports slots.
+    pub slot: Option<Slot>,
+    /// Time since the account was fetched, for fetched accounts.
+    pub age: Option<Duration>,
+    /// The account's TTL elapsed, so the next lookup refetches it.
+    pub expired: bool,
+}
+
+impl CachedAccountInfo {
+    /// The account as a JSON object, ready to be logged or written to a file.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "pubkey": self.pubkey.to_string(),
+            "layer": self.layer.as_str(),
+            "lamports": self.lamports,
+            "owner": self.owner.to_string(),
+            "executable": self.executable,
+            "dataLen": self.data_len,
+            "dataHash": self.data_hash.to_string(),
+            "dataSliced": self.data_sliced,
+            "slot": self.slot,
+            "ageMs": self.age.map(|age| age.as_millis() as u64),
+            "expired": self.expired,
+        })
+    }
+}
+
+impl Display for CachedAccountInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} [{}] lamports={} owner={} data={}B hash={}",
+            self.pubkey,
+            self.layer.as_str(),
+            self.lamports,
+            self.owner,
+            self.data_len,
+            self.data_hash
+        )?;
+        if self.executable {
+            write!(f, " executable")?;
+        }
+        if self.data_sliced {
+            write!(f, " sliced")?;
+        }
+        if let Some(slot) = self.slot {
+            write!(f, " slot={}", slot)?;
+        }
+        if let Some(age) = self.age {
+            write!(f, " age={:?}", age)?;
+        }
+        if self.expired {
+            write!(f, " expired")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,181 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use solana_sdk::account::{AccountSharedData, ReadableAccount, WritableAccount};
+use solana_sdk::clock::Slot;
+use solana_sdk::hash::{hash, HASH_BYTES};
+use solana_sdk::pubkey::Pubkey;
+
+/// First bytes of every cache file.
+const MAGIC: &[u8; 4] = b"RLAC";
+
+/// Version of the layout below; files of any other version are ignored.
+const VERSION: u8 = 1;
+
+/// Size of the fixed header preceding an account's data in its file.
+const HEADER_SIZE: usize = 4 + 1 + 8 + 8 + 8 + 8 + 32 + 1 + 8;
+
+/// Extension of cache files, so clearing the cache leaves other files of the directory alone.
+const EXTENSION: &str = "account";
+
+/// Slot recorded for an account fetched from a source that doesn't report slots.
+const UNKNOWN_SLOT: u64 = u64::MAX;
+
+/// Distinguishes the temporary files of concurrent writes.
+static NEXT_TEMP: AtomicU64 = AtomicU64::new(0);
+
+/// Fetched accounts persisted between runs, one file per account.
+///
+/// Each account lives in `<dir>/<base58 pubkey>.account`, laid out as:
+///
+/// | Offset | Size | Field                                         |
+/// |--------|------|-----------------------------------------------|
+/// | 0      | 4    | `RLAC`                                        |
+/// | 4      | 1    | layout version, 1                             |
+/// | 5      | 8    | fetch time, ms since the Unix epoch, LE       |
+/// | 13     | 8    | fetch slot, LE, `u64::MAX` if unknown         |
+/// | 21     | 8    | lamports, LE                                  |
+/// | 29     | 8    | rent epoch, LE                                |
+/// | 37     | 32   | owner                                         |
+/// | 69     | 1    | executable (0 or 1)                           |
+/// | 70     | 8    | data length, LE                               |
+/// | 78     | len  | account data                                  |
+/// | 78+len | 32   | SHA-256 of everything before                  |
+///
+/// Files are written to a temporary name, then renamed, so a crashed run leaves no
+/// partial file behind. A file that doesn't match the layout or its checksum is treated
+/// as absent.
+pub(crate) struct DiskCache {
+    dir: PathBuf,
+}
+
+/// An account read back from a [`DiskCache`].
+pub(crate) struct PersistedAccount {
+    pub(crate) account: AccountSharedData,
+    /// Slot the account was fetched at, if the source reported one.
+    pub(crate) slot: Option<Slot>,
+    pub(crate) fetched_at: SystemTime,
+}
+
+impl DiskCache {
+    pub(crate) fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub(crate) fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Reads the account persisted for `pubkey`, `None` if there is none or its file is
+    /// corrupt.
+    pub(crate) fn read(&self, pubkey: &Pubkey) -> Option<PersistedAccount> {
+        decode_account(&fs::read(self.path(pubkey)).ok()?)
+    }
+
+    /// Persists `account`, fetched at `slot` and `fetched_at`, replacing any previous file.
+    pub(crate) fn write(
+        &self,
+        pubkey: &Pubkey,
+        account: &AccountSharedData,
+        slot: Option<Slot>,
+        fetched_at: SystemTime,
+    ) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let temp = self.dir.join(format!(
+            "{}.{}.{}.tmp",
+            pubkey,
+            std::process::id(),
+            NEXT_TEMP.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&temp, encode_account(account, slot, fetched_at))?;
+        fs::rename(&temp, self.path(pubkey)).inspect_err(|_| {
+            let _ = fs::remove_file(&temp);
+        })
+    }
+
+    /// Deletes the file of `pubkey`, if any.
+    pub(crate) fn remove(&self, pubkey: &Pubkey) {
+        let _ = fs::remove_file(self.path(pubkey));
+    }
+
+    /// Deletes every cache file of the directory.
+    pub(crate) fn clear(&self) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == EXTENSION)
+            {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+
+    fn path(&self, pubkey: &Pubkey) -> PathBuf {
+        self.dir.join(format!("{}.{}", pubkey, EXTENSION))
+    }
+}
+
+fn encode_account(
+    account: &AccountSharedData,
+    slot: Option<Slot>,
+    fetched_at: SystemTime,
+) -> Vec<u8> {
+    let fetched_at_ms = fetched_at
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    let mut bytes = Vec::with_capacity(HEADER_SIZE + account.data().len() + HASH_BYTES);
+    bytes.extend_from_slice(MAGIC);
+    bytes.push(VERSION);
+    bytes.extend_from_slice(&fetched_at_ms.to_le_bytes());
+    bytes.extend_from_slice(&slot.unwrap_or(UNKNOWN_SLOT).to_le_bytes());
+    bytes.extend_from_slice(&account.lamports().to_le_bytes());
+    bytes.extend_from_slice(&account.rent_epoch().to_le_bytes());
+    bytes.extend_from_slice(account.owner().as_ref());
+    bytes.push(account.executable() as u8);
+    bytes.extend_from_slice(&(account.data().len() as u64).to_le_bytes());
+    bytes.extend_from_slice(account.data());
+    let checksum = hash(&bytes);
+    bytes.extend_from_slice(checksum.as_ref());
+    bytes
+}
+
+fn decode_account(bytes: &[u8]) -> Option<PersistedAccount> {
+    let (content, checksum) = bytes.split_at(bytes.len().checked_sub(HASH_BYTES)?);
+    if content.len() < HEADER_SIZE
+        || &content[0..4] != MAGIC
+        || content[4] != VERSION
+        || hash(content).as_ref() != checksum
+    {
+        return None;
+    }
+
+    let u64_at =
+        |offset: usize| u64::from_le_bytes(content[offset..offset + 8].try_into().unwrap());
+    let data_len = usize::try_from(u64_at(70)).ok()?;
+    if content.len() - HEADER_SIZE != data_len {
+        return None;
+    }
+    let slot = match u64_at(13) {
+        UNKNOWN_SLOT => None,
+        slot => Some(slot),
+    };
+    let owner = Pubkey::try_from(&content[37..69]).ok()?;
+
+    let mut account = AccountSharedData::new(u64_at(21), 0, &owner);
+    account.set_data_from_slice(&content[HEADER_SIZE..]);
+    account.set_executable(content[69] != 0);
+    account.set_rent_epoch(u64_at(29));
+    Some(PersistedAccount {
+        account,
+        slot,
+        fetched_at: UNIX_EPOCH + Duration::from_millis(u64_at(5)),
+    })
+}
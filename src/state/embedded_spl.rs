@@ -0,0 +1,48 @@
+use solana_sdk::account::AccountSharedData;
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+
+use crate::state::local_program::{program_accounts, LoaderKind};
+
+/// The SPL Token program's id.
+pub const SPL_TOKEN_ID: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+/// The SPL Token-2022 program's id.
+pub const SPL_TOKEN_2022_ID: Pubkey = pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+/// The SPL Associated Token Account program's id.
+pub const SPL_ASSOCIATED_TOKEN_ACCOUNT_ID: Pubkey =
+    pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
+/// The embedded SPL programs: their id, the loader they are deployed with, and their ELF.
+const EMBEDDED_SPL_PROGRAMS: [(Pubkey, LoaderKind, &[u8]); 3] = [
+    (
+        SPL_TOKEN_ID,
+        LoaderKind::BpfLoader,
+        include_bytes!("../programs/spl_token-3.5.0.so"),
+    ),
+    (
+        SPL_TOKEN_2022_ID,
+        LoaderKind::Upgradeable,
+        include_bytes!("../programs/spl_token_2022-1.0.0.so"),
+    ),
+    (
+        SPL_ASSOCIATED_TOKEN_ACCOUNT_ID,
+        LoaderKind::BpfLoader,
+        include_bytes!("../programs/spl_associated_token_account-1.1.1.so"),
+    ),
+];
+
+/// The accounts of the SPL programs embedded in the crate, rent exempt under `rent`: SPL
+/// Token 3.5.0 and Associated Token Account 1.1.1 deployed with the BPF loader, and
+/// Token-2022 1.0.0 with the upgradeable loader along with its ProgramData account.
+///
+/// The binaries lag behind the cluster's deployments; see
+/// [`RollUpChannelConfig::embedded_spl_programs`](crate::RollUpChannelConfig::embedded_spl_programs).
+pub fn embedded_spl_accounts(rent: &Rent) -> Vec<(Pubkey, AccountSharedData)> {
+    EMBEDDED_SPL_PROGRAMS
+        .iter()
+        .flat_map(|(program_id, loader, elf)| program_accounts(program_id, elf, *loader, rent))
+        .collect()
+}
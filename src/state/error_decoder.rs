@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::{SanitizedTransaction, TransactionError};
+
+/// A `Custom` instruction error traced back to the program that returned it, as found in
+/// [`ReturnStruct::decoded_error`](crate::ReturnStruct::decoded_error).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DecodedError {
+    /// Index of the failing top-level instruction.
+    pub instruction_index: u8,
+    /// The program the failing top-level instruction invoked.
+    ///
+    /// The error may have been raised by a program it called through CPI.
+    pub program_id: Pubkey,
+    /// The custom error code.
+    pub code: u32,
+    /// Name registered for the program in the channel's [`ErrorDecoder`].
+    pub program_name: Option<String>,
+    /// Name registered for the code, e.g. `SlippageExceeded`.
+    pub error_name: Option<String>,
+}
+
+impl Display for DecodedError {
+    /// `MyProgram::SlippageExceeded` when both names are known, falling back to the
+    /// program id and the code, e.g. `<program id>: custom program error: 0x1771`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match (&self.program_name, &self.error_name) {
+            (Some(program_name), Some(error_name)) => {
+                write!(f, "{}::{}", program_name, error_name)
+            }
+            (Some(program_name), None) => write!(
+                f,
+                "{}: custom program error: {:#x}",
+                program_name, self.code
+            ),
+            (None, _) => write!(
+                f,
+                "{}: custom program error: {:#x}",
+                self.program_id, self.code
+            ),
+        }
+    }
+}
+
+/// Names of the custom errors of known programs, used to decode the errors of failed
+/// transactions.
+///
+/// A channel uses an empty decoder unless given one through
+/// [`RollUpChannelBuilder::error_decoder`](crate::RollUpChannelBuilder::error_decoder);
+/// errors of programs it doesn't know are still decoded, without names.
+#[derive(Debug, Clone, Default)]
+pub struct ErrorDecoder {
+    programs: HashMap<Pubkey, ProgramErrors>,
+}
+
+#[derive(Debug, Clone)]
+struct ProgramErrors {
+    name: String,
+    errors: HashMap<u32, String>,
+}
+
+impl ErrorDecoder {
+    /// A decoder knowing no program.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the program `program_id` under `name`, with the name of each of its
+    /// error codes, e.g. the `errors` of an Anchor IDL. Replaces any previous
+    /// registration of the program.
+    pub fn register<S: Into<String>>(
+        mut self,
+        program_id: Pubkey,
+        name: impl Into<String>,
+        errors: impl IntoIterator<Item = (u32, S)>,
+    ) -> Self {
+        self.programs.insert(
+            program_id,
+            ProgramErrors {
+                name: name.into(),
+                errors: errors
+                    .into_iter()
+                    .map(|(code, error_name)| (code, error_name.into()))
+                    .collect(),
+            },
+        );
+        self
+    }
+
+    /// Decodes `error`, returned by `tx`, if it is a `Custom` instruction error.
+    pub fn decode(
+        &self,
+        tx: &SanitizedTransaction,
+        error: &TransactionError,
+    ) -> Option<DecodedError> {
+        let TransactionError::InstructionError(instruction_index, InstructionError::Custom(code)) =
+            error
+        else {
+            return None;
+        };
+        let (program_id, _) = tx
+            .message()
+            .program_instructions_iter()
+            .nth(usize::from(*instruction_index))?;
+        let program = self.programs.get(program_id);
+        Some(DecodedError {
+            instruction_index: *instruction_index,
+            program_id: *program_id,
+            code: *code,
+            program_name: program.map(|program| program.name.clone()),
+            error_name: program.and_then(|program| program.errors.get(code).cloned()),
+        })
+    }
+}
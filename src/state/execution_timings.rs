@@ -0,0 +1,119 @@
+use solana_sdk::pubkey::Pubkey;
+use solana_timings::{ExecuteTimingType, ExecuteTimings};
+
+/// Where the SVM spent its time executing batches, summarized from the `ExecuteTimings`
+/// it reports, see [`RollUpChannel::execution_timings`](crate::RollUpChannel::execution_timings).
+///
+/// Times are wall-clock microseconds. A program cheap in compute units but slow to run
+/// shows whether setting up its VM (`create_vm_us`, `serialize_us`, `deserialize_us`)
+/// or executing it (`vm_execute_us`) dominates.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExecutionTimings {
+    /// Validating fee payers.
+    pub validate_fees_us: u64,
+    /// Loading transactions' accounts.
+    pub load_us: u64,
+    /// Executing transactions, VM setup included.
+    pub execute_us: u64,
+    /// Finding and compiling the programs transactions invoke.
+    pub program_cache_us: u64,
+    /// Serializing instruction accounts into the VM's input.
+    pub serialize_us: u64,
+    /// Creating VMs.
+    pub create_vm_us: u64,
+    /// Running programs in the VM.
+    pub vm_execute_us: u64,
+    /// Deserializing instruction accounts from the VM's output.
+    pub deserialize_us: u64,
+    /// Per-program figures, the slowest program first.
+    pub programs: Vec<ProgramTimings>,
+}
+
+/// Time spent executing one program's top-level instructions, see
+/// [`ExecutionTimings::programs`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProgramTimings {
+    /// The program.
+    pub program_id: Pubkey,
+    /// Time spent executing it, CPIs it made included.
+    pub execute_us: u64,
+    /// Compute units its instructions consumed.
+    pub compute_units: u64,
+    /// Instructions invoking it.
+    pub invocations: u64,
+    /// Of these, instructions that failed.
+    pub failed_invocations: u64,
+}
+
+impl ExecutionTimings {
+    /// Summarizes the timings an SVM call reported.
+    pub fn from_execute_timings(timings: &ExecuteTimings) -> Self {
+        let details = &timings.details;
+        let mut programs: Vec<ProgramTimings> = details
+            .per_program_timings
+            .iter()
+            .map(|(program_id, timing)| {
+                let failed_invocations = timing.errored_txs_compute_consumed.len() as u64;
+                ProgramTimings {
+                    program_id: *program_id,
+                    execute_us: timing.accumulated_us.0,
+                    compute_units: timing.accumulated_units.0 + timing.total_errored_units.0,
+                    invocations: u64::from(timing.count.0) + failed_invocations,
+                    failed_invocations,
+                }
+            })
+            .collect();
+        sort_programs(&mut programs);
+
+        Self {
+            validate_fees_us: timings.metrics[ExecuteTimingType::ValidateFeesUs].0,
+            load_us: timings.metrics[ExecuteTimingType::LoadUs].0,
+            execute_us: timings.metrics[ExecuteTimingType::ExecuteUs].0,
+            program_cache_us: timings.metrics[ExecuteTimingType::ProgramCacheUs].0,
+            serialize_us: details.serialize_us.0,
+            create_vm_us: details.create_vm_us.0,
+            vm_execute_us: details.execute_us.0,
+            deserialize_us: details.deserialize_us.0,
+            programs,
+        }
+    }
+
+    /// Adds `other`'s timings to these.
+    pub fn accumulate(&mut self, other: &ExecutionTimings) {
+        self.validate_fees_us += other.validate_fees_us;
+        self.load_us += other.load_us;
+        self.execute_us += other.execute_us;
+        self.program_cache_us += other.program_cache_us;
+        self.serialize_us += other.serialize_us;
+        self.create_vm_us += other.create_vm_us;
+        self.vm_execute_us += other.vm_execute_us;
+        self.deserialize_us += other.deserialize_us;
+        for timing in &other.programs {
+            match self
+                .programs
+                .iter_mut()
+                .find(|program| program.program_id == timing.program_id)
+            {
+                Some(program) => {
+                    program.execute_us += timing.execute_us;
+                    program.compute_units += timing.compute_units;
+                    program.invocations += timing.invocations;
+                    program.failed_invocations += timing.failed_invocations;
+                }
+                None => self.programs.push(timing.clone()),
+            }
+        }
+        sort_programs(&mut self.programs);
+    }
+}
+
+/// Orders programs the slowest first, then by id so the order is stable.
+fn sort_programs(programs: &mut [ProgramTimings]) {
+    programs.sort_by(|a, b| {
+        b.execute_us
+            .cmp(&a.execute_us)
+            .then_with(|| a.program_id.cmp(&b.program_id))
+    });
+}
@@ -0,0 +1,253 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use solana_sdk::inner_instruction::{InnerInstruction, InnerInstructionsList};
+use solana_sdk::instruction::CompiledInstruction;
+use solana_sdk::pubkey::Pubkey;
+
+/// A structured view of how a transaction executed, one tree per top-level instruction.
+///
+/// The tree is assembled from the runtime's recorded logs (`Program X invoke [n]`,
+/// `Program X consumed A of B compute units`, `Program X success/failed`) and, when
+/// available, the recorded inner instructions, which are attached to the matching CPI
+/// entries. Only produced when tracing is enabled on the channel.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExecutionTrace {
+    /// One entry per top-level instruction, in execution order.
+    pub instructions: Vec<TracedInstruction>,
+}
+
+/// How a traced instruction ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TraceStatus {
+    /// The program returned successfully.
+    Success,
+    /// The program failed with the given error message.
+    Failed(String),
+    /// No completion line was recorded, e.g. because logs were truncated.
+    Incomplete,
+}
+
+/// A single program invocation within an [`ExecutionTrace`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TracedInstruction {
+    /// The invoked program.
+    pub program_id: Pubkey,
+    /// Invocation depth; top-level instructions have a stack height of 1.
+    pub stack_height: usize,
+    /// Compute units remaining when the program was invoked.
+    ///
+    /// Builtin programs don't report consumption in the logs, so this is `None` for them.
+    pub cu_before: Option<u64>,
+    /// Compute units remaining when the program returned.
+    pub cu_after: Option<u64>,
+    /// How the invocation ended.
+    pub status: TraceStatus,
+    /// Log lines emitted directly by this invocation (excluding nested invocations).
+    pub logs: Vec<String>,
+    /// The CPI as recorded by the SVM, for nested entries when inner instructions were recorded.
+    pub instruction: Option<CompiledInstruction>,
+    /// Nested CPI invocations, in execution order.
+    pub inner: Vec<TracedInstruction>,
+}
+
+impl TracedInstruction {
+    fn new(program_id: Pubkey, stack_height: usize) -> Self {
+        Self {
+            program_id,
+            stack_height,
+            cu_before: None,
+            cu_after: None,
+            status: TraceStatus::Incomplete,
+            logs: Vec::new(),
+            instruction: None,
+            inner: Vec::new(),
+        }
+    }
+
+    /// Compute units consumed by this invocation, including nested ones, if known.
+    pub fn consumed(&self) -> Option<u64> {
+        Some(self.cu_before?.saturating_sub(self.cu_after?))
+    }
+}
+
+impl ExecutionTrace {
+    /// Builds a trace from recorded log messages and, optionally, inner instructions.
+    pub fn from_execution(
+        logs: &[String],
+        inner_instructions: Option<&InnerInstructionsList>,
+    ) -> Self {
+        let mut instructions: Vec<TracedInstruction> = Vec::new();
+        let mut stack: Vec<TracedInstruction> = Vec::new();
+
+        for line in logs {
+            if let Some((program_id, stack_height)) = parse_invoke(line) {
+                stack.push(TracedInstruction::new(program_id, stack_height));
+            } else if let Some((consumed, budget)) = parse_consumed(line) {
+                if let Some(frame) = stack.last_mut() {
+                    frame.cu_before = Some(budget);
+                    frame.cu_after = Some(budget.saturating_sub(consumed));
+                }
+            } else if let Some(status) = parse_completion(line) {
+                if let Some(mut frame) = stack.pop() {
+                    frame.status = status;
+                    match stack.last_mut() {
+                        Some(parent) => parent.inner.push(frame),
+                        None => instructions.push(frame),
+                    }
+                }
+            } else if let Some(frame) = stack.last_mut() {
+                frame.logs.push(line.clone());
+            }
+        }
+
+        // Unterminated frames (truncated logs or an aborted transaction) are kept as incomplete.
+        while let Some(frame) = stack.pop() {
+            match stack.last_mut() {
+                Some(parent) => parent.inner.push(frame),
+                None => instructions.push(frame),
+            }
+        }
+
+        // Inner instructions are recorded per top-level instruction in execution order,
+        // which matches a pre-order walk of the nested invocations.
+        if let Some(inner_instructions) = inner_instructions {
+            for (top_level, recorded) in instructions.iter_mut().zip(inner_instructions) {
+                attach_inner_instructions(&mut top_level.inner, &mut recorded.iter());
+            }
+        }
+
+        Self { instructions }
+    }
+}
+
+/// Assigns recorded inner instructions to nested invocations in pre-order.
+fn attach_inner_instructions<'a>(
+    frames: &mut [TracedInstruction],
+    recorded: &mut impl Iterator<Item = &'a InnerInstruction>,
+) {
+    for frame in frames {
+        if let Some(inner) = recorded.next() {
+            frame.instruction = Some(inner.instruction.clone());
+        }
+        attach_inner_instructions(&mut frame.inner, recorded);
+    }
+}
+
+/// Parses a `Program X invoke [n]` log line into the program and its stack height.
+pub(crate) fn parse_invoke(line: &str) -> Option<(Pubkey, usize)> {
+    let rest = line.strip_prefix("Program ")?;
+    let (program_id, rest) = rest.split_once(" invoke [")?;
+    let stack_height = rest.strip_suffix(']')?.parse().ok()?;
+    Some((Pubkey::from_str(program_id).ok()?, stack_height))
+}
+
+fn parse_consumed(line: &str) -> Option<(u64, u64)> {
+    let rest = line.strip_prefix("Program ")?;
+    let (_, rest) = rest.split_once(" consumed ")?;
+    let (consumed, rest) = rest.split_once(" of ")?;
+    let budget = rest.strip_suffix(" compute units")?;
+    Some((consumed.parse().ok()?, budget.parse().ok()?))
+}
+
+fn parse_completion(line: &str) -> Option<TraceStatus> {
+    let rest = line.strip_prefix("Program ")?;
+    let (program_id, outcome) = rest.split_once(' ')?;
+    Pubkey::from_str(program_id).ok()?;
+    if outcome == "success" {
+        Some(TraceStatus::Success)
+    } else {
+        outcome
+            .strip_prefix("failed: ")
+            .map(|err| TraceStatus::Failed(err.to_string()))
+    }
+}
+
+impl Display for TraceStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TraceStatus::Success => write!(f, "success"),
+            TraceStatus::Failed(err) => write!(f, "failed: {}", err),
+            TraceStatus::Incomplete => write!(f, "incomplete"),
+        }
+    }
+}
+
+impl TracedInstruction {
+    /// Whether the invocation didn't return successfully.
+    fn failed(&self) -> bool {
+        self.status != TraceStatus::Success
+    }
+
+    /// Writes the invocation's line to `out`, marked with `>` if it failed, then for a
+    /// failed invocation its logs and nested invocations; a successful one is kept to
+    /// its line, with a count of what it invoked.
+    fn render_failure_path(&self, out: &mut Vec<String>, label: &str) {
+        let marker = if self.failed() { ">" } else { " " };
+        let indent = "  ".repeat(self.stack_height.saturating_sub(1));
+        let mut line = format!("{} {}{} {}", marker, indent, label, self.program_id);
+        if let Some(consumed) = self.consumed() {
+            line.push_str(&format!(" {} CU", consumed));
+        }
+        line.push_str(&format!(" {}", self.status));
+        if !self.failed() {
+            if !self.inner.is_empty() {
+                line.push_str(&format!(" (+{} nested)", self.inner.len()));
+            }
+            out.push(line);
+            return;
+        }
+        out.push(line);
+        for log in &self.logs {
+            out.push(format!("{} {}    {}", marker, indent, log));
+        }
+        for (i, inner) in self.inner.iter().enumerate() {
+            inner.render_failure_path(out, &format!("{}.{}", label, i));
+        }
+    }
+
+    fn fmt_indented(&self, f: &mut Formatter<'_>, label: &str) -> std::fmt::Result {
+        let indent = "  ".repeat(self.stack_height.saturating_sub(1));
+        write!(
+            f,
+            "{}{} {} [{}]",
+            indent, label, self.program_id, self.stack_height
+        )?;
+        if let (Some(consumed), Some(before), Some(after)) =
+            (self.consumed(), self.cu_before, self.cu_after)
+        {
+            write!(f, " {} CU ({} -> {})", consumed, before, after)?;
+        }
+        writeln!(f, " {}", self.status)?;
+        for (i, inner) in self.inner.iter().enumerate() {
+            inner.fmt_indented(f, &format!("{}.{}", label, i))?;
+        }
+        Ok(())
+    }
+}
+
+impl ExecutionTrace {
+    /// Renders the trace the way explorers show failed transactions: one line per
+    /// top-level instruction, with the failed ones, marked with `>`, expanded into their
+    /// logs and nested invocations.
+    pub(crate) fn render_failure_path(&self) -> Vec<String> {
+        let mut out = Vec::new();
+        for (i, instruction) in self.instructions.iter().enumerate() {
+            instruction.render_failure_path(&mut out, &format!("#{}", i));
+        }
+        out
+    }
+}
+
+/// Renders the trace as an indented tree, one line per invocation.
+impl Display for ExecutionTrace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, instruction) in self.instructions.iter().enumerate() {
+            instruction.fmt_indented(f, &format!("#{}", i))?;
+        }
+        Ok(())
+    }
+}
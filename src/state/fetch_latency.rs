@@ -0,0 +1,76 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Upper bounds of the [`FetchLatency`] buckets; the last bucket holds everything slower.
+pub const LATENCY_BUCKET_BOUNDS: [Duration; 10] = [
+    Duration::from_millis(1),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(25),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(250),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_secs(5),
+];
+
+/// Latency summary of the requests a [`RollUpAccountLoader`](crate::RollUpAccountLoader)
+/// sent to its source, in a shape ready to export as a histogram.
+///
+/// Every attempt counts, including throttled ones the loader retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FetchLatency {
+    /// Requests timed.
+    pub requests: u64,
+    /// Account data bytes the successful requests returned.
+    pub bytes: u64,
+    /// Sum of every request's latency.
+    pub total: Duration,
+    /// Latency of the slowest request.
+    pub max: Duration,
+    /// Requests per latency bucket: `buckets[i]` counts the requests that took at most
+    /// `LATENCY_BUCKET_BOUNDS[i]` and more than the previous bound, the last one those
+    /// slower than every bound.
+    pub buckets: [u64; LATENCY_BUCKET_BOUNDS.len() + 1],
+}
+
+impl FetchLatency {
+    /// Average latency of a request, zero if none was timed.
+    pub fn mean(&self) -> Duration {
+        if self.requests == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_nanos((self.total.as_nanos() / u128::from(self.requests)) as u64)
+    }
+
+    fn record(&mut self, latency: Duration, bytes: u64) {
+        self.requests += 1;
+        self.bytes += bytes;
+        self.total += latency;
+        self.max = self.max.max(latency);
+        let bucket = LATENCY_BUCKET_BOUNDS
+            .iter()
+            .position(|bound| latency <= *bound)
+            .unwrap_or(LATENCY_BUCKET_BOUNDS.len());
+        self.buckets[bucket] += 1;
+    }
+}
+
+/// Accumulates a [`FetchLatency`] from every thread using a loader.
+#[derive(Default)]
+pub(crate) struct LatencyRecorder(Mutex<FetchLatency>);
+
+impl LatencyRecorder {
+    pub(crate) fn record(&self, latency: Duration, bytes: u64) {
+        self.0.lock().unwrap().record(latency, bytes);
+    }
+
+    pub(crate) fn summary(&self) -> FetchLatency {
+        *self.0.lock().unwrap()
+    }
+
+    pub(crate) fn reset(&self) {
+        *self.0.lock().unwrap() = FetchLatency::default();
+    }
+}
@@ -1,7 +1,9 @@
+use std::collections::BTreeSet;
+
 use solana_program_runtime::loaded_programs::{BlockRelation, ForkGraph};
 use solana_sdk::clock::Slot;
 
-/// A minimal placeholder implementation of the `ForkGraph` trait.
+/// A linear chain of slots, the `ForkGraph` channels use by default.
 ///
 /// In a real validator, `ForkGraph` models the ledger’s fork structure, which helps
 /// determine relationships between blocks (e.g., which slots are ancestors of others).
@@ -9,21 +11,70 @@ use solana_sdk::clock::Slot;
 /// This is required by the `TransactionBatchProcessor` so it can reason about
 /// program visibility and slot relationships during transaction simulation.
 ///
-/// In our case, we don’t need full fork tracking for local CU estimation or isolated
-/// transaction simulation, so we stub it with an empty struct.
-pub(crate) struct ForkRollUpGraph {}
-/// Implements the `ForkGraph` trait for our mocked graph.
+/// A channel never forks: it executes its batches at increasing slots, registering each
+/// one on the graph (see [`RollUpChannel::process_blocks`](crate::RollUpChannel::process_blocks)),
+/// so every registered slot is an ancestor of the registered slots after it. Slots that
+/// weren't registered, e.g. those programs were deployed in before the channel started,
+/// are `Unknown`.
+///
+/// ## Implementing your own graph
 ///
-/// The `relationship()` method defines how two slots relate to each other.
-/// For simulation purposes, we simply return `BlockRelation::Unknown` to indicate
-/// that we make no assumptions about slot ancestry.
+/// A custom graph can be passed to [`RollUpChannel::with_fork_graph`](crate::RollUpChannel::with_fork_graph).
+/// For the program cache to behave, `relationship(a, b)` must:
+/// - return `Equal` when `a == b`,
+/// - return `Ancestor` when `a` is an ancestor of `b` on the same fork (and
+///   `Descendant` for the reverse),
+/// - return `Unrelated` only for slots on different forks.
 ///
-/// This is sufficient because:
-/// - We simulate in a single slot (usually slot 1)
-/// - We're not validating or resolving forks
-/// - We're not simulating program activation/deactivation across slots
+/// The cache only makes a program visible in slot `b` if it was deployed at or
+/// before the root, or in a slot `a` for which `relationship(a, b)` is `Equal`
+/// or `Ancestor`. Answering `Unknown` is always safe but pessimistic: programs
+/// deployed after the root are then reloaded for every batch instead of reused.
+///
+/// Channels only register slots on a `ForkRollUpGraph`, so a custom graph extending this
+/// chain, e.g. with known history, can wrap one and [register](Self::register_slot) the
+/// slots itself.
+#[derive(Debug, Clone, Default)]
+pub struct ForkRollUpGraph {
+    slots: BTreeSet<Slot>,
+}
+
+impl ForkRollUpGraph {
+    /// A chain made of `slots`.
+    pub fn with_slots(slots: impl IntoIterator<Item = Slot>) -> Self {
+        Self {
+            slots: slots.into_iter().collect(),
+        }
+    }
+
+    /// Adds `slot` to the chain, after the registered slots below it and before those
+    /// above it.
+    pub fn register_slot(&mut self, slot: Slot) {
+        self.slots.insert(slot);
+    }
+
+    /// Whether `slot` was registered.
+    pub fn contains(&self, slot: Slot) -> bool {
+        self.slots.contains(&slot)
+    }
+
+    /// The registered slots, in increasing order.
+    pub fn slots(&self) -> impl Iterator<Item = Slot> + '_ {
+        self.slots.iter().copied()
+    }
+}
+
+/// Registered slots are ordered along the chain; anything involving another slot is
+/// `Unknown`, since the chain makes no assumption about slots it wasn't told about.
 impl ForkGraph for ForkRollUpGraph {
-    fn relationship(&self, _a: Slot, _b: Slot) -> BlockRelation {
-        BlockRelation::Unknown
+    fn relationship(&self, a: Slot, b: Slot) -> BlockRelation {
+        if !self.contains(a) || !self.contains(b) {
+            return BlockRelation::Unknown;
+        }
+        match a.cmp(&b) {
+            std::cmp::Ordering::Less => BlockRelation::Ancestor,
+            std::cmp::Ordering::Equal => BlockRelation::Equal,
+            std::cmp::Ordering::Greater => BlockRelation::Descendant,
+        }
     }
 }
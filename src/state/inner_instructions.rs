@@ -0,0 +1,88 @@
+use serde_json::{json, Value};
+use solana_sdk::inner_instruction::InnerInstructionsList;
+use solana_sdk::instruction::CompiledInstruction;
+use solana_transaction_status_client_types::{
+    UiCompiledInstruction, UiInnerInstructions, UiInstruction,
+};
+
+/// The instructions invoked through CPI by one top-level instruction of a transaction,
+/// as listed in [`ReturnStruct::inner_instructions`](crate::ReturnStruct::inner_instructions).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InnerInstructionGroup {
+    /// Index of the top-level instruction in the transaction's message.
+    pub index: u8,
+    /// Invoked instructions, in execution order.
+    pub instructions: Vec<InvokedInstruction>,
+}
+
+/// One instruction invoked through CPI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InvokedInstruction {
+    /// The instruction, its program and accounts indexing the message's account keys.
+    pub instruction: CompiledInstruction,
+    /// Invocation depth; instructions invoked by a top-level instruction have a stack
+    /// height of 2.
+    pub stack_height: u32,
+}
+
+impl InnerInstructionGroup {
+    /// Groups the inner instructions the SVM recorded, one list per top-level instruction,
+    /// leaving out the instructions that invoked nothing, as RPC nodes do.
+    pub(crate) fn from_recorded(recorded: &InnerInstructionsList) -> Vec<Self> {
+        recorded
+            .iter()
+            .enumerate()
+            .filter(|(_, instructions)| !instructions.is_empty())
+            .map(|(index, instructions)| Self {
+                index: index as u8,
+                instructions: instructions
+                    .iter()
+                    .map(|inner| InvokedInstruction {
+                        instruction: inner.instruction.clone(),
+                        stack_height: u32::from(inner.stack_height),
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// The group as the RPC client types represent it, e.g. in
+    /// [`RpcSimulateTransactionResult::inner_instructions`](solana_client::rpc_response::RpcSimulateTransactionResult::inner_instructions).
+    pub fn to_ui(&self) -> UiInnerInstructions {
+        UiInnerInstructions {
+            index: self.index,
+            instructions: self
+                .instructions
+                .iter()
+                .map(|inner| {
+                    UiInstruction::Compiled(UiCompiledInstruction {
+                        program_id_index: inner.instruction.program_id_index,
+                        accounts: inner.instruction.accounts.clone(),
+                        data: bs58::encode(&inner.instruction.data).into_string(),
+                        stack_height: Some(inner.stack_height),
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    /// The group in the shape of the `innerInstructions` entries of `getTransaction` and
+    /// `simulateTransaction`, with base58 instruction data, so existing decoders accept it.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "index": self.index,
+            "instructions": self
+                .instructions
+                .iter()
+                .map(|inner| json!({
+                    "programIdIndex": inner.instruction.program_id_index,
+                    "accounts": inner.instruction.accounts,
+                    "data": bs58::encode(&inner.instruction.data).into_string(),
+                    "stackHeight": inner.stack_height,
+                }))
+                .collect::<Vec<_>>(),
+        })
+    }
+}
@@ -0,0 +1,163 @@
+use agave_feature_set::FeatureSet;
+use solana_bpf_loader_program::syscalls::create_program_runtime_environment_v1;
+use solana_compute_budget::compute_budget::ComputeBudget;
+use solana_program_runtime::loaded_programs::{LoadProgramMetrics, ProgramCacheEntry};
+use solana_sdk::account::{AccountSharedData, ReadableAccount, WritableAccount};
+use solana_sdk::account_utils::StateMut;
+use solana_sdk::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use solana_sdk::{bpf_loader, bpf_loader_deprecated};
+use std::sync::Arc;
+
+use crate::error::ProgramLoadError;
+
+/// Loader a program added with
+/// [`RollUpChannel::add_program`](crate::RollUpChannel::add_program) is deployed with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoaderKind {
+    /// The non-upgradeable BPF loader (v2): the ELF is the program account's data.
+    BpfLoader,
+    /// The upgradeable loader (v3): the ELF lives in a ProgramData account, deployed at
+    /// slot 0 without an upgrade authority.
+    Upgradeable,
+    /// Loader-v4: the ELF follows the loader state in the program account. Only
+    /// available with the `loader-v4` feature.
+    #[cfg(feature = "loader-v4")]
+    V4,
+}
+
+impl LoaderKind {
+    /// The loader's program id.
+    pub fn id(&self) -> Pubkey {
+        match self {
+            LoaderKind::BpfLoader => bpf_loader::id(),
+            LoaderKind::Upgradeable => bpf_loader_upgradeable::id(),
+            #[cfg(feature = "loader-v4")]
+            LoaderKind::V4 => solana_sdk::loader_v4::id(),
+        }
+    }
+}
+
+/// Checks that `elf`, deployed with the loader `loader_id`, loads and verifies in the
+/// runtime environment the channel executes programs with, rejecting broken ELFs if
+/// `reject_broken_elfs` is set, so a broken ELF is reported with the program's id rather
+/// than as a bare runtime error.
+pub(crate) fn verify_elf(
+    program_id: &Pubkey,
+    elf: &[u8],
+    loader_id: &Pubkey,
+    reject_broken_elfs: bool,
+) -> Result<(), ProgramLoadError> {
+    let invalid = |message: String| ProgramLoadError::InvalidElf {
+        program_id: *program_id,
+        message,
+    };
+    let environment = create_program_runtime_environment_v1(
+        &FeatureSet::all_enabled(),
+        &ComputeBudget::default(),
+        reject_broken_elfs,
+        false,
+    )
+    .map_err(|err| invalid(err.to_string()))?;
+    ProgramCacheEntry::new(
+        loader_id,
+        Arc::new(environment),
+        0,
+        0,
+        elf,
+        elf.len(),
+        &mut LoadProgramMetrics::default(),
+    )
+    .map(|_| ())
+    .map_err(|err| invalid(err.to_string()))
+}
+
+/// The ELF deployed in `program` if it is owned by one of the BPF loaders, read from its
+/// ProgramData account, fetched with `load`, for the upgradeable loader; `None` for
+/// builtins and accounts that aren't programs.
+pub(crate) fn program_elf(
+    program: &AccountSharedData,
+    load: impl Fn(&Pubkey) -> Option<AccountSharedData>,
+) -> Option<Vec<u8>> {
+    let owner = program.owner();
+    if bpf_loader::check_id(owner) || bpf_loader_deprecated::check_id(owner) {
+        return Some(program.data().to_vec());
+    }
+    if bpf_loader_upgradeable::check_id(owner) {
+        let UpgradeableLoaderState::Program {
+            programdata_address,
+        } = program.state().ok()?
+        else {
+            return None;
+        };
+        let programdata = load(&programdata_address)?;
+        let metadata_size = UpgradeableLoaderState::size_of_programdata_metadata();
+        return programdata.data().get(metadata_size..).map(<[u8]>::to_vec);
+    }
+    #[cfg(feature = "loader-v4")]
+    if solana_sdk::loader_v4::check_id(owner) {
+        let offset = solana_sdk::loader_v4::LoaderV4State::program_data_offset();
+        return program.data().get(offset..).map(<[u8]>::to_vec);
+    }
+    None
+}
+
+/// The accounts of `program_id` deployed at slot 0 with `loader`, rent exempt under
+/// `rent`: the program account, and its ProgramData account for the upgradeable loader.
+pub(crate) fn program_accounts(
+    program_id: &Pubkey,
+    elf: &[u8],
+    loader: LoaderKind,
+    rent: &Rent,
+) -> Vec<(Pubkey, AccountSharedData)> {
+    let executable = |data: Vec<u8>| {
+        let mut account = AccountSharedData::new(rent.minimum_balance(data.len()), 0, &loader.id());
+        account.set_data_from_slice(&data);
+        account.set_executable(true);
+        account
+    };
+    match loader {
+        LoaderKind::BpfLoader => vec![(*program_id, executable(elf.to_vec()))],
+        LoaderKind::Upgradeable => {
+            let (programdata_address, _) =
+                Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+            // Both states are serialized into accounts sized for them, which can't fail.
+            let mut program = AccountSharedData::new_data(
+                rent.minimum_balance(UpgradeableLoaderState::size_of_program()),
+                &UpgradeableLoaderState::Program {
+                    programdata_address,
+                },
+                &bpf_loader_upgradeable::id(),
+            )
+            .expect("program state fits its account");
+            program.set_executable(true);
+
+            let metadata_size = UpgradeableLoaderState::size_of_programdata_metadata();
+            let mut programdata = AccountSharedData::new_data_with_space(
+                rent.minimum_balance(metadata_size + elf.len()),
+                &UpgradeableLoaderState::ProgramData {
+                    slot: 0,
+                    upgrade_authority_address: None,
+                },
+                metadata_size + elf.len(),
+                &bpf_loader_upgradeable::id(),
+            )
+            .expect("ProgramData metadata fits its account");
+            programdata.data_as_mut_slice()[metadata_size..].copy_from_slice(elf);
+
+            vec![(*program_id, program), (programdata_address, programdata)]
+        }
+        #[cfg(feature = "loader-v4")]
+        LoaderKind::V4 => {
+            use solana_sdk::loader_v4::{LoaderV4State, LoaderV4Status};
+
+            // LoaderV4State is `#[repr(C)]`: the slot, the authority, then the status.
+            let offset = LoaderV4State::program_data_offset();
+            let mut data = vec![0; offset];
+            data[40..48].copy_from_slice(&(LoaderV4Status::Finalized as u64).to_le_bytes());
+            data.extend_from_slice(elf);
+            vec![(*program_id, executable(data))]
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::HashSet;
+
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::SanitizedTransaction;
+
+/// The account locks a single transaction takes.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TransactionLocks {
+    /// Keys locked for writing.
+    pub writable: HashSet<Pubkey>,
+    /// Keys locked for reading only.
+    pub readonly: HashSet<Pubkey>,
+}
+
+impl TransactionLocks {
+    /// Two transactions conflict if either one writes an account the other one touches.
+    pub fn conflicts_with(&self, other: &TransactionLocks) -> bool {
+        !self.writable.is_disjoint(&other.writable)
+            || !self.writable.is_disjoint(&other.readonly)
+            || !self.readonly.is_disjoint(&other.writable)
+    }
+}
+
+/// Write-lock contention within a batch, computed without executing anything.
+///
+/// Lock sets are taken from the sanitized messages, so for versioned transactions
+/// they include the addresses resolved through lookup tables.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct LockAnalysis {
+    /// Lock sets per transaction, in batch order.
+    pub locks: Vec<TransactionLocks>,
+    /// Pairs of conflicting transaction indexes `(i, j)` with `i < j`.
+    pub conflicts: Vec<(usize, usize)>,
+    /// A suggested grouping of transaction indexes where no two members of a group conflict.
+    ///
+    /// Groups are meant to run one after the other, each group in parallel. A transaction
+    /// is always placed in a later group than every earlier transaction it conflicts with,
+    /// so executing the groups in order yields the same result as the original order.
+    pub groups: Vec<Vec<usize>>,
+}
+
+impl LockAnalysis {
+    /// Analyzes the locks taken by `transactions`.
+    pub fn new(transactions: &[SanitizedTransaction]) -> Self {
+        let locks: Vec<TransactionLocks> = transactions
+            .iter()
+            .map(|tx| {
+                let message = tx.message();
+                let mut tx_locks = TransactionLocks::default();
+                for (index, key) in message.account_keys().iter().enumerate() {
+                    if message.is_writable(index) {
+                        tx_locks.writable.insert(*key);
+                    } else {
+                        tx_locks.readonly.insert(*key);
+                    }
+                }
+                tx_locks
+            })
+            .collect();
+
+        let mut conflicts = Vec::new();
+        let mut group_of: Vec<usize> = Vec::with_capacity(locks.len());
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+
+        for (j, tx_locks) in locks.iter().enumerate() {
+            let mut group = 0;
+            for (i, earlier) in locks[..j].iter().enumerate() {
+                if earlier.conflicts_with(tx_locks) {
+                    conflicts.push((i, j));
+                    group = group.max(group_of[i] + 1);
+                }
+            }
+            group_of.push(group);
+            if group == groups.len() {
+                groups.push(Vec::new());
+            }
+            groups[group].push(j);
+        }
+
+        Self {
+            locks,
+            conflicts,
+            groups,
+        }
+    }
+
+    /// Whether transactions `a` and `b` contend on at least one account.
+    pub fn conflicting(&self, a: usize, b: usize) -> bool {
+        let (a, b) = if a < b { (a, b) } else { (b, a) };
+        self.conflicts.contains(&(a, b))
+    }
+}
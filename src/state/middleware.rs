@@ -0,0 +1,44 @@
+use std::ops::ControlFlow;
+
+use solana_sdk::transaction::SanitizedTransaction;
+
+use crate::ReturnStruct;
+
+/// Reason given by a [`RollUpMiddleware`] for refusing to execute a transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reject {
+    /// Human-readable explanation, surfaced in the transaction's `ReturnStruct::result`.
+    pub message: String,
+}
+
+impl Reject {
+    /// Construct a rejection with the given message.
+    pub fn new(message: impl ToString) -> Self {
+        Self {
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Policy hooks a [`RollUpChannel`](crate::RollUpChannel) invokes around each transaction.
+///
+/// Within a batch, `before` is called for every transaction, in order, before any of
+/// them executes; `after` is then called for every result, in the same order. Policies
+/// that depend on a batch's running totals (e.g. a per-payer CU cap) should therefore
+/// budget against the transaction's requested limit rather than its executed units.
+///
+/// Middlewares compose in registration order. The first one to reject a transaction
+/// wins and later middlewares' `before` hooks are skipped for it; `after` hooks always
+/// run for every registered middleware.
+pub trait RollUpMiddleware {
+    /// Inspects transaction `index` of the batch before it executes.
+    ///
+    /// Returning `ControlFlow::Break` prevents the transaction from executing: it is
+    /// reported as a failure carrying the rejection message and commits no state.
+    fn before(&self, _index: usize, _transaction: &SanitizedTransaction) -> ControlFlow<Reject> {
+        ControlFlow::Continue(())
+    }
+
+    /// Observes the result of transaction `index` of the batch.
+    fn after(&self, _index: usize, _result: &ReturnStruct) {}
+}
@@ -1,7 +1,67 @@
 pub mod return_struct;
 
+pub mod result_json;
+
+pub mod batch_summary;
+
+pub mod account_change;
+
+pub mod block_result;
+
+pub mod error_decoder;
+
+pub mod execution_trace;
+
+pub mod execution_timings;
+
+pub mod inner_instructions;
+
+pub mod simulation_context;
+
+pub mod lock_analysis;
+
+pub mod middleware;
+
 pub mod rollup_channel;
 
+pub mod rollup_channel_builder;
+
+pub mod rollup_channel_config;
+
 pub mod fork_rollup_graph;
 
+pub mod processor_builder;
+
+pub mod local_program;
+
+pub mod program_cache;
+
+pub mod processing_environment;
+
+#[cfg(feature = "embedded-spl")]
+pub mod embedded_spl;
+
 pub mod rollup_account_loader;
+
+pub mod account_cache;
+
+pub mod account_fetcher;
+
+#[cfg(feature = "pubsub")]
+pub mod account_refresher;
+
+pub mod request_limiter;
+
+pub mod single_flight;
+
+pub mod fetch_latency;
+
+pub mod async_account_loader;
+
+pub mod account_fixtures;
+
+pub mod snapshot_accounts;
+
+pub mod disk_cache;
+
+pub mod cache_inspection;
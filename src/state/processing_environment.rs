@@ -0,0 +1,143 @@
+use std::sync::Arc;
+
+use solana_client::client_error::{ClientError, ClientErrorKind, Result as ClientResult};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::account::from_account;
+use solana_sdk::fee::FeeStructure;
+use solana_sdk::hash::Hash;
+use solana_sdk::rent::Rent;
+use solana_sdk::rent_collector::RentCollector;
+use solana_sdk::sysvar;
+use solana_svm::transaction_processor::TransactionProcessingEnvironment;
+
+use agave_feature_set::FeatureSet;
+
+use crate::utils::helpers::feature_set_from_cluster;
+
+/// Builds the [`TransactionProcessingEnvironment`] a processor executes a batch in: the
+/// slot-level parameters a bank would provide.
+///
+/// Channels build theirs from [`ProcessingEnvironmentBuilder::deterministic`], with the
+/// blockhash, stake, features and rent they resolved. Callers driving a processor from
+/// [`ProcessorBuilder`](crate::ProcessorBuilder) directly can start from the same preset,
+/// or mirror a live cluster with [`ProcessingEnvironmentBuilder::from_cluster`].
+///
+/// The environment borrows the builder's rent collector, so the builder must outlive it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessingEnvironmentBuilder {
+    blockhash: Hash,
+    blockhash_lamports_per_signature: u64,
+    epoch_total_stake: u64,
+    feature_set: Arc<FeatureSet>,
+    fee_lamports_per_signature: u64,
+    rent_collector: RentCollector,
+}
+
+impl ProcessingEnvironmentBuilder {
+    /// The environment channels execute in unless configured otherwise: the default
+    /// blockhash, the default fee of 5000 lamports per signature, no stake, every feature
+    /// enabled, and the default rent collector.
+    pub fn deterministic() -> Self {
+        let lamports_per_signature = FeeStructure::default().lamports_per_signature;
+        Self {
+            blockhash: Hash::default(),
+            blockhash_lamports_per_signature: lamports_per_signature,
+            epoch_total_stake: 0,
+            feature_set: Arc::new(FeatureSet::all_enabled()),
+            fee_lamports_per_signature: lamports_per_signature,
+            rent_collector: RentCollector::default(),
+        }
+    }
+
+    /// The environment of the cluster `rpc_client` points to: its latest blockhash, the
+    /// total stake of its vote accounts, its features (see [`feature_set_from_cluster`]),
+    /// and a rent collector with its rent, epoch schedule and current epoch.
+    ///
+    /// Fees are the default 5000 lamports per signature, as RPC nodes no longer expose
+    /// the fee rate.
+    pub fn from_cluster(rpc_client: &RpcClient) -> ClientResult<Self> {
+        let blockhash = rpc_client.get_latest_blockhash()?;
+        let vote_accounts = rpc_client.get_vote_accounts()?;
+        let epoch_total_stake = vote_accounts
+            .current
+            .iter()
+            .chain(&vote_accounts.delinquent)
+            .map(|vote_account| vote_account.activated_stake)
+            .sum();
+        let feature_set = feature_set_from_cluster(rpc_client)?;
+        let rent_account = rpc_client.get_account(&sysvar::rent::id())?;
+        let rent = from_account::<Rent, _>(&rent_account).ok_or_else(|| {
+            ClientError::from(ClientErrorKind::Custom(
+                "Failed to decode the rent sysvar".to_string(),
+            ))
+        })?;
+        let rent_collector = RentCollector {
+            epoch: rpc_client.get_epoch_info()?.epoch,
+            epoch_schedule: rpc_client.get_epoch_schedule()?,
+            rent,
+            ..RentCollector::default()
+        };
+        Ok(Self {
+            blockhash,
+            epoch_total_stake,
+            feature_set: Arc::new(feature_set),
+            rent_collector,
+            ..Self::deterministic()
+        })
+    }
+
+    /// Sets the blockhash transactions execute under, the one nonce accounts advance to.
+    pub fn blockhash(mut self, blockhash: Hash) -> Self {
+        self.blockhash = blockhash;
+        self
+    }
+
+    /// Sets the fee rate of the blockhash, stored in nonce accounts advancing to it.
+    pub fn blockhash_lamports_per_signature(mut self, lamports_per_signature: u64) -> Self {
+        self.blockhash_lamports_per_signature = lamports_per_signature;
+        self
+    }
+
+    /// Sets the total stake of the epoch, as programs read it through the
+    /// `sol_get_epoch_stake` syscall.
+    pub fn epoch_total_stake(mut self, epoch_total_stake: u64) -> Self {
+        self.epoch_total_stake = epoch_total_stake;
+        self
+    }
+
+    /// Sets the features transactions execute with.
+    pub fn feature_set(mut self, feature_set: Arc<FeatureSet>) -> Self {
+        self.feature_set = feature_set;
+        self
+    }
+
+    /// Sets the fee charged per signature.
+    pub fn fee_lamports_per_signature(mut self, lamports_per_signature: u64) -> Self {
+        self.fee_lamports_per_signature = lamports_per_signature;
+        self
+    }
+
+    /// Sets the rent collector checking accounts' rent state after execution.
+    pub fn rent_collector(mut self, rent_collector: RentCollector) -> Self {
+        self.rent_collector = rent_collector;
+        self
+    }
+
+    /// Builds the environment, borrowing the builder's rent collector.
+    pub fn build(&self) -> TransactionProcessingEnvironment<'_> {
+        TransactionProcessingEnvironment {
+            blockhash: self.blockhash,
+            blockhash_lamports_per_signature: self.blockhash_lamports_per_signature,
+            epoch_total_stake: self.epoch_total_stake,
+            feature_set: Arc::clone(&self.feature_set),
+            fee_lamports_per_signature: self.fee_lamports_per_signature,
+            rent_collector: Some(&self.rent_collector),
+        }
+    }
+}
+
+impl Default for ProcessingEnvironmentBuilder {
+    fn default() -> Self {
+        Self::deterministic()
+    }
+}
@@ -0,0 +1,421 @@
+use std::sync::{Arc, RwLock};
+
+use agave_feature_set::{
+    migrate_address_lookup_table_program_to_core_bpf, migrate_config_program_to_core_bpf,
+    migrate_stake_program_to_core_bpf, FeatureSet,
+};
+use solana_bpf_loader_program::syscalls::create_program_runtime_environment_v1;
+#[cfg(feature = "loader-v4")]
+use solana_bpf_loader_program::syscalls::create_program_runtime_environment_v2;
+use solana_compute_budget::compute_budget::ComputeBudget;
+use solana_program_runtime::invoke_context::BuiltinFunctionWithContext;
+use solana_program_runtime::loaded_programs::{
+    ForkGraph, ProgramCacheEntry, ProgramRuntimeEnvironment,
+};
+use solana_sdk::clock::{Epoch, Slot};
+use solana_sdk::pubkey::Pubkey;
+use solana_svm::transaction_processing_callback::TransactionProcessingCallback;
+use solana_svm::transaction_processor::TransactionBatchProcessor;
+use solana_system_program::system_processor;
+
+use crate::utils::helpers::{PROCESSOR_EPOCH, PROCESSOR_SLOT};
+
+/// A program implemented natively by the runtime, e.g. the system program, registered on
+/// a processor through [`ProcessorBuilder::add_builtin`] or
+/// [`RollUpChannelConfig::builtins`](crate::RollUpChannelConfig::builtins).
+///
+/// Any program id can be given a builtin, which makes it possible to script programs in
+/// Rust, e.g. mocks whose outcome is known, without compiling an ELF. The entrypoint is
+/// declared with `solana_program_runtime::declare_process_instruction!`: the declared
+/// type's `vm` function is the entrypoint. The macro charges a fixed number of compute
+/// units, then runs a closure receiving the instruction's `InvokeContext`, which gives
+/// access to its accounts and data, and returning `Result<(), InstructionError>`:
+///
+/// ```
+/// use solana_client_ext::Builtin;
+/// use solana_program_runtime::declare_process_instruction;
+/// use solana_sdk::{instruction::InstructionError, pubkey::Pubkey};
+///
+/// // Consumes 500 compute units, then fails with custom error 42.
+/// declare_process_instruction!(AlwaysFails, 500, |_invoke_context| {
+///     Err(InstructionError::Custom(42))
+/// });
+///
+/// let mock = Builtin::new(Pubkey::new_unique(), "always_fails", AlwaysFails::vm);
+/// ```
+///
+/// The processor wraps the entrypoint in a builtin program cache entry, so it is never
+/// loaded from an account.
+#[derive(Debug, Clone)]
+pub struct Builtin {
+    pub program_id: Pubkey,
+    /// Name stored in the builtin's native loader account.
+    pub name: String,
+    /// Function executing the program's instructions, see [`Builtin`].
+    pub entrypoint: BuiltinFunctionWithContext,
+    /// Feature migrating the program to core BPF: while it is active in the processor's
+    /// [feature set](ProcessorBuilder::feature_set), the builtin isn't registered and the
+    /// program is loaded from its account like any BPF program.
+    pub core_bpf_migration_feature: Option<Pubkey>,
+}
+
+impl Builtin {
+    /// A builtin registered under `program_id`, executing `entrypoint`.
+    pub fn new(
+        program_id: Pubkey,
+        name: impl Into<String>,
+        entrypoint: BuiltinFunctionWithContext,
+    ) -> Self {
+        Self {
+            program_id,
+            name: name.into(),
+            entrypoint,
+            core_bpf_migration_feature: None,
+        }
+    }
+
+    /// Leaves the builtin out once `feature_id`, migrating the program to core BPF, is
+    /// active.
+    pub fn migrated_by(mut self, feature_id: Pubkey) -> Self {
+        self.core_bpf_migration_feature = Some(feature_id);
+        self
+    }
+
+    /// The builtins every processor registers unless built
+    /// [`without_default_builtins`](ProcessorBuilder::without_default_builtins): the
+    /// system and compute budget programs, the BPF loader v2 and upgradeable loader (v3)
+    /// that most deployed programs are owned by, and the address lookup table and stake
+    /// programs, the last two until their migration to core BPF.
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            Self::new(
+                solana_system_program::id(),
+                "system_program",
+                system_processor::Entrypoint::vm,
+            ),
+            // Its instructions are read before execution, but still executed like any
+            // other, charging their compute units.
+            Self::new(
+                solana_sdk::compute_budget::id(),
+                "compute_budget_program",
+                solana_compute_budget_program::Entrypoint::vm,
+            ),
+            Self::new(
+                solana_sdk::bpf_loader::id(),
+                "solana_bpf_loader_program",
+                solana_bpf_loader_program::Entrypoint::vm,
+            ),
+            // The ELF of upgradeable programs lives in a separate ProgramData account,
+            // which the program cache loads through the callbacks. Registering the loader
+            // also executes its own instructions: deploys, upgrades, authority changes and
+            // closes.
+            Self::new(
+                solana_sdk::bpf_loader_upgradeable::id(),
+                "solana_bpf_loader_upgradeable_program",
+                solana_bpf_loader_program::Entrypoint::vm,
+            ),
+            // Reads the SlotHashes sysvar to validate the recent slot of created tables.
+            Self::new(
+                solana_sdk::address_lookup_table::program::id(),
+                "address_lookup_table_program",
+                solana_address_lookup_table_program::processor::Entrypoint::vm,
+            )
+            .migrated_by(migrate_address_lookup_table_program_to_core_bpf::id()),
+            // Reads the StakeHistory sysvar to compute the effective stake of delegations.
+            Self::new(
+                solana_sdk::stake::program::id(),
+                "stake_program",
+                solana_stake_program::stake_instruction::Entrypoint::vm,
+            )
+            .migrated_by(migrate_stake_program_to_core_bpf::id()),
+        ]
+    }
+
+    /// The vote program builtin, for vote account management: authorizations, commission
+    /// updates and withdrawals. It reads the Clock and SlotHashes sysvars.
+    pub fn vote() -> Self {
+        Self::new(
+            solana_sdk::vote::program::id(),
+            "vote_program",
+            solana_vote_program::vote_processor::Entrypoint::vm,
+        )
+    }
+
+    /// The config program builtin, which stores signed configuration data such as
+    /// validator info, until its migration to core BPF.
+    pub fn config() -> Self {
+        Self::new(
+            solana_sdk::config::program::id(),
+            "config_program",
+            solana_config_program::config_processor::Entrypoint::vm,
+        )
+        .migrated_by(migrate_config_program_to_core_bpf::id())
+    }
+
+    /// The loader-v4 builtin, which executes programs owned by it and their deploys,
+    /// upgrades and retractions. Only available with the `loader-v4` feature.
+    #[cfg(feature = "loader-v4")]
+    pub fn loader_v4() -> Self {
+        Self::new(
+            solana_sdk::loader_v4::id(),
+            "solana_loader_v4_program",
+            solana_loader_v4_program::Entrypoint::vm,
+        )
+    }
+
+    /// The ZK ElGamal proof program builtin, which verifies the zero-knowledge proofs of
+    /// Token-2022 confidential transfers. Transactions invoking it are rejected unless it
+    /// is registered. Only available with the `zk-elgamal-proof` feature.
+    #[cfg(feature = "zk-elgamal-proof")]
+    pub fn zk_elgamal_proof() -> Self {
+        Self::new(
+            solana_sdk_ids::zk_elgamal_proof_program::id(),
+            "zk_elgamal_proof_program",
+            solana_zk_elgamal_proof_program::Entrypoint::vm,
+        )
+    }
+}
+
+/// Step-by-step construction of the SVM's [`TransactionBatchProcessor`], the runtime a
+/// [`RollUpChannel`](crate::RollUpChannel) executes transactions with.
+///
+/// By default the processor executes at slot 1, since programs deployed in slot 0 aren't
+/// visible before slot 1, in epoch 1, with the program runtime environment of a feature
+/// set where every feature is active and the default compute budget, and with the
+/// [default builtins](Builtin::defaults).
+pub struct ProcessorBuilder<FG: ForkGraph> {
+    slot: Slot,
+    epoch: Epoch,
+    runtime_environment: Option<ProgramRuntimeEnvironment>,
+    runtime_environment_v2: Option<ProgramRuntimeEnvironment>,
+    compute_budget: ComputeBudget,
+    feature_set: Option<Arc<FeatureSet>>,
+    reject_broken_elfs: bool,
+    debugging_features: bool,
+    #[cfg(feature = "loader-v4")]
+    loader_v4: bool,
+    fork_graph: Arc<RwLock<FG>>,
+    default_builtins: bool,
+    builtins: Vec<Builtin>,
+}
+
+impl<FG: ForkGraph> ProcessorBuilder<FG> {
+    /// Starts a builder whose program cache consults `fork_graph`.
+    ///
+    /// The processor only keeps a weak reference to the fork graph: the caller must keep
+    /// it alive for as long as the processor is used.
+    pub fn new(fork_graph: Arc<RwLock<FG>>) -> Self {
+        Self {
+            slot: PROCESSOR_SLOT,
+            epoch: PROCESSOR_EPOCH,
+            runtime_environment: None,
+            runtime_environment_v2: None,
+            compute_budget: ComputeBudget::default(),
+            feature_set: None,
+            reject_broken_elfs: false,
+            debugging_features: false,
+            #[cfg(feature = "loader-v4")]
+            loader_v4: false,
+            fork_graph,
+            default_builtins: true,
+            builtins: Vec::new(),
+        }
+    }
+
+    /// Sets the slot the processor executes in.
+    ///
+    /// A program deployed in slot `d`, e.g. the `slot` of its ProgramData account, is
+    /// only visible from slot `d + 1` on: at slot `N`, programs deployed in slots before
+    /// `N` can be invoked, while those deployed in slot `N` itself fail as not deployed.
+    /// Builtins are added at slot 0 and visible from it, so they execute at any slot.
+    pub fn slot(mut self, slot: Slot) -> Self {
+        self.slot = slot;
+        self
+    }
+
+    /// Sets the epoch the processor executes in. It isn't derived from the slot, so
+    /// simulating an epoch boundary means setting both.
+    pub fn epoch(mut self, epoch: Epoch) -> Self {
+        self.epoch = epoch;
+        self
+    }
+
+    /// Sets the environment (syscalls and VM configuration) BPF programs are loaded and
+    /// executed with, e.g. one created for a specific feature set and compute budget.
+    pub fn runtime_environment(mut self, environment: ProgramRuntimeEnvironment) -> Self {
+        self.runtime_environment = Some(environment);
+        self
+    }
+
+    /// Sets the v2 program runtime environment, held by the program cache next to the
+    /// [v1 one](Self::runtime_environment). None by default.
+    pub fn runtime_environment_v2(mut self, environment: ProgramRuntimeEnvironment) -> Self {
+        self.runtime_environment_v2 = Some(environment);
+        self
+    }
+
+    /// Sets the compute budget the runtime environments are created for, which sizes
+    /// the VM: call depth, stack frames, and the like. The default budget otherwise.
+    ///
+    /// The units charged per syscall and instruction come from the budget transactions
+    /// execute with, see [`RollUpChannelConfig::compute_budget`](crate::RollUpChannelConfig::compute_budget).
+    /// Ignored with custom [runtime environments](Self::runtime_environment).
+    pub fn compute_budget(mut self, compute_budget: ComputeBudget) -> Self {
+        self.compute_budget = compute_budget;
+        self
+    }
+
+    /// Sets the features the processor follows: the v1 runtime environment is created
+    /// for them, and builtins [migrated to core BPF](Builtin::migrated_by) under an active
+    /// feature aren't registered, so the program's BPF version is loaded from its account,
+    /// as on a cluster where the migration happened.
+    ///
+    /// Without one, the environment is created for a feature set where every feature is
+    /// active, and every builtin is registered.
+    pub fn feature_set(mut self, feature_set: Arc<FeatureSet>) -> Self {
+        self.feature_set = Some(feature_set);
+        self
+    }
+
+    /// Rejects ELFs the cluster would refuse to deploy, e.g. with a text section whose
+    /// address doesn't match its offset, when programs are loaded, instead of executing
+    /// them like the cluster still does for programs deployed before the check existed.
+    ///
+    /// Disabled by default, like for programs already deployed on the cluster: enabling it
+    /// fails transactions invoking such programs. Ignored with a custom
+    /// [runtime environment](Self::runtime_environment).
+    pub fn reject_broken_elfs(mut self, reject: bool) -> Self {
+        self.reject_broken_elfs = reject;
+        self
+    }
+
+    /// Enables the VM's debugging features, instruction tracing and symbol labels, for
+    /// local tracing of program execution. They don't change which transactions pass, but
+    /// slow execution down.
+    ///
+    /// Disabled by default. Ignored with custom [runtime environments](Self::runtime_environment).
+    pub fn debugging_features(mut self, enabled: bool) -> Self {
+        self.debugging_features = enabled;
+        self
+    }
+
+    /// Registers the [loader-v4 builtin](Builtin::loader_v4), and a v2 runtime
+    /// environment for the [compute budget](Self::compute_budget) unless one was set.
+    ///
+    /// The program cache picks the environment of each program from the loader owning
+    /// it. Only available with the `loader-v4` feature.
+    #[cfg(feature = "loader-v4")]
+    pub fn loader_v4(mut self) -> Self {
+        self.loader_v4 = true;
+        self.builtins.push(Builtin::loader_v4());
+        self
+    }
+
+    /// Makes the processor's program cache consult another [`ForkGraph`], see
+    /// [`new`](Self::new).
+    pub fn fork_graph<G: ForkGraph>(self, fork_graph: Arc<RwLock<G>>) -> ProcessorBuilder<G> {
+        ProcessorBuilder {
+            slot: self.slot,
+            epoch: self.epoch,
+            runtime_environment: self.runtime_environment,
+            runtime_environment_v2: self.runtime_environment_v2,
+            compute_budget: self.compute_budget,
+            feature_set: self.feature_set,
+            reject_broken_elfs: self.reject_broken_elfs,
+            debugging_features: self.debugging_features,
+            #[cfg(feature = "loader-v4")]
+            loader_v4: self.loader_v4,
+            fork_graph,
+            default_builtins: self.default_builtins,
+            builtins: self.builtins,
+        }
+    }
+
+    /// Registers the builtin `program_id`, after the default builtins, replacing any
+    /// builtin registered before under the same id.
+    ///
+    /// `entrypoint` can implement any program, e.g. a mock; see [`Builtin`] for how to
+    /// declare one.
+    pub fn add_builtin(
+        mut self,
+        program_id: Pubkey,
+        name: impl Into<String>,
+        entrypoint: BuiltinFunctionWithContext,
+    ) -> Self {
+        self.builtins
+            .push(Builtin::new(program_id, name, entrypoint));
+        self
+    }
+
+    /// Registers `builtin`, e.g. [`Builtin::vote`], like [`add_builtin`](Self::add_builtin).
+    pub fn with_builtin(mut self, builtin: Builtin) -> Self {
+        self.builtins.push(builtin);
+        self
+    }
+
+    /// Leaves out the [default builtins](Builtin::defaults), so only those added with
+    /// [`add_builtin`](Self::add_builtin) are registered.
+    pub fn without_default_builtins(mut self) -> Self {
+        self.default_builtins = false;
+        self
+    }
+
+    /// Builds the processor, registering its builtins' accounts through `callbacks`.
+    pub fn build<CB: TransactionProcessingCallback>(
+        self,
+        callbacks: &CB,
+    ) -> TransactionBatchProcessor<FG> {
+        let feature_set = self.feature_set;
+        let runtime_environment = self.runtime_environment.unwrap_or_else(|| {
+            Arc::new(
+                create_program_runtime_environment_v1(
+                    feature_set.as_deref().unwrap_or(&FeatureSet::all_enabled()),
+                    &self.compute_budget,
+                    self.reject_broken_elfs,
+                    self.debugging_features,
+                )
+                .unwrap(),
+            )
+        });
+        #[cfg(feature = "loader-v4")]
+        let runtime_environment_v2 = self.runtime_environment_v2.or_else(|| {
+            self.loader_v4.then(|| {
+                Arc::new(create_program_runtime_environment_v2(
+                    &self.compute_budget,
+                    self.debugging_features,
+                ))
+            })
+        });
+        #[cfg(not(feature = "loader-v4"))]
+        let runtime_environment_v2 = self.runtime_environment_v2;
+        let processor = TransactionBatchProcessor::<FG>::new(
+            self.slot,
+            self.epoch,
+            Arc::downgrade(&self.fork_graph),
+            Some(runtime_environment),
+            runtime_environment_v2,
+        );
+
+        let defaults = if self.default_builtins {
+            Builtin::defaults()
+        } else {
+            Vec::new()
+        };
+        for builtin in defaults.into_iter().chain(self.builtins) {
+            let migrated = builtin
+                .core_bpf_migration_feature
+                .zip(feature_set.as_deref())
+                .is_some_and(|(feature_id, feature_set)| feature_set.is_active(&feature_id));
+            if migrated {
+                continue;
+            }
+            processor.add_builtin(
+                callbacks,
+                builtin.program_id,
+                &builtin.name,
+                ProgramCacheEntry::new_builtin(0, builtin.name.len(), builtin.entrypoint),
+            );
+        }
+        processor
+    }
+}
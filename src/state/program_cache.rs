@@ -0,0 +1,155 @@
+use std::cmp::Reverse;
+use std::time::{Duration, Instant};
+
+use solana_program_runtime::loaded_programs::{ForkGraph, ProgramCache, ProgramCacheEntryType};
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey::Pubkey;
+use solana_svm::program_loader::load_program_with_pubkey;
+use solana_svm::transaction_processor::TransactionBatchProcessor;
+
+use crate::error::ProgramLoadError;
+use crate::state::rollup_account_loader::RollUpAccountLoader;
+
+/// Bounds on the compiled programs a processor's program cache keeps, see
+/// [`RollUpChannelConfig::program_cache_limits`](crate::RollUpChannelConfig::program_cache_limits).
+///
+/// Builtins don't count towards the limits and are never evicted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ProgramCacheLimits {
+    /// Most compiled programs kept, `None` for no limit.
+    pub max_entries: Option<usize>,
+    /// Most bytes of program data, the ELFs programs were compiled from, kept compiled,
+    /// `None` for no limit.
+    pub max_bytes: Option<u64>,
+}
+
+impl ProgramCacheLimits {
+    /// Evicts compiled programs from `program_cache` until it is within the limits, the
+    /// least used ones, as of `slot`, first. Returns how many programs were evicted.
+    ///
+    /// An evicted program is loaded and compiled again the next time a transaction
+    /// invokes it.
+    pub fn evict<FG: ForkGraph>(&self, program_cache: &mut ProgramCache<FG>, slot: Slot) -> usize {
+        if self.max_entries.is_none() && self.max_bytes.is_none() {
+            return 0;
+        }
+        let mut entries = program_cache.get_flattened_entries(true, true);
+        entries.sort_by_key(|(_, entry)| Reverse(entry.decayed_usage_counter(slot)));
+
+        let mut kept = 0;
+        let mut kept_bytes = 0;
+        let mut evicted: Vec<Pubkey> = Vec::new();
+        for (program_id, entry) in entries {
+            let bytes = entry.account_size as u64;
+            let fits = self.max_entries.is_none_or(|max| kept < max)
+                && self.max_bytes.is_none_or(|max| kept_bytes + bytes <= max);
+            if fits {
+                kept += 1;
+                kept_bytes += bytes;
+            } else {
+                evicted.push(program_id);
+            }
+        }
+        evicted.sort_unstable();
+        evicted.dedup();
+        program_cache.remove_programs(evicted.iter().copied());
+        evicted.len()
+    }
+}
+
+/// Occupancy and effectiveness of a channel's program cache, as returned by
+/// [`RollUpChannel::program_cache_stats`](crate::RollUpChannel::program_cache_stats).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ProgramCacheStats {
+    /// Compiled programs held after the last batch.
+    pub entries: usize,
+    /// Bytes of program data they were compiled from.
+    pub bytes: u64,
+    /// Program lookups served by a compiled program, builtins included.
+    pub hits: u64,
+    /// Program lookups that had to load and compile the program.
+    pub misses: u64,
+    /// Compiled programs evicted to stay within the [`ProgramCacheLimits`].
+    pub evictions: u64,
+}
+
+/// Outcome of compiling one program ahead of time, see [`warm_up_programs`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgramWarmUp {
+    /// The program warmed up.
+    pub program_id: Pubkey,
+    /// Why the program couldn't be compiled, if it couldn't.
+    pub result: Result<(), ProgramLoadError>,
+    /// Time spent fetching and compiling it.
+    pub elapsed: Duration,
+}
+
+/// Fetches and compiles `program_ids` into the program cache of `processor`, so the
+/// batches it executes next find them compiled: their program lookups are hits in the
+/// cache's statistics, and no time is spent loading programs.
+///
+/// The program accounts are prefetched through `loader` in one request. Builtins are
+/// compiled in when the processor is built, so warming one up only checks it is
+/// registered. Programs are compiled for the cache's root slot, the processor's first
+/// slot, and stay in the cache of the processors derived from it.
+///
+/// Channels warm up their own processor with
+/// [`RollUpChannel::warm_up`](crate::RollUpChannel::warm_up).
+pub fn warm_up_programs<FG: ForkGraph>(
+    processor: &TransactionBatchProcessor<FG>,
+    loader: &RollUpAccountLoader,
+    program_ids: &[Pubkey],
+) -> Vec<ProgramWarmUp> {
+    loader.prefetch(program_ids);
+    let (slot, environments) = {
+        let program_cache = processor.program_cache.read().unwrap();
+        (
+            program_cache.latest_root_slot,
+            program_cache.environments.clone(),
+        )
+    };
+    let builtins = processor.builtin_program_ids.read().unwrap().clone();
+
+    program_ids
+        .iter()
+        .map(|program_id| {
+            let start = Instant::now();
+            let program_id = *program_id;
+            let result = if builtins.contains(&program_id) {
+                Ok(())
+            } else {
+                match load_program_with_pubkey(
+                    loader,
+                    &environments,
+                    &program_id,
+                    slot,
+                    &mut Default::default(),
+                    false,
+                ) {
+                    None => Err(ProgramLoadError::NotFound { program_id }),
+                    Some(entry) => match entry.program {
+                        ProgramCacheEntryType::FailedVerification(_) => {
+                            Err(ProgramLoadError::InvalidElf {
+                                program_id,
+                                message: "the program fails verification".to_string(),
+                            })
+                        }
+                        ProgramCacheEntryType::Closed | ProgramCacheEntryType::DelayVisibility => {
+                            Err(ProgramLoadError::NotAProgram { program_id })
+                        }
+                        _ => {
+                            let mut program_cache = processor.program_cache.write().unwrap();
+                            program_cache.assign_program(program_id, entry);
+                            Ok(())
+                        }
+                    },
+                }
+            };
+            ProgramWarmUp {
+                program_id,
+                result,
+                elapsed: start.elapsed(),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,131 @@
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Limits on the requests a [`RollUpAccountLoader`](crate::RollUpAccountLoader) sends to
+/// its source, for rate-limited RPC endpoints.
+///
+/// Both single and batched fetches count as one request each.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestLimits {
+    /// Requests in flight at once, across every thread using the loader. `None` for no limit.
+    pub max_in_flight: Option<usize>,
+    /// Requests started per second at most. `None` for no limit.
+    pub requests_per_second: Option<u32>,
+    /// How many times a throttled request (HTTP 429) is retried before it fails.
+    pub max_throttle_retries: u32,
+    /// Pause before the first retry of a throttled request, doubled for every next one.
+    ///
+    /// The pause applies to every request of the loader, not only the throttled one.
+    pub throttle_backoff: Duration,
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        Self {
+            max_in_flight: None,
+            requests_per_second: None,
+            max_throttle_retries: 3,
+            throttle_backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Enforces [`RequestLimits`] on the requests run through it.
+pub(crate) struct RequestLimiter {
+    limits: RequestLimits,
+    in_flight: Mutex<usize>,
+    released: Condvar,
+    /// Earliest moment the next request may start.
+    next_start: Mutex<Instant>,
+}
+
+impl RequestLimiter {
+    pub(crate) fn new(limits: RequestLimits) -> Self {
+        Self {
+            limits,
+            in_flight: Mutex::new(0),
+            released: Condvar::new(),
+            next_start: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Runs `request` once a slot is free and its turn came, retrying it after a backoff
+    /// while it fails with an error `is_throttled` accepts.
+    ///
+    /// `on_throttle` is called for every throttled attempt, including a final one.
+    pub(crate) fn run<T, E>(
+        &self,
+        mut request: impl FnMut() -> Result<T, E>,
+        is_throttled: impl Fn(&E) -> bool,
+        mut on_throttle: impl FnMut(),
+    ) -> Result<T, E> {
+        let mut backoff = self.limits.throttle_backoff;
+        let mut retries = 0;
+        loop {
+            let result = {
+                let _slot = self.acquire();
+                request()
+            };
+            match result {
+                Err(err) if is_throttled(&err) => {
+                    on_throttle();
+                    if retries == self.limits.max_throttle_retries {
+                        return Err(err);
+                    }
+                    retries += 1;
+                    self.pause(backoff);
+                    backoff = backoff.saturating_mul(2);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Waits for an in-flight slot, then for the request's turn.
+    fn acquire(&self) -> InFlight<'_> {
+        if let Some(max_in_flight) = self.limits.max_in_flight {
+            let mut in_flight = self
+                .released
+                .wait_while(self.in_flight.lock().unwrap(), |in_flight| {
+                    *in_flight >= max_in_flight.max(1)
+                })
+                .unwrap();
+            *in_flight += 1;
+        }
+
+        let interval = match self.limits.requests_per_second {
+            Some(requests_per_second) if requests_per_second > 0 => {
+                Duration::from_secs(1) / requests_per_second
+            }
+            _ => Duration::ZERO,
+        };
+        let start = {
+            let mut next_start = self.next_start.lock().unwrap();
+            let start = (*next_start).max(Instant::now());
+            *next_start = start + interval;
+            start
+        };
+        thread::sleep(start.saturating_duration_since(Instant::now()));
+
+        InFlight(self)
+    }
+
+    /// Holds every request back for `backoff` from now.
+    fn pause(&self, backoff: Duration) {
+        let mut next_start = self.next_start.lock().unwrap();
+        *next_start = (*next_start).max(Instant::now() + backoff);
+    }
+}
+
+/// An in-flight slot, released on drop.
+struct InFlight<'a>(&'a RequestLimiter);
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        if self.0.limits.max_in_flight.is_some() {
+            *self.0.in_flight.lock().unwrap() -= 1;
+            self.0.released.notify_one();
+        }
+    }
+}
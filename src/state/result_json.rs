@@ -0,0 +1,184 @@
+use base64::prelude::{Engine, BASE64_STANDARD};
+use serde_json::{json, Value};
+
+use crate::state::account_change::{AccountChange, AccountChangeKind};
+use crate::state::error_decoder::DecodedError;
+use crate::state::inner_instructions::InnerInstructionGroup;
+use crate::state::return_struct::TxStatus;
+use crate::ReturnStruct;
+
+/// Version of the schema below, bumped on any change that isn't a new field.
+pub const RESULT_JSON_SCHEMA_VERSION: u32 = 1;
+
+/// The results as a JSON object, `{"schemaVersion": 1, "results": [...]}`, each
+/// result as described in [`ReturnStruct::to_json`].
+pub fn results_to_json(results: &[ReturnStruct]) -> Value {
+    json!({
+        "schemaVersion": RESULT_JSON_SCHEMA_VERSION,
+        "results": results.iter().map(ReturnStruct::to_json).collect::<Vec<_>>(),
+    })
+}
+
+impl ReturnStruct {
+    /// The result as a JSON object meant for log pipelines and tools outside Rust, with
+    /// a schema kept stable across releases (see [`RESULT_JSON_SCHEMA_VERSION`]):
+    ///
+    /// - `signature`: string or `null`; `messageHash`: string.
+    /// - `status`: one of `executed_ok`, `executed_err`, `fees_only`, `not_processed`,
+    ///   `cancelled`; `success`: boolean.
+    /// - `computeUnits`, `feeLamports`: integers; `feeCharged`: boolean; `result`: string.
+    /// - `error`: the error as RPC nodes report `err`, e.g.
+    ///   `{"InstructionError": [0, {"Custom": 1}]}`, or `null`; `errorMessage`: its
+    ///   message, or `null`.
+    /// - `decodedError`: `{instructionIndex, programId, code, programName, errorName}`,
+    ///   the names being `null` when unknown, or `null`.
+    /// - `logs`: array of strings, `null` if not recorded.
+    /// - `returnData`: `{programId, data: [<base64>, "base64"]}` or `null`.
+    /// - `innerInstructions`: array of `{index, instructions}`, each instruction being
+    ///   `{programIdIndex, accounts, data, stackHeight}`, `null` if not recorded.
+    /// - `programsInvoked`, `writableAccountsModified`: arrays of strings;
+    ///   `deniedProgram`: string or `null`.
+    /// - `loadedAccountsCount`, `loadedAccountsDataSize` (bytes), `rentCollected`:
+    ///   integers; `rentDebits`: array of `{pubkey, lamports}`.
+    /// - `preBalances`, `postBalances`: arrays of integers.
+    /// - `accountChanges`: array of `{pubkey, kind, oldLamports, newLamports, oldDataLen,
+    ///   newDataLen, oldOwner, newOwner, oldDataHash, newDataHash, oldData, newData}`,
+    ///   `kind` being one of `created`, `closed`, `modified`, and the data `null` unless
+    ///   recorded.
+    /// - `requestedComputeUnitLimit`, `executionTimeMicros`: integers or `null`;
+    ///   `hitComputeLimit`: boolean.
+    /// - `context`: `{slot, blockhash, featureSetHash}`, `slot` possibly `null`, or `null`.
+    /// - `infrastructureError`: boolean.
+    ///
+    /// Keys and hashes are base58 strings; byte payloads (return data, instruction data,
+    /// account data) are base64 strings. The execution trace is left out, as it is
+    /// derived from `logs` and `innerInstructions`.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "signature": self.signature.map(|signature| signature.to_string()),
+            "messageHash": self.message_hash.to_string(),
+            "status": status_name(self.status),
+            "success": self.is_success(),
+            "computeUnits": self.cu,
+            "feeLamports": self.fee_lamports,
+            "feeCharged": self.fee_charged,
+            "result": self.result,
+            "error": self
+                .error
+                .as_ref()
+                .map(|error| serde_json::to_value(error).unwrap_or(Value::Null)),
+            "errorMessage": self.error.as_ref().map(|error| error.to_string()),
+            "decodedError": self.decoded_error.as_ref().map(decoded_error_json),
+            "logs": self.logs,
+            "returnData": self.return_data_json(),
+            "innerInstructions": self
+                .inner_instructions
+                .as_ref()
+                .map(|groups| {
+                    groups
+                        .iter()
+                        .map(inner_instruction_group_json)
+                        .collect::<Vec<_>>()
+                }),
+            "programsInvoked": self
+                .programs_invoked
+                .iter()
+                .map(|program_id| program_id.to_string())
+                .collect::<Vec<_>>(),
+            "deniedProgram": self.denied_program.map(|program_id| program_id.to_string()),
+            "loadedAccountsCount": self.loaded_accounts_count,
+            "loadedAccountsDataSize": self.loaded_accounts_data_size,
+            "rentCollected": self.rent_collected,
+            "rentDebits": self
+                .rent_debits
+                .iter()
+                .map(|(pubkey, lamports)| {
+                    json!({"pubkey": pubkey.to_string(), "lamports": lamports})
+                })
+                .collect::<Vec<_>>(),
+            "preBalances": self.pre_balances,
+            "postBalances": self.post_balances,
+            "accountChanges": self
+                .account_changes
+                .iter()
+                .map(account_change_json)
+                .collect::<Vec<_>>(),
+            "writableAccountsModified": self
+                .writable_accounts_modified
+                .iter()
+                .map(|pubkey| pubkey.to_string())
+                .collect::<Vec<_>>(),
+            "requestedComputeUnitLimit": self.requested_cu_limit,
+            "executionTimeMicros": self.execution_time.map(|time| time.as_micros() as u64),
+            "hitComputeLimit": self.hit_compute_limit,
+            "context": self.context.map(|context| json!({
+                "slot": context.slot,
+                "blockhash": context.blockhash.to_string(),
+                "featureSetHash": context.feature_set_hash.to_string(),
+            })),
+            "infrastructureError": self.infrastructure_error,
+        })
+    }
+
+    /// [`to_json`](Self::to_json), pretty-printed.
+    pub fn to_json_pretty(&self) -> String {
+        serde_json::to_string_pretty(&self.to_json()).unwrap_or_default()
+    }
+}
+
+fn status_name(status: TxStatus) -> &'static str {
+    match status {
+        TxStatus::ExecutedOk => "executed_ok",
+        TxStatus::ExecutedErr => "executed_err",
+        TxStatus::FeesOnly => "fees_only",
+        TxStatus::NotProcessed => "not_processed",
+        TxStatus::Cancelled => "cancelled",
+    }
+}
+
+fn decoded_error_json(decoded_error: &DecodedError) -> Value {
+    json!({
+        "instructionIndex": decoded_error.instruction_index,
+        "programId": decoded_error.program_id.to_string(),
+        "code": decoded_error.code,
+        "programName": decoded_error.program_name,
+        "errorName": decoded_error.error_name,
+    })
+}
+
+fn inner_instruction_group_json(group: &InnerInstructionGroup) -> Value {
+    json!({
+        "index": group.index,
+        "instructions": group
+            .instructions
+            .iter()
+            .map(|inner| json!({
+                "programIdIndex": inner.instruction.program_id_index,
+                "accounts": inner.instruction.accounts,
+                "data": BASE64_STANDARD.encode(&inner.instruction.data),
+                "stackHeight": inner.stack_height,
+            }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+fn account_change_json(change: &AccountChange) -> Value {
+    json!({
+        "pubkey": change.pubkey.to_string(),
+        "kind": match change.kind {
+            AccountChangeKind::Created => "created",
+            AccountChangeKind::Closed => "closed",
+            AccountChangeKind::Modified => "modified",
+        },
+        "oldLamports": change.old_lamports,
+        "newLamports": change.new_lamports,
+        "oldDataLen": change.old_data_len,
+        "newDataLen": change.new_data_len,
+        "oldOwner": change.old_owner.to_string(),
+        "newOwner": change.new_owner.to_string(),
+        "oldDataHash": change.old_data_hash.to_string(),
+        "newDataHash": change.new_data_hash.to_string(),
+        "oldData": change.old_data.as_ref().map(|data| BASE64_STANDARD.encode(data)),
+        "newData": change.new_data.as_ref().map(|data| BASE64_STANDARD.encode(data)),
+    })
+}
@@ -1,3 +1,26 @@
+use base64::prelude::{Engine, BASE64_STANDARD};
+use serde_json::{json, Value};
+use solana_client::rpc_response::RpcSimulateTransactionResult;
+use solana_sdk::clock::Slot;
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::{SanitizedTransaction, TransactionError};
+use solana_svm::transaction_processing_result::ProcessedTransaction;
+use solana_transaction_status_client_types::{UiReturnDataEncoding, UiTransactionReturnData};
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+use crate::state::account_change::AccountChange;
+use crate::state::error_decoder::DecodedError;
+use crate::state::execution_trace::{parse_invoke, ExecutionTrace};
+use crate::state::inner_instructions::InnerInstructionGroup;
+use crate::state::simulation_context::SimulationContext;
+
+/// Log lines the `Display` form of a failure ends with; the alternate form shows them all.
+const DISPLAYED_LOG_LINES: usize = 5;
+
 /// A simple struct that encapsulates the outcome of a simulated or real transaction execution.
 ///
 /// This is especially useful when working with local transaction simulation tools
@@ -5,40 +28,544 @@
 /// - Whether the transaction was successful
 /// - How many compute units were consumed
 /// - What the result or error message was
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ReturnStruct {
-    /// `true` if the transaction executed successfully without runtime errors.
-    pub success: bool,
+    /// The transaction's first signature, `None` if it isn't signed.
+    pub signature: Option<Signature>,
+    /// Hash of the transaction's message, which identifies it even when unsigned.
+    ///
+    /// Together with `signature`, this keys a result back to its transaction whatever
+    /// order results are handled in. Default only for [`no_results`](Self::no_results).
+    pub message_hash: Hash,
+    /// How far the transaction went through the runtime, see [`is_success`](Self::is_success).
+    pub status: TxStatus,
     /// The number of compute units consumed during execution.
     ///
-    /// This is only meaningful when `success == true`. On failure, this will be 0.
+    /// Set for every executed transaction, successful or not, in which case it counts the
+    /// units consumed up to the failure. `0` for fees-only, not processed and cancelled
+    /// transactions, which never executed.
     pub cu: u64,
-    /// A human-readable result message, used for debugging and logs.
-    /// Can contain either success details or an error description.
+    /// Lamports charged to the fee payer: the signature fee plus the prioritization fee.
+    ///
+    /// Also set for transactions that failed or were only charged fees, since the fee is
+    /// debited either way; `0` if the transaction was never processed.
+    ///
+    /// When the channel doesn't [charge fees](crate::RollUpChannelConfig::charge_fees),
+    /// the fee the transaction would have been charged, see [`fee_charged`](Self::fee_charged).
+    pub fee_lamports: u64,
+    /// `true` if `fee_lamports` was debited from the fee payer, `false` if the transaction
+    /// was never processed or the channel doesn't charge fees.
+    pub fee_charged: bool,
+    /// A human-readable summary of the outcome: success details or an error description.
+    ///
+    /// The program logs are in [`logs`](Self::logs), not here.
     pub result: String,
+    /// The error the transaction failed with, to match on instead of parsing `result`.
+    ///
+    /// Set for transactions that executed and failed, that were only charged fees, and that
+    /// the runtime refused to process. `None` on success, and for failures that have no
+    /// runtime equivalent: a middleware rejection or an account that couldn't be fetched.
+    pub error: Option<TransactionError>,
+    /// `error` traced back to the program that returned it, when it is a `Custom`
+    /// instruction error, named through the channel's [`ErrorDecoder`](crate::ErrorDecoder).
+    pub decoded_error: Option<DecodedError>,
+    /// Log messages the transaction's programs emitted, successful or not, when the
+    /// channel records them (see [`RollUpChannelConfig::record_trace`](crate::RollUpChannelConfig::record_trace))
+    /// and the transaction was executed.
+    pub logs: Option<Vec<String>>,
+    /// The program that last set return data, and that data, when the channel records it
+    /// (see [`RollUpChannelConfig::record_return_data`](crate::RollUpChannelConfig::record_return_data)).
+    ///
+    /// `None` if no program set any, or set it empty.
+    pub return_data: Option<(Pubkey, Vec<u8>)>,
+    /// Number of accounts the SVM's account loader loaded for this transaction,
+    /// including program accounts.
+    ///
+    /// `0` unless the transaction was executed: it was never processed, or its accounts
+    /// failed to load and only fees were charged.
+    pub loaded_accounts_count: u32,
+    /// Total size in bytes of the loaded account data, as computed by the SVM's
+    /// account loader. This is the value checked against the transaction's
+    /// loaded-accounts data size limit, i.e. what `SetLoadedAccountsDataSizeLimit` must
+    /// allow.
+    ///
+    /// `0` unless the transaction was executed, like `loaded_accounts_count`.
+    pub loaded_accounts_data_size: u32,
+    /// Lamports the runtime collected as rent from the transaction's accounts while
+    /// loading them, under the channel's [`rent`](crate::RollUpChannelConfig::rent).
+    ///
+    /// `0` unless the transaction was executed, like `loaded_accounts_count`. While the
+    /// feature disabling rent fee collection is active, as in every channel's feature
+    /// set, nothing is collected.
+    pub rent_collected: u64,
+    /// The accounts rent was collected from, with the lamports each one was debited, in
+    /// the order of the transaction's account keys.
+    pub rent_debits: Vec<(Pubkey, u64)>,
+    /// Lamports of every account of the transaction before it ran, in the order of
+    /// `getTransaction`'s `preBalances`: the message's static keys, then for v0 messages the
+    /// writable and the readonly addresses loaded from lookup tables.
+    ///
+    /// Empty if the transaction never reached the runtime, e.g. the blockhash couldn't be
+    /// resolved.
+    pub pre_balances: Vec<u64>,
+    /// Lamports of the same accounts after the transaction ran.
+    ///
+    /// For a failed transaction only the fee debit (and an advanced nonce) shows, as the
+    /// rest of its changes are rolled back.
+    pub post_balances: Vec<u64>,
+    /// Accounts whose state the transaction changed, in the order of its account keys.
+    ///
+    /// Like `post_balances`, a failed transaction only lists its fee payer (and an
+    /// advanced nonce).
+    pub account_changes: Vec<AccountChange>,
+    /// The accounts the transaction declared writable whose lamports or data it
+    /// changed, in the order of its account keys.
+    ///
+    /// Writable accounts missing from this list were locked for nothing and could be
+    /// passed as readonly. Empty unless the transaction succeeded.
+    pub writable_accounts_modified: Vec<Pubkey>,
+    /// Per-instruction execution trace, present when tracing is enabled on the channel
+    /// and the transaction was executed.
+    pub trace: Option<ExecutionTrace>,
+    /// Instructions invoked through CPI, grouped by top-level instruction, when the channel
+    /// records them (see [`RollUpChannelConfig::record_trace`](crate::RollUpChannelConfig::record_trace))
+    /// and the transaction was executed.
+    ///
+    /// `None` when not recorded; an empty list means the transaction made no CPI.
+    pub inner_instructions: Option<Vec<InnerInstructionGroup>>,
+    /// Every program that executed, top-level or through CPI, in the order they were
+    /// first invoked.
+    ///
+    /// Taken from the recorded inner instructions or logs; when neither is recorded, only
+    /// the top-level programs are known. A failed transaction lists the programs up to
+    /// the failing instruction. Empty unless the transaction was executed.
+    pub programs_invoked: Vec<Pubkey>,
+    /// The first program the transaction invoked that the channel's
+    /// [`ProgramFilter`](crate::ProgramFilter) refuses, if any.
+    ///
+    /// A denied top-level program kept the transaction from executing. One invoked through
+    /// CPI is only detected after execution: the transaction still ran, and its effects
+    /// are reported, and committed by blocks, as usual.
+    pub denied_program: Option<Pubkey>,
+    /// The compute unit limit the transaction executed under, derived from its
+    /// compute budget instructions (or the runtime default) during the pre-check.
+    pub requested_cu_limit: Option<u32>,
+    /// Wall-clock time the runtime took to load and execute the transaction, its
+    /// accounts already prefetched, when the channel times transactions (see
+    /// [`RollUpChannelConfig::record_execution_time`](crate::RollUpChannelConfig::record_execution_time)).
+    pub execution_time: Option<Duration>,
+    /// `true` if execution consumed the whole compute unit limit or failed with
+    /// `ComputationalBudgetExceeded`.
+    ///
+    /// In that case `cu` is a truncated number, not what the transaction actually needs.
+    pub hit_compute_limit: bool,
+    /// Slot every account prefetched for the transaction's batch was read at or after.
+    ///
+    /// `None` if the source doesn't report slots or the batch couldn't be kept consistent.
+    pub context_slot: Option<Slot>,
+    /// The slot, blockhash and features the transaction was simulated against.
+    ///
+    /// `None` if the channel couldn't set up its environment, e.g. the latest blockhash
+    /// couldn't be fetched.
+    pub context: Option<SimulationContext>,
+    /// `true` if the transaction failed for a reason unrelated to its content, e.g. the
+    /// RPC node timed out while one of its accounts was fetched.
+    ///
+    /// Such a failure says nothing about the transaction: retrying it may succeed.
+    pub infrastructure_error: bool,
+}
+
+/// What [`ReturnStruct::from_processed_transaction`] derives from a processed transaction
+/// besides its outcome.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConversionOptions {
+    /// Build an [`ExecutionTrace`] from the recorded logs and inner instructions.
+    pub record_trace: bool,
+}
+
+/// How far a transaction went through the runtime, see [`ReturnStruct::status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TxStatus {
+    /// Executed and succeeded.
+    ExecutedOk,
+    /// Executed and failed: only the fee was charged, the rest was rolled back.
+    ExecutedErr,
+    /// Charged fees without executing, because its accounts or programs couldn't be
+    /// loaded. Retrying it unchanged fails, and charges fees, again.
+    FeesOnly,
+    /// Stopped before being charged anything: refused by the runtime's checks, or its
+    /// accounts couldn't be fetched.
+    NotProcessed,
+    /// Not run on purpose: rejected by a middleware, or cut from a block that reached
+    /// its compute unit limit.
+    Cancelled,
+}
+
+impl TxStatus {
+    /// Whether the transaction went through the runtime and was charged a fee, or would
+    /// have been, see [`ReturnStruct::fee_charged`].
+    pub fn was_charged(&self) -> bool {
+        matches!(
+            self,
+            TxStatus::ExecutedOk | TxStatus::ExecutedErr | TxStatus::FeesOnly
+        )
+    }
 }
 
 impl ReturnStruct {
+    /// Shared base for the constructors below, with every detail field left empty.
+    fn new(status: TxStatus, cu: u64, result: String) -> Self {
+        Self {
+            signature: None,
+            message_hash: Hash::default(),
+            status,
+            cu,
+            fee_lamports: 0,
+            fee_charged: false,
+            result,
+            error: None,
+            decoded_error: None,
+            logs: None,
+            return_data: None,
+            loaded_accounts_count: 0,
+            loaded_accounts_data_size: 0,
+            rent_collected: 0,
+            rent_debits: Vec::new(),
+            pre_balances: Vec::new(),
+            post_balances: Vec::new(),
+            account_changes: Vec::new(),
+            writable_accounts_modified: Vec::new(),
+            trace: None,
+            inner_instructions: None,
+            programs_invoked: Vec::new(),
+            denied_program: None,
+            requested_cu_limit: None,
+            execution_time: None,
+            hit_compute_limit: false,
+            context_slot: None,
+            context: None,
+            infrastructure_error: false,
+        }
+    }
+
+    /// `true` if the transaction executed successfully without runtime errors, i.e.
+    /// [`status`](Self::status) is [`TxStatus::ExecutedOk`].
+    pub fn is_success(&self) -> bool {
+        self.status == TxStatus::ExecutedOk
+    }
+
     /// Construct a successful result with the given compute unit usage.
     ///
     /// The compute unit count helps benchmark cost and complexity.
     pub fn success(cu: u64) -> Self {
-        Self {
-            success: true,
+        let outcome = Outcome {
+            index: None,
+            status: TxStatus::ExecutedOk,
             cu,
-            result: format!(
-                "Transaction executed successfully with {} compute units",
-                cu
-            ),
-        }
+            error: None,
+            fee_charged: false,
+        };
+        Self::new(TxStatus::ExecutedOk, cu, outcome.message())
+    }
+
+    /// Construct a successful result with the given compute unit usage and the fee it was
+    /// charged, see [`fee_lamports`](Self::fee_lamports).
+    pub fn success_with_fee(cu: u64, fee_lamports: u64) -> Self {
+        let mut succeeded = Self::success(cu);
+        succeeded.fee_lamports = fee_lamports;
+        succeeded.fee_charged = true;
+        succeeded
     }
 
-    /// Construct a failed result with a specific error message.
+    /// Construct a failed result with a specific error message, for a transaction that
+    /// wasn't processed.
     pub fn failure(error: impl ToString) -> Self {
-        Self {
-            success: false,
+        Self::new(TxStatus::NotProcessed, 0, error.to_string())
+    }
+
+    /// Construct a failed result carrying the runtime's typed `error`, see [`error`](Self::error).
+    pub fn transaction_failure(error: TransactionError, message: impl ToString) -> Self {
+        let mut failed = Self::failure(message);
+        failed.error = Some(error);
+        failed
+    }
+
+    /// Construct the result of the transaction at `index` of its batch from what the SVM
+    /// reported for it.
+    ///
+    /// Executed transactions carry their compute units, loaded accounts, and whatever
+    /// was recorded during execution (logs, return data, inner instructions); fees-only
+    /// ones only their load error and fee. Every channel method converts its results
+    /// through this, and [`from_processing_error`](Self::from_processing_error).
+    pub fn from_processed_transaction(
+        index: usize,
+        processed_tx: &ProcessedTransaction,
+        options: ConversionOptions,
+    ) -> Self {
+        let mut tx_result = match processed_tx {
+            ProcessedTransaction::Executed(executed_tx) => {
+                let details = &executed_tx.execution_details;
+                let mut tx_result = match &details.status {
+                    Ok(()) => Self::success(details.executed_units),
+                    Err(err) => {
+                        let mut failed =
+                            Self::failed_transaction(index, err.clone(), TxStatus::ExecutedErr);
+                        failed.cu = details.executed_units;
+                        failed
+                    }
+                };
+                // Record what the SVM's account loader actually pulled in for this transaction.
+                tx_result.loaded_accounts_count =
+                    executed_tx.loaded_transaction.accounts.len() as u32;
+                tx_result.loaded_accounts_data_size =
+                    executed_tx.loaded_transaction.loaded_accounts_data_size;
+                tx_result.rent_collected = executed_tx.loaded_transaction.rent;
+                tx_result.rent_debits = executed_tx
+                    .loaded_transaction
+                    .accounts
+                    .iter()
+                    .map(|(pubkey, _)| {
+                        let debit = executed_tx
+                            .loaded_transaction
+                            .rent_debits
+                            .get_account_rent_debit(pubkey);
+                        (*pubkey, debit)
+                    })
+                    .filter(|(_, debit)| *debit > 0)
+                    .collect();
+                tx_result.logs = details.log_messages.clone();
+                tx_result.return_data = details
+                    .return_data
+                    .as_ref()
+                    .filter(|return_data| !return_data.data.is_empty())
+                    .map(|return_data| (return_data.program_id, return_data.data.clone()));
+                tx_result.inner_instructions = details
+                    .inner_instructions
+                    .as_ref()
+                    .map(InnerInstructionGroup::from_recorded);
+                if options.record_trace {
+                    tx_result.trace = Some(ExecutionTrace::from_execution(
+                        details.log_messages.as_deref().unwrap_or_default(),
+                        details.inner_instructions.as_ref(),
+                    ));
+                }
+                tx_result
+            }
+            // The transaction failed to load, so it reports no loaded accounts nor rent.
+            ProcessedTransaction::FeesOnly(fees_only) => {
+                Self::failed_transaction(index, fees_only.load_error.clone(), TxStatus::FeesOnly)
+            }
+        };
+        tx_result.fee_lamports = processed_tx.fee_details().total_fee();
+        tx_result.fee_charged = true;
+        tx_result
+    }
+
+    /// Records that the transaction at `index` of its batch went through the runtime
+    /// without paying `fee_lamports`, the fee it would have been charged.
+    pub(crate) fn waive_fee(&mut self, index: usize, fee_lamports: u64) {
+        self.fee_lamports = fee_lamports;
+        self.fee_charged = false;
+        if self.status == TxStatus::FeesOnly && self.error.is_some() {
+            self.result = self.outcome(Some(index)).message();
+        }
+    }
+
+    /// Construct the result of the transaction at `index` of its batch that the SVM
+    /// refused to process with `error`, see [`from_processed_transaction`](Self::from_processed_transaction).
+    pub fn from_processing_error(index: usize, error: &TransactionError) -> Self {
+        Self::failed_transaction(index, error.clone(), TxStatus::NotProcessed)
+    }
+
+    /// Construct the failed result of the transaction at `index` of its batch, with the
+    /// `result` message matching how far it went.
+    pub(crate) fn failed_transaction(
+        index: usize,
+        error: TransactionError,
+        status: TxStatus,
+    ) -> Self {
+        let message = Outcome {
+            index: Some(index),
+            status,
             cu: 0,
-            result: error.to_string(),
+            error: Some(&error),
+            fee_charged: status.was_charged(),
         }
+        .message();
+        let mut failed = Self::transaction_failure(error, message);
+        failed.status = status;
+        failed
+    }
+
+    /// The outcome this result reports, for the transaction at `index` of its batch.
+    fn outcome(&self, index: Option<usize>) -> Outcome<'_> {
+        Outcome {
+            index,
+            status: self.status,
+            cu: self.cu,
+            error: self.error.as_ref(),
+            fee_charged: self.fee_charged,
+        }
+    }
+
+    /// Records which transaction the result is for, see [`signature`](Self::signature) and
+    /// [`message_hash`](Self::message_hash).
+    pub(crate) fn identify(&mut self, tx: &SanitizedTransaction) {
+        self.signature =
+            Some(*tx.signature()).filter(|signature| *signature != Signature::default());
+        self.message_hash = *tx.message_hash();
+    }
+
+    /// Fills [`programs_invoked`](Self::programs_invoked) for the executed `tx`, from what
+    /// was recorded of its execution.
+    pub(crate) fn record_programs_invoked(&mut self, tx: &SanitizedTransaction) {
+        if !matches!(self.status, TxStatus::ExecutedOk | TxStatus::ExecutedErr) {
+            return;
+        }
+        let message = tx.message();
+        let account_keys = message.account_keys();
+        // Instructions after the failing one never ran.
+        let executed = match &self.error {
+            Some(TransactionError::InstructionError(index, _)) => usize::from(*index) + 1,
+            _ => usize::MAX,
+        };
+
+        let mut programs = Vec::new();
+        match (&self.inner_instructions, &self.logs) {
+            (Some(groups), _) => {
+                for (index, (program_id, _)) in message
+                    .program_instructions_iter()
+                    .enumerate()
+                    .take(executed)
+                {
+                    programs.push(*program_id);
+                    let invoked = groups
+                        .iter()
+                        .filter(|group| usize::from(group.index) == index)
+                        .flat_map(|group| &group.instructions);
+                    programs.extend(invoked.filter_map(|invoked| {
+                        account_keys
+                            .get(usize::from(invoked.instruction.program_id_index))
+                            .copied()
+                    }));
+                }
+            }
+            (None, Some(logs)) => {
+                programs.extend(
+                    logs.iter()
+                        .filter_map(|line| parse_invoke(line))
+                        .map(|(program_id, _)| program_id),
+                );
+            }
+            (None, None) => programs.extend(
+                message
+                    .program_instructions_iter()
+                    .take(executed)
+                    .map(|(program_id, _)| *program_id),
+            ),
+        }
+        let mut seen = HashSet::new();
+        programs.retain(|program_id| seen.insert(*program_id));
+        self.programs_invoked = programs;
+    }
+
+    /// Construct a failed result for a transaction that couldn't be simulated because of
+    /// the environment, e.g. an account fetch failure, see [`infrastructure_error`](Self::infrastructure_error).
+    pub fn infrastructure_failure(error: impl ToString) -> Self {
+        let mut failed = Self::failure(error);
+        failed.infrastructure_error = true;
+        failed
+    }
+
+    /// Compute units the transaction's limit allowed beyond what it consumed, `None` if
+    /// the limit is unknown (the transaction wasn't pre-checked, or its compute budget
+    /// instructions are invalid).
+    ///
+    /// A large headroom means the transaction requests, and pays priority fees for, more
+    /// compute than it needs.
+    pub fn headroom(&self) -> Option<u64> {
+        self.requested_cu_limit
+            .map(|limit| u64::from(limit).saturating_sub(self.cu))
+    }
+
+    /// The [`return_data`](Self::return_data) in the shape of `simulateTransaction`'s
+    /// `returnData`: `{"programId": "<base58>", "data": ["<base64>", "base64"]}`.
+    pub fn return_data_json(&self) -> Option<Value> {
+        self.return_data.as_ref().map(|(program_id, data)| {
+            json!({
+                "programId": program_id.to_string(),
+                "data": [BASE64_STANDARD.encode(data), "base64"],
+            })
+        })
+    }
+
+    /// The result in the shape of a `simulateTransaction` response, for code written
+    /// against remote simulation.
+    ///
+    /// `None` for failures the runtime has no error for, i.e. a middleware rejection or
+    /// an account that couldn't be fetched, since RPC nodes have no result to give for
+    /// them. Otherwise, the fields the local path can't fill are `None`:
+    /// - `logs` and `inner_instructions` unless the channel records them;
+    /// - `return_data` unless the channel records it, or if no program set any;
+    /// - `accounts`, as no addresses are requested; see [`account_changes`](Self::account_changes);
+    /// - `replacement_blockhash`, as no blockhash is ever replaced.
+    pub fn to_rpc_simulation_result(&self) -> Option<RpcSimulateTransactionResult> {
+        if !self.is_success() && self.error.is_none() {
+            return None;
+        }
+        Some(RpcSimulateTransactionResult {
+            err: self.error.clone(),
+            logs: self.logs.clone(),
+            accounts: None,
+            units_consumed: Some(self.cu),
+            return_data: self.return_data.as_ref().map(|(program_id, data)| {
+                UiTransactionReturnData {
+                    program_id: program_id.to_string(),
+                    data: (BASE64_STANDARD.encode(data), UiReturnDataEncoding::Base64),
+                }
+            }),
+            inner_instructions: self
+                .inner_instructions
+                .as_ref()
+                .map(|groups| groups.iter().map(InnerInstructionGroup::to_ui).collect()),
+            replacement_blockhash: None,
+        })
+    }
+
+    /// Renders a failure the way explorers show failed transactions, `None` on success.
+    ///
+    /// The `result` message and the error come first. Then, if logs were recorded, one
+    /// line per top-level instruction: the failed one, marked with `>`, is expanded into
+    /// its logs and nested invocations, while successful ones are kept to a single line.
+    /// Logs that can't be parsed into instructions are shown as they are, and without
+    /// logs only the error is shown. The alternate form of `Display` uses this.
+    pub fn render_failure(&self) -> Option<String> {
+        if self.is_success() {
+            return None;
+        }
+        let mut lines = vec![self.result.clone()];
+        match (&self.decoded_error, &self.error) {
+            (Some(decoded_error), _) => lines.push(format!("error: {}", decoded_error)),
+            (None, Some(error)) => lines.push(format!("error: {:?}", error)),
+            (None, None) => {}
+        }
+
+        let logs = self.logs.as_deref().unwrap_or_default();
+        // Prefer the channel's trace, which carries the recorded inner instructions.
+        let trace = match &self.trace {
+            Some(trace) => trace.clone(),
+            None => ExecutionTrace::from_execution(logs, None),
+        };
+        if !trace.instructions.is_empty() {
+            lines.push("instructions:".to_string());
+            lines.extend(trace.render_failure_path());
+        } else if !logs.is_empty() {
+            lines.push("logs:".to_string());
+            lines.extend(logs.iter().map(|line| format!("  {}", line)));
+        }
+        Some(lines.join("\n"))
     }
 
     /// Construct a result representing a missing or empty response.
@@ -46,10 +573,132 @@ impl ReturnStruct {
     /// It can occur when SVM engine doesn't return results—e.g.,
     /// due to a misconfigured processor, lack of transaction output, or internal error.
     pub fn no_results() -> Self {
-        Self {
-            success: false,
-            cu: 0,
-            result: "No transaction results returned".to_string(),
+        Self::new(
+            TxStatus::NotProcessed,
+            0,
+            "No transaction results returned".to_string(),
+        )
+    }
+}
+
+/// A one-line summary for successes, e.g. `ok, 4,213 CU, fee 5,000 lamports`, and for
+/// failures the same line followed by the `result` message, the typed error and the last
+/// log lines.
+///
+/// The alternate form, `{:#}`, renders failures with [`render_failure`](ReturnStruct::render_failure),
+/// and shows every log line of successes.
+impl Display for ReturnStruct {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let succeeded = self.is_success();
+        write!(f, "{}", self.outcome(None).summary(self.fee_lamports))?;
+        if f.alternate() {
+            if let Some(rendered) = self.render_failure() {
+                for line in rendered.lines() {
+                    write!(f, "\n  {}", line)?;
+                }
+                return Ok(());
+            }
+        }
+        if !succeeded {
+            write!(f, "\n  {}", self.result)?;
+            if let Some(error) = &self.error {
+                write!(f, "\n  error: {:?}", error)?;
+            }
+            if let Some(decoded_error) = &self.decoded_error {
+                write!(f, "\n  decoded: {}", decoded_error)?;
+            }
+        }
+
+        let logs = self.logs.as_deref().unwrap_or_default();
+        let shown = match (f.alternate(), succeeded) {
+            (true, _) => logs.len(),
+            (false, true) => 0,
+            (false, false) => logs.len().min(DISPLAYED_LOG_LINES),
+        };
+        if shown == 0 {
+            return Ok(());
+        }
+        if shown == logs.len() {
+            write!(f, "\n  logs:")?;
+        } else {
+            write!(f, "\n  logs (last {} of {}):", shown, logs.len())?;
+        }
+        for line in &logs[logs.len() - shown..] {
+            write!(f, "\n    {}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// How a transaction ended, described the same way by [`ReturnStruct::result`] and the
+/// summary line of its `Display` form.
+struct Outcome<'a> {
+    /// Position of the transaction in its batch, `None` if unknown.
+    index: Option<usize>,
+    status: TxStatus,
+    cu: u64,
+    error: Option<&'a TransactionError>,
+    fee_charged: bool,
+}
+
+impl Outcome<'_> {
+    /// The [`result`](ReturnStruct::result) message.
+    fn message(&self) -> String {
+        let error = match self.error {
+            Some(error) if self.status != TxStatus::ExecutedOk => error,
+            _ => {
+                return format!(
+                    "Transaction executed successfully with {} compute units",
+                    self.cu
+                )
+            }
+        };
+        let transaction = match self.index {
+            Some(index) => format!("Transaction {}", index),
+            None => "Transaction".to_string(),
+        };
+        match self.status {
+            TxStatus::ExecutedOk | TxStatus::ExecutedErr => {
+                format!("{} failed with error: {}", transaction, error)
+            }
+            TxStatus::FeesOnly if self.fee_charged => format!(
+                "{} failed with error: {}. Only fees were charged.",
+                transaction, error
+            ),
+            TxStatus::FeesOnly => format!(
+                "{} failed with error: {}. No fees were charged.",
+                transaction, error
+            ),
+            TxStatus::NotProcessed | TxStatus::Cancelled => {
+                format!("{} failed: {}", transaction, error)
+            }
+        }
+    }
+
+    /// The one-line summary, e.g. `ok, 4,213 CU, fee 5,000 lamports`.
+    fn summary(&self, fee_lamports: u64) -> String {
+        format!(
+            "{}, {} CU, fee {} lamports",
+            if self.status == TxStatus::ExecutedOk {
+                "ok"
+            } else {
+                "failed"
+            },
+            group_digits(self.cu),
+            group_digits(fee_lamports)
+        )
+    }
+}
+
+/// Formats `value` with a comma between every group of three digits, e.g. `4,213`.
+fn group_digits(value: u64) -> String {
+    let digits = value.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index) % 3 == 0 {
+            grouped.push(',');
         }
+        grouped.push(digit);
     }
+    grouped
 }
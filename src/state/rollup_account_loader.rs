@@ -1,23 +1,758 @@
+use serde_json::json;
+use solana_account_decoder_client_types::{
+    UiAccount, UiAccountData, UiAccountEncoding, UiDataSliceConfig,
+};
+use solana_client::client_error::{ClientError, ClientErrorKind, Result as ClientResult};
 use solana_client::rpc_client::RpcClient;
-use solana_sdk::account::ReadableAccount;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::RpcFilterType;
+use solana_client::rpc_request::{RpcError, RpcRequest};
+use solana_client::rpc_response::Response as RpcResponse;
+use solana_sdk::account::{
+    create_account_shared_data_with_fields, Account, ReadableAccount, INITIAL_RENT_EPOCH,
+};
+use solana_sdk::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
+use solana_sdk::clock::{Clock, Epoch, Slot};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::epoch_schedule::EpochSchedule;
+use solana_sdk::hash::hash;
+use solana_sdk::native_loader;
+use solana_sdk::rent::Rent;
+use solana_sdk::slot_hashes::{SlotHash, SlotHashes};
+use solana_sdk::stake_history::{StakeHistory, StakeHistoryEntry};
+use solana_sdk::sysvar::{self, Sysvar};
+use solana_sdk::transaction::SanitizedTransaction;
 use solana_sdk::{account::AccountSharedData, pubkey::Pubkey};
 use solana_svm::transaction_processing_callback::TransactionProcessingCallback;
-use std::collections::HashMap;
-use std::sync::RwLock;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 
-/// A lightweight account loader that retrieves account data from an RPC client,
+use crate::error::{AccountLoadError, ProgramAccountsError, SourceError};
+use crate::state::account_cache::{AccountCache, CacheEntry};
+use crate::state::account_fetcher::AccountFetcher;
+use crate::state::account_fixtures::{load_account_fixtures, parse_account_fixture};
+use crate::state::cache_inspection::{AccountLayer, CachedAccountInfo};
+use crate::state::disk_cache::DiskCache;
+use crate::state::fetch_latency::{FetchLatency, LatencyRecorder};
+use crate::state::request_limiter::{RequestLimiter, RequestLimits};
+use crate::state::snapshot_accounts::SnapshotAccounts;
+
+/// Maximum number of keys a single `getMultipleAccounts` request may ask for.
+pub(crate) const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// JSON-RPC error code of a request with an invalid parameter, e.g. an unsupported encoding.
+const INVALID_PARAMS: i64 = -32602;
+
+/// JSON-RPC error code of a node that hasn't reached the requested `minContextSlot` yet.
+const MIN_CONTEXT_SLOT_NOT_REACHED: i64 = -32016;
+
+/// HTTP status, and JSON-RPC error code of some providers, of a rate-limited request.
+const TOO_MANY_REQUESTS: i64 = 429;
+
+/// Accounts a `getProgramAccounts` query may return unless the loader says otherwise.
+const DEFAULT_MAX_PROGRAM_ACCOUNTS: usize = 10_000;
+
+/// Pause between two attempts at a request the node was too far behind to serve.
+const SLOT_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Where a [`RollUpAccountLoader`] reads accounts from when they aren't cached yet.
+///
+/// RPC is the default source of every channel constructor taking an `RpcClient`; any
+/// other backend can be plugged in by implementing [`AccountFetcher`].
+pub enum AccountSource<'a> {
+    /// Fetch accounts on demand through an RPC client.
+    Rpc(&'a RpcClient),
+    /// Serve accounts from a fixed, caller-supplied map.
+    ///
+    /// Any key not present in the map is treated as a missing account and no
+    /// network IO is ever performed, which makes this suitable for unit tests
+    /// and air-gapped tooling.
+    Static(HashMap<Pubkey, AccountSharedData>),
+    /// Read accounts from files on disk, see [`SnapshotAccounts`] for the layout.
+    Snapshot(SnapshotAccounts),
+    /// Ask the first source, then the second one for accounts the first doesn't have.
+    ///
+    /// Errors of the first source are reported as is, never retried on the second.
+    WithFallback(Box<AccountSource<'a>>, Box<AccountSource<'a>>),
+    /// Fetch accounts through a caller-supplied backend.
+    ///
+    /// Custom backends aren't asked for a commitment, a minimum slot or a data slice.
+    Custom(Box<dyn AccountFetcher + 'a>),
+}
+
+impl<'a> AccountSource<'a> {
+    /// Falls back to `fallback` for accounts this source doesn't have, e.g. an RPC
+    /// client for keys missing from a snapshot.
+    pub fn with_fallback(self, fallback: AccountSource<'a>) -> Self {
+        AccountSource::WithFallback(Box::new(self), Box::new(fallback))
+    }
+
+    /// A source reading accounts through `fetcher`, e.g. an indexer's database.
+    pub fn custom(fetcher: impl AccountFetcher + 'a) -> Self {
+        AccountSource::Custom(Box::new(fetcher))
+    }
+
+    /// Fetches a single account from the backing source.
+    ///
+    /// `Ok(None)` means the account doesn't exist; `Err` means the source couldn't tell.
+    fn fetch(
+        &self,
+        pubkey: &Pubkey,
+        config: FetchConfig,
+    ) -> Result<Fetched<Option<AccountSharedData>>, FetchError> {
+        match self {
+            AccountSource::Rpc(rpc_client) => {
+                let (response, zstd_rejected) = rpc_request(rpc_client, config, |rpc_config| {
+                    rpc_client.send::<RpcResponse<Option<UiAccount>>>(
+                        RpcRequest::GetAccountInfo,
+                        json!([pubkey.to_string(), rpc_config]),
+                    )
+                })?;
+                let mut fetched = Fetched::remote(None, response.context.slot, zstd_rejected);
+                fetched.value = response
+                    .value
+                    .map(|account| decode_account(account, &mut fetched.compression_saved))
+                    .transpose()?;
+                Ok(fetched)
+            }
+            AccountSource::Static(accounts) => Ok(Fetched::local(accounts.get(pubkey).cloned())),
+            AccountSource::Snapshot(snapshot) => snapshot
+                .get(pubkey)
+                .map(Fetched::local)
+                .map_err(|err| FetchError::Failed(err.to_string())),
+            AccountSource::WithFallback(primary, fallback) => {
+                let fetched = primary.fetch(pubkey, config)?;
+                if fetched.value.is_some() {
+                    return Ok(fetched);
+                }
+                fallback
+                    .fetch(pubkey, config)
+                    .map(|fallback_fetched| fetched.merge(fallback_fetched, |_, account| account))
+            }
+            AccountSource::Custom(fetcher) => Ok(Fetched::local(fetcher.fetch(pubkey)?)),
+        }
+    }
+
+    /// Fetches several accounts from the backing source in a single request.
+    ///
+    /// Returns one entry per key, in order, along with the slot the source read them
+    /// at if it reports one.
+    fn fetch_multiple(
+        &self,
+        pubkeys: &[Pubkey],
+        config: FetchConfig,
+    ) -> Result<Fetched<Vec<Option<AccountSharedData>>>, FetchError> {
+        match self {
+            AccountSource::Rpc(rpc_client) => {
+                let keys: Vec<String> = pubkeys.iter().map(Pubkey::to_string).collect();
+                let (response, zstd_rejected) = rpc_request(rpc_client, config, |rpc_config| {
+                    rpc_client.send::<RpcResponse<Vec<Option<UiAccount>>>>(
+                        RpcRequest::GetMultipleAccounts,
+                        json!([keys, rpc_config]),
+                    )
+                })?;
+                let mut fetched = Fetched::remote(Vec::new(), response.context.slot, zstd_rejected);
+                fetched.value = response
+                    .value
+                    .into_iter()
+                    .map(|account| {
+                        account
+                            .map(|account| decode_account(account, &mut fetched.compression_saved))
+                            .transpose()
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok(fetched)
+            }
+            AccountSource::Static(accounts) => Ok(Fetched::local(
+                pubkeys
+                    .iter()
+                    .map(|pubkey| accounts.get(pubkey).cloned())
+                    .collect(),
+            )),
+            AccountSource::Snapshot(snapshot) => pubkeys
+                .iter()
+                .map(|pubkey| snapshot.get(pubkey))
+                .collect::<io::Result<_>>()
+                .map(Fetched::local)
+                .map_err(|err| FetchError::Failed(err.to_string())),
+            AccountSource::WithFallback(primary, fallback) => {
+                let fetched = primary.fetch_multiple(pubkeys, config)?;
+                let (indexes, missing): (Vec<usize>, Vec<Pubkey>) = fetched
+                    .value
+                    .iter()
+                    .zip(pubkeys)
+                    .enumerate()
+                    .filter(|(_, (account, _))| account.is_none())
+                    .map(|(index, (_, pubkey))| (index, *pubkey))
+                    .unzip();
+                if missing.is_empty() {
+                    return Ok(fetched);
+                }
+                let found = fallback.fetch_multiple(&missing, config)?;
+                Ok(fetched.merge(found, |mut accounts, found| {
+                    for (index, account) in indexes.into_iter().zip(found) {
+                        accounts[index] = account;
+                    }
+                    accounts
+                }))
+            }
+            AccountSource::Custom(fetcher) => {
+                let accounts = fetcher.fetch_multiple(pubkeys)?;
+                if accounts.len() != pubkeys.len() {
+                    return Err(FetchError::Failed(format!(
+                        "source returned {} accounts for {} keys",
+                        accounts.len(),
+                        pubkeys.len()
+                    )));
+                }
+                Ok(Fetched::local(accounts))
+            }
+        }
+    }
+
+    /// A static source holding every account fixture of `dir`, in the format printed
+    /// by `solana account <PUBKEY> --output json`.
+    pub fn from_fixture_dir(dir: impl AsRef<Path>) -> io::Result<Self> {
+        load_account_fixtures(dir).map(AccountSource::Static)
+    }
+
+    /// A static source holding the given account fixtures, in the format printed by
+    /// `solana account <PUBKEY> --output json`.
+    pub fn from_fixtures<'j>(fixtures: impl IntoIterator<Item = &'j str>) -> io::Result<Self> {
+        fixtures
+            .into_iter()
+            .map(parse_account_fixture)
+            .collect::<io::Result<_>>()
+            .map(AccountSource::Static)
+    }
+
+    /// Returns the RPC client when the source is RPC-backed, or falls back to one.
+    pub fn rpc_client(&self) -> Option<&RpcClient> {
+        match self {
+            AccountSource::Rpc(rpc_client) => Some(rpc_client),
+            AccountSource::Static(_) | AccountSource::Snapshot(_) | AccountSource::Custom(_) => {
+                None
+            }
+            AccountSource::WithFallback(primary, fallback) => {
+                primary.rpc_client().or_else(|| fallback.rpc_client())
+            }
+        }
+    }
+}
+
+/// Request settings a [`RollUpAccountLoader`] applies to every fetch from its source.
+#[derive(Debug, Clone, Copy, Default)]
+struct FetchConfig {
+    /// Commitment for RPC requests, `None` to use the client's own.
+    commitment: Option<CommitmentConfig>,
+    /// Slot an RPC node must have reached to answer, `None` for any slot.
+    min_context_slot: Option<Slot>,
+    /// Ask an RPC node for the accounts' metadata only, with their data left empty.
+    metadata_only: bool,
+    /// Ask an RPC node for base64+zstd data rather than plain base64.
+    compress: bool,
+}
+
+impl FetchConfig {
+    fn commitment_for(&self, rpc_client: &RpcClient) -> CommitmentConfig {
+        self.commitment.unwrap_or_else(|| rpc_client.commitment())
+    }
+
+    /// The request's configuration for `rpc_client`, with data in `encoding`.
+    fn rpc_config(
+        &self,
+        rpc_client: &RpcClient,
+        encoding: UiAccountEncoding,
+    ) -> RpcAccountInfoConfig {
+        RpcAccountInfoConfig {
+            encoding: Some(encoding),
+            data_slice: self.metadata_only.then_some(UiDataSliceConfig {
+                offset: 0,
+                length: 0,
+            }),
+            commitment: Some(self.commitment_for(rpc_client)),
+            min_context_slot: self.min_context_slot,
+        }
+    }
+}
+
+/// A source's answer to a request.
+struct Fetched<T> {
+    value: T,
+    /// Slot the source read the accounts at, if it reports one.
+    slot: Option<Slot>,
+    /// Bytes base64+zstd saved on the wire over plain base64.
+    compression_saved: u64,
+    /// The RPC node rejected base64+zstd, so the request was answered in plain base64.
+    zstd_rejected: bool,
+}
+
+impl<T> Fetched<T> {
+    /// The answer of a source that doesn't go over the network.
+    fn local(value: T) -> Self {
+        Self {
+            value,
+            slot: None,
+            compression_saved: 0,
+            zstd_rejected: false,
+        }
+    }
+
+    /// The answer of an RPC node, read at `slot`.
+    fn remote(value: T, slot: Slot, zstd_rejected: bool) -> Self {
+        Self {
+            value,
+            slot: Some(slot),
+            compression_saved: 0,
+            zstd_rejected,
+        }
+    }
+
+    /// Combines the answers of a source and its fallback, with `merge` combining their values.
+    fn merge<U>(self, other: Fetched<U>, merge: impl FnOnce(T, U) -> T) -> Self {
+        Fetched {
+            value: merge(self.value, other.value),
+            slot: self.slot.or(other.slot),
+            compression_saved: self.compression_saved + other.compression_saved,
+            zstd_rejected: self.zstd_rejected || other.zstd_rejected,
+        }
+    }
+}
+
+/// Sends an account request to `rpc_client` through `send`, in base64+zstd if `config`
+/// asks for it, then again in plain base64 if the node rejects that encoding.
+///
+/// Returns the response and whether base64+zstd was rejected.
+fn rpc_request<T>(
+    rpc_client: &RpcClient,
+    config: FetchConfig,
+    send: impl Fn(RpcAccountInfoConfig) -> ClientResult<T>,
+) -> Result<(T, bool), FetchError> {
+    if !config.compress {
+        return Ok((
+            send(config.rpc_config(rpc_client, UiAccountEncoding::Base64))?,
+            false,
+        ));
+    }
+    match send(config.rpc_config(rpc_client, UiAccountEncoding::Base64Zstd)) {
+        Ok(response) => Ok((response, false)),
+        Err(err) if rejects_encoding(&err) => {
+            let response = send(config.rpc_config(rpc_client, UiAccountEncoding::Base64))?;
+            Ok((response, true))
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Whether `err` is a node refusing the requested account encoding.
+fn rejects_encoding(err: &ClientError) -> bool {
+    matches!(
+        err.kind(),
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code: INVALID_PARAMS, message, .. })
+            if message.to_lowercase().contains("encoding")
+    )
+}
+
+/// Decodes an account returned by an RPC node, adding to `compression_saved` what
+/// base64+zstd saved over plain base64 for it.
+fn decode_account(
+    account: UiAccount,
+    compression_saved: &mut u64,
+) -> Result<AccountSharedData, FetchError> {
+    let wire_len = match &account.data {
+        UiAccountData::Binary(blob, UiAccountEncoding::Base64Zstd) => Some(blob.len()),
+        _ => None,
+    };
+    let decoded: Account = account
+        .decode()
+        .ok_or_else(|| FetchError::Failed("could not decode account data".to_string()))?;
+    if let Some(wire_len) = wire_len {
+        let base64_len = decoded.data.len().div_ceil(3) * 4;
+        *compression_saved += base64_len.saturating_sub(wire_len) as u64;
+    }
+    Ok(decoded.into())
+}
+
+/// Why a source couldn't answer a request.
+enum FetchError {
+    /// The RPC node hasn't reached the request's `min_context_slot` yet.
+    Behind,
+    /// The RPC node rate-limited the request (HTTP 429).
+    Throttled(String),
+    /// The RPC node rejected the request itself, e.g. a disabled method or a query it
+    /// won't serve.
+    Refused(String),
+    /// Any other failure, described by its message.
+    Failed(String),
+}
+
+impl FetchError {
+    fn is_throttled(&self) -> bool {
+        matches!(self, FetchError::Throttled(_))
+    }
+}
+
+impl From<ClientError> for FetchError {
+    fn from(err: ClientError) -> Self {
+        match err.kind() {
+            ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                code: MIN_CONTEXT_SLOT_NOT_REACHED,
+                ..
+            }) => FetchError::Behind,
+            // Some providers report rate limiting as a JSON-RPC error rather than a status.
+            ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                code: TOO_MANY_REQUESTS,
+                ..
+            }) => FetchError::Throttled(err.to_string()),
+            ClientErrorKind::Reqwest(reqwest_err)
+                if reqwest_err
+                    .status()
+                    .is_some_and(|status| i64::from(status.as_u16()) == TOO_MANY_REQUESTS) =>
+            {
+                FetchError::Throttled(err.to_string())
+            }
+            ClientErrorKind::RpcError(RpcError::RpcResponseError { .. }) => {
+                FetchError::Refused(err.to_string())
+            }
+            _ => FetchError::Failed(err.to_string()),
+        }
+    }
+}
+
+impl From<SourceError> for FetchError {
+    fn from(err: SourceError) -> Self {
+        match err {
+            SourceError::Throttled(_) => FetchError::Throttled(err.to_string()),
+            SourceError::Failed(message) => FetchError::Failed(message),
+        }
+    }
+}
+
+impl Display for FetchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FetchError::Behind => write!(f, "node has not reached the minimum context slot"),
+            FetchError::Throttled(message)
+            | FetchError::Refused(message)
+            | FetchError::Failed(message) => {
+                write!(f, "{}", message)
+            }
+        }
+    }
+}
+
+/// How [`RollUpAccountLoader::prefetch`] keeps the accounts of its requests at one slot.
+///
+/// Every request after the first asks the node for the first response's slot as its
+/// `minContextSlot`, so no account is read at an older slot than the others. While the
+/// node is behind that slot the request is retried, until `deadline`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotConsistency {
+    /// How long a request is retried while the node is behind.
+    pub deadline: Duration,
+    /// Once `deadline` passed, fail the request's keys with
+    /// [`AccountLoadError::Inconsistent`] instead of reading them at whatever slot the
+    /// node is at.
+    pub strict: bool,
+}
+
+impl Default for SlotConsistency {
+    fn default() -> Self {
+        Self {
+            deadline: Duration::from_secs(1),
+            strict: false,
+        }
+    }
+}
+
+/// How a [`RollUpAccountLoader`] asks an RPC source to encode account data.
+///
+/// Whatever the encoding, a node rejecting base64+zstd is asked for plain base64
+/// instead, and never asked for base64+zstd again by that loader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataEncoding {
+    /// Plain base64, cheapest for small accounts.
+    Base64,
+    /// base64 of the zstd-compressed data, which shrinks large accounts dramatically.
+    Base64Zstd,
+    /// base64+zstd for requests expected to return at least `threshold` bytes of data,
+    /// plain base64 for smaller ones.
+    ///
+    /// The expected size of an account is its size when last fetched; accounts never
+    /// fetched are assumed to be small, so a cold fetch asks for plain base64.
+    Adaptive { threshold: usize },
+}
+
+impl Default for DataEncoding {
+    fn default() -> Self {
+        DataEncoding::Adaptive { threshold: 1024 }
+    }
+}
+
+/// What a [`RollUpAccountLoader`] does when its source reports an account doesn't exist.
+///
+/// Whatever the policy, a source that fails to answer (e.g. an RPC transport error) is
+/// never mistaken for a missing account: transactions referencing that account fail
+/// with [`AccountLoadError::Transport`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum MissingAccountPolicy {
+    /// Let the SVM see the account as not found, like a validator would.
+    #[default]
+    TreatAsMissing,
+    /// Fail every transaction referencing the account with [`AccountLoadError::Missing`].
+    Error,
+    /// Serve a copy of the given account instead, e.g. an empty system account.
+    Synthesize(AccountSharedData),
+}
+
+/// Values a [`RollUpAccountLoader`] synthesizes the Clock, Rent, EpochSchedule,
+/// SlotHashes and StakeHistory sysvar accounts from when its source doesn't have them,
+/// e.g. a static or snapshot source.
+///
+/// Sysvars the source has, like every sysvar of an RPC node, are served as fetched.
+/// Whatever the [`MissingAccountPolicy`], a missing sysvar is synthesized.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SysvarDefaults {
+    pub clock: Clock,
+    pub rent: Rent,
+    pub epoch_schedule: EpochSchedule,
+    /// Entries of the SlotHashes sysvar, e.g. the recent slots lookup tables are created
+    /// for.
+    pub slot_hashes: Vec<SlotHash>,
+    /// Entries of the StakeHistory sysvar, which stake warmup and cooldown are computed
+    /// from.
+    pub stake_history: Vec<(Epoch, StakeHistoryEntry)>,
+}
+
+impl SysvarDefaults {
+    /// The sysvar account `pubkey` holds, if it is one of the synthesized sysvars.
+    pub(crate) fn account(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        fn create<S: Sysvar>(sysvar: &S, rent: &Rent) -> AccountSharedData {
+            let lamports = rent.minimum_balance(S::size_of()).max(1);
+            create_account_shared_data_with_fields(sysvar, (lamports, INITIAL_RENT_EPOCH))
+        }
+        if sysvar::clock::check_id(pubkey) {
+            Some(create(&self.clock, &self.rent))
+        } else if sysvar::rent::check_id(pubkey) {
+            Some(create(&self.rent, &self.rent))
+        } else if sysvar::epoch_schedule::check_id(pubkey) {
+            Some(create(&self.epoch_schedule, &self.rent))
+        } else if sysvar::slot_hashes::check_id(pubkey) {
+            Some(create(&SlotHashes::new(&self.slot_hashes), &self.rent))
+        } else if sysvar::stake_history::check_id(pubkey) {
+            let mut stake_history = StakeHistory::default();
+            for (epoch, entry) in &self.stake_history {
+                stake_history.add(*epoch, entry.clone());
+            }
+            Some(create(&stake_history, &self.rent))
+        } else {
+            None
+        }
+    }
+}
+
+/// Number of requests a [`RollUpAccountLoader`] sent to its [`AccountSource`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FetchStats {
+    /// Requests for a single account, i.e. `getAccountInfo` calls for an RPC source.
+    pub single_requests: u64,
+    /// Requests for up to 100 accounts at once, i.e. `getMultipleAccounts` calls.
+    pub batch_requests: u64,
+}
+
+/// Outcome of [`RollUpAccountLoader::prefetch`], each key appearing in exactly one list.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PrefetchReport {
+    /// Keys fetched from the source and now cached.
+    pub fetched: Vec<Pubkey>,
+    /// Keys that were already cached or overridden, so they weren't requested.
+    pub cached: Vec<Pubkey>,
+    /// Keys the source reported, or recently reported, as nonexistent.
+    pub missing: Vec<Pubkey>,
+    /// Keys whose request failed.
+    pub failed: Vec<AccountLoadError>,
+    /// Slot every fetched account was read at or after, when the source reports slots.
+    ///
+    /// `None` as well if some request had to give up on consistency, see [`SlotConsistency`].
+    pub context_slot: Option<Slot>,
+}
+
+/// Cache effectiveness counters of a [`RollUpAccountLoader`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheStats {
+    /// Lookups served by an override or a fresh cache entry.
+    pub hits: u64,
+    /// Lookups that had to go to the source.
+    pub misses: u64,
+    /// Lookups and prefetched keys served by an account an earlier run persisted, see
+    /// [`RollUpAccountLoader::with_disk_cache`]. Lookups among them count as hits too.
+    pub disk_hits: u64,
+    /// Lookups that waited for a concurrent lookup of the same account to fetch it,
+    /// instead of sending their own request.
+    pub coalesced: u64,
+    /// Lookups of accounts recently found missing, answered without going to the
+    /// source, see [`RollUpAccountLoader::with_negative_ttl`].
+    pub negative_hits: u64,
+    /// Requests the source failed to answer, single or batched.
+    pub rpc_errors: u64,
+    /// Requests the source rate-limited, whether a retry succeeded or not.
+    pub throttled: u64,
+    /// Bytes of account data base64+zstd saved on the wire over plain base64, see
+    /// [`DataEncoding`].
+    pub compression_saved_bytes: u64,
+    /// Cache entries dropped because they expired, were invalidated, or were the least
+    /// recently used of a cache over its size bound.
+    pub evictions: u64,
+    /// Account data bytes currently held by the [`AccountCache`], overrides and committed
+    /// state excluded.
+    ///
+    /// This is a gauge of the cache's current size, so resetting the stats keeps it. A
+    /// shared cache reports the accounts fetched by every loader using it.
+    pub bytes_cached: u64,
+    /// Latency and response size of the requests sent to the source.
+    pub latency: FetchLatency,
+}
+
+/// Accounts the SVM requested from a [`RollUpAccountLoader`] while executing, as opposed
+/// to the accounts the transactions declare.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccessedAccounts {
+    /// Every key looked up through the processing callbacks.
+    pub reads: HashSet<Pubkey>,
+    /// Keys whose post-execution state the channel committed, a subset of `reads`.
+    pub writes: HashSet<Pubkey>,
+}
+
+/// Accounts layered over the cache, shared with the snapshots taken of the loader until
+/// one of them changes.
+type Layer<V> = RwLock<Arc<HashMap<Pubkey, V>>>;
+
+/// Applies `f` to the layer's accounts, copying them first if a snapshot shares them.
+fn update<V: Clone, R>(layer: &Layer<V>, f: impl FnOnce(&mut HashMap<Pubkey, V>) -> R) -> R {
+    f(Arc::make_mut(&mut layer.write().unwrap()))
+}
+
+/// Removes `pubkey` from the layer, without copying it if the key isn't there.
+fn remove<V: Clone>(layer: &Layer<V>, pubkey: &Pubkey) -> Option<V> {
+    let mut accounts = layer.write().unwrap();
+    if !accounts.contains_key(pubkey) {
+        return None;
+    }
+    Arc::make_mut(&mut accounts).remove(pubkey)
+}
+
+/// The state of a [`RollUpAccountLoader`] at one point, to get back to with
+/// [`RollUpAccountLoader::restore`].
+///
+/// Taking one is cheap: the snapshot shares the loader's accounts until either side
+/// changes them.
+#[derive(Clone)]
+pub struct LoaderSnapshot {
+    cache: Arc<HashMap<Pubkey, CacheEntry>>,
+    committed: Arc<HashMap<Pubkey, AccountSharedData>>,
+    overrides: Arc<HashMap<Pubkey, AccountSharedData>>,
+    known_missing: Arc<HashMap<Pubkey, Instant>>,
+}
+
+/// Accumulates the outcome of a prefetch spanning several requests.
+#[derive(Default)]
+struct PrefetchProgress {
+    report: PrefetchReport,
+    /// Slot of the first response, which later requests must not read behind.
+    context_slot: Option<Slot>,
+    /// Some response was read behind `context_slot`.
+    inconsistent: bool,
+}
+
+impl PrefetchProgress {
+    fn finish(mut self) -> PrefetchReport {
+        self.report.context_slot = self.context_slot.filter(|_| !self.inconsistent);
+        self.report
+    }
+}
+
+/// A lightweight account loader that retrieves account data from an [`AccountSource`],
 /// with a built-in in-memory cache for fast repeated access during transaction simulation.
 ///
 /// This struct is intended to be used with the SVM's `TransactionBatchProcessor` by
 /// implementing the `TransactionProcessingCallback` trait.
 ///
-/// It avoids redundant RPC calls by caching account data locally in a thread-safe
-/// `RwLock<HashMap<...>>`.
+/// It avoids redundant RPC calls by caching fetched accounts in an [`AccountCache`],
+/// which several loaders may share. Accounts fetched from the source are cached forever
+/// unless a TTL is set with [`with_ttl`](Self::with_ttl); state written by the channel
+/// itself (committed results, synthesized sysvars) is kept apart from the cache, private
+/// to this loader, and only dropped by an explicit invalidation.
 pub struct RollUpAccountLoader<'a> {
-    /// A local, thread-safe cache of account data by Pubkey.
-    cache: RwLock<HashMap<Pubkey, AccountSharedData>>,
-    // Reference to the RPC client used to fetch uncached accounts.
-    rpc_client: &'a RpcClient,
+    /// Accounts fetched from the source, possibly shared with other loaders.
+    cache: AccountCache,
+    /// State committed or synthesized by the channel, served ahead of the cache.
+    committed: Layer<AccountSharedData>,
+    /// User-supplied accounts served ahead of both the cache and the source.
+    overrides: Layer<AccountSharedData>,
+    /// Accounts the source reported missing, until when they are assumed to still be.
+    known_missing: Layer<Instant>,
+    /// How long an account found missing is assumed to stay missing, `None` to always ask.
+    negative_ttl: Option<Duration>,
+    // Backing source used to fetch uncached accounts.
+    source: AccountSource<'a>,
+    /// How accounts the source doesn't have are handled.
+    missing_account_policy: MissingAccountPolicy,
+    /// Sysvars served when the source doesn't have them.
+    sysvar_defaults: SysvarDefaults,
+    /// Commitment for RPC fetches, `None` to use the client's own.
+    commitment: Option<CommitmentConfig>,
+    /// How prefetch requests are kept at a single slot.
+    slot_consistency: SlotConsistency,
+    /// Fetch readonly, non-program accounts without their data in `prefetch_transactions`.
+    data_slicing: bool,
+    /// Accounts always fetched with their data, whatever `data_slicing` says.
+    full_data_keys: RwLock<HashSet<Pubkey>>,
+    /// How RPC sources are asked to encode account data.
+    data_encoding: DataEncoding,
+    /// The RPC node rejected base64+zstd, so plain base64 is asked for from now on.
+    zstd_rejected: AtomicBool,
+    /// Accounts a `getProgramAccounts` query may return.
+    max_program_accounts: usize,
+    /// Where fetched accounts are persisted for later runs, if anywhere.
+    disk_cache: Option<DiskCache>,
+    /// Newest slot a source response reported, 0 before any.
+    last_slot: AtomicU64,
+    /// Keys requested and committed since the last reset.
+    accessed: Mutex<AccessedAccounts>,
+    /// Activated stake of every vote account this epoch, once configured or fetched.
+    epoch_stakes: RwLock<Option<Arc<HashMap<Pubkey, u64>>>>,
+    /// Paces the requests sent to the source.
+    limiter: RequestLimiter,
+    /// Number of single-account requests sent to the source.
+    single_requests: AtomicU64,
+    /// Number of multiple-account requests sent to the source.
+    batch_requests: AtomicU64,
+    /// Lookups served without going to the source.
+    hits: AtomicU64,
+    /// Lookups that went to the source.
+    misses: AtomicU64,
+    /// Lookups and prefetched keys served by an account an earlier run persisted.
+    disk_hits: AtomicU64,
+    /// Lookups that shared a concurrent lookup's fetch.
+    coalesced: AtomicU64,
+    /// Lookups answered by a negative cache entry.
+    negative_hits: AtomicU64,
+    /// Requests the source failed to answer.
+    rpc_errors: AtomicU64,
+    /// Requests the source rate-limited.
+    throttled: AtomicU64,
+    /// Bytes base64+zstd saved on the wire.
+    compression_saved: AtomicU64,
+    /// Cache entries dropped because they expired or were invalidated.
+    evictions: AtomicU64,
+    /// Latency of the requests sent to the source.
+    latency: LatencyRecorder,
 }
 
 impl<'a> RollUpAccountLoader<'a> {
@@ -26,9 +761,1159 @@ impl<'a> RollUpAccountLoader<'a> {
     /// This loader will attempt to cache all accounts retrieved, making it efficient
     /// for use in high-frequency local simulations.
     pub fn new(rpc_client: &'a RpcClient) -> Self {
+        Self::with_source(AccountSource::Rpc(rpc_client))
+    }
+
+    /// Create a new account loader backed by an arbitrary [`AccountSource`].
+    pub fn with_source(source: AccountSource<'a>) -> Self {
         Self {
-            cache: RwLock::new(HashMap::new()),
-            rpc_client,
+            cache: AccountCache::new(),
+            committed: Layer::default(),
+            overrides: Layer::default(),
+            known_missing: Layer::default(),
+            negative_ttl: None,
+            source,
+            missing_account_policy: MissingAccountPolicy::default(),
+            sysvar_defaults: SysvarDefaults::default(),
+            commitment: None,
+            slot_consistency: SlotConsistency::default(),
+            data_slicing: false,
+            full_data_keys: RwLock::new(HashSet::new()),
+            data_encoding: DataEncoding::default(),
+            zstd_rejected: AtomicBool::new(false),
+            max_program_accounts: DEFAULT_MAX_PROGRAM_ACCOUNTS,
+            disk_cache: None,
+            last_slot: AtomicU64::new(0),
+            accessed: Mutex::new(AccessedAccounts::default()),
+            epoch_stakes: RwLock::new(None),
+            limiter: RequestLimiter::new(RequestLimits::default()),
+            single_requests: AtomicU64::new(0),
+            batch_requests: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            disk_hits: AtomicU64::new(0),
+            coalesced: AtomicU64::new(0),
+            negative_hits: AtomicU64::new(0),
+            rpc_errors: AtomicU64::new(0),
+            throttled: AtomicU64::new(0),
+            compression_saved: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+            latency: LatencyRecorder::default(),
+        }
+    }
+
+    /// Expire accounts fetched from the source `ttl` after they were fetched, so the
+    /// next lookup refetches them.
+    ///
+    /// This gives the loader a fresh, unshared cache, with the same size bound; pass a
+    /// cache created with [`AccountCache::with_ttl`] to [`with_cache`](Self::with_cache) to
+    /// share one instead.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        let cache = AccountCache::with_ttl(ttl);
+        self.cache = match self.cache.max_bytes() {
+            Some(max_bytes) => cache.with_max_bytes(max_bytes),
+            None => cache,
+        };
+        self
+    }
+
+    /// Bound the account data of the cache to `max_bytes`, evicting the least recently
+    /// used accounts beyond it, see [`AccountCache::with_max_bytes`].
+    ///
+    /// Overrides and committed state aren't part of the cache, so they are never evicted.
+    pub fn with_max_cache_bytes(mut self, max_bytes: u64) -> Self {
+        self.cache = self.cache.with_max_bytes(max_bytes);
+        self
+    }
+
+    /// Cache fetched accounts in `cache`, shared with every loader holding a clone of it.
+    pub fn with_cache(mut self, cache: AccountCache) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// The cache holding the accounts fetched by this loader, e.g. to share it with
+    /// another loader.
+    pub fn cache(&self) -> &AccountCache {
+        &self.cache
+    }
+
+    /// Remember accounts the source doesn't have for `ttl`, so repeated lookups, e.g. of
+    /// a PDA a batch creates, don't go back to the source every time.
+    ///
+    /// The negative entry is dropped as soon as the account gets an override, is
+    /// committed by the channel, or is invalidated.
+    pub fn with_negative_ttl(mut self, ttl: Duration) -> Self {
+        self.negative_ttl = Some(ttl);
+        self
+    }
+
+    /// Pace the requests sent to the source according to `limits`, e.g. for a
+    /// rate-limited public RPC endpoint.
+    pub fn with_request_limits(mut self, limits: RequestLimits) -> Self {
+        self.limiter = RequestLimiter::new(limits);
+        self
+    }
+
+    /// Synthesize the Clock, Rent, EpochSchedule, SlotHashes and StakeHistory sysvars from
+    /// `defaults` when the source doesn't have them.
+    pub fn with_sysvar_defaults(mut self, defaults: SysvarDefaults) -> Self {
+        self.sysvar_defaults = defaults;
+        self
+    }
+
+    /// Read accounts the source doesn't have from `fallback`, see
+    /// [`AccountSource::with_fallback`].
+    pub fn with_fallback_source(mut self, fallback: AccountSource<'a>) -> Self {
+        let source = std::mem::replace(&mut self.source, AccountSource::Static(HashMap::new()));
+        self.source = source.with_fallback(fallback);
+        self
+    }
+
+    /// Handle accounts the source doesn't have according to `policy`.
+    pub fn with_missing_account_policy(mut self, policy: MissingAccountPolicy) -> Self {
+        self.missing_account_policy = policy;
+        self
+    }
+
+    /// Fetch accounts from an RPC source at `commitment` instead of the client's own,
+    /// e.g. `finalized` reads from a client that sends at `confirmed`.
+    pub fn with_commitment(mut self, commitment: CommitmentConfig) -> Self {
+        self.commitment = Some(commitment);
+        self
+    }
+
+    /// Keep the requests of a prefetch at one slot according to `consistency`.
+    pub fn with_slot_consistency(mut self, consistency: SlotConsistency) -> Self {
+        self.slot_consistency = consistency;
+        self
+    }
+
+    /// Let [`prefetch_transactions`](Self::prefetch_transactions) fetch the accounts a
+    /// batch only reads, and which aren't programs, without their data.
+    ///
+    /// Such accounts are then served with empty data, which is enough for owner checks
+    /// and fee payer validation but not for a program reading them: mark those with
+    /// [`require_full_data`](Self::require_full_data). Only RPC sources honor slicing.
+    pub fn with_data_slicing(mut self, enabled: bool) -> Self {
+        self.data_slicing = enabled;
+        self
+    }
+
+    /// Serve `stakes` as the activated stake of every vote account this epoch, instead of
+    /// fetching them from an RPC source, e.g. for offline sources.
+    pub fn with_epoch_stakes(self, stakes: HashMap<Pubkey, u64>) -> Self {
+        *self.epoch_stakes.write().unwrap() = Some(Arc::new(stakes));
+        self
+    }
+
+    /// Ask RPC sources for account data in `encoding`.
+    pub fn with_data_encoding(mut self, encoding: DataEncoding) -> Self {
+        self.data_encoding = encoding;
+        self
+    }
+
+    /// Persist fetched accounts in `dir`, and serve accounts persisted there by earlier
+    /// runs instead of fetching them again, as long as they are within the cache's TTL.
+    ///
+    /// Accounts read from the disk were fetched at whatever slot the earlier run was at,
+    /// so a batch mixing them with freshly fetched accounts may see several slots.
+    /// Invalidated accounts are deleted from the disk too. The directory is created on the
+    /// first write; I/O errors and corrupt files are ignored, the accounts concerned being
+    /// fetched from the source instead.
+    pub fn with_disk_cache(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.disk_cache = Some(DiskCache::new(dir));
+        self
+    }
+
+    /// The directory fetched accounts are persisted in, if any.
+    pub fn disk_cache_dir(&self) -> Option<&Path> {
+        self.disk_cache.as_ref().map(DiskCache::dir)
+    }
+
+    /// Fail [`prefetch_program_accounts`](Self::prefetch_program_accounts) queries matching
+    /// more than `limit` accounts, 10,000 by default.
+    pub fn with_max_program_accounts(mut self, limit: usize) -> Self {
+        self.max_program_accounts = limit;
+        self
+    }
+
+    /// Always fetch `pubkey` with its data, even when data slicing would skip it, e.g. a
+    /// readonly account a program deserializes.
+    pub fn require_full_data(&self, pubkey: Pubkey) {
+        self.full_data_keys.write().unwrap().insert(pubkey);
+    }
+
+    /// Request settings applied to every fetch from the source.
+    fn fetch_config(&self) -> FetchConfig {
+        FetchConfig {
+            commitment: self.commitment,
+            min_context_slot: None,
+            metadata_only: false,
+            compress: false,
+        }
+    }
+
+    /// Whether a request for `pubkeys` should ask for base64+zstd data, according to the
+    /// [`DataEncoding`].
+    ///
+    /// Keys the cache holds, even expired, are expected to be as large as their entry;
+    /// others are assumed small, most accounts being a few hundred bytes at most.
+    fn compress(&self, pubkeys: &[Pubkey], metadata_only: bool) -> bool {
+        if metadata_only || self.zstd_rejected.load(Ordering::Relaxed) {
+            return false;
+        }
+        match self.data_encoding {
+            DataEncoding::Base64 => false,
+            DataEncoding::Base64Zstd => true,
+            DataEncoding::Adaptive { threshold } => {
+                let cache = self.cache.entries();
+                let expected: usize = pubkeys
+                    .iter()
+                    .map(|pubkey| match cache.get(pubkey) {
+                        Some(entry) if !entry.data_sliced => entry.account.data().len(),
+                        _ => 0,
+                    })
+                    .sum();
+                expected >= threshold
+            }
+        }
+    }
+
+    /// Records what a source's answer tells about the transfer, e.g. that the node
+    /// doesn't support base64+zstd, so later requests don't ask for it.
+    fn record_transfer<T>(&self, fetched: &Fetched<T>) {
+        self.compression_saved
+            .fetch_add(fetched.compression_saved, Ordering::Relaxed);
+        if fetched.zstd_rejected {
+            self.zstd_rejected.store(true, Ordering::Relaxed);
+        }
+        if let Some(slot) = fetched.slot {
+            self.last_slot.fetch_max(slot, Ordering::Relaxed);
+        }
+    }
+
+    /// Looks `pubkey` up like [`get_account_shared_data`](TransactionProcessingCallback::get_account_shared_data),
+    /// but reports why an account couldn't be provided.
+    ///
+    /// `Ok(None)` means the account doesn't exist and the policy lets it be missing.
+    ///
+    /// Concurrent lookups of the same uncached account, through this loader or another
+    /// one sharing its cache, send a single request and share its outcome.
+    pub fn load(&self, pubkey: &Pubkey) -> Result<Option<AccountSharedData>, AccountLoadError> {
+        if let Some(account) = self.overrides.read().unwrap().get(pubkey) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(Some(account.clone()));
+        }
+
+        if let Some(account) = self.committed.read().unwrap().get(pubkey) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(Some(account.clone()));
+        }
+
+        if let Some(account) = self.cached(pubkey) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(Some(account));
+        }
+
+        if self.is_known_missing(pubkey) {
+            self.negative_hits.fetch_add(1, Ordering::Relaxed);
+            return self.missing(pubkey);
+        }
+
+        if let Some(account) = self.restore_persisted(pubkey) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(Some(account));
+        }
+
+        // If not cached, fetch from the backing source, unless a concurrent lookup already
+        // does: its outcome is shared, and it caches the account.
+        let mut served_from_cache = false;
+        let (fetched, leader) = self.cache.fetch_once(*pubkey, || {
+            // A lookup that missed the cache just before the previous flight landed leads
+            // a new one, and finds the account that flight cached.
+            if let Some(account) = self.cached(pubkey) {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                served_from_cache = true;
+                return Ok(Some(account));
+            }
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            let fetched = self
+                .limiter
+                .run(
+                    || {
+                        self.single_requests.fetch_add(1, Ordering::Relaxed);
+                        let keys = std::slice::from_ref(pubkey);
+                        let config = FetchConfig {
+                            compress: self.compress(keys, false),
+                            ..self.fetch_config()
+                        };
+                        self.timed(
+                            keys,
+                            false,
+                            || self.source.fetch(pubkey, config),
+                            |fetched| {
+                                fetched
+                                    .value
+                                    .as_ref()
+                                    .map_or(0, |account| account.data().len())
+                            },
+                        )
+                        .map(|fetched| {
+                            self.record_transfer(&fetched);
+                            fetched.value
+                        })
+                    },
+                    FetchError::is_throttled,
+                    || {
+                        self.throttled.fetch_add(1, Ordering::Relaxed);
+                    },
+                )
+                .map_err(|err| err.to_string());
+            // The outcome is cached before the flight lands, so a lookup arriving after
+            // it finds the account instead of sending a second request.
+            match &fetched {
+                Ok(Some(account)) => self.insert_fetched(*pubkey, account.clone(), false),
+                // Don't keep an expired copy of an account the source no longer has.
+                Ok(None) => self.remove_cached(pubkey),
+                Err(_) => {}
+            }
+            fetched
+        });
+        if !leader {
+            self.coalesced.fetch_add(1, Ordering::Relaxed);
+        }
+        let account = match fetched {
+            Ok(Some(account)) => account,
+            Ok(None) => {
+                self.insert_missing(*pubkey);
+                return self.missing(pubkey);
+            }
+            Err(message) => {
+                if leader {
+                    self.rpc_errors.fetch_add(1, Ordering::Relaxed);
+                }
+                return Err(AccountLoadError::Transport {
+                    pubkey: *pubkey,
+                    message,
+                });
+            }
+        };
+        if !leader || served_from_cache {
+            return Ok(Some(account));
+        }
+
+        // Refresh the ProgramData along with its program, so the pair stays consistent.
+        if let Some(programdata_address) = programdata_address(&account) {
+            self.remove_cached(&programdata_address);
+            let _ = self.load(&programdata_address);
+        }
+
+        Ok(Some(account))
+    }
+
+    /// Serves `account` for `pubkey` instead of whatever the cache or the source hold,
+    /// e.g. a funded test wallet or a mocked oracle price.
+    ///
+    /// Overrides never expire and survive invalidation. State the channel commits for
+    /// an overridden account replaces the override's value, so later transactions
+    /// observe it.
+    pub fn insert_override(&self, pubkey: Pubkey, account: AccountSharedData) {
+        remove(&self.known_missing, &pubkey);
+        update(&self.overrides, |overrides| {
+            overrides.insert(pubkey, account)
+        });
+    }
+
+    /// Removes the override for `pubkey`, returning its current value.
+    pub fn remove_override(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        remove(&self.overrides, pubkey)
+    }
+
+    /// A snapshot of the current overrides, for debugging.
+    pub fn overrides(&self) -> HashMap<Pubkey, AccountSharedData> {
+        HashMap::clone(&self.overrides.read().unwrap())
+    }
+
+    /// Drops `pubkey` from the cache and the committed state, so the next lookup fetches
+    /// it from the source.
+    ///
+    /// Invalidating an upgradeable program invalidates its ProgramData account too.
+    pub fn invalidate(&self, pubkey: &Pubkey) {
+        let programdata = match self.committed.read().unwrap().get(pubkey) {
+            Some(account) => programdata_address(account),
+            None => self
+                .cache
+                .entries()
+                .get(pubkey)
+                .and_then(|entry| programdata_address(&entry.account)),
+        };
+        if let Some(programdata) = programdata {
+            self.invalidate(&programdata);
+        }
+        remove(&self.known_missing, pubkey);
+        if remove(&self.committed, pubkey).is_some() {
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+        self.remove_cached(pubkey);
+    }
+
+    /// Drops every cached account, including state committed by the channel.
+    ///
+    /// A shared [`AccountCache`] is emptied for every loader using it.
+    pub fn invalidate_all(&self) {
+        *self.known_missing.write().unwrap() = Arc::default();
+        let committed = std::mem::take(&mut *self.committed.write().unwrap()).len();
+        let cached = self.cache.clear();
+        if let Some(disk_cache) = &self.disk_cache {
+            disk_cache.clear();
+        }
+        self.evictions
+            .fetch_add((committed + cached) as u64, Ordering::Relaxed);
+    }
+
+    /// Captures the cached, committed, overridden and known-missing accounts, e.g. before
+    /// trying one ordering of a batch.
+    pub fn snapshot(&self) -> LoaderSnapshot {
+        LoaderSnapshot {
+            cache: self.cache.snapshot(),
+            committed: Arc::clone(&self.committed.read().unwrap()),
+            overrides: Arc::clone(&self.overrides.read().unwrap()),
+            known_missing: Arc::clone(&self.known_missing.read().unwrap()),
+        }
+    }
+
+    /// Puts the accounts captured by [`snapshot`](Self::snapshot) back, discarding
+    /// everything fetched, committed or overridden since.
+    ///
+    /// A shared [`AccountCache`] is restored for every loader using it.
+    pub fn restore(&self, snapshot: &LoaderSnapshot) {
+        self.cache.restore(Arc::clone(&snapshot.cache));
+        *self.committed.write().unwrap() = Arc::clone(&snapshot.committed);
+        *self.overrides.write().unwrap() = Arc::clone(&snapshot.overrides);
+        *self.known_missing.write().unwrap() = Arc::clone(&snapshot.known_missing);
+    }
+
+    /// The source this loader fetches uncached accounts from.
+    pub fn source(&self) -> &AccountSource<'a> {
+        &self.source
+    }
+
+    /// Requests sent to the source so far.
+    pub fn fetch_stats(&self) -> FetchStats {
+        FetchStats {
+            single_requests: self.single_requests.load(Ordering::Relaxed),
+            batch_requests: self.batch_requests.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Cache effectiveness so far.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            disk_hits: self.disk_hits.load(Ordering::Relaxed),
+            coalesced: self.coalesced.load(Ordering::Relaxed),
+            negative_hits: self.negative_hits.load(Ordering::Relaxed),
+            rpc_errors: self.rpc_errors.load(Ordering::Relaxed),
+            throttled: self.throttled.load(Ordering::Relaxed),
+            compression_saved_bytes: self.compression_saved.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            bytes_cached: self.cache.bytes_cached(),
+            latency: self.latency.summary(),
+        }
+    }
+
+    /// Zeroes every counter of [`stats`](Self::stats) and [`fetch_stats`](Self::fetch_stats),
+    /// except the `bytes_cached` gauge.
+    pub fn reset_stats(&self) {
+        self.latency.reset();
+        for counter in [
+            &self.single_requests,
+            &self.batch_requests,
+            &self.hits,
+            &self.misses,
+            &self.disk_hits,
+            &self.coalesced,
+            &self.negative_hits,
+            &self.rpc_errors,
+            &self.throttled,
+            &self.compression_saved,
+            &self.evictions,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Activated stake of every vote account in the current epoch, by vote address.
+    ///
+    /// Stakes set with [`with_epoch_stakes`](Self::with_epoch_stakes) win; otherwise they
+    /// are fetched once with `getVoteAccounts` from an RPC source. Sources without an RPC
+    /// client know no stake. A failed fetch is retried on the next call.
+    pub fn epoch_stakes(&self) -> Arc<HashMap<Pubkey, u64>> {
+        if let Some(stakes) = &*self.epoch_stakes.read().unwrap() {
+            return Arc::clone(stakes);
+        }
+        let Some(rpc_client) = self.source.rpc_client() else {
+            return Arc::clone(
+                self.epoch_stakes
+                    .write()
+                    .unwrap()
+                    .get_or_insert_with(Arc::default),
+            );
+        };
+
+        let commitment = self.fetch_config().commitment_for(rpc_client);
+        let fetched = self.limiter.run(
+            || {
+                rpc_client
+                    .get_vote_accounts_with_commitment(commitment)
+                    .map_err(FetchError::from)
+            },
+            FetchError::is_throttled,
+            || {
+                self.throttled.fetch_add(1, Ordering::Relaxed);
+            },
+        );
+        match fetched {
+            Ok(status) => {
+                let stakes = status
+                    .current
+                    .iter()
+                    .chain(&status.delinquent)
+                    .filter_map(|vote_account| {
+                        let vote_address = vote_account.vote_pubkey.parse().ok()?;
+                        Some((vote_address, vote_account.activated_stake))
+                    })
+                    .collect();
+                Arc::clone(
+                    self.epoch_stakes
+                        .write()
+                        .unwrap()
+                        .get_or_insert_with(|| Arc::new(stakes)),
+                )
+            }
+            Err(_) => {
+                self.rpc_errors.fetch_add(1, Ordering::Relaxed);
+                Arc::default()
+            }
+        }
+    }
+
+    /// Total activated stake of the current epoch, as far as already known.
+    ///
+    /// Unlike [`epoch_stakes`](Self::epoch_stakes) this never goes to the source: it is 0
+    /// until stakes were configured or fetched.
+    pub fn epoch_total_stake(&self) -> u64 {
+        self.epoch_stakes
+            .read()
+            .unwrap()
+            .as_ref()
+            .map_or(0, |stakes| stakes.values().sum())
+    }
+
+    /// Every account the loader holds, sorted by key, e.g. to dump the exact state a
+    /// simulation used.
+    ///
+    /// Each key is reported once, for the layer lookups see. Expired cache entries are
+    /// included and flagged. Inspecting counts neither as a lookup in the stats nor as
+    /// a use for the cache's eviction order.
+    pub fn inspect(&self) -> Vec<CachedAccountInfo> {
+        let overrides = Arc::clone(&self.overrides.read().unwrap());
+        let committed = Arc::clone(&self.committed.read().unwrap());
+        let cache = self.cache.snapshot();
+        let now = Instant::now();
+        let describe = |pubkey: &Pubkey, layer, account: &AccountSharedData| CachedAccountInfo {
+            pubkey: *pubkey,
+            layer,
+            lamports: account.lamports(),
+            owner: *account.owner(),
+            executable: account.executable(),
+            data_len: account.data().len(),
+            data_hash: hash(account.data()),
+            data_sliced: false,
+            slot: None,
+            age: None,
+            expired: false,
+        };
+
+        let mut accounts: Vec<CachedAccountInfo> = overrides
+            .iter()
+            .map(|(pubkey, account)| describe(pubkey, AccountLayer::Override, account))
+            .chain(
+                committed
+                    .iter()
+                    .filter(|(pubkey, _)| !overrides.contains_key(pubkey))
+                    .map(|(pubkey, account)| describe(pubkey, AccountLayer::Committed, account)),
+            )
+            .chain(
+                cache
+                    .iter()
+                    .filter(|(pubkey, _)| {
+                        !overrides.contains_key(pubkey) && !committed.contains_key(pubkey)
+                    })
+                    .map(|(pubkey, entry)| CachedAccountInfo {
+                        data_sliced: entry.data_sliced,
+                        slot: entry.slot,
+                        age: Some(entry.fetched_at.elapsed().unwrap_or_default()),
+                        expired: !entry.is_fresh(now),
+                        ..describe(pubkey, AccountLayer::Fetched, &entry.account)
+                    }),
+            )
+            .collect();
+        accounts.sort_unstable_by_key(|info| info.pubkey);
+        accounts
+    }
+
+    /// Keys the SVM requested, and the channel committed, since the last
+    /// [`reset_accessed_accounts`](Self::reset_accessed_accounts).
+    ///
+    /// The channel resets them when it starts a batch, so after processing they describe
+    /// the last batch, e.g. to build a minimal address lookup table.
+    pub fn accessed_accounts(&self) -> AccessedAccounts {
+        self.accessed.lock().unwrap().clone()
+    }
+
+    /// Forgets the keys recorded by [`accessed_accounts`](Self::accessed_accounts).
+    pub fn reset_accessed_accounts(&self) {
+        *self.accessed.lock().unwrap() = AccessedAccounts::default();
+    }
+
+    /// Fetches every uncached key of `pubkeys` in batches of up to 100 and caches the
+    /// accounts found, e.g. to warm the cache before processing a batch.
+    ///
+    /// Keys missing from the source are left uncached, so a later lookup still goes
+    /// through the per-key path. Accounts only discovered during execution (e.g. the
+    /// ProgramData of an upgradeable program) are fetched per key as well.
+    ///
+    /// A failed request doesn't stop the others: its keys are reported as failed and
+    /// left to the per-key path.
+    ///
+    /// Requests after the first are read at or after the first response's slot, see
+    /// [`SlotConsistency`]; the slot is reported as [`PrefetchReport::context_slot`].
+    pub fn prefetch(&self, pubkeys: &[Pubkey]) -> PrefetchReport {
+        let mut progress = PrefetchProgress::default();
+        let uncached = self.uncached(pubkeys, |_| true, &mut progress.report);
+        self.fetch_full(&uncached, &mut progress);
+        progress.finish()
+    }
+
+    /// Prefetches every account `transactions` reference, like [`prefetch`](Self::prefetch).
+    ///
+    /// With [data slicing](Self::with_data_slicing) enabled, accounts no transaction
+    /// writes or invokes are first fetched without their data. Those turning out to be
+    /// programs or ProgramData accounts are then fetched in full, along with the others.
+    pub fn prefetch_transactions(&self, transactions: &[SanitizedTransaction]) -> PrefetchReport {
+        let mut keys = Vec::new();
+        let mut full_data = HashSet::new();
+        for tx in transactions {
+            let message = tx.message();
+            for (index, key) in message.account_keys().iter().enumerate() {
+                keys.push(*key);
+                if !self.data_slicing || message.is_writable(index) {
+                    full_data.insert(*key);
+                }
+            }
+            full_data.extend(message.program_ids());
+        }
+        let needs_full_data =
+            |pubkey: &Pubkey| full_data.contains(pubkey) || self.needs_full_data(pubkey);
+
+        let mut progress = PrefetchProgress::default();
+        let (mut full, sliced): (Vec<Pubkey>, Vec<Pubkey>) = self
+            .uncached(&keys, needs_full_data, &mut progress.report)
+            .into_iter()
+            .partition(needs_full_data);
+        for (pubkey, account) in self.fetch_chunks(&sliced, true, &mut progress) {
+            if account.executable() || bpf_loader_upgradeable::check_id(account.owner()) {
+                full.push(pubkey);
+            } else {
+                // Sources that don't support slicing return the data anyway.
+                let data_sliced = account.data().is_empty();
+                self.insert_fetched(pubkey, account, data_sliced);
+                progress.report.fetched.push(pubkey);
+            }
+        }
+        self.fetch_full(&full, &mut progress);
+        progress.finish()
+    }
+
+    /// Fetches every account `program_id` owns matching `filters` (e.g.
+    /// [`RpcFilterType::Memcmp`] and [`RpcFilterType::DataSize`]) in a single
+    /// `getProgramAccounts` request and caches them, returning their keys.
+    ///
+    /// Much faster than prefetching hundreds of accounts of one program key by key, but
+    /// expensive for the node: many nodes refuse the query for large programs, or unless
+    /// `filters` narrow it down. A query matching more accounts than the
+    /// [limit](Self::with_max_program_accounts) fails and caches none of them.
+    ///
+    /// Only sources with an RPC client support the query. Overridden and committed
+    /// accounts keep being served from their layer.
+    pub fn prefetch_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<Pubkey>, ProgramAccountsError> {
+        let Some(rpc_client) = self.source.rpc_client() else {
+            return Err(ProgramAccountsError::Unsupported(*program_id));
+        };
+        // The response is as large as the program's matching accounts, so it is always
+        // worth compressing unless told otherwise.
+        let config = FetchConfig {
+            compress: self.data_encoding != DataEncoding::Base64
+                && !self.zstd_rejected.load(Ordering::Relaxed),
+            ..self.fetch_config()
+        };
+        let filters = (!filters.is_empty()).then_some(filters);
+        let fetched = self.limiter.run(
+            || {
+                self.timed(
+                    std::slice::from_ref(program_id),
+                    true,
+                    || {
+                        rpc_request(rpc_client, config, |account_config| {
+                            rpc_client.get_program_accounts_with_config(
+                                program_id,
+                                RpcProgramAccountsConfig {
+                                    filters: filters.clone(),
+                                    account_config,
+                                    ..RpcProgramAccountsConfig::default()
+                                },
+                            )
+                        })
+                    },
+                    |(accounts, _)| accounts.iter().map(|(_, account)| account.data.len()).sum(),
+                )
+            },
+            FetchError::is_throttled,
+            || {
+                self.throttled.fetch_add(1, Ordering::Relaxed);
+            },
+        );
+
+        let (accounts, zstd_rejected) = fetched.map_err(|err| {
+            self.rpc_errors.fetch_add(1, Ordering::Relaxed);
+            match err {
+                FetchError::Refused(message) => ProgramAccountsError::Refused {
+                    program_id: *program_id,
+                    message,
+                },
+                err => ProgramAccountsError::Transport {
+                    program_id: *program_id,
+                    message: err.to_string(),
+                },
+            }
+        })?;
+        if zstd_rejected {
+            self.zstd_rejected.store(true, Ordering::Relaxed);
+        }
+        if accounts.len() > self.max_program_accounts {
+            return Err(ProgramAccountsError::TooManyAccounts {
+                program_id: *program_id,
+                count: accounts.len(),
+                limit: self.max_program_accounts,
+            });
+        }
+        Ok(accounts
+            .into_iter()
+            .map(|(pubkey, account)| {
+                self.insert_fetched(pubkey, account.into(), false);
+                pubkey
+            })
+            .collect())
+    }
+
+    /// Fetches `pubkeys` with their data and caches them, along with the ProgramData
+    /// account of every upgradeable program among them.
+    ///
+    /// The ProgramData is refetched even if it was cached, so a program and its ELF
+    /// always come from the same prefetch.
+    fn fetch_full(&self, pubkeys: &[Pubkey], progress: &mut PrefetchProgress) {
+        let mut programdata = Vec::new();
+        for (pubkey, account) in self.fetch_chunks(pubkeys, false, progress) {
+            programdata.extend(programdata_address(&account));
+            self.insert_fetched(pubkey, account, false);
+            progress.report.fetched.push(pubkey);
+        }
+
+        programdata.retain(|pubkey| !pubkeys.contains(pubkey) && !self.is_pinned(pubkey));
+        programdata.sort_unstable();
+        programdata.dedup();
+        progress
+            .report
+            .cached
+            .retain(|pubkey| !programdata.contains(pubkey));
+        for (pubkey, account) in self.fetch_chunks(&programdata, false, progress) {
+            self.insert_fetched(pubkey, account, false);
+            progress.report.fetched.push(pubkey);
+        }
+    }
+
+    /// Whether `pubkey` is served from an override or committed state, never the cache.
+    fn is_pinned(&self, pubkey: &Pubkey) -> bool {
+        self.overrides.read().unwrap().contains_key(pubkey)
+            || self.committed.read().unwrap().contains_key(pubkey)
+    }
+
+    /// Deduplicates `pubkeys` and returns those that must be fetched, recording the others
+    /// as cached in `report`.
+    ///
+    /// Accounts cached without their data count as uncached when `needs_full_data` says so.
+    fn uncached(
+        &self,
+        pubkeys: &[Pubkey],
+        needs_full_data: impl Fn(&Pubkey) -> bool,
+        report: &mut PrefetchReport,
+    ) -> Vec<Pubkey> {
+        let uncached: Vec<Pubkey> = {
+            let overrides = self.overrides.read().unwrap();
+            let committed = self.committed.read().unwrap();
+            let cache = self.cache.entries();
+            let known_missing = self.known_missing.read().unwrap();
+            let now = Instant::now();
+            let mut seen = HashSet::new();
+            pubkeys
+                .iter()
+                .copied()
+                .filter(|pubkey| seen.insert(*pubkey))
+                .filter(|pubkey| {
+                    let cached = overrides.contains_key(pubkey)
+                        || committed.contains_key(pubkey)
+                        || cache.get(pubkey).is_some_and(|entry| {
+                            entry.is_fresh(now) && !(entry.data_sliced && needs_full_data(pubkey))
+                        });
+                    if cached {
+                        self.cache.touch(pubkey);
+                        report.cached.push(*pubkey);
+                        return false;
+                    }
+                    let missing = known_missing
+                        .get(pubkey)
+                        .is_some_and(|expires_at| now < *expires_at);
+                    if missing {
+                        self.negative_hits.fetch_add(1, Ordering::Relaxed);
+                        report.missing.push(*pubkey);
+                    }
+                    !missing
+                })
+                .collect()
+        };
+        // The disk is read once the locks are released, since restoring takes them.
+        uncached
+            .into_iter()
+            .filter(|pubkey| {
+                let restored = self.restore_persisted(pubkey).is_some();
+                if restored {
+                    report.cached.push(*pubkey);
+                }
+                !restored
+            })
+            .collect()
+    }
+
+    /// Caches the account an earlier run persisted for `pubkey`, if there is one and it
+    /// hasn't expired, and returns it.
+    fn restore_persisted(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        let persisted = self.disk_cache.as_ref()?.read(pubkey)?;
+        let account = persisted.account.clone();
+        let evicted = self.cache.insert_persisted(
+            *pubkey,
+            persisted.account,
+            persisted.slot,
+            persisted.fetched_at,
+        )?;
+        remove(&self.known_missing, pubkey);
+        self.evictions.fetch_add(evicted, Ordering::Relaxed);
+        self.disk_hits.fetch_add(1, Ordering::Relaxed);
+        Some(account)
+    }
+
+    /// Fetches `pubkeys` in batches of up to 100, returning the accounts found.
+    ///
+    /// Missing and failed keys are recorded in `progress`.
+    fn fetch_chunks(
+        &self,
+        pubkeys: &[Pubkey],
+        metadata_only: bool,
+        progress: &mut PrefetchProgress,
+    ) -> Vec<(Pubkey, AccountSharedData)> {
+        let mut found = Vec::new();
+        for chunk in pubkeys.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let context_slot = progress.context_slot;
+            let (accounts, slot) = match self.fetch_multiple_at(chunk, context_slot, metadata_only)
+            {
+                Ok(response) => response,
+                Err(err) => {
+                    self.rpc_errors.fetch_add(1, Ordering::Relaxed);
+                    progress.report.failed.extend(chunk.iter().map(|pubkey| {
+                        match (&err, context_slot) {
+                            (FetchError::Behind, Some(slot)) => AccountLoadError::Inconsistent {
+                                pubkey: *pubkey,
+                                slot,
+                            },
+                            _ => AccountLoadError::Transport {
+                                pubkey: *pubkey,
+                                message: err.to_string(),
+                            },
+                        }
+                    }));
+                    continue;
+                }
+            };
+            match (context_slot, slot) {
+                (None, _) => progress.context_slot = slot,
+                (Some(context_slot), Some(slot)) if slot < context_slot => {
+                    progress.inconsistent = true
+                }
+                _ => {}
+            }
+
+            for (pubkey, account) in chunk.iter().zip(accounts) {
+                match account {
+                    Some(account) => found.push((*pubkey, account)),
+                    None => {
+                        self.insert_missing(*pubkey);
+                        progress.report.missing.push(*pubkey);
+                    }
+                }
+            }
+        }
+        found
+    }
+
+    /// Fetches `pubkeys` in a single request, read at or after `min_context_slot`.
+    ///
+    /// While the node is behind that slot the request is retried until the
+    /// [`SlotConsistency`] deadline. Past it, strict mode gives up with
+    /// `FetchError::Behind`; otherwise the request is sent once more without a minimum
+    /// slot, and the caller notices from the returned slot.
+    fn fetch_multiple_at(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<Slot>,
+        metadata_only: bool,
+    ) -> Result<(Vec<Option<AccountSharedData>>, Option<Slot>), FetchError> {
+        let deadline = Instant::now() + self.slot_consistency.deadline;
+        let mut config = FetchConfig {
+            min_context_slot,
+            metadata_only,
+            ..self.fetch_config()
+        };
+        loop {
+            config.compress = self.compress(pubkeys, metadata_only);
+            let fetched = self.limiter.run(
+                || {
+                    self.batch_requests.fetch_add(1, Ordering::Relaxed);
+                    self.timed(
+                        pubkeys,
+                        true,
+                        || self.source.fetch_multiple(pubkeys, config),
+                        |fetched| {
+                            fetched
+                                .value
+                                .iter()
+                                .flatten()
+                                .map(|account| account.data().len())
+                                .sum()
+                        },
+                    )
+                    .map(|fetched| {
+                        self.record_transfer(&fetched);
+                        (fetched.value, fetched.slot)
+                    })
+                },
+                FetchError::is_throttled,
+                || {
+                    self.throttled.fetch_add(1, Ordering::Relaxed);
+                },
+            );
+            match fetched {
+                Err(FetchError::Behind) if Instant::now() < deadline => {
+                    thread::sleep(SLOT_RETRY_INTERVAL)
+                }
+                Err(FetchError::Behind)
+                    if !self.slot_consistency.strict && config.min_context_slot.is_some() =>
+                {
+                    config.min_context_slot = None
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Sends a single request for `pubkeys` to the source, recording its latency and the
+    /// data bytes `bytes` counts in its response.
+    ///
+    /// With the `tracing` feature enabled the request runs in an `account_fetch` span,
+    /// nested in the channel's spans, carrying the first key, the number of keys, whether
+    /// it was batched, the response size and the latency.
+    fn timed<T>(
+        &self,
+        pubkeys: &[Pubkey],
+        batched: bool,
+        request: impl FnOnce() -> Result<T, FetchError>,
+        bytes: impl FnOnce(&T) -> usize,
+    ) -> Result<T, FetchError> {
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!(
+            "account_fetch",
+            key = %pubkeys[0],
+            keys = pubkeys.len(),
+            batched,
+            bytes = tracing::field::Empty,
+            latency_us = tracing::field::Empty,
+        )
+        .entered();
+        #[cfg(not(feature = "tracing"))]
+        let _ = (pubkeys, batched);
+
+        let start = Instant::now();
+        let result = request();
+        let latency = start.elapsed();
+        let bytes = result.as_ref().map_or(0, bytes) as u64;
+        self.latency.record(latency, bytes);
+
+        #[cfg(feature = "tracing")]
+        {
+            span.record("bytes", bytes);
+            span.record("latency_us", latency.as_micros() as u64);
+            if let Err(err) = &result {
+                tracing::debug!(error = %err, "account fetch failed");
+            }
+        }
+        result
+    }
+
+    /// The cached account of `pubkey`, unless it expired or lacks data this loader needs.
+    fn cached(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        let entries = self.cache.entries();
+        let entry = entries.get(pubkey)?;
+        if !entry.is_fresh(Instant::now()) || (entry.data_sliced && self.needs_full_data(pubkey)) {
+            return None;
+        }
+        self.cache.touch(pubkey);
+        Some(entry.account.clone())
+    }
+
+    /// Whether `pubkey` was marked with [`require_full_data`](Self::require_full_data).
+    fn needs_full_data(&self, pubkey: &Pubkey) -> bool {
+        self.full_data_keys.read().unwrap().contains(pubkey)
+    }
+
+    /// Owner of `pubkey` if it is overridden, committed or freshly cached.
+    ///
+    /// Accounts cached without their data qualify too, since their owner is known.
+    fn cached_owner(&self, pubkey: &Pubkey) -> Option<Pubkey> {
+        let owner = self
+            .overrides
+            .read()
+            .unwrap()
+            .get(pubkey)
+            .map(|account| *account.owner())
+            .or_else(|| {
+                self.committed
+                    .read()
+                    .unwrap()
+                    .get(pubkey)
+                    .map(|account| *account.owner())
+            })
+            .or_else(|| {
+                self.cache
+                    .entries()
+                    .get(pubkey)
+                    .filter(|entry| entry.is_fresh(Instant::now()))
+                    .map(|entry| {
+                        self.cache.touch(pubkey);
+                        *entry.account.owner()
+                    })
+            })?;
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        Some(owner)
+    }
+
+    /// Applies the missing account policy to `pubkey`.
+    ///
+    /// Sysvars the loader can synthesize are served, and kept as committed state, instead.
+    fn missing(&self, pubkey: &Pubkey) -> Result<Option<AccountSharedData>, AccountLoadError> {
+        if let Some(account) = self.sysvar_defaults.account(pubkey) {
+            self.insert_cached(*pubkey, account.clone());
+            return Ok(Some(account));
+        }
+        match &self.missing_account_policy {
+            MissingAccountPolicy::TreatAsMissing => Ok(None),
+            MissingAccountPolicy::Error => Err(AccountLoadError::Missing(*pubkey)),
+            MissingAccountPolicy::Synthesize(account) => Ok(Some(account.clone())),
+        }
+    }
+
+    /// Whether the source recently reported `pubkey` missing.
+    fn is_known_missing(&self, pubkey: &Pubkey) -> bool {
+        self.known_missing
+            .read()
+            .unwrap()
+            .get(pubkey)
+            .is_some_and(|expires_at| Instant::now() < *expires_at)
+    }
+
+    /// Remembers that the source doesn't have `pubkey`, if negative caching is enabled.
+    fn insert_missing(&self, pubkey: Pubkey) {
+        if let Some(ttl) = self.negative_ttl {
+            update(&self.known_missing, |known_missing| {
+                known_missing.insert(pubkey, Instant::now() + ttl)
+            });
+        }
+    }
+
+    /// Store an account as committed state, replacing any previously cached value, or
+    /// update the account's override if it has one.
+    ///
+    /// Used by the channel to serve synthesized accounts (e.g. sysvars) without
+    /// going through the RPC client.
+    pub(crate) fn insert_cached(&self, pubkey: Pubkey, account: AccountSharedData) {
+        remove(&self.known_missing, &pubkey);
+        {
+            let mut overrides = self.overrides.write().unwrap();
+            if overrides.contains_key(&pubkey) {
+                Arc::make_mut(&mut overrides).insert(pubkey, account);
+                return;
+            }
+        }
+        update(&self.committed, |committed| {
+            committed.insert(pubkey, account)
+        });
+    }
+
+    /// Store the post-execution state of an account, like [`insert_cached`](Self::insert_cached),
+    /// and record it as written.
+    pub(crate) fn commit_account(&self, pubkey: Pubkey, account: AccountSharedData) {
+        self.accessed.lock().unwrap().writes.insert(pubkey);
+        self.insert_cached(pubkey, account);
+    }
+
+    /// Store an account fetched from the source, expiring it after the cache's TTL.
+    fn insert_fetched(&self, pubkey: Pubkey, account: AccountSharedData, data_sliced: bool) {
+        remove(&self.known_missing, &pubkey);
+        let slot = match self.last_slot.load(Ordering::Relaxed) {
+            0 => None,
+            slot => Some(slot),
+        };
+        if let Some(disk_cache) = self.disk_cache.as_ref().filter(|_| !data_sliced) {
+            // Persisting is best effort: an account that couldn't be written is fetched
+            // again by the next run.
+            let _ = disk_cache.write(&pubkey, &account, slot, SystemTime::now());
+        }
+        let evicted = self.cache.insert(pubkey, account, data_sliced, slot);
+        self.evictions.fetch_add(evicted, Ordering::Relaxed);
+    }
+
+    /// Removes a cache entry, and its persisted copy, keeping the eviction counter up to
+    /// date.
+    fn remove_cached(&self, pubkey: &Pubkey) {
+        if let Some(disk_cache) = &self.disk_cache {
+            disk_cache.remove(pubkey);
+        }
+        if self.cache.remove(pubkey) {
+            self.evictions.fetch_add(1, Ordering::Relaxed);
         }
     }
 }
@@ -37,31 +1922,76 @@ impl<'a> RollUpAccountLoader<'a> {
 /// loader to be used during SVM transaction processing.
 ///
 /// The processor will use this callback to fetch account data as needed during execution.
+///
+/// Every callback of the trait is implemented, except `inspect_account`: the SVM reports
+/// each loaded account through it for a bank to maintain its accounts hash, which a
+/// local simulation has no use for. The loader records accesses from the account
+/// callbacks instead.
 impl TransactionProcessingCallback for RollUpAccountLoader<'_> {
     /// Attempts to retrieve account data for the given public key.
     ///
-    /// First checks the internal cache. If the account is not cached, it fetches
-    /// the data via RPC, stores it in the cache, and returns it.
+    /// Overrides win over everything else. Otherwise it checks the state committed by
+    /// the channel, then the cache, and if the account is not cached or its entry
+    /// expired, it fetches the data from the source, stores it in the cache, and returns it.
+    ///
+    /// Fetching an upgradeable (loader v3) program account also fetches its
+    /// ProgramData account, which the program cache needs to compile the program.
     fn get_account_shared_data(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
-        if let Some(account) = self.cache.read().unwrap().get(pubkey) {
-            return Some(account.clone());
-        }
-
-        // If not cached, fetch from RPC
-        let account: AccountSharedData = self.rpc_client.get_account(pubkey).ok()?.into();
-
-        // Cache for future lookups
-        self.cache.write().unwrap().insert(*pubkey, account.clone());
-
-        Some(account)
+        self.accessed.lock().unwrap().reads.insert(*pubkey);
+        // Load errors can't be reported through this callback; the channel checks
+        // every transaction's accounts with `load` before execution instead.
+        self.load(pubkey).ok().flatten()
     }
 
     /// Determines whether the specified account is owned by one of the provided owners.
     ///
     /// This is useful during transaction processing for filtering or validating accounts
     /// that must be owned by a specific program (e.g., System or Token program).
+    ///
+    /// The SVM calls this for every account of every transaction while resolving
+    /// programs, so a known account's owner is read in place, and the source is only
+    /// asked about accounts the loader doesn't hold yet.
     fn account_matches_owners(&self, account: &Pubkey, owners: &[Pubkey]) -> Option<usize> {
-        self.get_account_shared_data(account)
-            .and_then(|account| owners.iter().position(|key| account.owner().eq(key)))
+        self.accessed.lock().unwrap().reads.insert(*account);
+        let owner = match self.cached_owner(account) {
+            Some(owner) => owner,
+            None => *self.load(account).ok().flatten()?.owner(),
+        };
+        owners.iter().position(|key| *key == owner)
+    }
+
+    /// Registers the program account of a builtin, the same way a bank does.
+    ///
+    /// Builtins are owned by the native loader and never need to be fetched,
+    /// so offline sources don't have to provide them.
+    fn add_builtin_account(&self, name: &str, program_id: &Pubkey) {
+        self.insert_cached(
+            *program_id,
+            native_loader::create_loadable_account_for_test(name),
+        );
+    }
+
+    /// Activated stake of `vote_address` in the current epoch, 0 for unknown accounts,
+    /// as returned to programs by the `sol_get_epoch_stake` syscall.
+    ///
+    /// See [`RollUpAccountLoader::epoch_stakes`] for where stakes come from.
+    fn get_current_epoch_vote_account_stake(&self, vote_address: &Pubkey) -> u64 {
+        self.epoch_stakes()
+            .get(vote_address)
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// Returns the ProgramData address of an upgradeable (loader v3) program account.
+pub(crate) fn programdata_address(account: &AccountSharedData) -> Option<Pubkey> {
+    if !bpf_loader_upgradeable::check_id(account.owner()) {
+        return None;
+    }
+    match account.deserialize_data() {
+        Ok(UpgradeableLoaderState::Program {
+            programdata_address,
+        }) => Some(programdata_address),
+        _ => None,
     }
 }
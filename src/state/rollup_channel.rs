@@ -1,151 +1,1575 @@
-use std::sync::{Arc, RwLock};
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::ops::ControlFlow;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+use solana_bpf_loader_program::syscalls::create_program_runtime_environment_v1;
+#[cfg(feature = "loader-v4")]
+use solana_bpf_loader_program::syscalls::create_program_runtime_environment_v2;
 use solana_client::rpc_client::RpcClient;
-use solana_compute_budget::compute_budget::ComputeBudget;
+use solana_sdk::account::{
+    create_account_shared_data_for_test, from_account, AccountSharedData, ReadableAccount,
+};
+use solana_sdk::clock::{Clock, Slot, UnixTimestamp};
+use solana_sdk::epoch_schedule::EpochSchedule;
 use solana_sdk::fee::FeeStructure;
-use solana_sdk::hash::Hash;
+use solana_sdk::hash::{hash, Hash};
+use solana_sdk::instruction::InstructionError;
 use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
 use solana_sdk::rent_collector::RentCollector;
-use solana_sdk::transaction::{SanitizedTransaction as SolanaSanitizedTransaction, Transaction};
+use solana_sdk::slot_hashes::SlotHashes;
+use solana_sdk::sysvar;
+use solana_sdk::transaction::{
+    SanitizedTransaction as SolanaSanitizedTransaction, Transaction, TransactionError,
+};
 
 use agave_feature_set::FeatureSet;
-use solana_svm::transaction_processing_result::ProcessedTransaction;
+use solana_program_runtime::loaded_programs::ForkGraph;
+use solana_svm::account_loader::TransactionCheckResult;
+use solana_svm::rollback_accounts::RollbackAccounts;
+use solana_svm::transaction_processing_callback::TransactionProcessingCallback;
+use solana_svm::transaction_processing_result::{
+    ProcessedTransaction, TransactionProcessingResult,
+};
 use solana_svm::transaction_processor::{
+    ExecutionRecordingConfig, LoadAndExecuteSanitizedTransactionsOutput, TransactionBatchProcessor,
     TransactionProcessingConfig, TransactionProcessingEnvironment,
 };
 
-use crate::state::rollup_account_loader::RollUpAccountLoader;
-use crate::utils::helpers::{create_transaction_batch_processor, get_transaction_check_results};
+use crate::error::{AccountLoadError, ProgramLoadError};
+use crate::state::account_change::AccountChange;
+use crate::state::block_result::BlockResult;
+#[cfg(feature = "embedded-spl")]
+use crate::state::embedded_spl::embedded_spl_accounts;
+use crate::state::error_decoder::ErrorDecoder;
+use crate::state::execution_timings::ExecutionTimings;
+use crate::state::local_program::{program_accounts, program_elf, verify_elf, LoaderKind};
+use crate::state::lock_analysis::LockAnalysis;
+use crate::state::middleware::RollUpMiddleware;
+use crate::state::processing_environment::ProcessingEnvironmentBuilder;
+use crate::state::processor_builder::ProcessorBuilder;
+use crate::state::program_cache::{warm_up_programs, ProgramCacheStats, ProgramWarmUp};
+use crate::state::return_struct::{ConversionOptions, TxStatus};
+use crate::state::rollup_account_loader::{AccountSource, RollUpAccountLoader, SysvarDefaults};
+use crate::state::rollup_channel_builder::RollUpChannelBuilder;
+use crate::state::rollup_channel_config::{
+    BlockhashSource, FeatureSetSource, ProgramFilter, RentSource, RollUpChannelConfig,
+    SanitizationMode, SysvarSource,
+};
+use crate::state::simulation_context::SimulationContext;
+use crate::utils::helpers::{
+    check_transactions, create_recent_blockhashes_account, feature_set_from_cluster,
+    feature_set_from_cluster_pending_at, get_compute_unit_limit, populate_sysvars,
+    sanitize_transactions, transaction_fee, verify_precompiles, CheckConfig,
+};
 use crate::{ForkRollUpGraph, ReturnStruct};
 
 /// Handles a group of accounts and enables simulation of transactions
 /// using Solana's SVM runtime with preconfigured defaults.
-pub struct RollUpChannel<'a> {
+///
+/// The channel is generic over the [`ForkGraph`] handed to the program cache; see
+/// [`ForkRollUpGraph`] for the contract an implementation must satisfy.
+pub struct RollUpChannel<'a, F: ForkGraph = ForkRollUpGraph> {
     /// A list of the account keys extracted from the transaction,
     /// passed into the rollup channel for SVM simulation and processing.
     keys: Vec<Pubkey>,
-    /// Reference to an RPC client used to fetch account and cluster data.
-    rpc_client: &'a RpcClient,
+    /// Account loader shared by every batch processed through this channel.
+    ///
+    /// Its cache lives as long as the channel, so repeated batches don't refetch accounts.
+    account_loader: RollUpAccountLoader<'a>,
+    /// Settings for the processing environment.
+    config: RollUpChannelConfig,
+    /// The slot batches execute at, starting at [`RollUpChannelConfig::slot`], see
+    /// [`advance_slot`](Self::advance_slot).
+    slot: AtomicU64,
+    /// Slot ancestry consulted by the program cache.
+    ///
+    /// Processors only hold a weak reference to it, so the channel keeps it alive.
+    fork_graph: Arc<RwLock<F>>,
+    /// Policy hooks run around each transaction, in registration order.
+    middlewares: Vec<Box<dyn RollUpMiddleware + 'a>>,
+    /// Names the custom errors of failed transactions.
+    error_decoder: ErrorDecoder,
+    /// Programs whose ELF, identified by its hash, verified, see
+    /// [`RollUpChannelConfig::verify_programs`].
+    verified_programs: Mutex<HashSet<(Pubkey, Hash)>>,
+    /// The processor every call derives its own from, sharing its program cache, and the
+    /// hash of the feature set it was built for, see
+    /// [`program_cache_stats`](Self::program_cache_stats).
+    processor: Mutex<Option<(Hash, TransactionBatchProcessor<F>)>>,
+    /// Occupancy of the program cache, see [`program_cache_stats`](Self::program_cache_stats).
+    program_cache_stats: Mutex<ProgramCacheStats>,
+    /// Where the SVM spent its time, see [`execution_timings`](Self::execution_timings).
+    execution_timings: Mutex<ExecutionTimings>,
+    /// Rent parameters and epoch schedule fetched from the cluster, see
+    /// [`RentSource::FromCluster`].
+    cluster_rent: Mutex<Option<(Rent, EpochSchedule)>>,
 }
 
 impl<'a> RollUpChannel<'a> {
     /// Constructs a new `RollUpChannel` with a list of public keys and an RPC client reference.
     pub fn new(keys: Vec<Pubkey>, rpc_client: &'a RpcClient) -> Self {
-        Self { keys, rpc_client }
+        Self::with_config(keys, rpc_client, RollUpChannelConfig::default())
+    }
+
+    /// Constructs a new `RollUpChannel` using a custom [`RollUpChannelConfig`].
+    pub fn with_config(
+        keys: Vec<Pubkey>,
+        rpc_client: &'a RpcClient,
+        config: RollUpChannelConfig,
+    ) -> Self {
+        Self::with_account_source(keys, AccountSource::Rpc(rpc_client), config)
+    }
+
+    /// Constructs a new `RollUpChannel` reading accounts from the given [`AccountSource`].
+    ///
+    /// With [`AccountSource::Static`] the channel never touches the network, so
+    /// `BlockhashSource::Latest` can't be resolved and makes every transaction fail.
+    pub fn with_account_source(
+        keys: Vec<Pubkey>,
+        source: AccountSource<'a>,
+        config: RollUpChannelConfig,
+    ) -> Self {
+        Self::with_fork_graph(
+            keys,
+            source,
+            config,
+            Arc::new(RwLock::new(ForkRollUpGraph::default())),
+        )
+    }
+
+    /// Starts building a `RollUpChannel` reading accounts from the given [`AccountSource`].
+    pub fn builder(source: AccountSource<'a>) -> RollUpChannelBuilder<'a> {
+        RollUpChannelBuilder::new(source)
+    }
+
+    /// Constructs a fully offline `RollUpChannel` from a fixed set of accounts.
+    pub fn new_offline(keys: Vec<Pubkey>, accounts: HashMap<Pubkey, AccountSharedData>) -> Self {
+        Self::with_account_source(
+            keys,
+            AccountSource::Static(accounts),
+            RollUpChannelConfig::default(),
+        )
+    }
+}
+
+impl<'a, F: ForkGraph + 'static> RollUpChannel<'a, F> {
+    /// Constructs a new `RollUpChannel` whose program cache consults a custom [`ForkGraph`].
+    ///
+    /// Use this to model your own slot ancestry, e.g. when simulating across forks.
+    pub fn with_fork_graph(
+        keys: Vec<Pubkey>,
+        source: AccountSource<'a>,
+        config: RollUpChannelConfig,
+        fork_graph: Arc<RwLock<F>>,
+    ) -> Self {
+        Self::from_parts(
+            keys,
+            RollUpAccountLoader::with_source(source),
+            config,
+            fork_graph,
+            Vec::new(),
+        )
+    }
+
+    /// Constructs a new `RollUpChannel` from already configured parts.
+    pub(crate) fn from_parts(
+        keys: Vec<Pubkey>,
+        account_loader: RollUpAccountLoader<'a>,
+        config: RollUpChannelConfig,
+        fork_graph: Arc<RwLock<F>>,
+        middlewares: Vec<Box<dyn RollUpMiddleware + 'a>>,
+    ) -> Self {
+        let account_loader = match config.commitment {
+            Some(commitment) => account_loader.with_commitment(commitment),
+            None => account_loader,
+        }
+        .with_slot_consistency(config.slot_consistency)
+        .with_data_slicing(config.slice_readonly_accounts)
+        .with_data_encoding(config.data_encoding)
+        .with_sysvar_defaults(match &config.sysvars {
+            SysvarSource::Fetch => sysvar_defaults(&config),
+            SysvarSource::Explicit(values) => values.clone(),
+        });
+        let account_loader = match config.negative_cache_ttl {
+            Some(ttl) => account_loader.with_negative_ttl(ttl),
+            None => account_loader,
+        };
+        let account_loader = match &config.disk_cache_dir {
+            Some(dir) => account_loader.with_disk_cache(dir.clone()),
+            None => account_loader,
+        };
+        #[cfg(feature = "embedded-spl")]
+        let account_loader = if config.embedded_spl_programs {
+            account_loader.with_fallback_source(AccountSource::Static(
+                embedded_spl_accounts(&config.rent).into_iter().collect(),
+            ))
+        } else {
+            account_loader
+        };
+        Self {
+            keys,
+            account_loader,
+            slot: AtomicU64::new(config.slot),
+            config,
+            fork_graph,
+            middlewares,
+            error_decoder: ErrorDecoder::default(),
+            verified_programs: Mutex::new(HashSet::new()),
+            processor: Mutex::new(None),
+            program_cache_stats: Mutex::new(ProgramCacheStats::default()),
+            execution_timings: Mutex::new(ExecutionTimings::default()),
+            cluster_rent: Mutex::new(None),
+        }
+    }
+
+    /// Registers a [`RollUpMiddleware`], run after every previously registered one.
+    pub fn add_middleware(&mut self, middleware: impl RollUpMiddleware + 'a) -> &mut Self {
+        self.middlewares.push(Box::new(middleware));
+        self
+    }
+
+    /// Decodes the custom errors of failed transactions with `decoder`, see
+    /// [`ReturnStruct::decoded_error`].
+    pub fn set_error_decoder(&mut self, decoder: ErrorDecoder) -> &mut Self {
+        self.error_decoder = decoder;
+        self
+    }
+
+    /// Deploys the program ELF at `path` under `program_id` with `loader`, so the next
+    /// batches can invoke it, e.g. to execute a freshly built program before it exists on
+    /// any cluster.
+    ///
+    /// See [`add_program`](Self::add_program).
+    pub fn add_program_from_file(
+        &self,
+        program_id: Pubkey,
+        path: &Path,
+        loader: LoaderKind,
+    ) -> Result<(), ProgramLoadError> {
+        let elf = fs::read(path).map_err(|err| ProgramLoadError::Io {
+            path: path.to_path_buf(),
+            message: err.to_string(),
+        })?;
+        self.add_program(program_id, &elf, loader)
+    }
+
+    /// Deploys the program `elf` under `program_id` with `loader`, so the next batches can
+    /// invoke it.
+    ///
+    /// The ELF is verified first, an invalid one being reported as
+    /// [`ProgramLoadError::InvalidElf`]. The program's accounts, deployed at slot 0, are
+    /// then set as [overrides](RollUpAccountLoader::insert_override), replacing whatever
+    /// the source holds for them, and compiled into the processor's program cache like
+    /// any deployed program. A version of the program already compiled is dropped from
+    /// the cache.
+    pub fn add_program(
+        &self,
+        program_id: Pubkey,
+        elf: &[u8],
+        loader: LoaderKind,
+    ) -> Result<(), ProgramLoadError> {
+        verify_elf(
+            &program_id,
+            elf,
+            &loader.id(),
+            self.config.reject_broken_elfs,
+        )?;
+        for (pubkey, account) in program_accounts(&program_id, elf, loader, &self.config.rent) {
+            self.account_loader.insert_override(pubkey, account);
+        }
+        if let Some((_, processor)) = &*self.processor.lock().unwrap() {
+            processor
+                .program_cache
+                .write()
+                .unwrap()
+                .remove_programs(std::iter::once(program_id));
+        }
+        Ok(())
+    }
+
+    /// Fetches and compiles `program_ids` into the channel's program cache, so the next
+    /// batches find them compiled and spend no time loading them, see
+    /// [`warm_up_programs`].
+    ///
+    /// If the processing environment can't be set up, e.g. the blockhash can't be
+    /// fetched, every program fails with [`ProgramLoadError::Environment`].
+    pub fn warm_up(&self, program_ids: &[Pubkey]) -> Vec<ProgramWarmUp> {
+        match self.create_processing_context(self.slot()) {
+            Ok(context) => warm_up_programs(&context.processor, &self.account_loader, program_ids),
+            Err(message) => program_ids
+                .iter()
+                .map(|program_id| ProgramWarmUp {
+                    program_id: *program_id,
+                    result: Err(ProgramLoadError::Environment {
+                        message: message.clone(),
+                    }),
+                    elapsed: Duration::ZERO,
+                })
+                .collect(),
+        }
+    }
+
+    /// The account loader used to serve account data to the SVM.
+    pub fn account_loader(&self) -> &RollUpAccountLoader<'a> {
+        &self.account_loader
+    }
+
+    /// Whether [`RollUpChannelConfig::debug_execution`] is on.
+    #[cfg(feature = "tracing")]
+    fn debug_execution(&self) -> bool {
+        self.config.debug_execution
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    fn debug_execution(&self) -> bool {
+        false
+    }
+
+    /// Whether programs are compiled with the VM's debugging features.
+    fn debugging_features(&self) -> bool {
+        self.config.debugging_features || self.debug_execution()
+    }
+
+    /// Resolves the configured [`BlockhashSource`] into a concrete blockhash.
+    fn resolve_blockhash(&self) -> Result<Hash, String> {
+        match self.config.blockhash {
+            BlockhashSource::Default => Ok(Hash::default()),
+            BlockhashSource::Latest => self
+                .account_loader
+                .source()
+                .rpc_client()
+                .ok_or_else(|| {
+                    "Latest blockhash requires an RPC-backed account source".to_string()
+                })?
+                .get_latest_blockhash()
+                .map_err(|err| format!("Failed to fetch latest blockhash: {}", err)),
+            BlockhashSource::Explicit(hash) => Ok(hash),
+        }
+    }
+
+    /// Builds the feature set selected by [`RollUpChannelConfig::feature_set`].
+    fn resolve_feature_set(&self) -> Result<FeatureSet, String> {
+        let rpc_client = || {
+            self.account_loader
+                .source()
+                .rpc_client()
+                .ok_or_else(|| "Cluster features require an RPC-backed account source".to_string())
+        };
+        match self.config.feature_set {
+            FeatureSetSource::AllEnabled => Ok(FeatureSet::all_enabled()),
+            FeatureSetSource::FromCluster => feature_set_from_cluster(rpc_client()?),
+            FeatureSetSource::FromClusterPendingAt(slot) => {
+                feature_set_from_cluster_pending_at(rpc_client()?, slot)
+            }
+        }
+        .map_err(|err| format!("Failed to fetch the cluster's features: {}", err))
+    }
+
+    /// Resolves the configured [`RentSource`] into rent parameters and an epoch schedule,
+    /// fetching them from the cluster on first use.
+    fn resolve_rent(&self) -> Result<(Rent, EpochSchedule), String> {
+        if self.config.rent_source == RentSource::Explicit {
+            return Ok((self.config.rent.clone(), self.config.epoch_schedule.clone()));
+        }
+        let mut cluster_rent = self.cluster_rent.lock().unwrap();
+        if let Some(cached) = cluster_rent.as_ref() {
+            return Ok(cached.clone());
+        }
+        let rpc_client = self
+            .account_loader
+            .source()
+            .rpc_client()
+            .ok_or_else(|| "Cluster rent requires an RPC-backed account source".to_string())?;
+        let epoch_schedule = rpc_client
+            .get_epoch_schedule()
+            .map_err(|err| format!("Failed to fetch the epoch schedule: {}", err))?;
+        let rent_account = rpc_client
+            .get_account(&sysvar::rent::id())
+            .map_err(|err| format!("Failed to fetch the rent sysvar: {}", err))?;
+        let rent = from_account::<Rent, _>(&rent_account)
+            .ok_or_else(|| "Failed to decode the rent sysvar".to_string())?;
+        Ok(cluster_rent.insert((rent, epoch_schedule)).clone())
     }
 
     /// Simulates a batch of Solana transactions using the SVM runtime.
     ///
     /// This method:
     /// 1. Converts `Transaction`s into `SanitizedTransaction`s
-    /// 2. Creates an SVM batch processor with default settings
+    /// 2. Derives an SVM batch processor from the channel's, keeping its program cache
     /// 3. Executes the transactions using the processor
     /// 4. Returns execution results, including compute units used and logs
     pub fn process_rollup_transfers(&self, transactions: &[Transaction]) -> Vec<ReturnStruct> {
-        // Step 1: Convert raw transactions into sanitized format required by the SVM processor.
-        let sanitized = transactions
-            .iter()
-            .map(|tx| SolanaSanitizedTransaction::from_transaction_for_tests(tx.clone()))
-            .collect::<Vec<SolanaSanitizedTransaction>>();
+        // Resolve the blockhash and build the processor up front so a failing RPC call
+        // doesn't leave us half set up.
+        let context = match self.create_processing_context(self.slot()) {
+            Ok(context) => context,
+            Err(err) => {
+                return sanitize_transactions(transactions)
+                    .iter()
+                    .map(|tx| {
+                        let mut failed = ReturnStruct::infrastructure_failure(&err);
+                        failed.identify(tx);
+                        failed
+                    })
+                    .collect();
+            }
+        };
 
-        // Default configuration values for SVM transaction simulation.
-        // These can be overridden later if custom behavior is needed.
-        let compute_budget = ComputeBudget::default();
-        let feature_set = Arc::new(FeatureSet::all_enabled());
-        let fee_structure = FeeStructure::default();
-        let _rent_collector = RentCollector::default();
+        let batch = self.execute_batch(&context, transactions, None);
+        self.forget_modified_programs(&context, &batch);
+        self.maintain_program_cache(&context, context.slot);
 
-        // Custom account loader implementation for fetching account data via the RPC client.
-        let account_loader = RollUpAccountLoader::new(&self.rpc_client);
+        // Step 3: Parse each transaction result and convert it into a ReturnStruct.
+        let mut return_results = self.convert_results(&batch);
+
+        // If there were no results but transactions were submitted,
+        // return a fallback result to avoid empty output.
+        if return_results.is_empty() && !transactions.is_empty() {
+            return_results.push(ReturnStruct::no_results());
+        }
+
+        return_results
+    }
+
+    /// Computes which transactions of the batch contend on the same accounts, without
+    /// executing anything.
+    ///
+    /// Any parallel execution of a batch is expected to follow [`LockAnalysis::groups`].
+    pub fn analyze_locks(&self, transactions: &[Transaction]) -> LockAnalysis {
+        LockAnalysis::new(&sanitize_transactions(transactions))
+    }
+
+    /// Simulates a sequence of blocks, each one executing against the state committed
+    /// by all previous blocks.
+    ///
+    /// The processor slot advances by one per block (starting at the channel's
+    /// [slot](Self::slot)), and the Clock sysvar follows it, so Clock-reading programs and program-cache
+    /// visibility behave as they would across consecutive slots. Within a block,
+    /// transactions see each other's writes in order.
+    ///
+    /// A failing transaction or block never prevents later blocks from executing: they
+    /// simply run against whatever state was committed. Committed state is kept in the
+    /// channel's account loader, so later calls on this channel observe it too.
+    ///
+    /// When [`RollUpChannelConfig::batch_cu_limit`] is set, the block is executed one
+    /// transaction at a time and stops at the first one whose executed units would take
+    /// the block's total past the limit. That transaction is executed to measure its
+    /// units, but its effects, timings and deployed programs are discarded; later ones
+    /// aren't executed at all. They all fail with `WouldExceedMaxBlockCostLimit`, commit
+    /// nothing, and [`BlockResult::cutoff`] reports where the cut happened.
+    pub fn process_blocks(&self, blocks: &[Vec<Transaction>]) -> Vec<BlockResult> {
+        let first_slot = self.slot();
+        let mut context = match self.create_processing_context(first_slot) {
+            Ok(context) => context,
+            Err(err) => {
+                return blocks
+                    .iter()
+                    .enumerate()
+                    .map(|(i, block)| BlockResult {
+                        slot: first_slot + i as Slot,
+                        results: sanitize_transactions(block)
+                            .iter()
+                            .map(|tx| {
+                                let mut failed = ReturnStruct::infrastructure_failure(&err);
+                                failed.identify(tx);
+                                failed
+                            })
+                            .collect(),
+                        total_cu: 0,
+                        total_fees: 0,
+                        cutoff: None,
+                        infrastructure_error: !block.is_empty(),
+                        timings: ExecutionTimings::default(),
+                    })
+                    .collect();
+            }
+        };
+
+        let mut block_results = Vec::with_capacity(blocks.len());
+        for (i, block) in blocks.iter().enumerate() {
+            let slot = first_slot + i as Slot;
+            if i > 0 {
+                // Child processors share the program cache, so compiled programs carry over
+                // as long as the fork graph knows the slot descends from the previous ones.
+                self.register_slot(slot);
+                context.processor = context.processor.new_from(slot, self.config.epoch);
+                self.refresh_sysvars(&context, slot);
+            }
+
+            let batch = self.execute_batch(&context, block, self.config.batch_cu_limit);
+            self.maintain_program_cache(&context, slot);
+            self.commit_results(&context, &batch);
+
+            let total_fees = batch
+                .output
+                .processing_results
+                .iter()
+                .take(batch.cutoff.unwrap_or(usize::MAX))
+                .filter_map(|result| result.as_ref().ok())
+                .map(|processed_tx| processed_tx.fee_details().total_fee())
+                .sum();
+            let results = self.convert_results(&batch);
+            let total_cu = results.iter().map(|result| result.cu).sum();
+            let infrastructure_error = results.iter().any(|result| result.infrastructure_error);
+
+            block_results.push(BlockResult {
+                slot,
+                results,
+                total_cu,
+                total_fees,
+                cutoff: batch.cutoff,
+                infrastructure_error,
+                timings: ExecutionTimings::from_execute_timings(&batch.output.execute_timings),
+            });
+        }
+
+        block_results
+    }
+
+    /// Builds the processor and environment inputs shared by every batch of a call, the
+    /// processor executing at `slot`.
+    ///
+    /// The processor derives from the channel's, so programs compiled by earlier calls
+    /// carry over; a new one is built the first time and whenever the feature set changes.
+    fn create_processing_context(&self, slot: Slot) -> Result<ProcessingContext<F>, String> {
+        let blockhash = self.resolve_blockhash()?;
+        let feature_set = Arc::new(self.resolve_feature_set()?);
+        let feature_set_hash = SimulationContext::feature_set_hash(&feature_set);
+        let (rent, epoch_schedule) = self.resolve_rent()?;
+
+        self.register_slot(slot);
+        let processor = {
+            let mut root = self.processor.lock().unwrap();
+            match &*root {
+                Some((hash, processor)) if *hash == feature_set_hash => {
+                    processor.new_from(slot, self.config.epoch)
+                }
+                _ => {
+                    let processor = self.build_processor(slot, &feature_set)?;
+                    let child = processor.new_from(slot, self.config.epoch);
+                    *root = Some((feature_set_hash, processor));
+                    child
+                }
+            }
+        };
+
+        let context = ProcessingContext {
+            processor,
+            slot,
+            feature_set_hash,
+            feature_set,
+            blockhash,
+            rent,
+            epoch_schedule,
+        };
+        self.refresh_sysvars(&context, slot);
+
+        Ok(context)
+    }
+
+    /// Builds a processor executing at `slot` with `feature_set`, its program cache empty
+    /// but for the builtins.
+    fn build_processor(
+        &self,
+        slot: Slot,
+        feature_set: &Arc<FeatureSet>,
+    ) -> Result<TransactionBatchProcessor<F>, String> {
+        let compute_budget = self.config.compute_budget.unwrap_or_default();
 
         // Create an SVM-compatible transaction batch processor.
         // This is the entry point for executing transactions against the Solana runtime logic.
-        let fork_graph = Arc::new(RwLock::new(ForkRollUpGraph {}));
-        let processor = create_transaction_batch_processor(
-            &account_loader,
-            &feature_set,
+        let runtime_environment = create_program_runtime_environment_v1(
+            feature_set,
             &compute_budget,
-            Arc::clone(&fork_graph),
+            self.config.reject_broken_elfs,
+            self.debugging_features(),
+        )
+        .map_err(|err| format!("Failed to create the program runtime environment: {}", err))?;
+        let mut builder = ProcessorBuilder::new(Arc::clone(&self.fork_graph))
+            .slot(slot)
+            .epoch(self.config.epoch)
+            .runtime_environment(Arc::new(runtime_environment));
+        // Every feature being active doesn't mean the cluster migrated its builtins: keep
+        // them, since their core BPF versions may not be available from the source.
+        if self.config.feature_set != FeatureSetSource::AllEnabled {
+            builder = builder.feature_set(Arc::clone(feature_set));
+        }
+        for builtin in &self.config.builtins {
+            builder = builder.with_builtin(builtin.clone());
+        }
+        #[cfg(feature = "loader-v4")]
+        if self.config.loader_v4 {
+            builder = builder
+                .runtime_environment_v2(Arc::new(create_program_runtime_environment_v2(
+                    &compute_budget,
+                    self.debugging_features(),
+                )))
+                .loader_v4();
+        }
+        Ok(builder.build(&self.account_loader))
+    }
+
+    /// Occupancy of the program cache after the last batch, and its hits, misses and
+    /// evictions over every batch this channel processed.
+    ///
+    /// The program cache is kept across calls, and warmed up by [`warm_up`](Self::warm_up).
+    /// It starts over, empty, when the feature set the channel executes with changes.
+    /// Programs the source upgrades after they were compiled keep executing their compiled
+    /// version until evicted, see [`RollUpChannelConfig::program_cache_limits`].
+    pub fn program_cache_stats(&self) -> ProgramCacheStats {
+        *self.program_cache_stats.lock().unwrap()
+    }
+
+    /// The slot the next batches execute at.
+    pub fn slot(&self) -> Slot {
+        self.slot.load(Ordering::Relaxed)
+    }
+
+    /// Moves the channel to the next slot, returning it.
+    ///
+    /// Later batches execute at that slot: the fork graph registers it as the new tip,
+    /// and the Clock and SlotHashes sysvars follow it. Programs deployed in the previous
+    /// slot become invokable, as on the cluster, see
+    /// [`ProcessorBuilder::slot`](crate::ProcessorBuilder::slot).
+    pub fn advance_slot(&self) -> Slot {
+        let slot = self.slot.fetch_add(1, Ordering::Relaxed) + 1;
+        self.register_slot(slot);
+        self.pin_sysvars(slot);
+        slot
+    }
+
+    /// Where the SVM spent its time executing every batch this channel processed: loading
+    /// accounts, setting up VMs, and running each program.
+    ///
+    /// The blocks of [`process_blocks`](Self::process_blocks) also report their own
+    /// timings, see [`BlockResult::timings`].
+    pub fn execution_timings(&self) -> ExecutionTimings {
+        self.execution_timings.lock().unwrap().clone()
+    }
+
+    /// Evicts compiled programs beyond [`RollUpChannelConfig::program_cache_limits`]
+    /// after a batch executed at `slot`, and records the cache's occupancy.
+    fn maintain_program_cache(&self, context: &ProcessingContext<F>, slot: Slot) {
+        let mut program_cache = context.processor.program_cache.write().unwrap();
+        let evicted = self
+            .config
+            .program_cache_limits
+            .evict(&mut program_cache, slot);
+        let entries = program_cache.get_flattened_entries(true, true);
+
+        let mut stats = self.program_cache_stats.lock().unwrap();
+        stats.entries = entries.len();
+        stats.bytes = entries
+            .iter()
+            .map(|(_, entry)| entry.account_size as u64)
+            .sum();
+        stats.hits += program_cache.stats.hits.swap(0, Ordering::Relaxed);
+        stats.misses += program_cache.stats.misses.swap(0, Ordering::Relaxed);
+        stats.evictions += evicted as u64;
+    }
+
+    /// Drops the programs deployed by a batch that isn't committed from the program cache,
+    /// so later calls don't execute programs their accounts don't hold.
+    fn forget_modified_programs(&self, context: &ProcessingContext<F>, batch: &ExecutedBatch) {
+        let modified: Vec<Pubkey> = batch
+            .output
+            .processing_results
+            .iter()
+            .filter_map(|result| match result {
+                Ok(ProcessedTransaction::Executed(executed_tx)) => {
+                    Some(executed_tx.programs_modified_by_tx.keys().copied())
+                }
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        if !modified.is_empty() {
+            context
+                .processor
+                .program_cache
+                .write()
+                .unwrap()
+                .remove_programs(modified.into_iter());
+        }
+    }
+
+    /// Registers `slot` on the fork graph if it is a [`ForkRollUpGraph`]; custom graphs
+    /// track slots themselves.
+    fn register_slot(&self, slot: Slot) {
+        let mut fork_graph = self.fork_graph.write().unwrap();
+        if let Some(graph) = (&mut *fork_graph as &mut dyn Any).downcast_mut::<ForkRollUpGraph>() {
+            graph.register_slot(slot);
+        }
+    }
+
+    /// Refills the processor's sysvar cache for `slot`.
+    ///
+    /// The RecentBlockhashes, Clock and SlotHashes sysvars are served by the loader with
+    /// values matching the environment blockhash and the processor slot, so the sysvar cache
+    /// (filled from the loader) and the accounts programs read agree with each other. At
+    /// the context's own slot, the cache is first populated from
+    /// [`RollUpChannelConfig::sysvars`].
+    fn refresh_sysvars(&self, context: &ProcessingContext<F>, slot: Slot) {
+        let fee_structure = FeeStructure::default();
+        self.account_loader.insert_cached(
+            sysvar::recent_blockhashes::id(),
+            create_recent_blockhashes_account(
+                &context.blockhash,
+                fee_structure.lamports_per_signature,
+            ),
         );
-        println!("transaction batch processor created ");
+
+        if slot == context.slot {
+            populate_sysvars(
+                &context.processor,
+                &self.account_loader,
+                &self.config.sysvars,
+            );
+            if slot == self.config.slot {
+                return;
+            }
+        }
+
+        self.pin_sysvars(slot);
+        context.processor.reset_sysvar_cache();
+        context
+            .processor
+            .fill_missing_sysvar_cache_entries(&self.account_loader);
+    }
+
+    /// Pins the loader's Clock and SlotHashes to `slot`, past the configured one.
+    fn pin_sysvars(&self, slot: Slot) {
+        let mut clock = self
+            .account_loader
+            .get_account_shared_data(&sysvar::clock::id())
+            .and_then(|account| from_account::<Clock, _>(&account))
+            .unwrap_or_default();
+        clock.slot = slot;
+        self.account_loader.insert_cached(
+            sysvar::clock::id(),
+            create_account_shared_data_for_test(&clock),
+        );
+
+        let mut slot_hashes = self
+            .account_loader
+            .get_account_shared_data(&sysvar::slot_hashes::id())
+            .and_then(|account| from_account::<SlotHashes, _>(&account))
+            .unwrap_or_default();
+        slot_hashes.add(slot.saturating_sub(1), Hash::default());
+        self.account_loader.insert_cached(
+            sysvar::slot_hashes::id(),
+            create_account_shared_data_for_test(&slot_hashes),
+        );
+    }
+
+    /// Verifies the BPF programs `tx` invokes, returning the error of the first one that
+    /// doesn't compile. Programs that verified are remembered, by ELF hash, for later
+    /// batches.
+    fn verify_programs(&self, tx: &SolanaSanitizedTransaction) -> Option<ProgramLoadError> {
+        tx.message()
+            .program_instructions_iter()
+            .find_map(|(program_id, _)| {
+                let program = self.account_loader.load(program_id).ok()??;
+                let elf = program_elf(&program, |pubkey| {
+                    self.account_loader.load(pubkey).ok().flatten()
+                })?;
+                let key = (*program_id, hash(&elf));
+                if self.verified_programs.lock().unwrap().contains(&key) {
+                    return None;
+                }
+                match verify_elf(
+                    program_id,
+                    &elf,
+                    program.owner(),
+                    self.config.reject_broken_elfs,
+                ) {
+                    Ok(()) => {
+                        self.verified_programs.lock().unwrap().insert(key);
+                        None
+                    }
+                    Err(err) => Some(err),
+                }
+            })
+    }
+
+    /// Executes one batch of transactions against the context's processor, stopping
+    /// before the transaction that would take the executed units past `cu_limit`.
+    fn execute_batch(
+        &self,
+        context: &ProcessingContext<F>,
+        transactions: &[Transaction],
+        cu_limit: Option<u64>,
+    ) -> ExecutedBatch {
+        // Account fetches made while processing the batch are traced inside this span.
+        #[cfg(feature = "tracing")]
+        let _span =
+            tracing::info_span!("execute_batch", transactions = transactions.len()).entered();
+
+        // Step 1: Convert raw transactions into sanitized format required by the SVM processor.
+        let sanitized = sanitize_transactions(transactions);
+        self.account_loader.reset_accessed_accounts();
+
+        // Keep a bounded cache from evicting the batch's accounts before they are used.
+        let _pinned = self
+            .account_loader
+            .cache()
+            .pin(sanitized.iter().flat_map(|tx| {
+                tx.message()
+                    .account_keys()
+                    .iter()
+                    .copied()
+                    .collect::<Vec<_>>()
+            }));
+
+        // Fetch every account the batch references up front, in as few requests as possible.
+        // Keys that fail here are retried, and reported, by the per-key check below, except
+        // those strict slot consistency gave up on: a per-key read could land at any slot.
+        let prefetch = self.account_loader.prefetch_transactions(&sanitized);
+        let inconsistent: HashMap<Pubkey, AccountLoadError> = prefetch
+            .failed
+            .into_iter()
+            .filter_map(|err| match err {
+                AccountLoadError::Inconsistent { pubkey, .. } => Some((pubkey, err)),
+                _ => None,
+            })
+            .collect();
+
+        // Stop transactions whose accounts can't be provided, then give middlewares a
+        // chance to veto the rest, before anything runs.
+        let zk_proof_program_enabled =
+            self.config.builtins.iter().any(|builtin| {
+                solana_sdk_ids::zk_elgamal_proof_program::check_id(&builtin.program_id)
+            });
+        let rejections: Vec<Option<Rejection>> = sanitized
+            .iter()
+            .enumerate()
+            .map(|(index, tx)| {
+                // Without its builtin, the program's account is usually missing too.
+                if !zk_proof_program_enabled
+                    && tx
+                        .message()
+                        .program_instructions_iter()
+                        .any(|(program_id, _)| {
+                            solana_sdk_ids::zk_elgamal_proof_program::check_id(program_id)
+                        })
+                {
+                    return Some(Rejection {
+                        message: format!(
+                            "Transaction {} failed: {}",
+                            index,
+                            ProgramLoadError::ZkProofProgramNotEnabled
+                        ),
+                        error: Some(TransactionError::InvalidProgramForExecution),
+                        infrastructure: false,
+                        status: TxStatus::NotProcessed,
+                    });
+                }
+                let load_error = tx.message().account_keys().iter().find_map(|key| {
+                    match inconsistent.get(key) {
+                        Some(err) => Some(err.clone()),
+                        None => self.account_loader.load(key).err(),
+                    }
+                });
+                if let Some(err) = load_error {
+                    return Some(Rejection {
+                        message: format!("Transaction {} failed: {}", index, err),
+                        error: match err {
+                            AccountLoadError::Missing(_) => Some(TransactionError::AccountNotFound),
+                            _ => None,
+                        },
+                        infrastructure: err.is_infrastructure(),
+                        status: TxStatus::NotProcessed,
+                    });
+                }
+                let denied = self.config.program_filter.first_denied(
+                    tx.message()
+                        .program_instructions_iter()
+                        .map(|(program_id, _)| program_id),
+                );
+                if let Some(program_id) = denied {
+                    return Some(Rejection {
+                        message: format!(
+                            "Transaction {} failed: {}",
+                            index,
+                            ProgramLoadError::Denied { program_id }
+                        ),
+                        error: Some(TransactionError::InvalidProgramForExecution),
+                        infrastructure: false,
+                        status: TxStatus::NotProcessed,
+                    });
+                }
+                if self.config.verify_programs {
+                    if let Some(err) = self.verify_programs(tx) {
+                        return Some(Rejection {
+                            message: format!("Transaction {} failed: {}", index, err),
+                            error: Some(TransactionError::InvalidProgramForExecution),
+                            infrastructure: false,
+                            status: TxStatus::NotProcessed,
+                        });
+                    }
+                }
+
+                self.middlewares
+                    .iter()
+                    .find_map(|middleware| match middleware.before(index, tx) {
+                        ControlFlow::Break(reject) => Some(reject),
+                        ControlFlow::Continue(()) => None,
+                    })
+                    .map(|reject| Rejection {
+                        message: format!(
+                            "Transaction {} rejected by middleware: {}",
+                            index, reject.message
+                        ),
+                        error: None,
+                        infrastructure: false,
+                        status: TxStatus::Cancelled,
+                    })
+            })
+            .collect();
+
+        // Derive each transaction's effective compute unit limit the way the runtime will:
+        // a configured budget replaces the limits transactions request.
+        let compute_unit_limits = sanitized
+            .iter()
+            .map(|tx| {
+                get_compute_unit_limit(tx, &context.feature_set).map(|limit| {
+                    self.config.compute_budget.map_or(limit, |budget| {
+                        u32::try_from(budget.compute_unit_limit).unwrap_or(u32::MAX)
+                    })
+                })
+            })
+            .collect();
 
         // Create a simulation environment, similar to a Solana runtime slot.
-        let processing_environment = TransactionProcessingEnvironment {
-            blockhash: Hash::default(),
-            blockhash_lamports_per_signature: fee_structure.lamports_per_signature,
-            epoch_total_stake: 0,
-            feature_set,
-            fee_lamports_per_signature: 5000,
-            rent_collector: None,
+        let mut environment = ProcessingEnvironmentBuilder::deterministic()
+            .blockhash(context.blockhash)
+            // Known once a program asked for a vote account's stake, or if configured.
+            .epoch_total_stake(self.account_loader.epoch_total_stake())
+            .feature_set(Arc::clone(&context.feature_set))
+            .rent_collector(RentCollector {
+                epoch: self.config.epoch,
+                epoch_schedule: context.epoch_schedule.clone(),
+                rent: context.rent.clone(),
+                ..RentCollector::default()
+            });
+        // The fees transactions would pay, reported in place of the zero fees charged.
+        let lamports_per_signature = FeeStructure::default().lamports_per_signature;
+        let uncharged_fees = if self.config.charge_fees {
+            Vec::new()
+        } else {
+            environment = environment
+                .blockhash_lamports_per_signature(0)
+                .fee_lamports_per_signature(0);
+            sanitized
+                .iter()
+                .map(|tx| {
+                    transaction_fee(tx, lamports_per_signature, &context.feature_set)
+                        .unwrap_or_default()
+                })
+                .collect()
         };
+        let processing_environment = environment.build();
 
-        // Use the default transaction processing config.
-        // Can be extended to support more fine-grained control.
-        let processing_config = TransactionProcessingConfig::default();
-
-        println!("transaction processing_config created ");
+        // Use the default transaction processing config, recording what tracing, debugging
+        // and the program filter need, and the configured compute budget.
+        let processing_config = TransactionProcessingConfig {
+            recording_config: ExecutionRecordingConfig {
+                enable_cpi_recording: self.config.record_trace
+                    || self.config.program_filter != ProgramFilter::AllowAll,
+                enable_log_recording: self.config.record_trace || self.debug_execution(),
+                enable_return_data_recording: self.config.record_return_data,
+            },
+            compute_budget: self.config.compute_budget,
+            ..TransactionProcessingConfig::default()
+        };
 
-        // Step 2: Execute the sanitized transactions using the simulated runtime.
-        let results = processor.load_and_execute_sanitized_transactions(
-            &account_loader,
+        let mut check_results = check_transactions(
             &sanitized,
-            get_transaction_check_results(transactions.len()),
-            &processing_environment,
-            &processing_config,
+            &self.account_loader,
+            &CheckConfig {
+                reject_duplicates: self.config.reject_duplicate_transactions,
+                lamports_per_signature,
+                charge_fees: self.config.charge_fees,
+                rent: &context.rent,
+                feature_set: &context.feature_set,
+            },
         );
-        println!("Executed");
+        if self.config.sanitization == SanitizationMode::FullChecks {
+            for (tx, check_result) in sanitized.iter().zip(check_results.iter_mut()) {
+                if check_result.is_ok() {
+                    if let Err(err) = tx.verify() {
+                        *check_result = Err(err);
+                    }
+                }
+            }
+            verify_precompiles(&sanitized, &context.feature_set, &mut check_results);
+        }
+        // Rejected transactions fail the pre-check so the SVM neither executes them nor
+        // produces state to commit. The error itself is replaced by the rejection reason.
+        for (check_result, rejection) in check_results.iter_mut().zip(&rejections) {
+            if rejection.is_some() {
+                *check_result = Err(TransactionError::CommitCancelled);
+            }
+        }
 
-        // Step 3: Parse each transaction result and convert it into a ReturnStruct.
+        // Step 2: Execute the sanitized transactions using the simulated runtime.
+        // Debugged transactions are executed one at a time so the runtime's output can be
+        // told apart, and limited batches so they can stop once the limit is reached.
+        let (output, mut execution_times, cutoff) =
+            if self.config.record_execution_time || self.debug_execution() || cu_limit.is_some() {
+                self.execute_sequentially(
+                    context,
+                    &sanitized,
+                    check_results,
+                    &processing_environment,
+                    &processing_config,
+                    cu_limit,
+                )
+            } else {
+                let output = context.processor.load_and_execute_sanitized_transactions(
+                    &self.account_loader,
+                    &sanitized,
+                    check_results,
+                    &processing_environment,
+                    &processing_config,
+                );
+                (output, Vec::new(), None)
+            };
+        if !self.config.record_execution_time {
+            execution_times.clear();
+        }
+
+        self.execution_timings
+            .lock()
+            .unwrap()
+            .accumulate(&ExecutionTimings::from_execute_timings(
+                &output.execute_timings,
+            ));
+
+        let effects = self.effects(&sanitized, &output);
+        ExecutedBatch {
+            sanitized,
+            effects,
+            execution_times,
+            compute_unit_limits,
+            uncharged_fees,
+            rejections,
+            output,
+            cutoff,
+            context: SimulationContext {
+                slot: prefetch.context_slot,
+                blockhash: context.blockhash,
+                feature_set_hash: context.feature_set_hash,
+            },
+        }
+    }
+
+    /// Executes a batch one transaction at a time, timing each one.
+    ///
+    /// Every transaction sees the accounts left by the earlier ones, and the programs they
+    /// deployed, as within a single SVM call, but nothing is written to the loader.
+    ///
+    /// With a `cu_limit`, execution stops at the first transaction whose executed units
+    /// would take the total past it, which is returned as the cutoff: its result, timings
+    /// and program changes are discarded, and it and every later transaction are reported
+    /// as `WouldExceedMaxBlockCostLimit` without later ones being executed.
+    fn execute_sequentially(
+        &self,
+        context: &ProcessingContext<F>,
+        sanitized: &[SolanaSanitizedTransaction],
+        check_results: Vec<TransactionCheckResult>,
+        environment: &TransactionProcessingEnvironment,
+        config: &TransactionProcessingConfig,
+        cu_limit: Option<u64>,
+    ) -> (
+        LoadAndExecuteSanitizedTransactionsOutput,
+        Vec<Duration>,
+        Option<usize>,
+    ) {
+        let overlay = BatchOverlay {
+            loader: &self.account_loader,
+            accounts: RwLock::default(),
+        };
+        let mut output = LoadAndExecuteSanitizedTransactionsOutput {
+            error_metrics: Default::default(),
+            execute_timings: Default::default(),
+            processing_results: Vec::with_capacity(sanitized.len()),
+        };
+        let mut execution_times = Vec::with_capacity(sanitized.len());
+        let mut consumed: u64 = 0;
+        let mut cutoff = None;
+
+        for (tx, check_result) in sanitized.iter().zip(check_results) {
+            // Marks what the runtime emits with the transaction's index in the batch.
+            #[cfg(feature = "tracing")]
+            let index = output.processing_results.len();
+            #[cfg(feature = "tracing")]
+            let _span = self
+                .config
+                .debug_execution
+                .then(|| tracing::trace_span!("transaction", index).entered());
+            let start = Instant::now();
+            let single = context.processor.load_and_execute_sanitized_transactions(
+                &overlay,
+                std::slice::from_ref(tx),
+                vec![check_result],
+                environment,
+                config,
+            );
+            let elapsed = start.elapsed();
+
+            if let Some(limit) = cu_limit {
+                consumed = consumed.saturating_add(
+                    single
+                        .processing_results
+                        .iter()
+                        .map(executed_units)
+                        .sum::<u64>(),
+                );
+                if consumed > limit {
+                    cutoff = Some(output.processing_results.len());
+                    break;
+                }
+            }
+
+            execution_times.push(elapsed);
+            output.error_metrics.accumulate(&single.error_metrics);
+            output.execute_timings.accumulate(&single.execute_timings);
+            for result in single.processing_results {
+                #[cfg(feature = "tracing")]
+                if self.config.debug_execution {
+                    trace_processing_result(index, &result);
+                }
+                let mut accounts = overlay.accounts.write().unwrap();
+                for (pubkey, account) in resulting_accounts(tx, &result) {
+                    accounts.insert(*pubkey, account.clone());
+                }
+                if let Ok(ProcessedTransaction::Executed(executed_tx)) = &result {
+                    if executed_tx.execution_details.status.is_ok() {
+                        context
+                            .processor
+                            .program_cache
+                            .write()
+                            .unwrap()
+                            .merge(&executed_tx.programs_modified_by_tx);
+                    }
+                }
+                output.processing_results.push(result);
+            }
+        }
+        // Transactions from the cutoff on are left out of the batch.
+        output.processing_results.resize_with(sanitized.len(), || {
+            Err(TransactionError::WouldExceedMaxBlockCostLimit)
+        });
+        (output, execution_times, cutoff)
+    }
+
+    /// Writes the post-execution state of a batch back into the account loader.
+    ///
+    /// Successful transactions commit every writable account and any program they
+    /// (re)deployed; failed and fees-only transactions only commit their rollback
+    /// accounts (the fee payer after the fee debit, and an advanced nonce).
+    ///
+    /// Transactions past the batch's cutoff commit nothing.
+    fn commit_results(&self, context: &ProcessingContext<F>, batch: &ExecutedBatch) {
+        for (tx, result) in batch
+            .sanitized
+            .iter()
+            .zip(&batch.output.processing_results)
+            .take(batch.cutoff.unwrap_or(usize::MAX))
+        {
+            for (pubkey, account) in resulting_accounts(tx, result) {
+                self.account_loader.commit_account(*pubkey, account.clone());
+            }
+            if let Ok(ProcessedTransaction::Executed(executed_tx)) = result {
+                if executed_tx.execution_details.status.is_ok() {
+                    context
+                        .processor
+                        .program_cache
+                        .write()
+                        .unwrap()
+                        .merge(&executed_tx.programs_modified_by_tx);
+                }
+            }
+        }
+    }
+
+    /// What every transaction of the batch did to its accounts: their balances before and
+    /// after it ran, in the order of its account keys, and the accounts it changed.
+    ///
+    /// Transactions see the state left by the earlier ones of the batch, as they do when
+    /// executed, so states are chained through the batch rather than all read from the
+    /// loader.
+    fn effects(
+        &self,
+        sanitized: &[SolanaSanitizedTransaction],
+        output: &LoadAndExecuteSanitizedTransactionsOutput,
+    ) -> Vec<TransactionEffects> {
+        let mut current: HashMap<Pubkey, Option<AccountSharedData>> = HashMap::new();
+        sanitized
+            .iter()
+            .zip(&output.processing_results)
+            .map(|(tx, result)| {
+                let keys = tx.message().account_keys();
+                let pre_states: Vec<Option<AccountSharedData>> = keys
+                    .iter()
+                    .map(|key| {
+                        current
+                            .entry(*key)
+                            .or_insert_with(|| self.account_loader.load(key).ok().flatten())
+                            .clone()
+                    })
+                    .collect();
+                let pre_balances: Vec<u64> = pre_states
+                    .iter()
+                    .map(|state| state.as_ref().map_or(0, |account| account.lamports()))
+                    .collect();
+
+                // Failed transactions only leave their rollback accounts, which don't
+                // tell whether their writable accounts would have been touched.
+                let succeeded = matches!(
+                    result,
+                    Ok(ProcessedTransaction::Executed(executed_tx))
+                        if executed_tx.execution_details.status.is_ok()
+                );
+
+                let mut post_balances = pre_balances.clone();
+                let mut account_changes = Vec::new();
+                let mut writable_accounts_modified = Vec::new();
+                for (pubkey, account) in resulting_accounts(tx, result) {
+                    let index = keys.iter().position(|key| key == pubkey);
+                    if let Some(index) = index {
+                        post_balances[index] = account.lamports();
+                        let (old_lamports, old_data) = pre_states[index]
+                            .as_ref()
+                            .map_or((0, &[][..]), |old| (old.lamports(), old.data()));
+                        if succeeded
+                            && (old_lamports != account.lamports() || old_data != account.data())
+                        {
+                            writable_accounts_modified.push(*pubkey);
+                        }
+                        account_changes.extend(AccountChange::between(
+                            *pubkey,
+                            pre_states[index].as_ref(),
+                            account,
+                            self.config.record_account_data,
+                        ));
+                    }
+                    current.insert(*pubkey, Some(account.clone()));
+                }
+                TransactionEffects {
+                    pre_balances,
+                    post_balances,
+                    account_changes,
+                    writable_accounts_modified,
+                }
+            })
+            .collect()
+    }
+
+    /// Converts raw SVM processing results into `ReturnStruct`s, one per transaction.
+    fn convert_results(&self, batch: &ExecutedBatch) -> Vec<ReturnStruct> {
         let mut return_results = Vec::new();
 
-        for (i, transaction_result) in results.processing_results.iter().enumerate() {
-            let tx_result = match transaction_result {
-                Ok(processed_tx) => {
-                    match processed_tx {
-                        ProcessedTransaction::Executed(executed_tx) => {
-                            let cu = executed_tx.execution_details.executed_units;
-                            let logs = executed_tx.execution_details.log_messages.clone();
-                            let status = executed_tx.execution_details.status.clone();
-                            let is_success = status.is_ok();
-
-                            if is_success {
-                                ReturnStruct::success(cu)
-                            } else {
-                                match status {
-                                    Err(err) => {
-                                        let error_msg =
-                                            format!("Transaction {} failed with error: {}", i, err);
-                                        let log_msg =
-                                            logs.map(|logs| logs.join("\n")).unwrap_or_default();
-                                        ReturnStruct {
-                                            success: false,
-                                            cu,
-                                            result: format!("{}\nLogs:\n{}", error_msg, log_msg),
-                                        }
-                                    }
-                                    _ => ReturnStruct::success(cu), // This shouldn't happen as we checked is_success
-                                }
+        for (i, transaction_result) in batch.output.processing_results.iter().enumerate() {
+            let cut = batch.cutoff.is_some_and(|cutoff| i >= cutoff);
+            let mut tx_result = match transaction_result {
+                _ if cut => ReturnStruct::failed_transaction(
+                    i,
+                    TransactionError::WouldExceedMaxBlockCostLimit,
+                    TxStatus::Cancelled,
+                ),
+                Ok(processed_tx) => ReturnStruct::from_processed_transaction(
+                    i,
+                    processed_tx,
+                    ConversionOptions {
+                        record_trace: self.config.record_trace,
+                    },
+                ),
+                Err(err) => match &batch.rejections[i] {
+                    Some(rejection) => {
+                        let mut rejected = if rejection.infrastructure {
+                            ReturnStruct::infrastructure_failure(&rejection.message)
+                        } else {
+                            match &rejection.error {
+                                Some(error) => ReturnStruct::transaction_failure(
+                                    error.clone(),
+                                    &rejection.message,
+                                ),
+                                None => ReturnStruct::failure(&rejection.message),
                             }
-                        }
-                        ProcessedTransaction::FeesOnly(fees_only) => {
-                            ReturnStruct::failure(format!(
-                                "Transaction {} failed with error: {}. Only fees were charged.",
-                                i, fees_only.load_error
-                            ))
-                        }
+                        };
+                        rejected.status = rejection.status;
+                        rejected
                     }
+                    None => ReturnStruct::from_processing_error(i, err),
+                },
+            };
+            if let Some(effects) = batch.effects.get(i) {
+                tx_result.pre_balances = effects.pre_balances.clone();
+                // Transactions cut from the batch change nothing.
+                if cut {
+                    tx_result.post_balances = effects.pre_balances.clone();
+                } else {
+                    tx_result.post_balances = effects.post_balances.clone();
+                    tx_result.account_changes = effects.account_changes.clone();
+                    tx_result.writable_accounts_modified =
+                        effects.writable_accounts_modified.clone();
+                }
+            }
+            if let Some(tx) = batch.sanitized.get(i) {
+                tx_result.identify(tx);
+                tx_result.record_programs_invoked(tx);
+                // Denied top-level programs stopped the transaction before execution; denied
+                // programs it invoked through CPI are only known now.
+                tx_result.denied_program = self.config.program_filter.first_denied(
+                    tx_result.programs_invoked.iter().chain(
+                        tx.message()
+                            .program_instructions_iter()
+                            .map(|(program_id, _)| program_id),
+                    ),
+                );
+                tx_result.decoded_error = tx_result
+                    .error
+                    .as_ref()
+                    .and_then(|error| self.error_decoder.decode(tx, error));
+            }
+            if let Some(fee_lamports) = batch.uncharged_fees.get(i) {
+                if tx_result.status.was_charged() {
+                    tx_result.waive_fee(i, *fee_lamports);
+                }
+            }
+            tx_result.execution_time = batch.execution_times.get(i).copied();
+            tx_result.requested_cu_limit = batch.compute_unit_limits.get(i).copied().flatten();
+            tx_result.context_slot = batch.context.slot;
+            tx_result.context = Some(batch.context);
+            tx_result.hit_compute_limit = match transaction_result {
+                Ok(ProcessedTransaction::Executed(executed_tx)) if !cut => {
+                    let exceeded = matches!(
+                        executed_tx.execution_details.status,
+                        Err(TransactionError::InstructionError(
+                            _,
+                            InstructionError::ComputationalBudgetExceeded
+                        ))
+                    );
+                    let exhausted = tx_result.requested_cu_limit.is_some_and(|limit| {
+                        executed_tx.execution_details.executed_units >= u64::from(limit)
+                    });
+                    exceeded || exhausted
                 }
-                Err(err) => ReturnStruct::failure(format!("Transaction {} failed: {}", i, err)),
+                _ => false,
             };
+            for middleware in &self.middlewares {
+                middleware.after(i, &tx_result);
+            }
             return_results.push(tx_result);
         }
 
-        /// If there were no results but transactions were submitted,
-        // return a fallback result to avoid empty output.
-        if return_results.is_empty() && !transactions.is_empty() {
-            return_results.push(ReturnStruct::no_results());
+        return_results
+    }
+}
+
+/// A batch that went through the SVM, along with the pre-check data derived for it.
+struct ExecutedBatch {
+    sanitized: Vec<SolanaSanitizedTransaction>,
+    /// What each transaction did to its accounts, see [`RollUpChannel::effects`].
+    effects: Vec<TransactionEffects>,
+    /// Time each transaction took to load and execute, empty unless
+    /// [`RollUpChannelConfig::record_execution_time`] is on.
+    execution_times: Vec<Duration>,
+    /// Effective compute unit limit per transaction, `None` if its budget instructions are invalid.
+    compute_unit_limits: Vec<Option<u32>>,
+    /// Fee each transaction would have been charged, empty unless
+    /// [`RollUpChannelConfig::charge_fees`] is off.
+    uncharged_fees: Vec<u64>,
+    /// Why each transaction was stopped before execution, `None` if it was allowed to execute.
+    rejections: Vec<Option<Rejection>>,
+    output: LoadAndExecuteSanitizedTransactionsOutput,
+    /// Index of the first transaction cut by the batch compute unit limit, if any.
+    cutoff: Option<usize>,
+    /// The state the batch was simulated against.
+    context: SimulationContext,
+}
+
+/// The channel's loader with the accounts left by the transactions of a batch executed
+/// so far laid on top, see [`RollUpChannel::execute_sequentially`].
+struct BatchOverlay<'l, 'a> {
+    loader: &'l RollUpAccountLoader<'a>,
+    accounts: RwLock<HashMap<Pubkey, AccountSharedData>>,
+}
+
+impl TransactionProcessingCallback for BatchOverlay<'_, '_> {
+    fn get_account_shared_data(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        match self.accounts.read().unwrap().get(pubkey) {
+            Some(account) => Some(account.clone()),
+            None => self.loader.get_account_shared_data(pubkey),
         }
+    }
 
-        return_results
+    fn account_matches_owners(&self, account: &Pubkey, owners: &[Pubkey]) -> Option<usize> {
+        match self.accounts.read().unwrap().get(account) {
+            Some(account) => owners.iter().position(|owner| owner == account.owner()),
+            None => self.loader.account_matches_owners(account, owners),
+        }
+    }
+
+    fn add_builtin_account(&self, name: &str, program_id: &Pubkey) {
+        self.loader.add_builtin_account(name, program_id);
+    }
+
+    fn get_current_epoch_vote_account_stake(&self, vote_address: &Pubkey) -> u64 {
+        self.loader
+            .get_current_epoch_vote_account_stake(vote_address)
+    }
+}
+
+/// What a transaction did to its accounts, see [`RollUpChannel::effects`].
+struct TransactionEffects {
+    pre_balances: Vec<u64>,
+    post_balances: Vec<u64>,
+    account_changes: Vec<AccountChange>,
+    writable_accounts_modified: Vec<Pubkey>,
+}
+
+/// Why a transaction was stopped before execution.
+struct Rejection {
+    message: String,
+    /// The runtime error equivalent to the rejection, if there is one.
+    error: Option<TransactionError>,
+    /// An account couldn't be fetched, as opposed to a policy refusing the transaction.
+    infrastructure: bool,
+    /// [`TxStatus::NotProcessed`] for accounts or programs that couldn't be provided,
+    /// [`TxStatus::Cancelled`] for middleware vetoes.
+    status: TxStatus,
+}
+
+/// Processor and environment inputs shared by every batch of a single channel call.
+struct ProcessingContext<F: ForkGraph> {
+    processor: TransactionBatchProcessor<F>,
+    /// The slot the processor was created at.
+    slot: Slot,
+    feature_set: Arc<FeatureSet>,
+    /// [`SimulationContext::feature_set_hash`] of `feature_set`.
+    feature_set_hash: Hash,
+    blockhash: Hash,
+    /// Rent parameters and epoch schedule resolved from [`RentSource`].
+    rent: Rent,
+    epoch_schedule: EpochSchedule,
+}
+
+/// Emits what the runtime recorded processing the batch's transaction `index`, see
+/// [`RollUpChannelConfig::debug_execution`].
+#[cfg(feature = "tracing")]
+fn trace_processing_result(index: usize, result: &TransactionProcessingResult) {
+    const TARGET: &str = "solana_client_ext::debug_execution";
+    match result {
+        Ok(ProcessedTransaction::Executed(executed_tx)) => {
+            let details = &executed_tx.execution_details;
+            for line in details.log_messages.iter().flatten() {
+                tracing::trace!(target: TARGET, index, "{}", line);
+            }
+            tracing::trace!(
+                target: TARGET,
+                index,
+                status = ?details.status,
+                executed_units = details.executed_units,
+                "transaction executed"
+            );
+        }
+        Ok(ProcessedTransaction::FeesOnly(fees_only)) => {
+            tracing::trace!(
+                target: TARGET,
+                index,
+                error = %fees_only.load_error,
+                "transaction failed to load, only its fee was charged"
+            );
+        }
+        Err(err) => {
+            tracing::trace!(target: TARGET, index, error = %err, "transaction not processed");
+        }
+    }
+}
+
+/// Compute units a processed transaction executed, 0 unless it was executed.
+fn executed_units(result: &TransactionProcessingResult) -> u64 {
+    match result {
+        Ok(ProcessedTransaction::Executed(executed_tx)) => {
+            executed_tx.execution_details.executed_units
+        }
+        _ => 0,
+    }
+}
+
+/// The accounts a processed transaction leaves behind, as the cluster would commit them.
+///
+/// A successful transaction commits every writable account; a failed or fees-only one
+/// only its rollback accounts: the fee payer after the fee debit, and an advanced nonce.
+fn resulting_accounts<'r>(
+    tx: &'r SolanaSanitizedTransaction,
+    result: &'r TransactionProcessingResult,
+) -> Vec<(&'r Pubkey, &'r AccountSharedData)> {
+    let rollback_accounts = match result {
+        Ok(ProcessedTransaction::Executed(executed_tx)) => {
+            if executed_tx.execution_details.status.is_ok() {
+                let message = tx.message();
+                return executed_tx
+                    .loaded_transaction
+                    .accounts
+                    .iter()
+                    .take(message.account_keys().len())
+                    .enumerate()
+                    .filter(|(index, _)| message.is_writable(*index))
+                    .map(|(_, (pubkey, account))| (pubkey, account))
+                    .collect();
+            }
+            &executed_tx.loaded_transaction.rollback_accounts
+        }
+        Ok(ProcessedTransaction::FeesOnly(fees_only)) => &fees_only.rollback_accounts,
+        Err(_) => return Vec::new(),
+    };
+
+    match rollback_accounts {
+        RollbackAccounts::FeePayerOnly { fee_payer_account } => {
+            vec![(tx.message().fee_payer(), fee_payer_account)]
+        }
+        RollbackAccounts::SameNonceAndFeePayer { nonce } => {
+            vec![(nonce.address(), nonce.account())]
+        }
+        RollbackAccounts::SeparateNonceAndFeePayer {
+            nonce,
+            fee_payer_account,
+        } => vec![
+            (nonce.address(), nonce.account()),
+            (tx.message().fee_payer(), fee_payer_account),
+        ],
+    }
+}
+
+/// Sysvars matching the processor's slot and epoch and the configured rent, for sources
+/// without them; the processor's sysvar cache is filled from the loader, so syscalls and
+/// sysvar accounts agree. SlotHashes holds the slot before the processor's, with a
+/// default hash, as real bank hashes can't be known offline.
+fn sysvar_defaults(config: &RollUpChannelConfig) -> SysvarDefaults {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() as UnixTimestamp);
+    SysvarDefaults {
+        clock: Clock {
+            slot: config.slot,
+            epoch_start_timestamp: now,
+            epoch: config.epoch,
+            leader_schedule_epoch: config.epoch + 1,
+            unix_timestamp: now,
+        },
+        rent: config.rent.clone(),
+        epoch_schedule: config.epoch_schedule.clone(),
+        slot_hashes: vec![(config.slot.saturating_sub(1), Hash::default())],
+        stake_history: Vec::new(),
     }
 }
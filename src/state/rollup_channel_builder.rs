@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use solana_program_runtime::loaded_programs::ForkGraph;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::state::account_cache::AccountCache;
+use crate::state::error_decoder::ErrorDecoder;
+use crate::state::middleware::RollUpMiddleware;
+use crate::state::request_limiter::RequestLimits;
+use crate::state::rollup_account_loader::{
+    AccountSource, MissingAccountPolicy, RollUpAccountLoader,
+};
+use crate::state::rollup_channel_config::RollUpChannelConfig;
+use crate::{ForkRollUpGraph, RollUpChannel};
+
+/// Step-by-step construction of a [`RollUpChannel`], for settings that don't fit the
+/// plain constructors.
+///
+/// Obtained through [`RollUpChannel::builder`]; every setting left untouched keeps
+/// the same default as [`RollUpChannel::with_account_source`].
+pub struct RollUpChannelBuilder<'a, F: ForkGraph = ForkRollUpGraph> {
+    keys: Vec<Pubkey>,
+    source: AccountSource<'a>,
+    config: RollUpChannelConfig,
+    fork_graph: Arc<RwLock<F>>,
+    cache_ttl: Option<Duration>,
+    max_cache_bytes: Option<u64>,
+    account_cache: Option<AccountCache>,
+    missing_account_policy: MissingAccountPolicy,
+    request_limits: RequestLimits,
+    epoch_stakes: Option<HashMap<Pubkey, u64>>,
+    middlewares: Vec<Box<dyn RollUpMiddleware + 'a>>,
+    error_decoder: ErrorDecoder,
+}
+
+impl<'a> RollUpChannelBuilder<'a> {
+    /// Starts a builder reading accounts from `source`.
+    pub fn new(source: AccountSource<'a>) -> Self {
+        Self {
+            keys: Vec::new(),
+            source,
+            config: RollUpChannelConfig::default(),
+            fork_graph: Arc::new(RwLock::new(ForkRollUpGraph::default())),
+            cache_ttl: None,
+            max_cache_bytes: None,
+            account_cache: None,
+            missing_account_policy: MissingAccountPolicy::default(),
+            request_limits: RequestLimits::default(),
+            epoch_stakes: None,
+            middlewares: Vec::new(),
+            error_decoder: ErrorDecoder::default(),
+        }
+    }
+}
+
+impl<'a, F: ForkGraph + 'static> RollUpChannelBuilder<'a, F> {
+    /// Sets the account keys handed to the channel.
+    pub fn keys(mut self, keys: Vec<Pubkey>) -> Self {
+        self.keys = keys;
+        self
+    }
+
+    /// Sets the channel's [`RollUpChannelConfig`].
+    pub fn config(mut self, config: RollUpChannelConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Expires accounts fetched from the source `ttl` after they were fetched.
+    ///
+    /// By default fetched accounts are cached for the channel's whole lifetime.
+    pub fn cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = Some(ttl);
+        self
+    }
+
+    /// Bounds the account data the channel caches to `max_bytes`, evicting the least
+    /// recently used accounts beyond it, except those of the batch being executed.
+    ///
+    /// By default the cache grows with every account the channel touches.
+    pub fn max_cache_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_cache_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Caches fetched accounts in `cache`, e.g. one shared by every channel of a server.
+    ///
+    /// The cache's own TTL and size bound apply; [`cache_ttl`](Self::cache_ttl) and
+    /// [`max_cache_bytes`](Self::max_cache_bytes) are ignored.
+    pub fn account_cache(mut self, cache: AccountCache) -> Self {
+        self.account_cache = Some(cache);
+        self
+    }
+
+    /// Sets how accounts missing from the source are handled.
+    pub fn missing_account_policy(mut self, policy: MissingAccountPolicy) -> Self {
+        self.missing_account_policy = policy;
+        self
+    }
+
+    /// Paces the requests the channel's loader sends to its source, e.g. for a
+    /// rate-limited public RPC endpoint.
+    pub fn request_limits(mut self, limits: RequestLimits) -> Self {
+        self.request_limits = limits;
+        self
+    }
+
+    /// Sets the activated stake of every vote account this epoch, as seen by programs
+    /// through `sol_get_epoch_stake`.
+    ///
+    /// By default stakes are fetched from an RPC source when a program first asks.
+    pub fn epoch_stakes(mut self, stakes: HashMap<Pubkey, u64>) -> Self {
+        self.epoch_stakes = Some(stakes);
+        self
+    }
+
+    /// Registers a [`RollUpMiddleware`], run after every previously registered one.
+    pub fn middleware(mut self, middleware: impl RollUpMiddleware + 'a) -> Self {
+        self.middlewares.push(Box::new(middleware));
+        self
+    }
+
+    /// Names the custom errors of failed transactions with `decoder`, see
+    /// [`ReturnStruct::decoded_error`](crate::ReturnStruct::decoded_error).
+    pub fn error_decoder(mut self, decoder: ErrorDecoder) -> Self {
+        self.error_decoder = decoder;
+        self
+    }
+
+    /// Makes the channel's program cache consult a custom [`ForkGraph`].
+    pub fn fork_graph<G: ForkGraph>(
+        self,
+        fork_graph: Arc<RwLock<G>>,
+    ) -> RollUpChannelBuilder<'a, G> {
+        RollUpChannelBuilder {
+            keys: self.keys,
+            source: self.source,
+            config: self.config,
+            fork_graph,
+            cache_ttl: self.cache_ttl,
+            max_cache_bytes: self.max_cache_bytes,
+            account_cache: self.account_cache,
+            missing_account_policy: self.missing_account_policy,
+            request_limits: self.request_limits,
+            epoch_stakes: self.epoch_stakes,
+            middlewares: self.middlewares,
+            error_decoder: self.error_decoder,
+        }
+    }
+
+    /// Builds the channel.
+    pub fn build(self) -> RollUpChannel<'a, F> {
+        let mut account_loader = RollUpAccountLoader::with_source(self.source)
+            .with_missing_account_policy(self.missing_account_policy)
+            .with_request_limits(self.request_limits);
+        if let Some(stakes) = self.epoch_stakes {
+            account_loader = account_loader.with_epoch_stakes(stakes);
+        }
+        if let Some(cache) = self.account_cache {
+            account_loader = account_loader.with_cache(cache);
+        } else {
+            if let Some(ttl) = self.cache_ttl {
+                account_loader = account_loader.with_ttl(ttl);
+            }
+            if let Some(max_bytes) = self.max_cache_bytes {
+                account_loader = account_loader.with_max_cache_bytes(max_bytes);
+            }
+        }
+
+        let mut channel = RollUpChannel::from_parts(
+            self.keys,
+            account_loader,
+            self.config,
+            self.fork_graph,
+            self.middlewares,
+        );
+        channel.set_error_decoder(self.error_decoder);
+        channel
+    }
+}
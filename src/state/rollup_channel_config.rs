@@ -0,0 +1,368 @@
+use solana_compute_budget::compute_budget::ComputeBudget;
+use solana_sdk::clock::{Epoch, Slot};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::epoch_schedule::EpochSchedule;
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::state::processor_builder::Builtin;
+use crate::state::program_cache::ProgramCacheLimits;
+use crate::state::rollup_account_loader::{DataEncoding, SlotConsistency, SysvarDefaults};
+use crate::utils::helpers::{PROCESSOR_EPOCH, PROCESSOR_SLOT};
+
+/// Selects which blockhash the local processing environment runs against.
+///
+/// The chosen value is used both as the environment's `blockhash` (which the
+/// SVM stores into advanced durable nonces) and as the single entry of the
+/// `RecentBlockhashes` sysvar exposed to programs, so the two never disagree.
+///
+/// Note that the pre-check step (`check_transactions`) does not
+/// compare a transaction's `recent_blockhash` against this value: transactions
+/// signed with any blockhash are executed. Pick `Explicit` when a transaction
+/// relies on a durable nonce so that the nonce is advanced to a known value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlockhashSource {
+    /// Use `Hash::default()`, the historical behavior.
+    #[default]
+    Default,
+    /// Fetch the cluster's latest blockhash through the RPC client once per batch.
+    Latest,
+    /// Use the provided blockhash, e.g. the one stored in a nonce account.
+    Explicit(Hash),
+}
+
+/// Selects what the processor's sysvar cache, which programs read the Clock, Rent,
+/// EpochSchedule, SlotHashes and StakeHistory sysvars from through syscalls, is populated
+/// with before the first batch; see [`populate_sysvars`](crate::populate_sysvars).
+///
+/// Either way, the sysvar accounts the loader serves hold the same values, so a program
+/// reading a sysvar account sees what the syscall returns.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum SysvarSource {
+    /// Fetch the sysvar accounts from the account source in a single request, e.g. from
+    /// the cluster for an RPC source. Those the source doesn't have are synthesized from
+    /// the channel's slot, epoch and rent.
+    #[default]
+    Fetch,
+    /// Use the provided values, whatever the source has.
+    ///
+    /// They are used as given: the Clock's slot and epoch aren't tied to
+    /// [`RollUpChannelConfig::slot`] and [`RollUpChannelConfig::epoch`], and the Rent
+    /// doesn't replace [`RollUpChannelConfig::rent`] in the fee payer pre-check.
+    Explicit(SysvarDefaults),
+}
+
+/// Selects the features the channel executes with, see
+/// [`feature_set_from_cluster`](crate::feature_set_from_cluster).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FeatureSetSource {
+    /// Every feature known to this runtime is active, the historical behavior.
+    #[default]
+    AllEnabled,
+    /// The features active on the cluster, fetched through the RPC client once per call.
+    ///
+    /// Builtins the cluster migrated to core BPF are then loaded from their program
+    /// accounts instead, see [`Builtin::migrated_by`](crate::Builtin::migrated_by).
+    FromCluster,
+    /// The features active on the cluster, and those pending activation active from the
+    /// given slot.
+    FromClusterPendingAt(Slot),
+}
+
+/// How thoroughly a channel verifies transactions before executing them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SanitizationMode {
+    /// Verify neither signatures nor precompile instructions, so unsigned transactions
+    /// can be simulated. The historical behavior.
+    #[default]
+    SkipVerification,
+    /// Verify signatures and precompile instructions before execution, as a bank does:
+    /// failing transactions aren't executed nor charged fees. Signature failures are
+    /// reported as `SignatureFailure`, precompile failures as described in
+    /// [`verify_precompiles`](crate::verify_precompiles).
+    FullChecks,
+}
+
+/// Selects the rent parameters and epoch schedule a channel runs with, which differ
+/// between clusters and test validators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RentSource {
+    /// Use [`RollUpChannelConfig::rent`] and [`RollUpChannelConfig::epoch_schedule`].
+    #[default]
+    Explicit,
+    /// Fetch the cluster's Rent sysvar and epoch schedule through the RPC client, once
+    /// per channel, in place of the configured ones.
+    FromCluster,
+}
+
+/// Restricts the programs a channel's transactions may invoke.
+///
+/// A transaction invoking a refused program at the top level isn't executed: it fails
+/// with [`ProgramLoadError::Denied`](crate::ProgramLoadError::Denied) in its result
+/// message and `InvalidProgramForExecution` as its error. One reaching it through CPI
+/// has already executed, and is flagged by [`ReturnStruct::denied_program`](crate::ReturnStruct::denied_program).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ProgramFilter {
+    /// Every program may be invoked.
+    #[default]
+    AllowAll,
+    /// Only these programs may be invoked, builtins such as the system and compute
+    /// budget programs included.
+    Allow(HashSet<Pubkey>),
+    /// Every program but these may be invoked.
+    Deny(HashSet<Pubkey>),
+}
+
+impl ProgramFilter {
+    /// Whether transactions may invoke `program_id`.
+    pub fn allows(&self, program_id: &Pubkey) -> bool {
+        match self {
+            ProgramFilter::AllowAll => true,
+            ProgramFilter::Allow(allowed) => allowed.contains(program_id),
+            ProgramFilter::Deny(denied) => !denied.contains(program_id),
+        }
+    }
+
+    /// The first of `program_ids` transactions may not invoke.
+    pub fn first_denied<'a>(
+        &self,
+        program_ids: impl IntoIterator<Item = &'a Pubkey>,
+    ) -> Option<Pubkey> {
+        program_ids
+            .into_iter()
+            .find(|program_id| !self.allows(program_id))
+            .copied()
+    }
+}
+
+/// Configuration knobs for a [`RollUpChannel`](crate::RollUpChannel).
+///
+/// Every field has a default matching the channel's previous hardcoded behavior,
+/// so `RollUpChannelConfig::default()` is always a safe starting point.
+#[derive(Debug, Clone)]
+pub struct RollUpChannelConfig {
+    /// Blockhash used by the processing environment and the recent-blockhashes sysvar.
+    pub blockhash: BlockhashSource,
+    /// Record logs and inner instructions, and attach them and an
+    /// [`ExecutionTrace`](crate::ExecutionTrace) to every executed transaction's result.
+    ///
+    /// Off by default since recording has a runtime cost.
+    pub record_trace: bool,
+    /// Record the data programs set with `set_return_data` and attach it to every
+    /// executed transaction's [`return_data`](crate::ReturnStruct::return_data).
+    ///
+    /// Off by default, like `record_trace`.
+    pub record_return_data: bool,
+    /// Carry the data of changed accounts, before and after the transaction, in every
+    /// [`AccountChange`](crate::AccountChange) of the results.
+    ///
+    /// Off by default, so large accounts aren't copied: changes only carry data hashes.
+    pub record_account_data: bool,
+    /// Time how long every transaction takes to load and execute, see
+    /// [`ReturnStruct::execution_time`](crate::ReturnStruct::execution_time).
+    ///
+    /// Off by default, since timed batches are executed one transaction at a time, which
+    /// is slower.
+    pub record_execution_time: bool,
+    /// Fail repeated occurrences of the same transaction within a batch with
+    /// `AlreadyProcessed` instead of executing them again, as the cluster would.
+    ///
+    /// On by default; disable it to intentionally replay a transaction.
+    pub reject_duplicate_transactions: bool,
+    /// Charge transactions their fee. When off, for what-if analysis, fee payers aren't
+    /// debited and only need to exist and stay rent exempt; results still report the fee
+    /// that would have been charged, see [`ReturnStruct::fee_charged`](crate::ReturnStruct::fee_charged).
+    ///
+    /// On by default.
+    pub charge_fees: bool,
+    /// Rent parameters used by the fee payer pre-check and the runtime's rent-state checks.
+    pub rent: Rent,
+    /// Epoch schedule used for rent collection and the synthesized EpochSchedule sysvar.
+    ///
+    /// The default schedule of a cluster with warmup.
+    pub epoch_schedule: EpochSchedule,
+    /// Where the rent parameters and epoch schedule come from.
+    ///
+    /// The `rent` and `epoch_schedule` fields by default; fetching them from the cluster
+    /// requires an RPC-backed account source.
+    pub rent_source: RentSource,
+    /// Total compute units a block may consume in [`RollUpChannel::process_blocks`](crate::RollUpChannel::process_blocks).
+    ///
+    /// `None` (the default) leaves blocks unbounded.
+    pub batch_cu_limit: Option<u64>,
+    /// Commitment every account fetch from an RPC source is made at.
+    ///
+    /// `None` (the default) uses the RPC client's own commitment.
+    pub commitment: Option<CommitmentConfig>,
+    /// How the accounts a batch references are kept at a single slot when prefetched.
+    ///
+    /// Best effort by default; in strict mode, transactions referencing an account that
+    /// couldn't be read at the batch's slot fail with
+    /// [`AccountLoadError::Inconsistent`](crate::AccountLoadError::Inconsistent).
+    pub slot_consistency: SlotConsistency,
+    /// Prefetch the accounts a batch only reads, and which aren't programs, without their
+    /// data; see [`RollUpAccountLoader::with_data_slicing`](crate::RollUpAccountLoader::with_data_slicing).
+    ///
+    /// Off by default since programs reading such an account then see it empty.
+    pub slice_readonly_accounts: bool,
+    /// How long an account the source doesn't have is assumed to stay missing, see
+    /// [`RollUpAccountLoader::with_negative_ttl`](crate::RollUpAccountLoader::with_negative_ttl).
+    ///
+    /// `None` (the default) asks the source again on every lookup.
+    pub negative_cache_ttl: Option<Duration>,
+    /// How account data is requested from an RPC source.
+    ///
+    /// By default, base64+zstd for requests expected to return at least 1 KiB of data.
+    pub data_encoding: DataEncoding,
+    /// Directory fetched accounts are persisted in, so later runs reuse them within the
+    /// cache's TTL; see [`RollUpAccountLoader::with_disk_cache`](crate::RollUpAccountLoader::with_disk_cache).
+    ///
+    /// `None` (the default) keeps accounts in memory only.
+    pub disk_cache_dir: Option<PathBuf>,
+    /// Builtins registered on the channel's processor besides the
+    /// [default ones](crate::Builtin::defaults), replacing a default one with the same id.
+    ///
+    /// Empty by default.
+    pub builtins: Vec<Builtin>,
+    /// Slot the processor executes batches in until
+    /// [`RollUpChannel::advance_slot`](crate::RollUpChannel::advance_slot) moves it, and
+    /// the Clock sysvar reports when the source has none; see
+    /// [`ProcessorBuilder::slot`](crate::ProcessorBuilder::slot) for which deployments it
+    /// makes visible.
+    ///
+    /// Slot 1 by default, so programs deployed in slot 0 are visible.
+    pub slot: Slot,
+    /// Epoch the processor executes in, and the Clock sysvar reports when the source has
+    /// none.
+    ///
+    /// Epoch 1 by default.
+    pub epoch: Epoch,
+    /// Bounds on the compiled programs the processor's program cache keeps, enforced
+    /// after every batch; evicted programs are recompiled when invoked again.
+    ///
+    /// Unbounded by default.
+    pub program_cache_limits: ProgramCacheLimits,
+    /// What the processor's sysvar cache is populated with.
+    ///
+    /// By default, the sysvars are fetched from the account source.
+    pub sysvars: SysvarSource,
+    /// Features active in the runtime and the pre-check.
+    ///
+    /// Every feature is active by default; fetching them from the cluster requires an
+    /// RPC-backed account source.
+    pub feature_set: FeatureSetSource,
+    /// What the pre-check verifies beyond the fee payer, nonces and duplicates.
+    ///
+    /// Signatures and precompiles are left unverified by default.
+    pub sanitization: SanitizationMode,
+    /// Verify the BPF programs a batch invokes, and their ProgramData, fetched with the
+    /// batch's other accounts, before executing it: a program that doesn't compile fails
+    /// only the transactions invoking it, with an error naming it, instead of a bare
+    /// `InvalidProgramForExecution`.
+    ///
+    /// Enabled by default; builtin-only workloads can skip the verification.
+    pub verify_programs: bool,
+    /// Programs transactions may invoke, checked before execution for top-level
+    /// instructions and after it for CPI. Inner instructions are recorded to that end
+    /// whenever a filter is set, and attached to the results.
+    ///
+    /// Every program is allowed by default.
+    pub program_filter: ProgramFilter,
+    /// Compute budget every transaction executes with, e.g. with the cost of a syscall
+    /// raised to see how programs fare under a hypothetical protocol change. The runtime
+    /// environments are created for it too.
+    ///
+    /// Its `compute_unit_limit` and `heap_size` replace those transactions request with
+    /// compute budget instructions, as the runtime does with a fixed budget.
+    ///
+    /// `None` (the default) executes transactions with the default budget and the limits
+    /// they request.
+    pub compute_budget: Option<ComputeBudget>,
+    /// Reject ELFs the cluster would refuse to deploy when programs are loaded, see
+    /// [`ProcessorBuilder::reject_broken_elfs`](crate::ProcessorBuilder::reject_broken_elfs).
+    /// With [`verify_programs`](Self::verify_programs), they are reported as
+    /// [`ProgramLoadError::InvalidElf`](crate::ProgramLoadError::InvalidElf), and
+    /// [`RollUpChannel::add_program`](crate::RollUpChannel::add_program) refuses them.
+    ///
+    /// Disabled by default, so programs already deployed with such ELFs execute, like on
+    /// the cluster.
+    pub reject_broken_elfs: bool,
+    /// Enable the VM's debugging features, see
+    /// [`ProcessorBuilder::debugging_features`](crate::ProcessorBuilder::debugging_features).
+    ///
+    /// Disabled by default.
+    pub debugging_features: bool,
+    /// Debug execution in depth: enables the [VM's debugging features](Self::debugging_features)
+    /// and log recording, and emits what the runtime records executing each transaction,
+    /// its program and syscall logs and how it ended, as `trace` level events of the
+    /// `solana_client_ext::debug_execution` target. Events are emitted inside a
+    /// `transaction` span carrying the transaction's `index` in its batch. Only available
+    /// with the `tracing` feature.
+    ///
+    /// Disabled by default, and costly: transactions are executed one at a time, like with
+    /// [`record_execution_time`](Self::record_execution_time), programs are compiled with
+    /// symbol labels and traced instruction by instruction, and every log line is
+    /// formatted. Meant for local debugging only.
+    #[cfg(feature = "tracing")]
+    pub debug_execution: bool,
+    /// Register the loader-v4 builtin and the v2 program runtime environment, so programs
+    /// owned by loader-v4 can be executed. Only available with the `loader-v4` feature.
+    ///
+    /// Disabled by default.
+    #[cfg(feature = "loader-v4")]
+    pub loader_v4: bool,
+    /// Serve the SPL Token, Token-2022 and Associated Token Account programs embedded in
+    /// the crate when the source doesn't have them, so token transactions execute fully
+    /// offline. Programs the source holds, e.g. those the cluster has deployed, are
+    /// always executed instead. Only available with the `embedded-spl` feature.
+    ///
+    /// Enabled by default with the feature.
+    #[cfg(feature = "embedded-spl")]
+    pub embedded_spl_programs: bool,
+}
+
+impl Default for RollUpChannelConfig {
+    fn default() -> Self {
+        Self {
+            blockhash: BlockhashSource::default(),
+            record_trace: false,
+            record_return_data: false,
+            record_account_data: false,
+            record_execution_time: false,
+            reject_duplicate_transactions: true,
+            charge_fees: true,
+            rent: Rent::default(),
+            epoch_schedule: EpochSchedule::default(),
+            rent_source: RentSource::default(),
+            batch_cu_limit: None,
+            commitment: None,
+            slot_consistency: SlotConsistency::default(),
+            slice_readonly_accounts: false,
+            negative_cache_ttl: None,
+            data_encoding: DataEncoding::default(),
+            disk_cache_dir: None,
+            builtins: Vec::new(),
+            slot: PROCESSOR_SLOT,
+            epoch: PROCESSOR_EPOCH,
+            program_cache_limits: ProgramCacheLimits::default(),
+            sysvars: SysvarSource::default(),
+            feature_set: FeatureSetSource::default(),
+            sanitization: SanitizationMode::default(),
+            verify_programs: true,
+            program_filter: ProgramFilter::default(),
+            compute_budget: None,
+            reject_broken_elfs: false,
+            debugging_features: false,
+            #[cfg(feature = "tracing")]
+            debug_execution: false,
+            #[cfg(feature = "loader-v4")]
+            loader_v4: false,
+            #[cfg(feature = "embedded-spl")]
+            embedded_spl_programs: true,
+        }
+    }
+}
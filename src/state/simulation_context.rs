@@ -0,0 +1,34 @@
+use agave_feature_set::FeatureSet;
+use solana_sdk::clock::Slot;
+use solana_sdk::hash::{hashv, Hash};
+
+/// The state a transaction was simulated against, to tell whether it moved between
+/// an estimate and the transaction's submission.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SimulationContext {
+    /// Slot every account prefetched for the batch was read at or after, see
+    /// [`ReturnStruct::context_slot`](crate::ReturnStruct::context_slot).
+    pub slot: Option<Slot>,
+    /// The blockhash the transaction executed under, as resolved from
+    /// [`RollUpChannelConfig::blockhash`](crate::RollUpChannelConfig::blockhash).
+    pub blockhash: Hash,
+    /// Hash of the ids of the features active during execution, see
+    /// [`feature_set_hash`](Self::feature_set_hash).
+    pub feature_set_hash: Hash,
+}
+
+impl SimulationContext {
+    /// Hashes the ids of the features active in `feature_set`, in sorted order, so two
+    /// channels agree on the hash exactly when they run the same features.
+    pub fn feature_set_hash(feature_set: &FeatureSet) -> Hash {
+        let mut active: Vec<_> = feature_set.active.keys().collect();
+        active.sort_unstable();
+        hashv(
+            &active
+                .iter()
+                .map(|feature_id| feature_id.as_ref())
+                .collect::<Vec<_>>(),
+        )
+    }
+}
@@ -0,0 +1,104 @@
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+
+/// Deduplicates concurrent fetches of the same key: the first caller runs the fetch, the
+/// others wait for it and share its result, errors included.
+///
+/// No lock is held while a fetch runs. A fetch that panics lets its waiters go, and one
+/// of them fetches in its place.
+pub(crate) struct SingleFlight<V> {
+    flights: Mutex<HashMap<Pubkey, Arc<Flight<V>>>>,
+}
+
+struct Flight<V> {
+    outcome: Mutex<Outcome<V>>,
+    landed: Condvar,
+}
+
+enum Outcome<V> {
+    Pending,
+    Done(V),
+    /// The fetch panicked.
+    Abandoned,
+}
+
+impl<V> Default for SingleFlight<V> {
+    fn default() -> Self {
+        Self {
+            flights: Mutex::default(),
+        }
+    }
+}
+
+impl<V: Clone> SingleFlight<V> {
+    /// Runs `fetch` for `key` unless a fetch of `key` is already in flight, in which case
+    /// its result is awaited instead.
+    ///
+    /// Returns the result, and whether this call ran `fetch`.
+    pub(crate) fn run(&self, key: Pubkey, fetch: impl FnOnce() -> V) -> (V, bool) {
+        let mut fetch = Some(fetch);
+        loop {
+            let (flight, leader) = {
+                let mut flights = lock(&self.flights);
+                match flights.get(&key) {
+                    Some(flight) => (Arc::clone(flight), false),
+                    None => {
+                        let flight = Arc::new(Flight {
+                            outcome: Mutex::new(Outcome::Pending),
+                            landed: Condvar::new(),
+                        });
+                        flights.insert(key, Arc::clone(&flight));
+                        (flight, true)
+                    }
+                }
+            };
+
+            if leader {
+                let mut landing = Landing {
+                    single_flight: self,
+                    key,
+                    flight: &flight,
+                    outcome: Outcome::Abandoned,
+                };
+                // Leading either returns or unwinds, so a call leads at most once.
+                let value = (fetch.take().expect("a call leads at most once"))();
+                landing.outcome = Outcome::Done(value.clone());
+                return (value, true);
+            }
+
+            let outcome = flight
+                .landed
+                .wait_while(lock(&flight.outcome), |outcome| {
+                    matches!(outcome, Outcome::Pending)
+                })
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if let Outcome::Done(value) = &*outcome {
+                return (value.clone(), false);
+            }
+        }
+    }
+}
+
+/// Publishes a flight's outcome when dropped, `Abandoned` if the fetch unwound.
+struct Landing<'s, V> {
+    single_flight: &'s SingleFlight<V>,
+    key: Pubkey,
+    flight: &'s Arc<Flight<V>>,
+    outcome: Outcome<V>,
+}
+
+impl<V> Drop for Landing<'_, V> {
+    fn drop(&mut self) {
+        lock(&self.single_flight.flights).remove(&self.key);
+        *lock(&self.flight.outcome) = std::mem::replace(&mut self.outcome, Outcome::Abandoned);
+        self.flight.landed.notify_all();
+    }
+}
+
+/// Locks `mutex`, ignoring poisoning: the data behind it is only ever replaced whole.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
@@ -0,0 +1,97 @@
+use std::fs;
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use lru::LruCache;
+use solana_sdk::account::{AccountSharedData, ReadableAccount, WritableAccount};
+use solana_sdk::pubkey::Pubkey;
+
+/// Size of the fixed header preceding an account's data in its file.
+const HEADER_SIZE: usize = 8 + 8 + 32 + 1;
+
+/// Accounts stored on disk, one file per account, loaded lazily through an in-memory LRU.
+///
+/// Each account lives in `<dir>/<base58 pubkey>`, laid out as:
+///
+/// | Offset | Size | Field                     |
+/// |--------|------|---------------------------|
+/// | 0      | 8    | lamports, little-endian   |
+/// | 8      | 8    | rent epoch, little-endian |
+/// | 16     | 32   | owner                     |
+/// | 48     | 1    | executable (0 or 1)       |
+/// | 49     | rest | account data              |
+///
+/// Use [`write_account`](Self::write_account) to produce files in this layout, e.g. when
+/// extracting accounts from a validator snapshot. A key without a file is a missing account.
+pub struct SnapshotAccounts {
+    dir: PathBuf,
+    /// Recently read accounts, including keys known to have no file.
+    cache: Mutex<LruCache<Pubkey, Option<AccountSharedData>>>,
+}
+
+impl SnapshotAccounts {
+    /// Reads accounts from `dir`, keeping up to `capacity` of them in memory.
+    pub fn new(dir: impl Into<PathBuf>, capacity: NonZeroUsize) -> Self {
+        Self {
+            dir: dir.into(),
+            cache: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    /// The directory accounts are read from.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Reads the account stored for `pubkey`, `None` if it has no file.
+    pub fn get(&self, pubkey: &Pubkey) -> io::Result<Option<AccountSharedData>> {
+        if let Some(account) = self.cache.lock().unwrap().get(pubkey) {
+            return Ok(account.clone());
+        }
+
+        let account = match fs::read(self.dir.join(pubkey.to_string())) {
+            Ok(bytes) => Some(decode_account(&bytes)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err),
+        };
+        self.cache.lock().unwrap().put(*pubkey, account.clone());
+        Ok(account)
+    }
+
+    /// Writes `account` to `dir` in the layout [`SnapshotAccounts`] reads.
+    pub fn write_account(
+        dir: impl AsRef<Path>,
+        pubkey: &Pubkey,
+        account: &AccountSharedData,
+    ) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + account.data().len());
+        bytes.extend_from_slice(&account.lamports().to_le_bytes());
+        bytes.extend_from_slice(&account.rent_epoch().to_le_bytes());
+        bytes.extend_from_slice(account.owner().as_ref());
+        bytes.push(account.executable() as u8);
+        bytes.extend_from_slice(account.data());
+        fs::write(dir.as_ref().join(pubkey.to_string()), bytes)
+    }
+}
+
+fn decode_account(bytes: &[u8]) -> io::Result<AccountSharedData> {
+    if bytes.len() < HEADER_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "account file is shorter than its header",
+        ));
+    }
+
+    let lamports = u64::from_le_bytes(bytes[0..8].try_into().unwrap());
+    let rent_epoch = u64::from_le_bytes(bytes[8..16].try_into().unwrap());
+    let owner = Pubkey::try_from(&bytes[16..48]).unwrap();
+    let executable = bytes[48] != 0;
+
+    let mut account = AccountSharedData::new(lamports, 0, &owner);
+    account.set_data_from_slice(&bytes[HEADER_SIZE..]);
+    account.set_executable(executable);
+    account.set_rent_epoch(rent_epoch);
+    Ok(account)
+}
@@ -1,32 +1,529 @@
+use std::collections::HashSet;
 use std::sync::{Arc, RwLock};
 
 use solana_bpf_loader_program::syscalls::create_program_runtime_environment_v1;
-use solana_compute_budget::{
-    compute_budget::ComputeBudget, compute_budget_limits::ComputeBudgetLimits,
+use solana_client::client_error::Result as ClientResult;
+use solana_client::rpc_client::RpcClient;
+use solana_compute_budget::compute_budget::ComputeBudget;
+use solana_compute_budget_instruction::instructions_processor::process_compute_budget_instructions;
+use solana_precompiles::get_precompile;
+use solana_program_runtime::loaded_programs::ForkGraph;
+use solana_sdk::account::{
+    create_account_shared_data_for_test, AccountSharedData, ReadableAccount,
 };
-use solana_program_runtime::loaded_programs::ProgramCacheEntry;
-use solana_sdk::transaction;
-use solana_svm::account_loader::CheckedTransactionDetails;
+use solana_sdk::account_utils::StateMut;
+use solana_sdk::borsh1::try_from_slice_unchecked;
+use solana_sdk::clock::{Epoch, Slot};
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::feature;
+use solana_sdk::fee::{FeeBudgetLimits, FeeStructure};
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::message::Message;
+use solana_sdk::nonce::state::Versions as NonceVersions;
+use solana_sdk::nonce::NONCED_TX_MARKER_IX_INDEX;
+use solana_sdk::nonce_account::verify_nonce_account;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use solana_sdk::system_program;
+use solana_sdk::sysvar;
+use solana_sdk::transaction::{self, SanitizedTransaction, Transaction, TransactionError};
+use solana_svm::account_loader::{CheckedTransactionDetails, TransactionCheckResult};
+use solana_svm::nonce_info::NonceInfo;
 use solana_svm::transaction_processing_callback::TransactionProcessingCallback;
 use solana_svm::transaction_processor::TransactionBatchProcessor;
-use solana_system_program::system_processor;
+use solana_svm_transaction::svm_message::SVMMessage;
 
-use crate::ForkRollUpGraph;
-use agave_feature_set::FeatureSet;
+use agave_feature_set::{FeatureSet, FEATURE_NAMES};
 
-/// Generates a vector of placeholder "checked" transactions to simulate what a
-/// validator would normally do before execution (signature check, account ownership, etc).
+use crate::state::processor_builder::ProcessorBuilder;
+use crate::state::rollup_account_loader::{RollUpAccountLoader, MAX_MULTIPLE_ACCOUNTS};
+use crate::state::rollup_channel_config::SysvarSource;
+
+/// Slot the processor executes in.
+///
+/// We choose slot 1 deliberately: Solana treats programs deployed in slot 0
+/// as not visible until slot 1. This ensures deployed programs are active during simulation.
+pub(crate) const PROCESSOR_SLOT: Slot = 1;
+
+/// Epoch the processor executes in.
+pub(crate) const PROCESSOR_EPOCH: Epoch = 1;
+
+/// Sysvars the processor reads from the loader when filling its sysvar cache.
+pub(crate) const SYSVARS: [Pubkey; 7] = [
+    sysvar::clock::ID,
+    sysvar::epoch_rewards::ID,
+    sysvar::epoch_schedule::ID,
+    sysvar::last_restart_slot::ID,
+    sysvar::rent::ID,
+    sysvar::slot_hashes::ID,
+    sysvar::stake_history::ID,
+];
+
+/// Converts raw transactions into the sanitized format required by the SVM processor.
+///
+/// Signatures aren't verified, and every account lookup table is expected to be
+/// resolved already, as for a legacy transaction.
+pub fn sanitize_transactions(transactions: &[Transaction]) -> Vec<SanitizedTransaction> {
+    transactions
+        .iter()
+        .map(|tx| SanitizedTransaction::from_transaction_for_tests(tx.clone()))
+        .collect()
+}
+
+/// Settings for the pre-check performed by [`check_transactions`].
+///
+/// Fields may be added in minor releases: start from [`CheckConfig::new`] and adjust
+/// the fields to change.
+#[non_exhaustive]
+pub struct CheckConfig<'a> {
+    /// Fail repeated message hashes within the batch with `AlreadyProcessed`.
+    pub reject_duplicates: bool,
+    /// Fee charged per signature, used to compute the fee the payer must cover.
+    pub lamports_per_signature: u64,
+    /// Charge transactions their fee. When off, the fee payer is only checked to exist
+    /// and stay rent exempt, and the SVM is told to charge nothing.
+    pub charge_fees: bool,
+    /// Rent parameters used for the fee payer's rent-exemption check.
+    pub rent: &'a Rent,
+    /// Feature set used to process compute budget instructions.
+    pub feature_set: &'a FeatureSet,
+}
+
+impl<'a> CheckConfig<'a> {
+    /// Checks against `rent` and `feature_set`, rejecting duplicates and charging the
+    /// default fee per signature, like a channel does by default.
+    pub fn new(rent: &'a Rent, feature_set: &'a FeatureSet) -> Self {
+        Self {
+            reject_duplicates: true,
+            lamports_per_signature: FeeStructure::default().lamports_per_signature,
+            charge_fees: true,
+            rent,
+            feature_set,
+        }
+    }
+}
+
+/// Checks transactions like a bank does before handing them to the SVM, producing the
+/// check results [`TransactionBatchProcessor::load_and_execute_sanitized_transactions`]
+/// takes. Channels check their batches with it.
+///
+/// # Contract
+///
+/// One result is returned per transaction, in order. `Ok` holds the transaction's
+/// `CheckedTransactionDetails`: its durable nonce if it uses one, and the fee per
+/// signature it is charged, the nonce's or `config.lamports_per_signature` (`0` if
+/// `config.charge_fees` is off). `Err` holds the error the bank would refuse it with;
+/// the SVM then neither executes it nor charges it:
+///
+/// - `AlreadyProcessed` if `config.reject_duplicates` is set and its message hash was
+///   already seen earlier in the batch, like the bank's status cache would reject it.
+/// - `BlockhashNotFound` if it starts with an `AdvanceNonceAccount` instruction whose
+///   nonce account isn't an initialized nonce holding the transaction's
+///   `recent_blockhash`, or whose authority doesn't sign the instruction.
+/// - The error its invalid compute budget instructions fail with, since they also
+///   price its prioritization fee.
+/// - `AccountNotFound`, `InvalidAccountForFee`, `InsufficientFundsForFee` or
+///   `InsufficientFundsForRent` if the fee payer doesn't exist, isn't a system account
+///   (with no data, or a nonce account whose rent-exempt minimum stays locked), can't
+///   pay the fee, or would become rent paying by paying it. With `config.charge_fees`
+///   off, the fee is zero.
+///
+/// The fee payer and nonce accounts are read from `callbacks` as they are when called,
+/// without the effects of earlier transactions of the batch: the SVM validates the fee
+/// payer again as it executes the batch.
+///
+/// Signatures and precompiles aren't verified, see
+/// [`SanitizedTransaction::verify`] and [`verify_precompiles`]. Nor is a transaction's
+/// `recent_blockhash` compared to the environment's blockhash chosen via
+/// `BlockhashSource`, so stale or offline-signed transactions still execute. Account
+/// locks and programs are checked by the SVM itself.
+///
+/// # Example
 ///
-/// In a real validator, this step ensures transactions are structurally valid
-/// before passing them to the runtime. Here, we mock that behavior so that
-/// we can run fully in-memory simulations without real pre-validation.
+/// Running a batch directly against a [`RollUpAccountLoader`](crate::RollUpAccountLoader),
+/// as a [`RollUpChannel`](crate::RollUpChannel) does without its prefetching, middlewares
+/// and result conversion:
 ///
-/// `len` defines how many mock results to return, used for simulating batches.
-pub(crate) fn get_transaction_check_results(
-    len: usize,
+/// ```no_run
+/// use std::collections::HashMap;
+/// use std::sync::{Arc, RwLock};
+///
+/// use solana_client_ext::{
+///     check_transactions, populate_sysvars, sanitize_transactions, AccountSource, CheckConfig,
+///     ForkRollUpGraph, ProcessingEnvironmentBuilder, ProcessorBuilder, RollUpAccountLoader,
+///     SysvarSource,
+/// };
+/// use solana_sdk::{rent::Rent, transaction::Transaction};
+/// use solana_svm::transaction_processor::TransactionProcessingConfig;
+///
+/// # let transactions: Vec<Transaction> = Vec::new();
+/// let loader = RollUpAccountLoader::with_source(AccountSource::Static(HashMap::new()));
+/// // The processor only holds a weak reference to the fork graph: keep it alive.
+/// let fork_graph = Arc::new(RwLock::new(ForkRollUpGraph::with_slots([1])));
+/// let processor = ProcessorBuilder::new(Arc::clone(&fork_graph)).slot(1).build(&loader);
+/// populate_sysvars(&processor, &loader, &SysvarSource::Fetch);
+///
+/// let environment = ProcessingEnvironmentBuilder::deterministic();
+/// let processing_environment = environment.build();
+/// let rent = Rent::default();
+/// let sanitized = sanitize_transactions(&transactions);
+/// let check_config = CheckConfig::new(&rent, &processing_environment.feature_set);
+/// let check_results = check_transactions(&sanitized, &loader, &check_config);
+///
+/// let output = processor.load_and_execute_sanitized_transactions(
+///     &loader,
+///     &sanitized,
+///     check_results,
+///     &processing_environment,
+///     &TransactionProcessingConfig::default(),
+/// );
+/// for result in &output.processing_results {
+///     println!("{:?}", result.as_ref().map(|processed| processed.status()));
+/// }
+/// ```
+///
+/// Executed transactions' accounts aren't written back to the loader: commit them, e.g.
+/// as overrides, for later batches to see them.
+pub fn check_transactions<CB: TransactionProcessingCallback>(
+    transactions: &[SanitizedTransaction],
+    callbacks: &CB,
+    config: &CheckConfig,
 ) -> Vec<transaction::Result<CheckedTransactionDetails>> {
-    let _compute_budget_limit = ComputeBudgetLimits::default();
-    vec![transaction::Result::Ok(CheckedTransactionDetails::new(None, 5000,)); len]
+    let mut seen = HashSet::with_capacity(transactions.len());
+
+    transactions
+        .iter()
+        .map(|tx| {
+            if config.reject_duplicates && !seen.insert(*tx.message_hash()) {
+                return Err(TransactionError::AlreadyProcessed);
+            }
+
+            let (nonce, lamports_per_signature) = match check_nonce(tx, callbacks)? {
+                Some((nonce, lamports_per_signature)) => (Some(nonce), lamports_per_signature),
+                None => (None, config.lamports_per_signature),
+            };
+            let fee = transaction_fee(tx, lamports_per_signature, config.feature_set)?;
+            // A zero fee per signature makes the SVM charge nothing, prioritization included.
+            let (fee, lamports_per_signature) = if config.charge_fees {
+                (fee, lamports_per_signature)
+            } else {
+                (0, 0)
+            };
+            let fee_payer = callbacks.get_account_shared_data(tx.message().fee_payer());
+            validate_fee_payer(fee_payer.as_ref(), fee, config.rent)?;
+
+            Ok(CheckedTransactionDetails::new(
+                nonce,
+                lamports_per_signature,
+            ))
+        })
+        .collect()
+}
+
+/// The fee `tx` is charged at `lamports_per_signature`: the signature fee plus the
+/// prioritization fee its compute budget instructions request. Fails like the runtime
+/// does if those instructions are invalid.
+pub(crate) fn transaction_fee(
+    tx: &SanitizedTransaction,
+    lamports_per_signature: u64,
+    feature_set: &FeatureSet,
+) -> transaction::Result<u64> {
+    let limits = process_compute_budget_instructions(tx.program_instructions_iter(), feature_set)?;
+    Ok(lamports_per_signature
+        .saturating_mul(tx.num_total_signatures())
+        .saturating_add(FeeBudgetLimits::from(limits).prioritization_fee))
+}
+
+/// Validates the durable nonce `tx` uses, if it starts with an `AdvanceNonceAccount`
+/// instruction, like the bank does: the nonce account must be an initialized nonce
+/// holding the transaction's `recent_blockhash`, and its authority must sign the
+/// advance instruction. Any failure is reported as `BlockhashNotFound`.
+///
+/// Returns the nonce account and the fee per signature it stores, which the bank
+/// charges the transaction with.
+fn check_nonce<CB: TransactionProcessingCallback>(
+    tx: &SanitizedTransaction,
+    callbacks: &CB,
+) -> transaction::Result<Option<(NonceInfo, u64)>> {
+    let Some(nonce_address) = tx.message().get_durable_nonce() else {
+        return Ok(None);
+    };
+    let nonce_account = callbacks
+        .get_account_shared_data(nonce_address)
+        .ok_or(TransactionError::BlockhashNotFound)?;
+    let nonce_data = verify_nonce_account(&nonce_account, tx.message().recent_blockhash())
+        .ok_or(TransactionError::BlockhashNotFound)?;
+    if !tx
+        .message()
+        .get_ix_signers(NONCED_TX_MARKER_IX_INDEX as usize)
+        .any(|signer| signer == &nonce_data.authority)
+    {
+        return Err(TransactionError::BlockhashNotFound);
+    }
+
+    Ok(Some((
+        NonceInfo::new(*nonce_address, nonce_account),
+        nonce_data.get_lamports_per_signature(),
+    )))
+}
+
+/// Validates that `fee_payer` can pay `fee`, mirroring the bank's checks.
+///
+/// - The payer must exist and be a system account: either without data, or a nonce
+///   account, whose rent-exempt minimum for its data length stays locked.
+/// - Paying the fee must not move the payer into a rent-paying state it wasn't in
+///   before, otherwise the runtime fails with `InsufficientFundsForRent`. Draining
+///   the payer to exactly zero lamports is allowed.
+pub(crate) fn validate_fee_payer(
+    fee_payer: Option<&AccountSharedData>,
+    fee: u64,
+    rent: &Rent,
+) -> transaction::Result<()> {
+    let fee_payer = match fee_payer {
+        Some(account) if account.lamports() > 0 => account,
+        _ => return Err(TransactionError::AccountNotFound),
+    };
+
+    if !system_program::check_id(fee_payer.owner()) {
+        return Err(TransactionError::InvalidAccountForFee);
+    }
+    let min_balance = if fee_payer.data().is_empty() {
+        0
+    } else if StateMut::<NonceVersions>::state(fee_payer).is_ok() {
+        rent.minimum_balance(fee_payer.data().len())
+    } else {
+        return Err(TransactionError::InvalidAccountForFee);
+    };
+
+    let post_lamports = fee_payer
+        .lamports()
+        .checked_sub(min_balance)
+        .and_then(|available| available.checked_sub(fee))
+        .map(|_| fee_payer.lamports() - fee)
+        .ok_or(TransactionError::InsufficientFundsForFee)?;
+
+    let data_size = fee_payer.data().len();
+    let pre_state = RentState::from_account(fee_payer.lamports(), data_size, rent);
+    let post_state = RentState::from_account(post_lamports, data_size, rent);
+    if !pre_state.transition_allowed(&post_state) {
+        return Err(TransactionError::InsufficientFundsForRent { account_index: 0 });
+    }
+
+    Ok(())
+}
+
+/// Rent state of an account, as tracked by the runtime's rent-state transition rules.
+#[derive(Debug, PartialEq, Eq)]
+enum RentState {
+    /// The account holds no lamports.
+    Uninitialized,
+    /// The account is below the rent-exempt minimum for its data length.
+    RentPaying { lamports: u64, data_size: usize },
+    /// The account is rent-exempt.
+    RentExempt,
+}
+
+impl RentState {
+    fn from_account(lamports: u64, data_size: usize, rent: &Rent) -> Self {
+        if lamports == 0 {
+            RentState::Uninitialized
+        } else if rent.is_exempt(lamports, data_size) {
+            RentState::RentExempt
+        } else {
+            RentState::RentPaying {
+                lamports,
+                data_size,
+            }
+        }
+    }
+
+    /// A rent-paying account may only stay rent-paying without growing or gaining lamports.
+    fn transition_allowed(&self, post: &RentState) -> bool {
+        match post {
+            RentState::Uninitialized | RentState::RentExempt => true,
+            RentState::RentPaying {
+                lamports: post_lamports,
+                data_size: post_data_size,
+            } => match self {
+                RentState::RentPaying {
+                    lamports: pre_lamports,
+                    data_size: pre_data_size,
+                } => post_data_size == pre_data_size && post_lamports <= pre_lamports,
+                RentState::Uninitialized | RentState::RentExempt => false,
+            },
+        }
+    }
+}
+
+/// Verifies the precompile instructions, ed25519, secp256k1, and secp256r1 once its
+/// feature is active in `feature_set`, of the transactions whose `check_results` are `Ok`.
+///
+/// As in a bank, a transaction carrying an invalid signature payload fails before it
+/// executes, with an `InstructionError` at the precompile instruction whose custom code
+/// is the `PrecompileError`.
+pub fn verify_precompiles(
+    transactions: &[SanitizedTransaction],
+    feature_set: &FeatureSet,
+    check_results: &mut [TransactionCheckResult],
+) {
+    for (transaction, check_result) in transactions.iter().zip(check_results) {
+        if check_result.is_ok() {
+            if let Err(err) = verify_transaction_precompiles(transaction, feature_set) {
+                *check_result = Err(err);
+            }
+        }
+    }
+}
+
+fn verify_transaction_precompiles(
+    transaction: &SanitizedTransaction,
+    feature_set: &FeatureSet,
+) -> transaction::Result<()> {
+    // Precompiles may read signatures from any instruction of the transaction.
+    let mut instruction_datas = None;
+    for (index, (program_id, instruction)) in transaction.program_instructions_iter().enumerate() {
+        let Some(precompile) =
+            get_precompile(program_id, |feature_id| feature_set.is_active(feature_id))
+        else {
+            continue;
+        };
+        let instruction_datas: &Vec<&[u8]> = instruction_datas.get_or_insert_with(|| {
+            transaction
+                .instructions_iter()
+                .map(|instruction| instruction.data)
+                .collect()
+        });
+        precompile
+            .verify(instruction.data, instruction_datas, feature_set)
+            .map_err(|err| {
+                TransactionError::InstructionError(
+                    index as u8,
+                    InstructionError::Custom(err as u32),
+                )
+            })?;
+    }
+    Ok(())
+}
+
+/// Derives the compute unit limit the runtime will enforce for `transaction`.
+///
+/// This processes the transaction's compute budget instructions exactly like the
+/// SVM does during loading, falling back to the default per-instruction limit when
+/// no `SetComputeUnitLimit` is present. Returns `None` if the budget instructions
+/// are invalid, in which case the transaction won't execute at all.
+pub(crate) fn get_compute_unit_limit(
+    transaction: &SanitizedTransaction,
+    feature_set: &FeatureSet,
+) -> Option<u32> {
+    process_compute_budget_instructions(transaction.program_instructions_iter(), feature_set)
+        .ok()
+        .map(|limits| limits.compute_unit_limit)
+}
+
+/// The `SetComputeUnitLimit` instruction `message` already holds, as its index and the
+/// limit it sets, if any.
+pub(crate) fn compute_unit_limit_instruction(message: &Message) -> Option<(usize, u32)> {
+    message
+        .instructions
+        .iter()
+        .enumerate()
+        .find_map(|(index, ix)| {
+            let program_id = message.account_keys.get(usize::from(ix.program_id_index))?;
+            if !solana_sdk::compute_budget::check_id(program_id) {
+                return None;
+            }
+            match try_from_slice_unchecked(&ix.data) {
+                Ok(ComputeBudgetInstruction::SetComputeUnitLimit(limit)) => Some((index, limit)),
+                _ => None,
+            }
+        })
+}
+
+/// Builds a `RecentBlockhashes` sysvar account holding a single entry for `blockhash`.
+///
+/// Serving this account from the loader keeps the sysvar cache (filled from the
+/// loader) consistent with the blockhash used by the processing environment.
+#[allow(deprecated)]
+pub(crate) fn create_recent_blockhashes_account(
+    blockhash: &Hash,
+    lamports_per_signature: u64,
+) -> AccountSharedData {
+    use solana_sdk::sysvar::recent_blockhashes::{IterItem, RecentBlockhashes};
+
+    let recent_blockhashes: RecentBlockhashes =
+        std::iter::once(IterItem(0, blockhash, lamports_per_signature)).collect();
+    create_account_shared_data_for_test(&recent_blockhashes)
+}
+
+/// Populates the sysvar cache of `processor`, which programs read sysvars from through
+/// syscalls, from `source`, before its first batch.
+///
+/// With [`SysvarSource::Fetch`], the sysvar accounts `loader` doesn't hold yet are
+/// fetched in a single request, and those its source doesn't have are synthesized from
+/// its [`SysvarDefaults`](crate::SysvarDefaults). With [`SysvarSource::Explicit`], the
+/// values are stored in `loader` as committed state, replacing what it held or would
+/// fetch. Either way the cache is then filled from `loader`, so syscalls and the sysvar
+/// accounts programs read agree.
+pub fn populate_sysvars<FG: ForkGraph>(
+    processor: &TransactionBatchProcessor<FG>,
+    loader: &RollUpAccountLoader,
+    source: &SysvarSource,
+) {
+    match source {
+        SysvarSource::Fetch => {
+            loader.prefetch(&SYSVARS);
+        }
+        SysvarSource::Explicit(values) => {
+            for pubkey in SYSVARS {
+                if let Some(account) = values.account(&pubkey) {
+                    loader.insert_cached(pubkey, account);
+                }
+            }
+        }
+    }
+    processor.reset_sysvar_cache();
+    processor.fill_missing_sysvar_cache_entries(loader);
+}
+
+/// The feature set the cluster `rpc_client` points to runs with: the features known to
+/// this runtime whose feature account was activated, at the slot it was activated in.
+///
+/// The feature accounts are fetched in batches, a few `getMultipleAccounts` requests in
+/// total. Features the cluster doesn't know, or only has pending activation, are inactive.
+pub fn feature_set_from_cluster(rpc_client: &RpcClient) -> ClientResult<FeatureSet> {
+    fetch_feature_set(rpc_client, None)
+}
+
+/// [`feature_set_from_cluster`], with the features pending activation on the cluster
+/// active from `slot`, as they will be once the cluster activates them, to test against
+/// the upcoming feature set.
+pub fn feature_set_from_cluster_pending_at(
+    rpc_client: &RpcClient,
+    slot: Slot,
+) -> ClientResult<FeatureSet> {
+    fetch_feature_set(rpc_client, Some(slot))
+}
+
+/// Activates the features whose account was activated, and those pending activation at
+/// `pending_at` if given.
+fn fetch_feature_set(rpc_client: &RpcClient, pending_at: Option<Slot>) -> ClientResult<FeatureSet> {
+    let feature_ids: Vec<Pubkey> = FEATURE_NAMES.keys().copied().collect();
+    let mut feature_set = FeatureSet::default();
+    for chunk in feature_ids.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        let accounts = rpc_client.get_multiple_accounts(chunk)?;
+        for (feature_id, account) in chunk.iter().zip(accounts) {
+            // Accounts that aren't owned by the feature program don't decode.
+            let activated_at = account
+                .as_ref()
+                .and_then(feature::from_account)
+                .and_then(|feature| feature.activated_at.or(pending_at));
+            if let Some(slot) = activated_at {
+                feature_set.activate(feature_id, slot);
+            }
+        }
+    }
+    Ok(feature_set)
 }
 
 /// Creates a local, in-memory transaction processor capable of simulating
@@ -38,57 +535,26 @@ pub(crate) fn get_transaction_check_results(
 /// This is critical for features like `RpcClientExt::estimate_cu_local()`
 /// which depend on deterministic, offline simulation of a transaction.
 ///
-/// `fork_graph` is the mocked ledger state.
+/// `fork_graph` is the ledger's slot ancestry, usually a [`ForkRollUpGraph`](crate::ForkRollUpGraph).
 /// `feature_set` and `compute_budget` customize runtime behavior (e.g., instruction limits).
-pub(crate) fn create_transaction_batch_processor<CB: TransactionProcessingCallback>(
+///
+/// Kept for compatibility: this is a [`ProcessorBuilder`] with the runtime environment of
+/// `feature_set` and `compute_budget`, which also allows other builtins, and another slot
+/// or epoch than the default ones.
+pub fn create_transaction_batch_processor<CB, FG>(
     callbacks: &CB,
     feature_set: &FeatureSet,
     compute_budget: &ComputeBudget,
-    fork_graph: Arc<RwLock<ForkRollUpGraph>>,
-) -> TransactionBatchProcessor<ForkRollUpGraph> {
-    // Create a new transaction batch processor for slot 1.
-    //
-    // We choose slot 1 deliberately: Solana treats programs deployed in slot 0
-    // as not visible until slot 1. This ensures deployed programs are active during simulation.
-    let processor = TransactionBatchProcessor::<ForkRollUpGraph>::new(
-        /* slot */ 1,
-        /* epoch */ 1,
-        Arc::downgrade(&fork_graph),
-        Some(Arc::new(
+    fork_graph: Arc<RwLock<FG>>,
+) -> TransactionBatchProcessor<FG>
+where
+    CB: TransactionProcessingCallback,
+    FG: ForkGraph,
+{
+    ProcessorBuilder::new(fork_graph)
+        .runtime_environment(Arc::new(
             create_program_runtime_environment_v1(feature_set, compute_budget, false, false)
                 .unwrap(),
-        )),
-        None,
-    );
-
-    // Register the System Program as a built-in.
-    //
-    // This enables simulation of basic SOL instructions like transfers and account creation.
-    processor.add_builtin(
-        callbacks,
-        solana_system_program::id(),
-        "system_program",
-        ProgramCacheEntry::new_builtin(
-            0,
-            b"system_program".len(),
-            system_processor::Entrypoint::vm,
-        ),
-    );
-
-    // Register the BPF Loader v2 as a built-in.
-    //
-    // This is needed to simulate execution of BPF-based programs,
-    // including programs like SPL Token, associated token accounts, etc.
-    processor.add_builtin(
-        callbacks,
-        solana_sdk::bpf_loader::id(),
-        "solana_bpf_loader_program",
-        ProgramCacheEntry::new_builtin(
-            0,
-            b"solana_bpf_loader_program".len(),
-            solana_bpf_loader_program::Entrypoint::vm,
-        ),
-    );
-
-    processor
+        ))
+        .build(callbacks)
 }
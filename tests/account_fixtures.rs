@@ -0,0 +1,78 @@
+use std::str::FromStr;
+
+use solana_client_ext::*;
+
+use solana_sdk::{
+    account::ReadableAccount, message::Message, native_token::LAMPORTS_PER_SOL, pubkey::Pubkey,
+    system_instruction, system_program, transaction::Transaction,
+};
+use solana_svm::transaction_processing_callback::TransactionProcessingCallback;
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/accounts");
+const WALLET: &str = "8fZVDNN5Qu64eJ2FzCHC1X8JZxwH2g1GBiEn7DDVZ9TY";
+const MINT: &str = "9xkU49fBixt9jfgvsHquwoA6HBLriYgVsaqa6RaPKuQU";
+const TOKEN_ACCOUNT: &str = "3Wb5VJAPZy9z4jb37HuMzqNgA8DWaGdeJwfkK4HxK3JK";
+const TOKEN_PROGRAM: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
+#[test]
+fn fixtures_decode() {
+    let accounts = load_account_fixtures(FIXTURES).unwrap();
+    assert_eq!(accounts.len(), 3);
+
+    let wallet = &accounts[&Pubkey::from_str(WALLET).unwrap()];
+    assert_eq!(wallet.lamports(), 5 * LAMPORTS_PER_SOL);
+    assert_eq!(wallet.owner(), &system_program::id());
+
+    let token_program = Pubkey::from_str(TOKEN_PROGRAM).unwrap();
+    let mint = &accounts[&Pubkey::from_str(MINT).unwrap()];
+    assert_eq!(mint.owner(), &token_program);
+    assert_eq!(mint.data().len(), 82);
+
+    // A token account starts with its mint, then its owner.
+    let token_account = &accounts[&Pubkey::from_str(TOKEN_ACCOUNT).unwrap()];
+    assert_eq!(token_account.owner(), &token_program);
+    assert_eq!(token_account.data().len(), 165);
+    assert_eq!(
+        &token_account.data()[..32],
+        Pubkey::from_str(MINT).unwrap().as_ref()
+    );
+    assert_eq!(
+        &token_account.data()[32..64],
+        Pubkey::from_str(WALLET).unwrap().as_ref()
+    );
+}
+
+#[test]
+fn fixture_wallet_pays_for_transfer() {
+    let wallet = Pubkey::from_str(WALLET).unwrap();
+    let recipient = Pubkey::new_unique();
+
+    // Signatures aren't verified locally, so the fixture's keypair isn't needed.
+    let ix = system_instruction::transfer(&wallet, &recipient, LAMPORTS_PER_SOL);
+    let tx = Transaction::new_unsigned(Message::new(&[ix], Some(&wallet)));
+
+    let rollup_c = RollUpChannel::with_account_source(
+        vec![],
+        AccountSource::from_fixture_dir(FIXTURES).unwrap(),
+        RollUpChannelConfig::default(),
+    );
+    let blocks = rollup_c.process_blocks(&[vec![tx]]);
+    assert!(
+        blocks[0].results[0].is_success(),
+        "{}",
+        blocks[0].results[0].result
+    );
+    assert_eq!(
+        rollup_c
+            .account_loader()
+            .get_account_shared_data(&recipient)
+            .unwrap()
+            .lamports(),
+        LAMPORTS_PER_SOL
+    );
+}
+
+#[test]
+fn invalid_fixture_is_rejected() {
+    assert!(AccountSource::from_fixtures([r#"{"pubkey": "not a key"}"#]).is_err());
+}
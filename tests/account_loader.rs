@@ -0,0 +1,778 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use solana_client_ext::*;
+
+use solana_sdk::{
+    account::{AccountSharedData, ReadableAccount, WritableAccount},
+    message::Message,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_instruction, system_program,
+    transaction::Transaction,
+};
+use solana_svm::transaction_processing_callback::TransactionProcessingCallback;
+
+fn funded(pubkey: Pubkey) -> HashMap<Pubkey, AccountSharedData> {
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        pubkey,
+        AccountSharedData::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id()),
+    );
+    accounts
+}
+
+#[test]
+fn ttl_expires_fetched_accounts() {
+    let key = Pubkey::new_unique();
+
+    let loader = RollUpAccountLoader::with_source(AccountSource::Static(funded(key)));
+    loader.get_account_shared_data(&key).unwrap();
+    loader.get_account_shared_data(&key).unwrap();
+    assert_eq!(loader.fetch_stats().single_requests, 1);
+
+    // A zero TTL makes every lookup go back to the source.
+    let loader = RollUpAccountLoader::with_source(AccountSource::Static(funded(key)))
+        .with_ttl(Duration::ZERO);
+    loader.get_account_shared_data(&key).unwrap();
+    loader.get_account_shared_data(&key).unwrap();
+    assert_eq!(loader.fetch_stats().single_requests, 2);
+}
+
+#[test]
+fn invalidate_drops_committed_state() {
+    let payer = Keypair::new();
+    let recipient = Pubkey::new_unique();
+
+    let ix = system_instruction::transfer(&payer.pubkey(), &recipient, LAMPORTS_PER_SOL);
+    let tx = Transaction::new(
+        &[&payer],
+        Message::new(&[ix], Some(&payer.pubkey())),
+        Default::default(),
+    );
+
+    // Committed state is never expired by the TTL.
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(funded(payer.pubkey())))
+        .cache_ttl(Duration::ZERO)
+        .build();
+    let blocks = rollup_c.process_blocks(&[vec![tx]]);
+    assert!(
+        blocks[0].results[0].is_success(),
+        "{}",
+        blocks[0].results[0].result
+    );
+
+    let loader = rollup_c.account_loader();
+    assert!(loader.get_account_shared_data(&recipient).is_some());
+
+    loader.invalidate(&recipient);
+    assert!(loader.get_account_shared_data(&recipient).is_none());
+
+    loader.invalidate_all();
+    assert_eq!(
+        loader.get_account_shared_data(&payer.pubkey()),
+        Some(AccountSharedData::new(
+            10 * LAMPORTS_PER_SOL,
+            0,
+            &system_program::id()
+        ))
+    );
+}
+
+#[test]
+fn overrides_shadow_cache_and_source() {
+    let payer = Keypair::new();
+    let source_copy = AccountSharedData::new(LAMPORTS_PER_SOL, 0, &system_program::id());
+    let mut accounts = HashMap::new();
+    accounts.insert(payer.pubkey(), source_copy.clone());
+
+    let rollup_c = RollUpChannel::new_offline(vec![], accounts);
+    let loader = rollup_c.account_loader();
+
+    // Cache the source's copy first: the override must still win.
+    assert_eq!(
+        loader.get_account_shared_data(&payer.pubkey()),
+        Some(source_copy)
+    );
+    loader.insert_override(
+        payer.pubkey(),
+        AccountSharedData::new(100 * LAMPORTS_PER_SOL, 0, &system_program::id()),
+    );
+    assert_eq!(
+        loader.account_matches_owners(
+            &payer.pubkey(),
+            &[Pubkey::new_unique(), system_program::id()]
+        ),
+        Some(1)
+    );
+    loader.invalidate_all();
+
+    // Only the override can fund this transfer.
+    let ix = system_instruction::transfer(
+        &payer.pubkey(),
+        &Pubkey::new_unique(),
+        50 * LAMPORTS_PER_SOL,
+    );
+    let tx = Transaction::new(
+        &[&payer],
+        Message::new(&[ix], Some(&payer.pubkey())),
+        Default::default(),
+    );
+    let blocks = rollup_c.process_blocks(&[vec![tx]]);
+    assert!(
+        blocks[0].results[0].is_success(),
+        "{}",
+        blocks[0].results[0].result
+    );
+
+    // The committed debit landed in the override.
+    let overrides = loader.overrides();
+    assert_eq!(overrides.len(), 1);
+    assert_eq!(
+        overrides[&payer.pubkey()],
+        AccountSharedData::new(50 * LAMPORTS_PER_SOL - 5_000, 0, &system_program::id())
+    );
+}
+
+fn transfer_to(payer: &Keypair, to: &Pubkey) -> Transaction {
+    let ix = system_instruction::transfer(&payer.pubkey(), to, LAMPORTS_PER_SOL);
+    Transaction::new(
+        &[payer],
+        Message::new(&[ix], Some(&payer.pubkey())),
+        Default::default(),
+    )
+}
+
+#[test]
+fn missing_account_policies() {
+    let payer = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    let tx = transfer_to(&payer, &recipient);
+
+    // The recipient doesn't exist yet, which the default policy allows.
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(funded(payer.pubkey()))).build();
+    assert!(rollup_c.process_rollup_transfers(&[tx.clone()])[0].is_success());
+
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(funded(payer.pubkey())))
+        .missing_account_policy(MissingAccountPolicy::Error)
+        .build();
+    let results = rollup_c.process_rollup_transfers(&[tx.clone()]);
+    assert!(!results[0].is_success());
+    assert!(
+        results[0]
+            .result
+            .contains(&format!("account {} does not exist", recipient)),
+        "{}",
+        results[0].result
+    );
+
+    // Even the fee payer can be conjured up.
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(HashMap::new()))
+        .missing_account_policy(MissingAccountPolicy::Synthesize(AccountSharedData::new(
+            10 * LAMPORTS_PER_SOL,
+            0,
+            &system_program::id(),
+        )))
+        .build();
+    let results = rollup_c.process_rollup_transfers(&[tx]);
+    assert!(results[0].is_success(), "{}", results[0].result);
+}
+
+#[test]
+fn transport_errors_are_not_missing_accounts() {
+    use solana_client::rpc_client::RpcClient;
+
+    let payer = Keypair::new();
+    let tx = transfer_to(&payer, &Pubkey::new_unique());
+
+    // Nothing listens on this port, so every request fails.
+    let rpc_client = RpcClient::new("http://127.0.0.1:1".to_string());
+    let loader = RollUpAccountLoader::new(&rpc_client).with_missing_account_policy(
+        MissingAccountPolicy::Synthesize(AccountSharedData::default()),
+    );
+    assert!(matches!(
+        loader.load(&payer.pubkey()),
+        Err(AccountLoadError::Transport { pubkey, .. }) if pubkey == payer.pubkey()
+    ));
+
+    let rollup_c = RollUpChannel::new(vec![], &rpc_client);
+    let results = rollup_c.process_rollup_transfers(&[tx.clone()]);
+    assert!(!results[0].is_success());
+    assert!(
+        results[0]
+            .result
+            .contains(&format!("account fetch failed: {}: ", payer.pubkey())),
+        "{}",
+        results[0].result
+    );
+    assert!(results[0].infrastructure_error);
+    assert_eq!(
+        BatchSummary::from_results(&results).infrastructure_errors,
+        1
+    );
+
+    let blocks = rollup_c.process_blocks(&[vec![tx]]);
+    assert!(blocks[0].infrastructure_error);
+
+    // A transaction failing on its own doesn't count as an infrastructure error.
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(HashMap::new()))
+        .missing_account_policy(MissingAccountPolicy::Error)
+        .build();
+    let results = rollup_c.process_rollup_transfers(&[transfer_to(&payer, &Pubkey::new_unique())]);
+    assert!(!results[0].is_success());
+    assert!(!results[0].infrastructure_error);
+}
+
+#[test]
+fn cache_stats_track_hits_misses_and_evictions() {
+    let key = Pubkey::new_unique();
+    let mut accounts = funded(key);
+    accounts.insert(
+        Pubkey::new_unique(),
+        AccountSharedData::new(1, 64, &system_program::id()),
+    );
+    let data_key = *accounts.keys().find(|pubkey| **pubkey != key).unwrap();
+
+    let loader = RollUpAccountLoader::with_source(AccountSource::Static(accounts));
+    loader.get_account_shared_data(&key);
+    loader.get_account_shared_data(&key);
+    loader.get_account_shared_data(&data_key);
+    loader.get_account_shared_data(&Pubkey::new_unique());
+
+    let stats = loader.stats();
+    assert_eq!(stats.hits, 1);
+    assert_eq!(stats.misses, 3);
+    assert_eq!(stats.rpc_errors, 0);
+    assert_eq!(stats.bytes_cached, 64);
+
+    loader.invalidate(&data_key);
+    let stats = loader.stats();
+    assert_eq!(stats.evictions, 1);
+    assert_eq!(stats.bytes_cached, 0);
+
+    loader.reset_stats();
+    assert_eq!(loader.stats(), CacheStats::default());
+    assert_eq!(loader.fetch_stats(), FetchStats::default());
+}
+
+#[test]
+fn prefetch_reports_each_key() {
+    let existing = Pubkey::new_unique();
+    let absent = Pubkey::new_unique();
+    let overridden = Pubkey::new_unique();
+
+    let loader = RollUpAccountLoader::with_source(AccountSource::Static(funded(existing)));
+    loader.insert_override(overridden, AccountSharedData::default());
+
+    let report = loader.prefetch(&[existing, absent, overridden, existing]);
+    assert_eq!(report.fetched, vec![existing]);
+    assert_eq!(report.missing, vec![absent]);
+    assert_eq!(report.cached, vec![overridden]);
+    assert!(report.failed.is_empty());
+
+    // Warmed up: the second round doesn't request anything.
+    let report = loader.prefetch(&[existing]);
+    assert_eq!(report.cached, vec![existing]);
+    assert_eq!(loader.fetch_stats().batch_requests, 1);
+}
+
+#[test]
+fn loaders_share_fetched_accounts_but_not_committed_state() {
+    let key = Pubkey::new_unique();
+    let cache = AccountCache::new();
+
+    let first = RollUpAccountLoader::with_source(AccountSource::Static(funded(key)))
+        .with_cache(cache.clone());
+    first.get_account_shared_data(&key).unwrap();
+    assert_eq!(cache.len(), 1);
+
+    // The second loader's source doesn't even have the account.
+    let second = RollUpAccountLoader::with_source(AccountSource::Static(HashMap::new()))
+        .with_cache(cache.clone());
+    assert!(second.get_account_shared_data(&key).is_some());
+    assert_eq!(second.fetch_stats().single_requests, 0);
+
+    // State a channel commits stays with its own loader.
+    let payer = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    let ix = system_instruction::transfer(&payer.pubkey(), &recipient, LAMPORTS_PER_SOL);
+    let tx = Transaction::new(
+        &[&payer],
+        Message::new(&[ix], Some(&payer.pubkey())),
+        Default::default(),
+    );
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(funded(payer.pubkey())))
+        .account_cache(cache.clone())
+        .build();
+    let results = rollup_c.process_rollup_transfers(&[tx]);
+    assert!(results[0].is_success(), "{}", results[0].result);
+    assert!(rollup_c
+        .account_loader()
+        .get_account_shared_data(&recipient)
+        .is_some());
+    assert!(second.get_account_shared_data(&recipient).is_none());
+    assert_eq!(
+        second.get_account_shared_data(&payer.pubkey()),
+        Some(AccountSharedData::new(
+            10 * LAMPORTS_PER_SOL,
+            0,
+            &system_program::id()
+        ))
+    );
+}
+
+#[test]
+fn negative_cache_serves_missing_accounts_until_invalidated() {
+    let absent = Pubkey::new_unique();
+    let loader = RollUpAccountLoader::with_source(AccountSource::Static(HashMap::new()))
+        .with_negative_ttl(Duration::from_secs(60));
+
+    assert_eq!(loader.load(&absent), Ok(None));
+    assert_eq!(loader.load(&absent), Ok(None));
+    assert_eq!(loader.prefetch(&[absent]).missing, vec![absent]);
+    assert_eq!(
+        loader.fetch_stats(),
+        FetchStats {
+            single_requests: 1,
+            batch_requests: 0
+        }
+    );
+    assert_eq!(loader.stats().negative_hits, 2);
+
+    loader.invalidate(&absent);
+    assert_eq!(loader.load(&absent), Ok(None));
+    assert_eq!(loader.fetch_stats().single_requests, 2);
+
+    loader.insert_override(
+        absent,
+        AccountSharedData::new(LAMPORTS_PER_SOL, 0, &system_program::id()),
+    );
+    loader.remove_override(&absent);
+    assert_eq!(loader.load(&absent), Ok(None));
+    assert_eq!(loader.fetch_stats().single_requests, 3);
+}
+
+#[test]
+fn restore_undoes_a_batch() {
+    let payer = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    let transfer = |lamports| {
+        let ix = system_instruction::transfer(&payer.pubkey(), &recipient, lamports);
+        Transaction::new(
+            &[&payer],
+            Message::new(&[ix], Some(&payer.pubkey())),
+            Default::default(),
+        )
+    };
+
+    let rollup_c = RollUpChannel::new_offline(vec![], funded(payer.pubkey()));
+    let loader = rollup_c.account_loader();
+    let oracle = Pubkey::new_unique();
+    loader.insert_override(oracle, AccountSharedData::new(1, 8, &system_program::id()));
+    let snapshot = loader.snapshot();
+
+    let results = rollup_c.process_rollup_transfers(&[transfer(LAMPORTS_PER_SOL)]);
+    assert!(results[0].is_success(), "{}", results[0].result);
+    loader.remove_override(&oracle);
+    assert!(loader.get_account_shared_data(&recipient).is_some());
+
+    loader.restore(&snapshot);
+    assert!(loader.get_account_shared_data(&recipient).is_none());
+    assert!(loader.overrides().contains_key(&oracle));
+
+    // Another ordering starts from the captured state again.
+    let results = rollup_c.process_rollup_transfers(&[transfer(2 * LAMPORTS_PER_SOL)]);
+    assert!(results[0].is_success(), "{}", results[0].result);
+    assert_eq!(
+        loader
+            .get_account_shared_data(&recipient)
+            .map(|account| account.lamports()),
+        Some(2 * LAMPORTS_PER_SOL)
+    );
+}
+
+#[test]
+fn stats_summarize_fetch_latency() {
+    let key = Pubkey::new_unique();
+    let mut accounts = funded(key);
+    let data_key = Pubkey::new_unique();
+    accounts.insert(
+        data_key,
+        AccountSharedData::new(1, 64, &system_program::id()),
+    );
+
+    let loader = RollUpAccountLoader::with_source(AccountSource::Static(accounts));
+    loader.prefetch(&[key, data_key]);
+    loader.load(&Pubkey::new_unique()).unwrap();
+
+    let latency = loader.stats().latency;
+    assert_eq!(latency.requests, 2);
+    assert_eq!(latency.bytes, 64);
+    assert_eq!(latency.buckets.iter().sum::<u64>(), 2);
+    assert_eq!(latency.buckets.len(), LATENCY_BUCKET_BOUNDS.len() + 1);
+    assert!(latency.max <= latency.total);
+    assert!(latency.mean() <= latency.max);
+
+    loader.reset_stats();
+    assert_eq!(loader.stats().latency, FetchLatency::default());
+}
+
+#[test]
+fn bounded_cache_evicts_least_recently_used_accounts() {
+    let keys: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+    let accounts: HashMap<Pubkey, AccountSharedData> = keys
+        .iter()
+        .enumerate()
+        .map(|(index, key)| {
+            let mut account = AccountSharedData::new(1, 100, &system_program::id());
+            account.data_as_mut_slice()[0] = index as u8;
+            (*key, account)
+        })
+        .collect();
+    let loader =
+        RollUpAccountLoader::with_source(AccountSource::Static(accounts)).with_max_cache_bytes(250);
+    let pinned = loader.cache().pin([keys[0]]);
+
+    loader.load(&keys[0]).unwrap();
+    loader.load(&keys[1]).unwrap();
+    loader.load(&keys[2]).unwrap();
+    // keys[1] is the least recently used unpinned account.
+    let stats = loader.stats();
+    assert_eq!(stats.evictions, 1);
+    assert_eq!(stats.bytes_cached, 200);
+
+    // keys[0] stays pinned, so keys[2] makes room for keys[3].
+    loader.load(&keys[3]).unwrap();
+    assert_eq!(loader.stats().evictions, 2);
+    assert_eq!(loader.stats().bytes_cached, 200);
+    drop(pinned);
+
+    // Evicted accounts are refetched, with their own data; only keys[0] is still cached.
+    let requests = loader.fetch_stats().single_requests;
+    for (index, key) in keys.iter().enumerate() {
+        let account = loader.load(key).unwrap().unwrap();
+        assert_eq!(account.data()[0], index as u8);
+        assert!(loader.stats().bytes_cached <= 250);
+    }
+    assert_eq!(loader.fetch_stats().single_requests, requests + 3);
+}
+
+/// A backend answering from a map, rate limiting its first `throttled` requests.
+struct Indexer {
+    accounts: HashMap<Pubkey, AccountSharedData>,
+    throttled: AtomicUsize,
+    requests: AtomicUsize,
+}
+
+impl AccountFetcher for Indexer {
+    fn fetch(&self, pubkey: &Pubkey) -> Result<Option<AccountSharedData>, SourceError> {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        let throttled = self
+            .throttled
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| {
+                left.checked_sub(1)
+            });
+        if throttled.is_ok() {
+            return Err(SourceError::Throttled("slow down".to_string()));
+        }
+        Ok(self.accounts.get(pubkey).cloned())
+    }
+}
+
+#[test]
+fn custom_fetcher_backs_a_channel() {
+    let payer = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    let indexer = Indexer {
+        accounts: funded(payer.pubkey()),
+        throttled: AtomicUsize::new(1),
+        requests: AtomicUsize::new(0),
+    };
+
+    let rollup_c = RollUpChannel::builder(AccountSource::custom(&indexer))
+        .request_limits(RequestLimits {
+            throttle_backoff: Duration::from_millis(1),
+            ..RequestLimits::default()
+        })
+        .build();
+    let results = rollup_c.process_rollup_transfers(&[transfer_to(&payer, &recipient)]);
+    assert!(results[0].is_success(), "{}", results[0].result);
+
+    let stats = rollup_c.account_loader().stats();
+    assert_eq!(stats.throttled, 1);
+    assert_eq!(stats.rpc_errors, 0);
+    assert!(indexer.requests.load(Ordering::Relaxed) > 0);
+}
+
+/// A backend taking a while to answer, which panics instead on its first `panics` requests.
+struct SlowFetcher {
+    account: Result<AccountSharedData, SourceError>,
+    panics: AtomicUsize,
+    requests: AtomicUsize,
+}
+
+impl AccountFetcher for SlowFetcher {
+    fn fetch(&self, _pubkey: &Pubkey) -> Result<Option<AccountSharedData>, SourceError> {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        std::thread::sleep(Duration::from_millis(200));
+        let panicking = self
+            .panics
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| {
+                left.checked_sub(1)
+            });
+        assert!(panicking.is_err(), "backend crashed");
+        self.account.clone().map(Some)
+    }
+}
+
+/// Looks `key` up from `threads` threads at once, returning each thread's outcome, or
+/// `None` for threads that panicked.
+fn load_concurrently(
+    loader: &RollUpAccountLoader,
+    key: Pubkey,
+    threads: usize,
+) -> Vec<Option<Result<Option<AccountSharedData>, AccountLoadError>>> {
+    let barrier = std::sync::Barrier::new(threads);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    barrier.wait();
+                    loader.load(&key)
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().ok())
+            .collect()
+    })
+}
+
+#[test]
+fn concurrent_lookups_share_one_fetch() {
+    let key = Pubkey::new_unique();
+    let account = AccountSharedData::new(1, 0, &system_program::id());
+    let fetcher = SlowFetcher {
+        account: Ok(account.clone()),
+        panics: AtomicUsize::new(0),
+        requests: AtomicUsize::new(0),
+    };
+    let loader = RollUpAccountLoader::with_source(AccountSource::custom(&fetcher));
+
+    let outcomes = load_concurrently(&loader, key, 8);
+    assert!(outcomes
+        .iter()
+        .all(|outcome| outcome == &Some(Ok(Some(account.clone())))));
+    assert_eq!(fetcher.requests.load(Ordering::Relaxed), 1);
+    let stats = loader.stats();
+    assert_eq!(stats.misses, 1);
+    assert_eq!(stats.coalesced, 7);
+}
+
+#[test]
+fn late_lookups_find_the_fetched_account() {
+    let key = Pubkey::new_unique();
+    let account = AccountSharedData::new(1, 0, &system_program::id());
+    let fetcher = SlowFetcher {
+        account: Ok(account.clone()),
+        panics: AtomicUsize::new(0),
+        requests: AtomicUsize::new(0),
+    };
+    let loader = RollUpAccountLoader::with_source(AccountSource::custom(&fetcher));
+
+    // Half the lookups start with the fetch, the others around the time it lands, when
+    // they can miss the cache yet find no flight to join.
+    let threads = 16;
+    let barrier = std::sync::Barrier::new(threads);
+    let outcomes: Vec<_> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|thread| {
+                let (loader, barrier) = (&loader, &barrier);
+                scope.spawn(move || {
+                    barrier.wait();
+                    if thread % 2 == 1 {
+                        std::thread::sleep(Duration::from_millis(190 + 2 * thread as u64));
+                    }
+                    loader.load(&key)
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect()
+    });
+    assert!(outcomes
+        .iter()
+        .all(|outcome| outcome == &Ok(Some(account.clone()))));
+    assert_eq!(loader.fetch_stats().single_requests, 1);
+    assert_eq!(fetcher.requests.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn concurrent_lookups_share_errors() {
+    let key = Pubkey::new_unique();
+    let fetcher = SlowFetcher {
+        account: Err(SourceError::Failed("index unavailable".to_string())),
+        panics: AtomicUsize::new(0),
+        requests: AtomicUsize::new(0),
+    };
+    let loader = RollUpAccountLoader::with_source(AccountSource::custom(&fetcher));
+
+    for outcome in load_concurrently(&loader, key, 4) {
+        assert_eq!(
+            outcome,
+            Some(Err(AccountLoadError::Transport {
+                pubkey: key,
+                message: "index unavailable".to_string(),
+            }))
+        );
+    }
+    assert_eq!(fetcher.requests.load(Ordering::Relaxed), 1);
+    assert_eq!(loader.stats().rpc_errors, 1);
+}
+
+#[test]
+fn panicking_fetch_does_not_wedge_waiters() {
+    let key = Pubkey::new_unique();
+    let account = AccountSharedData::new(1, 0, &system_program::id());
+    let fetcher = SlowFetcher {
+        account: Ok(account.clone()),
+        panics: AtomicUsize::new(1),
+        requests: AtomicUsize::new(0),
+    };
+    let loader = RollUpAccountLoader::with_source(AccountSource::custom(&fetcher));
+
+    // The first fetch panics in its thread; a waiter fetches again for the others.
+    let outcomes = load_concurrently(&loader, key, 4);
+    assert_eq!(
+        outcomes.iter().filter(|outcome| outcome.is_none()).count(),
+        1
+    );
+    assert!(outcomes
+        .iter()
+        .flatten()
+        .all(|outcome| outcome == &Ok(Some(account.clone()))));
+    assert_eq!(fetcher.requests.load(Ordering::Relaxed), 2);
+    assert_eq!(loader.load(&key), Ok(Some(account)));
+}
+
+#[test]
+fn inspecting_reports_every_layer_without_touching_the_cache() {
+    // Sorted by key, `older` comes after `newer`, so touching in order would reverse them.
+    let older = Pubkey::new_from_array([2; 32]);
+    let newer = Pubkey::new_from_array([1; 32]);
+    let third = Pubkey::new_from_array([3; 32]);
+    let overridden = Pubkey::new_unique();
+    let accounts: HashMap<Pubkey, AccountSharedData> = [older, newer, third]
+        .into_iter()
+        .map(|pubkey| {
+            let mut account = AccountSharedData::new(1, 10, &system_program::id());
+            account.set_data_from_slice(&[pubkey.to_bytes()[0]; 10]);
+            (pubkey, account)
+        })
+        .collect();
+    let loader = RollUpAccountLoader::with_source(AccountSource::Static(accounts.clone()))
+        .with_max_cache_bytes(20);
+    loader.insert_override(
+        overridden,
+        AccountSharedData::new(7, 0, &system_program::id()),
+    );
+    loader.load(&older).unwrap();
+    loader.load(&newer).unwrap();
+
+    let stats = loader.stats();
+    let inspected = loader.inspect();
+    assert_eq!(loader.stats(), stats);
+    assert_eq!(inspected.len(), 3);
+    let info = |pubkey: &Pubkey| {
+        inspected
+            .iter()
+            .find(|info| info.pubkey == *pubkey)
+            .unwrap()
+    };
+    assert_eq!(info(&overridden).layer, AccountLayer::Override);
+    assert_eq!(info(&overridden).lamports, 7);
+    assert_eq!(info(&overridden).age, None);
+    let fetched = info(&older);
+    assert_eq!(fetched.layer, AccountLayer::Fetched);
+    assert_eq!(fetched.data_len, 10);
+    assert_eq!(
+        fetched.data_hash,
+        solana_sdk::hash::hash(accounts[&older].data())
+    );
+    assert!(fetched.age.is_some() && !fetched.expired);
+    assert_eq!(fetched.to_json()["pubkey"], older.to_string());
+    assert!(fetched.to_string().contains("[fetched]"));
+
+    // `older` is still the least recently used account.
+    loader.load(&third).unwrap();
+    let cached: Vec<Pubkey> = loader
+        .inspect()
+        .into_iter()
+        .filter(|info| info.layer == AccountLayer::Fetched)
+        .map(|info| info.pubkey)
+        .collect();
+    assert_eq!(cached, [newer, third]);
+}
+
+#[test]
+fn missing_sysvars_are_synthesized() {
+    use solana_sdk::account::from_account;
+    use solana_sdk::clock::Clock;
+    use solana_sdk::rent::Rent;
+    use solana_sdk::sysvar;
+
+    let defaults = SysvarDefaults {
+        clock: Clock {
+            slot: 42,
+            ..Clock::default()
+        },
+        rent: Rent {
+            lamports_per_byte_year: 1,
+            ..Rent::default()
+        },
+        ..SysvarDefaults::default()
+    };
+    let loader = RollUpAccountLoader::with_source(AccountSource::Static(HashMap::new()))
+        .with_missing_account_policy(MissingAccountPolicy::Error)
+        .with_sysvar_defaults(defaults.clone());
+
+    let clock = loader.load(&sysvar::clock::id()).unwrap().unwrap();
+    assert_eq!(from_account::<Clock, _>(&clock), Some(defaults.clock));
+    let rent = loader.load(&sysvar::rent::id()).unwrap().unwrap();
+    assert_eq!(from_account::<Rent, _>(&rent), Some(defaults.rent));
+    assert!(loader
+        .load(&sysvar::epoch_schedule::id())
+        .unwrap()
+        .is_some());
+    assert_eq!(loader.fetch_stats().single_requests, 3);
+    // Synthesized once, then served like committed state.
+    loader.load(&sysvar::clock::id()).unwrap();
+    assert_eq!(loader.fetch_stats().single_requests, 3);
+
+    // Other sysvars follow the missing account policy.
+    assert_eq!(
+        loader.load(&sysvar::epoch_rewards::id()),
+        Err(AccountLoadError::Missing(sysvar::epoch_rewards::id()))
+    );
+
+    // A sysvar the source has is served as is.
+    let fetched = Clock {
+        slot: 7,
+        ..Clock::default()
+    };
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        sysvar::clock::id(),
+        solana_sdk::account::create_account_shared_data_for_test(&fetched),
+    );
+    let loader = RollUpAccountLoader::with_source(AccountSource::Static(accounts))
+        .with_sysvar_defaults(defaults);
+    let clock = loader.load(&sysvar::clock::id()).unwrap().unwrap();
+    assert_eq!(from_account::<Clock, _>(&clock), Some(fetched));
+}
@@ -0,0 +1,239 @@
+#![cfg(feature = "pubsub")]
+
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc as std_mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use base64::prelude::{Engine, BASE64_STANDARD};
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use solana_client_ext::*;
+use solana_sdk::account::{Account, AccountSharedData, ReadableAccount};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::system_program;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio_tungstenite::tungstenite::Message;
+
+/// A websocket endpoint accepting one connection, which answers `accountSubscribe` and
+/// `accountUnsubscribe`, reports them to the test, and pushes the notifications the
+/// test hands it.
+struct FakeNode {
+    url: String,
+    requests: std_mpsc::Receiver<(String, Pubkey)>,
+    notifications: UnboundedSender<(Pubkey, Value)>,
+}
+
+impl FakeNode {
+    fn start() -> Self {
+        let (url_sender, url) = std_mpsc::channel();
+        let (request_sender, requests) = std_mpsc::channel();
+        let (notifications, mut pushed) = mpsc::unbounded_channel::<(Pubkey, Value)>();
+        thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            runtime.block_on(async move {
+                let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+                let address = listener.local_addr().unwrap();
+                url_sender.send(format!("ws://{address}")).unwrap();
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+                let mut subscriptions: HashMap<u64, Pubkey> = HashMap::new();
+                let mut next_id = 0;
+                loop {
+                    tokio::select! {
+                        message = ws.next() => {
+                            let text = match message {
+                                Some(Ok(Message::Text(text))) => text,
+                                Some(Ok(_)) => continue,
+                                _ => break,
+                            };
+                            let request: Value = serde_json::from_str(&text).unwrap();
+                            let method = request["method"].as_str().unwrap().to_string();
+                            let (pubkey, result) = match method.as_str() {
+                                "accountSubscribe" => {
+                                    let pubkey = request["params"][0].as_str().unwrap();
+                                    let pubkey: Pubkey = pubkey.parse().unwrap();
+                                    next_id += 1;
+                                    subscriptions.insert(next_id, pubkey);
+                                    (pubkey, json!(next_id))
+                                }
+                                "accountUnsubscribe" => {
+                                    let id = request["params"][0].as_u64().unwrap();
+                                    (subscriptions.remove(&id).unwrap(), json!(true))
+                                }
+                                _ => continue,
+                            };
+                            let _ = request_sender.send((method, pubkey));
+                            let response =
+                                json!({"jsonrpc": "2.0", "result": result, "id": request["id"]});
+                            if ws.send(Message::Text(response.to_string())).await.is_err() {
+                                break;
+                            }
+                        }
+                        Some((pubkey, result)) = pushed.recv() => {
+                            let Some(id) = subscriptions
+                                .iter()
+                                .find_map(|(id, subscribed)| (*subscribed == pubkey).then_some(*id))
+                            else {
+                                continue;
+                            };
+                            let notification = json!({
+                                "jsonrpc": "2.0",
+                                "method": "accountNotification",
+                                "params": {"result": result, "subscription": id},
+                            });
+                            if ws.send(Message::Text(notification.to_string())).await.is_err() {
+                                break;
+                            }
+                        }
+                    }
+                }
+            });
+        });
+        Self {
+            url: url.recv().unwrap(),
+            requests,
+            notifications,
+        }
+    }
+
+    /// The method and account of the next request the node received.
+    fn next_request(&self) -> (String, Pubkey) {
+        self.requests
+            .recv_timeout(Duration::from_secs(5))
+            .expect("the refresher sent no request")
+    }
+
+    /// Pushes `account` as the state of `pubkey` at `slot`, encoded as requested by the
+    /// refresher.
+    fn notify(&self, pubkey: Pubkey, slot: u64, account: &Account) {
+        let data = zstd::encode_all(account.data.as_slice(), 0).unwrap();
+        let result = json!({
+            "context": {"slot": slot},
+            "value": {
+                "lamports": account.lamports,
+                "data": [BASE64_STANDARD.encode(data), "base64+zstd"],
+                "owner": account.owner.to_string(),
+                "executable": account.executable,
+                "rentEpoch": account.rent_epoch,
+                "space": account.data.len(),
+            },
+        });
+        self.notifications.send((pubkey, result)).unwrap();
+    }
+}
+
+fn wait_until(mut condition: impl FnMut() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !condition() {
+        assert!(Instant::now() < deadline, "timed out");
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+#[test]
+fn unreachable_endpoints_fall_back_to_the_ttl() {
+    let key = Pubkey::new_unique();
+    let mut accounts = HashMap::new();
+    accounts.insert(key, AccountSharedData::new(1, 0, &system_program::id()));
+
+    let cache = AccountCache::with_ttl(Duration::ZERO);
+    let refresher =
+        AccountRefresher::start("ws://127.0.0.1:1", &cache, CommitmentConfig::confirmed());
+    let loader =
+        RollUpAccountLoader::with_source(AccountSource::Static(accounts)).with_cache(cache.clone());
+
+    for _ in 0..2 {
+        assert!(loader.load(&key).unwrap().is_some());
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(loader.fetch_stats().single_requests, 2);
+    assert!(!refresher.is_connected());
+    assert_eq!(refresher.subscriptions(), 0);
+
+    // Dropping the refresher detaches it from the cache, which keeps working.
+    drop(refresher);
+    assert!(loader.load(&key).unwrap().is_some());
+}
+
+#[test]
+fn subscriptions_follow_fetches_evictions_and_invalidations() {
+    let keys: Vec<Pubkey> = (0..2).map(|_| Pubkey::new_unique()).collect();
+    let accounts = keys
+        .iter()
+        .map(|key| (*key, AccountSharedData::new(1, 60, &system_program::id())))
+        .collect();
+
+    let node = FakeNode::start();
+    let cache = AccountCache::new().with_max_bytes(100);
+    let refresher = AccountRefresher::start(&node.url, &cache, CommitmentConfig::confirmed());
+    let loader =
+        RollUpAccountLoader::with_source(AccountSource::Static(accounts)).with_cache(cache.clone());
+
+    loader.load(&keys[0]).unwrap();
+    assert_eq!(
+        node.next_request(),
+        ("accountSubscribe".to_string(), keys[0])
+    );
+
+    // The second account doesn't fit beside the first, which is evicted.
+    loader.load(&keys[1]).unwrap();
+    let requests: HashSet<_> = (0..2).map(|_| node.next_request()).collect();
+    let expected = HashSet::from([
+        ("accountSubscribe".to_string(), keys[1]),
+        ("accountUnsubscribe".to_string(), keys[0]),
+    ]);
+    assert_eq!(requests, expected);
+
+    loader.invalidate(&keys[1]);
+    assert_eq!(
+        node.next_request(),
+        ("accountUnsubscribe".to_string(), keys[1])
+    );
+    wait_until(|| refresher.subscriptions() == 0);
+    assert!(refresher.is_connected());
+    assert_eq!(refresher.failed_subscriptions(), 0);
+}
+
+#[test]
+fn notifications_replace_cached_accounts_unless_older() {
+    let key = Pubkey::new_unique();
+    let mut accounts = HashMap::new();
+    accounts.insert(key, AccountSharedData::new(1, 0, &system_program::id()));
+
+    let node = FakeNode::start();
+    let cache = AccountCache::new();
+    let refresher = AccountRefresher::start(&node.url, &cache, CommitmentConfig::confirmed());
+    let loader =
+        RollUpAccountLoader::with_source(AccountSource::Static(accounts)).with_cache(cache.clone());
+    let lamports = || loader.load(&key).unwrap().unwrap().lamports();
+
+    assert_eq!(lamports(), 1);
+    assert_eq!(node.next_request(), ("accountSubscribe".to_string(), key));
+    wait_until(|| refresher.subscriptions() == 1);
+
+    let state = |lamports| Account {
+        lamports,
+        data: vec![7; 300],
+        owner: system_program::id(),
+        ..Account::default()
+    };
+    node.notify(key, 10, &state(2));
+    wait_until(|| lamports() == 2);
+    assert_eq!(loader.load(&key).unwrap().unwrap().data(), &[7; 300][..]);
+
+    // Notifications are applied in order, so once the newest shows, the older one before
+    // it was turned down.
+    node.notify(key, 5, &state(3));
+    node.notify(key, 12, &state(4));
+    wait_until(|| lamports() == 4);
+    wait_until(|| refresher.notifications() >= 2);
+    assert_eq!(refresher.notifications(), 2);
+    assert_eq!(loader.fetch_stats().single_requests, 1);
+}
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use base64::prelude::{Engine, BASE64_STANDARD};
+use serde_json::{json, Value};
+use solana_client::client_error::Result as ClientResult;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_client::RpcClientConfig;
+use solana_client::rpc_request::RpcRequest;
+use solana_client::rpc_sender::{RpcSender, RpcTransportStats};
+use solana_client_ext::*;
+use solana_sdk::{
+    account::{AccountSharedData, ReadableAccount},
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    instruction::Instruction,
+    message::Message,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_instruction, system_program, sysvar,
+    transaction::Transaction,
+};
+
+/// `account` as a base64-encoded `UiAccount`.
+fn ui_account(account: &AccountSharedData) -> Value {
+    json!({
+        "lamports": account.lamports(),
+        "data": [BASE64_STANDARD.encode(account.data()), "base64"],
+        "owner": account.owner().to_string(),
+        "executable": account.executable(),
+        "rentEpoch": 0,
+        "space": account.data().len(),
+    })
+}
+
+/// An RPC transport answering `getMultipleAccounts` with the `accounts` it holds, every
+/// other key being missing. It records how many keys each request asked for, and the
+/// most requests it had in flight at once.
+#[derive(Clone, Default)]
+struct AccountsSender {
+    accounts: HashMap<Pubkey, AccountSharedData>,
+    requests: Arc<Mutex<Vec<usize>>>,
+    in_flight: Arc<AtomicUsize>,
+    max_in_flight: Arc<AtomicUsize>,
+}
+
+#[async_trait::async_trait]
+impl RpcSender for AccountsSender {
+    async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+        assert_eq!(request, RpcRequest::GetMultipleAccounts);
+        let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+        // Let the other requests of the prefetch start before this one completes.
+        tokio::task::yield_now().await;
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+        let keys = params[0].as_array().unwrap();
+        self.requests.lock().unwrap().push(keys.len());
+        let value: Vec<Value> = keys
+            .iter()
+            .map(|key| {
+                let pubkey: Pubkey = key.as_str().unwrap().parse().unwrap();
+                self.accounts.get(&pubkey).map_or(Value::Null, ui_account)
+            })
+            .collect();
+        Ok(json!({ "context": { "slot": 1 }, "value": value }))
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        RpcTransportStats::default()
+    }
+
+    fn url(&self) -> String {
+        "accounts".to_string()
+    }
+}
+
+#[tokio::test]
+async fn prefetch_without_keys_sends_nothing() {
+    // Nothing listens on this port, so any request would fail.
+    let rpc_client = RpcClient::new("http://127.0.0.1:1".to_string());
+    let loader = AsyncAccountLoader::new(&rpc_client);
+
+    let accounts = loader.prefetch_keys([]).await.unwrap();
+    assert!(accounts.is_empty());
+}
+
+#[tokio::test]
+async fn prefetch_reports_transport_errors() {
+    let rpc_client = RpcClient::new("http://127.0.0.1:1".to_string());
+    let loader = AsyncAccountLoader::new(&rpc_client).with_max_concurrent_requests(2);
+
+    let keys: Vec<Pubkey> = (0..250).map(|_| Pubkey::new_unique()).collect();
+    let err = loader.prefetch_keys(keys).await.unwrap_err();
+    assert!(matches!(err, AccountLoadError::Transport { .. }), "{}", err);
+}
+
+#[tokio::test]
+async fn prefetched_accounts_execute_offline() {
+    let payer = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    let tx = Transaction::new(
+        &[&payer],
+        Message::new(
+            &[system_instruction::transfer(
+                &payer.pubkey(),
+                &recipient,
+                LAMPORTS_PER_SOL,
+            )],
+            Some(&payer.pubkey()),
+        ),
+        Default::default(),
+    );
+    let funded = AccountSharedData::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id());
+
+    // One request for the batch's keys and the sysvars, in sorted order, of which only the
+    // payer exists. Nothing is upgradeable, so there is no ProgramData to follow up with.
+    let mut keys = vec![payer.pubkey(), recipient, system_program::id()];
+    keys.extend([
+        sysvar::clock::id(),
+        sysvar::epoch_rewards::id(),
+        sysvar::epoch_schedule::id(),
+        sysvar::last_restart_slot::id(),
+        sysvar::rent::id(),
+        sysvar::slot_hashes::id(),
+        sysvar::stake_history::id(),
+    ]);
+    keys.sort_unstable();
+    let value: Vec<Value> = keys
+        .iter()
+        .map(|key| {
+            if *key == payer.pubkey() {
+                ui_account(&funded)
+            } else {
+                Value::Null
+            }
+        })
+        .collect();
+    let rpc_client = RpcClient::new_mock_with_mocks(
+        "succeeds".to_string(),
+        HashMap::from([(
+            RpcRequest::GetMultipleAccounts,
+            json!({ "context": { "slot": 1 }, "value": value }),
+        )]),
+    );
+
+    let accounts = AsyncAccountLoader::new(&rpc_client)
+        .prefetch(&[tx.clone()])
+        .await
+        .unwrap();
+    assert_eq!(accounts, HashMap::from([(payer.pubkey(), funded)]));
+
+    let results = RollUpChannel::new_offline(vec![], accounts).process_rollup_transfers(&[tx]);
+    assert!(results[0].is_success(), "{}", results[0].result);
+}
+
+#[tokio::test]
+async fn prefetch_follows_up_with_programdata() {
+    let program_id = Pubkey::new_unique();
+    let (programdata_address, _) =
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+    let program = AccountSharedData::new_data(
+        1,
+        &UpgradeableLoaderState::Program {
+            programdata_address,
+        },
+        &bpf_loader_upgradeable::id(),
+    )
+    .unwrap();
+    let programdata = AccountSharedData::new(1, 0, &bpf_loader_upgradeable::id());
+    let sender = AccountsSender {
+        accounts: HashMap::from([(program_id, program), (programdata_address, programdata)]),
+        ..AccountsSender::default()
+    };
+    let rpc_client = RpcClient::new_sender(sender.clone(), RpcClientConfig::default());
+
+    let payer = Pubkey::new_unique();
+    let tx = Transaction::new_unsigned(Message::new(
+        &[Instruction::new_with_bytes(program_id, &[], vec![])],
+        Some(&payer),
+    ));
+    let accounts = AsyncAccountLoader::new(&rpc_client)
+        .prefetch(&[tx])
+        .await
+        .unwrap();
+
+    let mut fetched: Vec<Pubkey> = accounts.into_keys().collect();
+    fetched.sort_unstable();
+    let mut expected = vec![program_id, programdata_address];
+    expected.sort_unstable();
+    assert_eq!(fetched, expected);
+    // The batch's keys and the sysvars, then the ProgramData alone.
+    assert_eq!(*sender.requests.lock().unwrap(), [9, 1]);
+}
+
+#[tokio::test]
+async fn prefetch_chunks_keys_under_the_concurrency_limit() {
+    let keys: Vec<Pubkey> = (0..250).map(|_| Pubkey::new_unique()).collect();
+    let existing = AccountSharedData::new(1, 0, &system_program::id());
+
+    for max_concurrent_requests in [1, 2] {
+        let sender = AccountsSender {
+            accounts: HashMap::from([(keys[0], existing.clone()), (keys[249], existing.clone())]),
+            ..AccountsSender::default()
+        };
+        let rpc_client = RpcClient::new_sender(sender.clone(), RpcClientConfig::default());
+        let loader = AsyncAccountLoader::new(&rpc_client)
+            .with_max_concurrent_requests(max_concurrent_requests);
+
+        let accounts = loader.prefetch_keys(keys.clone()).await.unwrap();
+        assert_eq!(accounts.len(), 2);
+        assert!(accounts.contains_key(&keys[0]) && accounts.contains_key(&keys[249]));
+
+        let mut requests = sender.requests.lock().unwrap().clone();
+        requests.sort_unstable();
+        assert_eq!(requests, [50, 100, 100]);
+        assert_eq!(
+            sender.max_in_flight.load(Ordering::SeqCst),
+            max_concurrent_requests
+        );
+    }
+}
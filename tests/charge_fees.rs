@@ -0,0 +1,91 @@
+mod common;
+
+use std::collections::HashMap;
+
+use common::{single_ix_tx, static_channel};
+use solana_client_ext::*;
+use solana_sdk::{
+    account::AccountSharedData, instruction::Instruction, native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey, rent::Rent, signature::Keypair, signer::Signer, system_instruction,
+    system_program, transaction::TransactionError,
+};
+
+/// A channel serving `accounts`, charging fees or not.
+fn channel(
+    accounts: HashMap<Pubkey, AccountSharedData>,
+    charge_fees: bool,
+) -> RollUpChannel<'static> {
+    static_channel(
+        accounts,
+        RollUpChannelConfig {
+            charge_fees,
+            ..RollUpChannelConfig::default()
+        },
+    )
+}
+
+#[test]
+fn uncharged_fees_leave_the_payer_untouched() {
+    let payer = Keypair::new();
+    // Rent exempt, but unable to pay any fee.
+    let lamports = Rent::default().minimum_balance(0);
+    let accounts = HashMap::from([(
+        payer.pubkey(),
+        AccountSharedData::new(lamports, 0, &system_program::id()),
+    )]);
+    let transfer = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 0);
+    let tx = single_ix_tx(&payer, transfer);
+
+    let results = channel(accounts.clone(), true).process_rollup_transfers(&[tx.clone()]);
+    assert_eq!(results[0].status, TxStatus::NotProcessed);
+
+    let results = channel(accounts, false).process_rollup_transfers(&[tx]);
+    assert!(results[0].is_success(), "{}", results[0].result);
+    assert_eq!(results[0].fee_lamports, 5000);
+    assert!(!results[0].fee_charged);
+    assert_eq!(results[0].pre_balances[0], lamports);
+    assert_eq!(results[0].post_balances[0], lamports);
+}
+
+#[test]
+fn fees_only_transactions_are_still_classified() {
+    let payer = Keypair::new();
+    // Not a program: the transaction fails to load once its fee payer was validated.
+    let not_a_program = Pubkey::new_unique();
+    let accounts = HashMap::from([
+        (
+            payer.pubkey(),
+            AccountSharedData::new(LAMPORTS_PER_SOL, 0, &system_program::id()),
+        ),
+        (
+            not_a_program,
+            AccountSharedData::new(1, 0, &system_program::id()),
+        ),
+    ]);
+    let ix = Instruction::new_with_bytes(not_a_program, &[], vec![]);
+    let tx = single_ix_tx(&payer, ix);
+
+    let charged = channel(accounts.clone(), true).process_rollup_transfers(&[tx.clone()]);
+    let uncharged = channel(accounts, false).process_rollup_transfers(&[tx]);
+    for result in [&charged[0], &uncharged[0]] {
+        assert_eq!(result.status, TxStatus::FeesOnly);
+        assert_eq!(
+            result.error,
+            Some(TransactionError::InvalidProgramForExecution)
+        );
+        assert_eq!(result.fee_lamports, 5000);
+    }
+
+    assert!(charged[0].fee_charged);
+    assert_eq!(
+        charged[0].post_balances[0],
+        charged[0].pre_balances[0] - 5000
+    );
+    assert!(!uncharged[0].fee_charged);
+    assert_eq!(uncharged[0].post_balances[0], uncharged[0].pre_balances[0]);
+    assert!(
+        uncharged[0].result.contains("No fees were charged"),
+        "{}",
+        uncharged[0].result
+    );
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use solana_client_ext::*;
+use solana_sdk::{
+    account::AccountSharedData,
+    message::Message,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::Keypair,
+    signer::Signer,
+    system_instruction, system_program,
+    transaction::{Transaction, TransactionError},
+};
+use solana_svm::transaction_processor::TransactionProcessingConfig;
+
+fn transfer(payer: &Keypair) -> Transaction {
+    let ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1);
+    Transaction::new(
+        &[payer],
+        Message::new(&[ix], Some(&payer.pubkey())),
+        Default::default(),
+    )
+}
+
+#[test]
+fn check_results_follow_the_contract() {
+    let (funded, missing, not_system, poor) = (
+        Keypair::new(),
+        Keypair::new(),
+        Keypair::new(),
+        Keypair::new(),
+    );
+    let loader = RollUpAccountLoader::with_source(AccountSource::Static(HashMap::from([
+        (
+            funded.pubkey(),
+            AccountSharedData::new(LAMPORTS_PER_SOL, 0, &system_program::id()),
+        ),
+        (
+            not_system.pubkey(),
+            AccountSharedData::new(LAMPORTS_PER_SOL, 0, &Pubkey::new_unique()),
+        ),
+        (
+            poor.pubkey(),
+            AccountSharedData::new(1, 0, &system_program::id()),
+        ),
+    ])));
+
+    let paid = transfer(&funded);
+    let sanitized = sanitize_transactions(&[
+        paid.clone(),
+        paid,
+        transfer(&missing),
+        transfer(&not_system),
+        transfer(&poor),
+    ]);
+    let environment = ProcessingEnvironmentBuilder::deterministic().build();
+    let rent = Rent::default();
+    let check_config = CheckConfig::new(&rent, &environment.feature_set);
+    let errors: Vec<_> = check_transactions(&sanitized, &loader, &check_config)
+        .into_iter()
+        .map(|result| result.err())
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            None,
+            Some(TransactionError::AlreadyProcessed),
+            Some(TransactionError::AccountNotFound),
+            Some(TransactionError::InvalidAccountForFee),
+            Some(TransactionError::InsufficientFundsForFee),
+        ]
+    );
+
+    // Without fees, the poor payer is accepted.
+    let mut check_config = CheckConfig::new(&rent, &environment.feature_set);
+    check_config.charge_fees = false;
+    let results = check_transactions(&sanitized[4..], &loader, &check_config);
+    assert!(results[0].is_ok(), "{:?}", results[0]);
+}
+
+#[test]
+fn check_results_drive_a_processor() {
+    let payer = Keypair::new();
+    let loader = RollUpAccountLoader::with_source(AccountSource::Static(HashMap::from([(
+        payer.pubkey(),
+        AccountSharedData::new(LAMPORTS_PER_SOL, 0, &system_program::id()),
+    )])));
+    let fork_graph = Arc::new(RwLock::new(ForkRollUpGraph::with_slots([1])));
+    let processor = ProcessorBuilder::new(Arc::clone(&fork_graph))
+        .slot(1)
+        .build(&loader);
+    populate_sysvars(&processor, &loader, &SysvarSource::Fetch);
+
+    let tx = transfer(&payer);
+    let sanitized = sanitize_transactions(&[tx.clone(), tx]);
+    let environment = ProcessingEnvironmentBuilder::deterministic();
+    let processing_environment = environment.build();
+    let rent = Rent::default();
+    let check_config = CheckConfig::new(&rent, &processing_environment.feature_set);
+    let output = processor.load_and_execute_sanitized_transactions(
+        &loader,
+        &sanitized,
+        check_transactions(&sanitized, &loader, &check_config),
+        &processing_environment,
+        &TransactionProcessingConfig::default(),
+    );
+
+    let processed = output.processing_results[0].as_ref().unwrap();
+    assert!(processed.status().is_ok(), "{:?}", processed.status());
+    assert_eq!(processed.fee_details().total_fee(), 5000);
+    // The refused duplicate is neither executed nor charged.
+    assert_eq!(
+        output.processing_results[1].as_ref().err(),
+        Some(&TransactionError::AlreadyProcessed)
+    );
+}
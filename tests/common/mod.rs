@@ -0,0 +1,45 @@
+//! Fixtures shared by the integration tests; each test crate uses some of them.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
+use solana_client_ext::*;
+use solana_sdk::{
+    account::AccountSharedData, instruction::Instruction, message::Message,
+    native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, signature::Keypair, signer::Signer,
+    system_program, transaction::Transaction,
+};
+
+/// A minimal SBPFv0 program (`mov64 r0, 0; exit`) that always succeeds.
+pub const NOOP_PROGRAM: &[u8] = include_bytes!("../fixtures/noop.so");
+
+/// Where [`NOOP_PROGRAM`] lives, relative to the crate root tests run from.
+pub const NOOP_PROGRAM_PATH: &str = "tests/fixtures/noop.so";
+
+/// A channel serving `accounts` and nothing else, configured with `config`.
+pub fn static_channel(
+    accounts: HashMap<Pubkey, AccountSharedData>,
+    config: RollUpChannelConfig,
+) -> RollUpChannel<'static> {
+    RollUpChannel::builder(AccountSource::Static(accounts))
+        .config(config)
+        .build()
+}
+
+/// A channel on which `payer` holds 1 SOL, its only account, configured with `config`.
+pub fn funded_channel(payer: &Pubkey, config: RollUpChannelConfig) -> RollUpChannel<'static> {
+    let accounts = HashMap::from([(
+        *payer,
+        AccountSharedData::new(LAMPORTS_PER_SOL, 0, &system_program::id()),
+    )]);
+    static_channel(accounts, config)
+}
+
+/// A transaction paid and signed by `payer` carrying `instruction` alone.
+pub fn single_ix_tx(payer: &Keypair, instruction: Instruction) -> Transaction {
+    Transaction::new(
+        &[payer],
+        Message::new(&[instruction], Some(&payer.pubkey())),
+        Default::default(),
+    )
+}
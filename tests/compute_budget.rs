@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use solana_client_ext::*;
+use solana_compute_budget::compute_budget::ComputeBudget;
+use solana_sdk::{
+    account::AccountSharedData, instruction::Instruction, message::Message,
+    native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, signature::Keypair, signer::Signer,
+    system_program, transaction::Transaction,
+};
+
+/// A minimal SBPFv0 program returning the `unix_timestamp` it reads with
+/// `sol_get_clock_sysvar` as its exit code, so it succeeds with a zero timestamp.
+const CLOCK_PROGRAM: &[u8] = include_bytes!("fixtures/clock.so");
+
+/// The result of invoking the clock program with `compute_budget`.
+fn invoke_clock_program(compute_budget: Option<ComputeBudget>) -> ReturnStruct {
+    let payer = Keypair::new();
+    let accounts = HashMap::from([(
+        payer.pubkey(),
+        AccountSharedData::new(LAMPORTS_PER_SOL, 0, &system_program::id()),
+    )]);
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(accounts))
+        .config(RollUpChannelConfig {
+            compute_budget,
+            sysvars: SysvarSource::Explicit(SysvarDefaults::default()),
+            ..RollUpChannelConfig::default()
+        })
+        .build();
+    let program_id = Pubkey::new_unique();
+    rollup_c
+        .add_program(program_id, CLOCK_PROGRAM, LoaderKind::BpfLoader)
+        .unwrap();
+
+    let tx = Transaction::new(
+        &[&payer],
+        Message::new(
+            &[Instruction::new_with_bytes(program_id, &[], vec![])],
+            Some(&payer.pubkey()),
+        ),
+        Default::default(),
+    );
+    rollup_c.process_rollup_transfers(&[tx]).remove(0)
+}
+
+#[test]
+fn custom_compute_budgets_change_the_units_charged() {
+    let default = invoke_clock_program(None);
+    assert!(default.is_success(), "{}", default.result);
+    assert_eq!(default.requested_cu_limit, Some(200_000));
+
+    // The same transaction, with the base cost of sysvar syscalls doubled.
+    let budget = ComputeBudget {
+        sysvar_base_cost: 2 * ComputeBudget::default().sysvar_base_cost,
+        compute_unit_limit: 50_000,
+        ..ComputeBudget::default()
+    };
+    let doubled = invoke_clock_program(Some(budget));
+    assert!(doubled.is_success(), "{}", doubled.result);
+    assert_eq!(
+        doubled.cu - default.cu,
+        ComputeBudget::default().sysvar_base_cost
+    );
+    // The budget's limit replaces the default one.
+    assert_eq!(doubled.requested_cu_limit, Some(50_000));
+}
+
+#[test]
+fn custom_compute_budgets_size_the_runtime_environment() {
+    let fork_graph = Arc::new(RwLock::new(ForkRollUpGraph::default()));
+    let loader = RollUpAccountLoader::with_source(AccountSource::Static(HashMap::new()));
+    let processor = ProcessorBuilder::new(fork_graph)
+        .compute_budget(ComputeBudget {
+            max_call_depth: 8,
+            ..ComputeBudget::default()
+        })
+        .build(&loader);
+    let program_cache = processor.program_cache.read().unwrap();
+    assert_eq!(
+        program_cache
+            .environments
+            .program_runtime_v1
+            .get_config()
+            .max_call_depth,
+        8
+    );
+}
@@ -0,0 +1,156 @@
+#![cfg(feature = "tracing")]
+
+mod common;
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use common::NOOP_PROGRAM_PATH;
+use solana_client_ext::*;
+use solana_sdk::{
+    account::AccountSharedData, instruction::Instruction, message::Message,
+    native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, signature::Keypair, signer::Signer,
+    system_program, transaction::Transaction,
+};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+/// The fields of a span or event, formatted.
+type Fields = HashMap<String, String>;
+
+#[derive(Default)]
+struct FieldVisitor(Fields);
+
+impl Visit for FieldVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+}
+
+/// Records the spans created and the events of the debug execution target.
+#[derive(Clone, Default)]
+struct Recorder {
+    next_id: Arc<AtomicU64>,
+    spans: Arc<Mutex<Vec<(&'static str, Fields)>>>,
+    events: Arc<Mutex<Vec<Fields>>>,
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut visitor = FieldVisitor::default();
+        span.record(&mut visitor);
+        self.spans
+            .lock()
+            .unwrap()
+            .push((span.metadata().name(), visitor.0));
+        Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        if event.metadata().target() == "solana_client_ext::debug_execution" {
+            let mut visitor = FieldVisitor::default();
+            event.record(&mut visitor);
+            self.events.lock().unwrap().push(visitor.0);
+        }
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+/// Two transactions invoking the noop program, processed on a channel debugging
+/// execution or not.
+fn process(debug_execution: bool) -> (Pubkey, Vec<ReturnStruct>, Recorder) {
+    let payer = Keypair::new();
+    let program_id = Pubkey::new_unique();
+    let accounts = HashMap::from([(
+        payer.pubkey(),
+        AccountSharedData::new(LAMPORTS_PER_SOL, 0, &system_program::id()),
+    )]);
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(accounts))
+        .config(RollUpChannelConfig {
+            debug_execution,
+            ..RollUpChannelConfig::default()
+        })
+        .build();
+    rollup_c
+        .add_program_from_file(
+            program_id,
+            Path::new(NOOP_PROGRAM_PATH),
+            LoaderKind::Upgradeable,
+        )
+        .unwrap();
+    let transactions: Vec<_> = (0..2)
+        .map(|i| {
+            let ix = Instruction::new_with_bytes(program_id, &[i], vec![]);
+            Transaction::new(
+                &[&payer],
+                Message::new(&[ix], Some(&payer.pubkey())),
+                Default::default(),
+            )
+        })
+        .collect();
+
+    let recorder = Recorder::default();
+    let results = tracing::subscriber::with_default(recorder.clone(), || {
+        rollup_c.process_rollup_transfers(&transactions)
+    });
+    (program_id, results, recorder)
+}
+
+#[test]
+fn debugged_transactions_are_traced_by_index() {
+    let (program_id, results, recorder) = process(true);
+    assert!(results.iter().all(|result| result.is_success()));
+    // Transactions are executed one at a time, but not timed unless asked to.
+    assert!(results.iter().all(|result| result.execution_time.is_none()));
+
+    let indexes: Vec<_> = recorder
+        .spans
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(name, _)| *name == "transaction")
+        .map(|(_, fields)| fields["index"].clone())
+        .collect();
+    assert_eq!(indexes, ["0", "1"]);
+
+    let events = recorder.events.lock().unwrap();
+    let invoke = format!("Program {} invoke [1]", program_id);
+    for index in ["0", "1"] {
+        let messages: Vec<_> = events
+            .iter()
+            .filter(|fields| fields["index"] == index)
+            .map(|fields| fields["message"].as_str())
+            .collect();
+        assert!(messages.contains(&invoke.as_str()), "{:?}", messages);
+        assert_eq!(messages.last(), Some(&"transaction executed"));
+    }
+}
+
+#[test]
+fn execution_is_not_traced_by_default() {
+    let (_, results, recorder) = process(false);
+    assert!(results.iter().all(|result| result.is_success()));
+    assert!(recorder.events.lock().unwrap().is_empty());
+    assert!(!recorder
+        .spans
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|(name, _)| *name == "transaction"));
+}
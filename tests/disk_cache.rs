@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use std::fs;
+use std::thread;
+use std::time::Duration;
+
+use solana_client_ext::*;
+use solana_sdk::account::{AccountSharedData, ReadableAccount, WritableAccount};
+use solana_sdk::pubkey::Pubkey;
+
+fn accounts(keys: &[Pubkey]) -> HashMap<Pubkey, AccountSharedData> {
+    keys.iter()
+        .enumerate()
+        .map(|(index, key)| {
+            let mut account = AccountSharedData::new(index as u64 + 1, 3, &Pubkey::new_unique());
+            account.set_data_from_slice(&[index as u8; 3]);
+            (*key, account)
+        })
+        .collect()
+}
+
+#[test]
+fn later_runs_reuse_persisted_accounts() {
+    let dir = tempfile::tempdir().unwrap();
+    let keys = [Pubkey::new_unique(), Pubkey::new_unique()];
+    let source = accounts(&keys);
+
+    let loader = RollUpAccountLoader::with_source(AccountSource::Static(source.clone()))
+        .with_disk_cache(dir.path());
+    assert_eq!(
+        loader.load(&keys[0]).unwrap().as_ref(),
+        source.get(&keys[0])
+    );
+    loader.prefetch(&keys[1..]);
+
+    // The next run's source has nothing: every account comes from the disk.
+    let loader = RollUpAccountLoader::with_source(AccountSource::Static(HashMap::new()))
+        .with_disk_cache(dir.path());
+    let report = loader.prefetch(&keys[..1]);
+    assert_eq!(report.cached, keys[..1]);
+    assert_eq!(
+        loader.load(&keys[1]).unwrap().as_ref(),
+        source.get(&keys[1])
+    );
+    assert_eq!(
+        loader.get_account_shared_data(&keys[0]).as_ref(),
+        source.get(&keys[0])
+    );
+    assert_eq!(loader.fetch_stats(), FetchStats::default());
+    assert_eq!(loader.stats().disk_hits, 2);
+
+    // Invalidated accounts are gone for later runs too.
+    loader.invalidate(&keys[0]);
+    let loader = RollUpAccountLoader::with_source(AccountSource::Static(HashMap::new()))
+        .with_disk_cache(dir.path());
+    assert_eq!(loader.load(&keys[0]), Ok(None));
+}
+
+#[test]
+fn persisted_accounts_expire_with_the_ttl() {
+    let dir = tempfile::tempdir().unwrap();
+    let key = Pubkey::new_unique();
+
+    let loader = RollUpAccountLoader::with_source(AccountSource::Static(accounts(&[key])))
+        .with_disk_cache(dir.path());
+    loader.load(&key).unwrap();
+    thread::sleep(Duration::from_millis(20));
+
+    let loader = RollUpAccountLoader::with_source(AccountSource::Static(HashMap::new()))
+        .with_ttl(Duration::from_secs(60))
+        .with_disk_cache(dir.path());
+    assert!(loader.load(&key).unwrap().is_some());
+
+    let loader = RollUpAccountLoader::with_source(AccountSource::Static(HashMap::new()))
+        .with_ttl(Duration::from_millis(10))
+        .with_disk_cache(dir.path());
+    assert_eq!(loader.load(&key), Ok(None));
+    assert_eq!(loader.stats().disk_hits, 0);
+}
+
+#[test]
+fn corrupt_files_are_ignored() {
+    let dir = tempfile::tempdir().unwrap();
+    let keys = [
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    ];
+    let source = accounts(&keys);
+
+    let loader = RollUpAccountLoader::with_source(AccountSource::Static(source.clone()))
+        .with_disk_cache(dir.path());
+    loader.prefetch(&keys);
+    let path = |key: &Pubkey| dir.path().join(format!("{}.account", key));
+
+    // Truncated, flipped and garbage files.
+    let bytes = fs::read(path(&keys[0])).unwrap();
+    fs::write(path(&keys[0]), &bytes[..bytes.len() / 2]).unwrap();
+    let mut flipped = fs::read(path(&keys[1])).unwrap();
+    flipped[30] ^= 0xff;
+    fs::write(path(&keys[1]), flipped).unwrap();
+    fs::write(path(&keys[2]), b"not an account").unwrap();
+
+    let mut changed = source.clone();
+    for account in changed.values_mut() {
+        account.set_lamports(account.lamports() + 100);
+    }
+    let loader = RollUpAccountLoader::with_source(AccountSource::Static(changed.clone()))
+        .with_disk_cache(dir.path());
+    for key in &keys {
+        assert_eq!(loader.load(key).unwrap().as_ref(), changed.get(key));
+    }
+    assert_eq!(loader.stats().disk_hits, 0);
+
+    // The refetched accounts replaced the corrupt files.
+    let loader = RollUpAccountLoader::with_source(AccountSource::Static(HashMap::new()))
+        .with_disk_cache(dir.path());
+    for key in &keys {
+        assert_eq!(loader.load(key).unwrap().as_ref(), changed.get(key));
+    }
+}
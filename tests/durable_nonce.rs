@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+
+use solana_client_ext::*;
+use solana_sdk::{
+    account::{AccountSharedData, WritableAccount},
+    account_utils::StateMut,
+    hash::Hash,
+    message::Message,
+    native_token::LAMPORTS_PER_SOL,
+    nonce::state::{Data, DurableNonce, State, Versions},
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_instruction, system_program,
+    transaction::{Transaction, TransactionError},
+};
+
+fn nonce_account(authority: Pubkey, durable_nonce: DurableNonce) -> AccountSharedData {
+    let rent = solana_sdk::rent::Rent::default();
+    AccountSharedData::new_data_with_space(
+        rent.minimum_balance(State::size()),
+        &Versions::new(State::Initialized(Data::new(
+            authority,
+            durable_nonce,
+            5_000,
+        ))),
+        State::size(),
+        &system_program::id(),
+    )
+    .unwrap()
+}
+
+/// Runs a transfer from `payer` using the nonce at `nonce_address`, signed with
+/// `recent_blockhash`.
+fn run(
+    payer: &Keypair,
+    nonce_address: Pubkey,
+    nonce: AccountSharedData,
+    recent_blockhash: Hash,
+) -> ReturnStruct {
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        payer.pubkey(),
+        AccountSharedData::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id()),
+    );
+    accounts.insert(nonce_address, nonce);
+
+    let msg = Message::new_with_nonce(
+        vec![system_instruction::transfer(
+            &payer.pubkey(),
+            &Pubkey::new_unique(),
+            LAMPORTS_PER_SOL,
+        )],
+        Some(&payer.pubkey()),
+        &nonce_address,
+        &payer.pubkey(),
+    );
+    let tx = Transaction::new(&[payer], msg, recent_blockhash);
+
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(accounts))
+        .config(RollUpChannelConfig {
+            record_account_data: true,
+            ..RollUpChannelConfig::default()
+        })
+        .build();
+    rollup_c.process_rollup_transfers(&[tx]).remove(0)
+}
+
+#[test]
+fn valid_nonces_are_advanced() {
+    let payer = Keypair::new();
+    let nonce_address = Pubkey::new_unique();
+    let durable_nonce = DurableNonce::from_blockhash(&Hash::new_unique());
+
+    let result = run(
+        &payer,
+        nonce_address,
+        nonce_account(payer.pubkey(), durable_nonce),
+        *durable_nonce.as_hash(),
+    );
+
+    assert!(result.is_success(), "{}", result.result);
+    let change = result
+        .account_changes
+        .iter()
+        .find(|change| change.pubkey == nonce_address)
+        .unwrap();
+    let mut advanced = AccountSharedData::new(0, 0, &system_program::id());
+    advanced.set_data_from_slice(change.new_data.as_deref().unwrap());
+    match StateMut::<Versions>::state(&advanced).unwrap().state() {
+        // The environment blockhash is the default one.
+        State::Initialized(data) => assert_eq!(
+            data.durable_nonce,
+            DurableNonce::from_blockhash(&Hash::default())
+        ),
+        state => panic!("unexpected nonce state: {:?}", state),
+    }
+}
+
+#[test]
+fn nonces_need_their_authority() {
+    let payer = Keypair::new();
+    let durable_nonce = DurableNonce::from_blockhash(&Hash::new_unique());
+
+    let result = run(
+        &payer,
+        Pubkey::new_unique(),
+        nonce_account(Pubkey::new_unique(), durable_nonce),
+        *durable_nonce.as_hash(),
+    );
+
+    assert_eq!(result.status, TxStatus::NotProcessed);
+    assert_eq!(result.error, Some(TransactionError::BlockhashNotFound));
+}
+
+#[test]
+fn nonces_must_match_the_blockhash() {
+    let payer = Keypair::new();
+    let durable_nonce = DurableNonce::from_blockhash(&Hash::new_unique());
+
+    let result = run(
+        &payer,
+        Pubkey::new_unique(),
+        nonce_account(payer.pubkey(), durable_nonce),
+        Hash::new_unique(),
+    );
+
+    assert_eq!(result.status, TxStatus::NotProcessed);
+    assert_eq!(result.error, Some(TransactionError::BlockhashNotFound));
+}
@@ -0,0 +1,107 @@
+#![cfg(feature = "embedded-spl")]
+
+use std::collections::HashMap;
+
+use solana_client_ext::*;
+use solana_sdk::{
+    account::{AccountSharedData, ReadableAccount, WritableAccount},
+    bpf_loader,
+    instruction::{AccountMeta, Instruction},
+    message::Message,
+    pubkey::Pubkey,
+    transaction::Transaction,
+};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/accounts");
+
+/// The wallet of `system_account.json`, owner of `token_account.json`.
+const WALLET: Pubkey = solana_sdk::pubkey!("8fZVDNN5Qu64eJ2FzCHC1X8JZxwH2g1GBiEn7DDVZ9TY");
+
+/// The token account of `token_account.json`, holding 250 tokens of the fixture mint.
+const SOURCE: Pubkey = solana_sdk::pubkey!("3Wb5VJAPZy9z4jb37HuMzqNgA8DWaGdeJwfkK4HxK3JK");
+
+/// Offset of the amount in an SPL Token account, after the mint and the owner.
+const AMOUNT_OFFSET: usize = 64;
+
+fn token_amount(data: &[u8]) -> u64 {
+    u64::from_le_bytes(data[AMOUNT_OFFSET..AMOUNT_OFFSET + 8].try_into().unwrap())
+}
+
+#[test]
+fn spl_transfer_executes_offline() {
+    let rollup_c =
+        RollUpChannel::builder(AccountSource::from_fixture_dir(FIXTURES).unwrap()).build();
+    let loader = rollup_c.account_loader();
+
+    // A second account of the same mint, owned by another wallet and empty.
+    let destination = Pubkey::new_unique();
+    let mut destination_account = loader.load(&SOURCE).unwrap().unwrap();
+    destination_account.data_as_mut_slice()[32..64].copy_from_slice(Pubkey::new_unique().as_ref());
+    destination_account.data_as_mut_slice()[AMOUNT_OFFSET..AMOUNT_OFFSET + 8]
+        .copy_from_slice(&0u64.to_le_bytes());
+    loader.insert_override(destination, destination_account);
+
+    // SPL Token `Transfer` of 100 base units, signed by the source's owner. Signatures
+    // aren't verified, so the fixture wallet's key isn't needed.
+    let mut data = vec![3];
+    data.extend_from_slice(&100u64.to_le_bytes());
+    let transfer = Instruction::new_with_bytes(
+        SPL_TOKEN_ID,
+        &data,
+        vec![
+            AccountMeta::new(SOURCE, false),
+            AccountMeta::new(destination, false),
+            AccountMeta::new_readonly(WALLET, true),
+        ],
+    );
+    let tx = Transaction::new_unsigned(Message::new(&[transfer], Some(&WALLET)));
+    // Blocks commit their results, so the balances can be read back.
+    let blocks = rollup_c.process_blocks(&[vec![tx]]);
+    let result = &blocks[0].results[0];
+    assert!(result.is_success(), "{}", result.result);
+    assert!(result.programs_invoked.contains(&SPL_TOKEN_ID));
+
+    let source = loader.load(&SOURCE).unwrap().unwrap();
+    assert_eq!(token_amount(source.data()), 250_000_000 - 100);
+    let destination = loader.load(&destination).unwrap().unwrap();
+    assert_eq!(token_amount(destination.data()), 100);
+}
+
+#[test]
+fn embedded_programs_can_be_disabled() {
+    let rollup_c = RollUpChannel::builder(AccountSource::from_fixture_dir(FIXTURES).unwrap())
+        .config(RollUpChannelConfig {
+            embedded_spl_programs: false,
+            ..RollUpChannelConfig::default()
+        })
+        .build();
+    for program_id in [
+        SPL_TOKEN_ID,
+        SPL_TOKEN_2022_ID,
+        SPL_ASSOCIATED_TOKEN_ACCOUNT_ID,
+    ] {
+        assert_eq!(rollup_c.account_loader().load(&program_id), Ok(None));
+    }
+}
+
+#[test]
+fn source_programs_take_precedence() {
+    let mut deployed = AccountSharedData::new(1, 0, &bpf_loader::id());
+    deployed.set_data_from_slice(include_bytes!("fixtures/noop.so"));
+    deployed.set_executable(true);
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(HashMap::from([(
+        SPL_TOKEN_ID,
+        deployed.clone(),
+    )])))
+    .build();
+    let loader = rollup_c.account_loader();
+
+    // The source's deployment is served, the embedded programs only fill in for the others.
+    assert_eq!(loader.load(&SPL_TOKEN_ID), Ok(Some(deployed)));
+    let embedded = loader
+        .load(&SPL_ASSOCIATED_TOKEN_ACCOUNT_ID)
+        .unwrap()
+        .unwrap();
+    assert!(embedded.executable());
+    assert_eq!(*embedded.owner(), bpf_loader::id());
+}
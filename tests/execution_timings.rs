@@ -0,0 +1,104 @@
+mod common;
+
+use std::path::Path;
+
+use common::{funded_channel, single_ix_tx, NOOP_PROGRAM_PATH};
+use solana_client_ext::*;
+use solana_sdk::{
+    instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer,
+    transaction::Transaction,
+};
+
+/// `count` distinct transactions invoking `program_id` once each.
+fn invocations(payer: &Keypair, program_id: Pubkey, count: u8) -> Vec<Transaction> {
+    (0..count)
+        .map(|i| single_ix_tx(payer, Instruction::new_with_bytes(program_id, &[i], vec![])))
+        .collect()
+}
+
+/// A channel paying with `payer`, on which `program_id` is the noop program.
+fn channel(payer: &Pubkey, program_id: Pubkey) -> RollUpChannel<'static> {
+    let rollup_c = funded_channel(payer, RollUpChannelConfig::default());
+    rollup_c
+        .add_program_from_file(
+            program_id,
+            Path::new(NOOP_PROGRAM_PATH),
+            LoaderKind::Upgradeable,
+        )
+        .unwrap();
+    rollup_c
+}
+
+#[test]
+fn channel_accumulates_per_program_timings() {
+    let payer = Keypair::new();
+    let program_id = Pubkey::new_unique();
+    let rollup_c = channel(&payer.pubkey(), program_id);
+    assert_eq!(rollup_c.execution_timings(), ExecutionTimings::default());
+
+    let mut total_cu = 0;
+    for _ in 0..2 {
+        let results = rollup_c.process_rollup_transfers(&invocations(&payer, program_id, 2));
+        assert!(results.iter().all(|result| result.is_success()));
+        total_cu += results.iter().map(|result| result.cu).sum::<u64>();
+    }
+
+    let timings = rollup_c.execution_timings();
+    let program = timings
+        .programs
+        .iter()
+        .find(|program| program.program_id == program_id)
+        .unwrap();
+    assert_eq!(program.invocations, 4);
+    assert_eq!(program.failed_invocations, 0);
+    assert_eq!(program.compute_units, total_cu);
+}
+
+#[test]
+fn blocks_report_their_own_timings() {
+    let payer = Keypair::new();
+    let program_id = Pubkey::new_unique();
+    let rollup_c = channel(&payer.pubkey(), program_id);
+
+    let blocks = rollup_c.process_blocks(&[
+        invocations(&payer, program_id, 1),
+        invocations(&payer, program_id, 3)[1..].to_vec(),
+    ]);
+    let invoked: Vec<_> = blocks
+        .iter()
+        .map(|block| block.timings.programs[0].invocations)
+        .collect();
+    assert_eq!(invoked, [1, 2]);
+    assert_eq!(
+        rollup_c.execution_timings().programs[0].invocations,
+        invoked.iter().sum::<u64>()
+    );
+}
+
+#[test]
+fn timings_accumulate_by_program() {
+    let (slow, fast) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let timing = |program_id, execute_us| ProgramTimings {
+        program_id,
+        execute_us,
+        compute_units: 100,
+        invocations: 1,
+        failed_invocations: 0,
+    };
+    let mut timings = ExecutionTimings {
+        load_us: 10,
+        programs: vec![timing(slow, 30), timing(fast, 20)],
+        ..ExecutionTimings::default()
+    };
+    timings.accumulate(&ExecutionTimings {
+        load_us: 5,
+        programs: vec![timing(fast, 20)],
+        ..ExecutionTimings::default()
+    });
+
+    assert_eq!(timings.load_us, 15);
+    assert_eq!(timings.programs[0].program_id, fast);
+    assert_eq!(timings.programs[0].execute_us, 40);
+    assert_eq!(timings.programs[0].invocations, 2);
+    assert_eq!(timings.programs[1], timing(slow, 30));
+}
@@ -0,0 +1,90 @@
+use solana_client_ext::*;
+
+use solana_sdk::instruction::CompiledInstruction;
+use solana_sdk::pubkey::Pubkey;
+
+fn logs(lines: &[String]) -> Vec<String> {
+    lines.to_vec()
+}
+
+#[test]
+fn trace_builds_nested_tree() {
+    let outer = Pubkey::new_unique();
+    let inner = Pubkey::new_unique();
+    let system = solana_sdk::system_program::id();
+
+    let trace = ExecutionTrace::from_execution(
+        &logs(&[
+            format!("Program {} invoke [1]", outer),
+            "Program log: Instruction: Swap".to_string(),
+            format!("Program {} invoke [2]", inner),
+            format!("Program {} consumed 300 of 190000 compute units", inner),
+            format!("Program {} success", inner),
+            format!("Program {} invoke [2]", system),
+            format!("Program {} success", system),
+            format!("Program {} consumed 10000 of 200000 compute units", outer),
+            format!("Program {} failed: custom program error: 0x1", outer),
+        ]),
+        None,
+    );
+
+    assert_eq!(trace.instructions.len(), 1);
+    let top = &trace.instructions[0];
+    assert_eq!(top.program_id, outer);
+    assert_eq!(top.cu_before, Some(200000));
+    assert_eq!(top.cu_after, Some(190000));
+    assert_eq!(top.consumed(), Some(10000));
+    assert_eq!(
+        top.status,
+        TraceStatus::Failed("custom program error: 0x1".to_string())
+    );
+    assert_eq!(top.logs, vec!["Program log: Instruction: Swap".to_string()]);
+    assert_eq!(top.inner.len(), 2);
+    assert_eq!(top.inner[0].program_id, inner);
+    assert_eq!(top.inner[0].stack_height, 2);
+    assert_eq!(top.inner[0].consumed(), Some(300));
+    assert_eq!(top.inner[1].cu_before, None);
+
+    let rendered = trace.to_string();
+    assert!(rendered.starts_with(&format!("#0 {} [1] 10000 CU", outer)));
+    assert!(rendered.contains(&format!("\n  #0.0 {} [2] 300 CU", inner)));
+}
+
+#[test]
+fn trace_keeps_truncated_frames() {
+    let program = Pubkey::new_unique();
+    let trace = ExecutionTrace::from_execution(
+        &logs(&[
+            format!("Program {} invoke [1]", program),
+            "Log truncated".to_string(),
+        ]),
+        None,
+    );
+
+    assert_eq!(trace.instructions.len(), 1);
+    assert_eq!(trace.instructions[0].status, TraceStatus::Incomplete);
+}
+
+#[test]
+fn inner_instructions_render_as_rpc_json() {
+    let group = InnerInstructionGroup {
+        index: 1,
+        instructions: vec![InvokedInstruction {
+            instruction: CompiledInstruction::new_from_raw_parts(3, vec![1, 2, 3], vec![0, 2]),
+            stack_height: 2,
+        }],
+    };
+
+    assert_eq!(
+        group.to_json(),
+        serde_json::json!({
+            "index": 1,
+            "instructions": [{
+                "programIdIndex": 3,
+                "accounts": [0, 2],
+                "data": "Ldp",
+                "stackHeight": 2,
+            }],
+        })
+    );
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use agave_feature_set::{blake3_syscall_enabled, enable_loader_v4, FeatureSet, FEATURE_NAMES};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use serde_json::{json, Value};
+use solana_client::client_error::Result as ClientResult;
+use solana_client::rpc_client::{RpcClient, RpcClientConfig};
+use solana_client::rpc_request::RpcRequest;
+use solana_client::rpc_sender::{RpcSender, RpcTransportStats};
+use solana_client_ext::*;
+use solana_sdk::{
+    feature, message::Message, native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, signature::Keypair,
+    signer::Signer, system_instruction, system_program, transaction::Transaction,
+};
+
+/// Slot `blake3_syscall_enabled` was activated in on the fake cluster.
+const ACTIVATION_SLOT: u64 = 5;
+
+/// An RPC transport serving a cluster on which `blake3_syscall_enabled` was activated,
+/// `enable_loader_v4` is pending activation, and the `funded` system account exists.
+/// Every other account is missing.
+#[derive(Clone, Default)]
+struct FeatureSender {
+    requests: Arc<Mutex<Vec<String>>>,
+    funded: Option<Pubkey>,
+}
+
+impl FeatureSender {
+    fn account(&self, key: &Value) -> Value {
+        let key = key.as_str().unwrap();
+        let feature_data = |activated_at: Option<u64>| {
+            let mut data = vec![activated_at.is_some() as u8];
+            data.extend_from_slice(&activated_at.unwrap_or_default().to_le_bytes());
+            data
+        };
+        let (lamports, data, owner) = if key == blake3_syscall_enabled::id().to_string() {
+            (1, feature_data(Some(ACTIVATION_SLOT)), feature::id())
+        } else if key == enable_loader_v4::id().to_string() {
+            (1, feature_data(None), feature::id())
+        } else if Some(key) == self.funded.map(|pubkey| pubkey.to_string()).as_deref() {
+            (LAMPORTS_PER_SOL, Vec::new(), system_program::id())
+        } else {
+            return Value::Null;
+        };
+        json!({
+            "lamports": lamports,
+            "data": [BASE64_STANDARD.encode(&data), "base64"],
+            "owner": owner.to_string(),
+            "executable": false,
+            "rentEpoch": 0,
+            "space": data.len(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl RpcSender for FeatureSender {
+    async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+        self.requests.lock().unwrap().push(request.to_string());
+        let value = match request {
+            RpcRequest::GetMultipleAccounts => params[0]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|key| self.account(key))
+                .collect(),
+            RpcRequest::GetAccountInfo => self.account(&params[0]),
+            RpcRequest::GetVersion => return Ok(json!({ "solana-core": "2.2.2" })),
+            _ => Value::Null,
+        };
+        Ok(json!({ "context": { "slot": 10 }, "value": value }))
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        RpcTransportStats::default()
+    }
+
+    fn url(&self) -> String {
+        "features".to_string()
+    }
+}
+
+#[test]
+fn cluster_features_are_active_from_their_activation_slot() {
+    let sender = FeatureSender::default();
+    let rpc_client = RpcClient::new_sender(sender.clone(), RpcClientConfig::default());
+
+    let feature_set = feature_set_from_cluster(&rpc_client).unwrap();
+    assert_eq!(feature_set.active.len(), 1);
+    assert_eq!(
+        feature_set.activated_slot(&blake3_syscall_enabled::id()),
+        Some(ACTIVATION_SLOT)
+    );
+    assert!(!feature_set.is_active(&enable_loader_v4::id()));
+
+    // Every known feature is fetched, a hundred per request.
+    let requests = sender.requests.lock().unwrap();
+    assert_eq!(requests.len(), FEATURE_NAMES.len().div_ceil(100));
+    assert!(requests
+        .iter()
+        .all(|request| request == "getMultipleAccounts"));
+}
+
+#[test]
+fn pending_features_are_active_from_the_given_slot() {
+    let rpc_client = RpcClient::new_sender(FeatureSender::default(), RpcClientConfig::default());
+
+    let feature_set = feature_set_from_cluster_pending_at(&rpc_client, 20).unwrap();
+    assert_eq!(feature_set.active.len(), 2);
+    assert_eq!(
+        feature_set.activated_slot(&blake3_syscall_enabled::id()),
+        Some(ACTIVATION_SLOT)
+    );
+    assert_eq!(
+        feature_set.activated_slot(&enable_loader_v4::id()),
+        Some(20)
+    );
+}
+
+#[test]
+fn channel_executes_with_the_cluster_features() {
+    let payer = Keypair::new();
+    let sender = FeatureSender {
+        funded: Some(payer.pubkey()),
+        ..FeatureSender::default()
+    };
+    let rpc_client = RpcClient::new_sender(sender, RpcClientConfig::default());
+    let rollup_c = RollUpChannel::builder(AccountSource::Rpc(&rpc_client))
+        .config(RollUpChannelConfig {
+            feature_set: FeatureSetSource::FromCluster,
+            ..RollUpChannelConfig::default()
+        })
+        .build();
+
+    let transfer = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1);
+    let message = Message::new(&[transfer], Some(&payer.pubkey()));
+    let tx = Transaction::new(&[&payer], message, Default::default());
+    let results = rollup_c.process_rollup_transfers(&[tx]);
+    assert!(results[0].is_success(), "{}", results[0].result);
+
+    let cluster_features = feature_set_from_cluster(&rpc_client).unwrap();
+    let context = results[0].context.unwrap();
+    assert_eq!(
+        context.feature_set_hash,
+        SimulationContext::feature_set_hash(&cluster_features)
+    );
+    assert_ne!(
+        context.feature_set_hash,
+        SimulationContext::feature_set_hash(&FeatureSet::all_enabled())
+    );
+}
+
+#[test]
+fn cluster_features_require_an_rpc_source() {
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(HashMap::new()))
+        .config(RollUpChannelConfig {
+            feature_set: FeatureSetSource::FromCluster,
+            ..RollUpChannelConfig::default()
+        })
+        .build();
+
+    let payer = Keypair::new();
+    let transfer = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1);
+    let message = Message::new(&[transfer], Some(&payer.pubkey()));
+    let tx = Transaction::new(&[&payer], message, Default::default());
+    let results = rollup_c.process_rollup_transfers(&[tx]);
+    assert!(results[0].infrastructure_error);
+    assert!(
+        results[0].result.contains("RPC-backed"),
+        "{}",
+        results[0].result
+    );
+}
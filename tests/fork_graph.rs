@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+
+use solana_client_ext::*;
+
+use solana_program_runtime::loaded_programs::{
+    BlockRelation, ForkGraph, ProgramCacheEntry, ProgramCacheEntryOwner, ProgramCacheEntryType,
+    ProgramCacheForTxBatch, ProgramCacheMatchCriteria,
+};
+use solana_sdk::{
+    account::{AccountSharedData, WritableAccount},
+    bpf_loader,
+    clock::Slot,
+    instruction::Instruction,
+    message::Message,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_program,
+    transaction::Transaction,
+};
+use solana_svm::transaction_processor::TransactionBatchProcessor;
+
+/// A linear chain that counts how often the program cache asks about it.
+#[derive(Default)]
+struct CountingGraph {
+    calls: AtomicUsize,
+}
+
+impl ForkGraph for CountingGraph {
+    fn relationship(&self, a: Slot, b: Slot) -> BlockRelation {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        match a.cmp(&b) {
+            std::cmp::Ordering::Less => BlockRelation::Ancestor,
+            std::cmp::Ordering::Equal => BlockRelation::Equal,
+            std::cmp::Ordering::Greater => BlockRelation::Descendant,
+        }
+    }
+}
+
+#[test]
+fn custom_fork_graph_is_consulted() {
+    let payer = Keypair::new();
+    let program_id = Pubkey::new_unique();
+
+    // A loader-v2 program with an invalid ELF: loading it puts a tombstone for the
+    // current slot into the program cache, which has to consult the fork graph.
+    let mut program = AccountSharedData::new(LAMPORTS_PER_SOL, 4, &bpf_loader::id());
+    program.set_data_from_slice(b"\x00bad");
+    program.set_executable(true);
+
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        payer.pubkey(),
+        AccountSharedData::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id()),
+    );
+    accounts.insert(program_id, program);
+
+    let ix = Instruction::new_with_bytes(program_id, &[], vec![]);
+    let tx = Transaction::new(
+        &[&payer],
+        Message::new(&[ix], Some(&payer.pubkey())),
+        Default::default(),
+    );
+
+    let fork_graph = Arc::new(RwLock::new(CountingGraph::default()));
+    let rollup_c = RollUpChannel::with_fork_graph(
+        vec![],
+        AccountSource::Static(accounts),
+        // Let the program reach the program cache instead of being rejected up front.
+        RollUpChannelConfig {
+            verify_programs: false,
+            ..RollUpChannelConfig::default()
+        },
+        Arc::clone(&fork_graph),
+    );
+    let results = rollup_c.process_rollup_transfers(&[tx]);
+
+    assert!(!results[0].is_success());
+    assert!(fork_graph.read().unwrap().calls.load(Ordering::Relaxed) > 0);
+}
+
+#[test]
+fn fork_rollup_graph_orders_registered_slots() {
+    let mut graph = ForkRollUpGraph::with_slots([1, 2]);
+    assert_eq!(graph.relationship(1, 2), BlockRelation::Ancestor);
+    assert_eq!(graph.relationship(2, 1), BlockRelation::Descendant);
+    assert_eq!(graph.relationship(2, 2), BlockRelation::Equal);
+    assert_eq!(graph.relationship(2, 3), BlockRelation::Unknown);
+    assert_eq!(graph.relationship(0, 0), BlockRelation::Unknown);
+
+    graph.register_slot(3);
+    assert_eq!(graph.relationship(1, 3), BlockRelation::Ancestor);
+}
+
+#[test]
+fn channels_register_the_slots_they_process_at() {
+    let fork_graph = Arc::new(RwLock::new(ForkRollUpGraph::default()));
+    let rollup_c = RollUpChannel::with_fork_graph(
+        vec![],
+        AccountSource::Static(HashMap::new()),
+        RollUpChannelConfig {
+            slot: 10,
+            ..RollUpChannelConfig::default()
+        },
+        Arc::clone(&fork_graph),
+    );
+    rollup_c.process_blocks(&[vec![], vec![], vec![]]);
+    assert_eq!(
+        fork_graph.read().unwrap().slots().collect::<Vec<_>>(),
+        [10, 11, 12]
+    );
+}
+
+/// Whether the program cache of `processor`, at `slot`, has an entry for `program_id`.
+fn is_cached(
+    processor: &TransactionBatchProcessor<ForkRollUpGraph>,
+    slot: Slot,
+    program_id: Pubkey,
+) -> bool {
+    let program_cache = processor.program_cache.read().unwrap();
+    let mut batch_cache =
+        ProgramCacheForTxBatch::new(slot, program_cache.environments.clone(), None, 1);
+    let mut search_for = vec![(program_id, (ProgramCacheMatchCriteria::NoCriteria, 1))];
+    program_cache.extract(&mut search_for, &mut batch_cache, true);
+    batch_cache.find(&program_id).is_some()
+}
+
+#[test]
+fn cache_entries_survive_a_slot_advance() {
+    let fork_graph = Arc::new(RwLock::new(ForkRollUpGraph::with_slots([1, 2])));
+    let loader = RollUpAccountLoader::with_source(AccountSource::Static(HashMap::new()));
+    let processor = ProcessorBuilder::new(Arc::clone(&fork_graph))
+        .slot(1)
+        .build(&loader);
+
+    // An entry added after the root, the processor's first slot, so only the fork graph
+    // can make it visible at later slots.
+    let program_id = Pubkey::new_unique();
+    processor.program_cache.write().unwrap().assign_program(
+        program_id,
+        Arc::new(ProgramCacheEntry::new_tombstone(
+            2,
+            ProgramCacheEntryOwner::LoaderV2,
+            ProgramCacheEntryType::Closed,
+        )),
+    );
+    assert!(is_cached(&processor, 2, program_id));
+
+    // The child processor shares the cache; the entry is found once its slot is known
+    // to descend from the entry's.
+    let processor = processor.new_from(3, 1);
+    assert!(!is_cached(&processor, 3, program_id));
+    fork_graph.write().unwrap().register_slot(3);
+    assert!(is_cached(&processor, 3, program_id));
+}
@@ -0,0 +1,73 @@
+#![cfg(feature = "loader-v4")]
+
+mod common;
+
+use std::collections::HashMap;
+
+use common::NOOP_PROGRAM;
+use solana_client_ext::*;
+use solana_sdk::{
+    account::{AccountSharedData, WritableAccount},
+    instruction::Instruction,
+    loader_v4::{self, LoaderV4State, LoaderV4Status},
+    message::Message,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_program,
+    transaction::Transaction,
+};
+
+/// Builds the account of a loader-v4 program deployed at slot 0, whose ELF follows the
+/// loader state in the account itself.
+fn loader_v4_program(elf: &[u8]) -> AccountSharedData {
+    let offset = LoaderV4State::program_data_offset();
+    let mut data = vec![0; offset + elf.len()];
+    // LoaderV4State is `#[repr(C)]`: the slot, the authority, then the status.
+    data[..8].copy_from_slice(&0u64.to_le_bytes());
+    data[8..40].copy_from_slice(Pubkey::new_unique().as_ref());
+    data[40..48].copy_from_slice(&(LoaderV4Status::Deployed as u64).to_le_bytes());
+    data[offset..].copy_from_slice(elf);
+
+    let mut program = AccountSharedData::new(LAMPORTS_PER_SOL, 0, &loader_v4::id());
+    program.set_data_from_slice(&data);
+    program.set_executable(true);
+    program
+}
+
+#[test]
+fn loader_v4_programs_execute_when_enabled() {
+    let payer = Keypair::new();
+    let program_id = Pubkey::new_unique();
+    let accounts = HashMap::from([
+        (program_id, loader_v4_program(NOOP_PROGRAM)),
+        (
+            payer.pubkey(),
+            AccountSharedData::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id()),
+        ),
+    ]);
+    let tx = Transaction::new(
+        &[&payer],
+        Message::new(
+            &[Instruction::new_with_bytes(program_id, &[], vec![])],
+            Some(&payer.pubkey()),
+        ),
+        Default::default(),
+    );
+
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(accounts.clone())).build();
+    let results = rollup_c.process_rollup_transfers(&[tx.clone()]);
+    assert!(!results[0].is_success());
+
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(accounts))
+        .config(RollUpChannelConfig {
+            loader_v4: true,
+            ..RollUpChannelConfig::default()
+        })
+        .build();
+    let results = rollup_c.process_rollup_transfers(&[tx]);
+    assert!(results[0].is_success(), "{}", results[0].result);
+    assert!(results[0].cu > 0);
+    assert_eq!(results[0].programs_invoked, [program_id]);
+}
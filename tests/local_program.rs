@@ -0,0 +1,190 @@
+mod common;
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use common::NOOP_PROGRAM_PATH;
+use solana_client_ext::*;
+use solana_sdk::{
+    account::{AccountSharedData, WritableAccount},
+    bpf_loader,
+    instruction::Instruction,
+    message::Message,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_program,
+    transaction::{Transaction, TransactionError},
+};
+
+/// The noop program with its text section's address (0) not matching its offset in the
+/// file: accepted by the lenient ELF parser, refused when rejecting broken ELFs.
+const MISALIGNED_TEXT_PROGRAM: &[u8] = include_bytes!("fixtures/misaligned_text.so");
+
+fn invoke(payer: &Keypair, program_id: Pubkey) -> Transaction {
+    Transaction::new(
+        &[payer],
+        Message::new(
+            &[Instruction::new_with_bytes(program_id, &[], vec![])],
+            Some(&payer.pubkey()),
+        ),
+        Default::default(),
+    )
+}
+
+#[test]
+fn programs_from_files_are_invokable() {
+    let payer = Keypair::new();
+    let accounts = HashMap::from([(
+        payer.pubkey(),
+        AccountSharedData::new(LAMPORTS_PER_SOL, 0, &system_program::id()),
+    )]);
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(accounts)).build();
+
+    for loader in [LoaderKind::BpfLoader, LoaderKind::Upgradeable] {
+        let program_id = Pubkey::new_unique();
+        let results = rollup_c.process_rollup_transfers(&[invoke(&payer, program_id)]);
+        assert!(!results[0].is_success());
+
+        rollup_c
+            .add_program_from_file(program_id, Path::new(NOOP_PROGRAM_PATH), loader)
+            .unwrap();
+        let results = rollup_c.process_rollup_transfers(&[invoke(&payer, program_id)]);
+        assert!(
+            results[0].is_success(),
+            "{:?}: {}",
+            loader,
+            results[0].result
+        );
+        assert!(results[0].cu > 0);
+    }
+}
+
+#[test]
+fn invalid_programs_are_reported() {
+    let rollup_c = RollUpChannel::new_offline(vec![], HashMap::new());
+    let program_id = Pubkey::new_unique();
+
+    let missing = Path::new("tests/fixtures/missing.so");
+    match rollup_c.add_program_from_file(program_id, missing, LoaderKind::Upgradeable) {
+        Err(ProgramLoadError::Io { path, .. }) => assert_eq!(path, missing),
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("garbage.so");
+    std::fs::write(&path, b"not an ELF").unwrap();
+    let err = rollup_c
+        .add_program_from_file(program_id, &path, LoaderKind::Upgradeable)
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        ProgramLoadError::InvalidElf { program_id: id, .. } if id == program_id
+    ));
+    assert!(
+        err.to_string().starts_with("invalid ELF for program"),
+        "{}",
+        err
+    );
+
+    // Nothing was deployed.
+    assert_eq!(rollup_c.account_loader().load(&program_id), Ok(None));
+}
+
+#[test]
+fn programs_that_dont_compile_fail_only_their_transactions() {
+    let payer = Keypair::new();
+    let broken_id = Pubkey::new_unique();
+    let mut broken = AccountSharedData::new(LAMPORTS_PER_SOL, 0, &bpf_loader::id());
+    broken.set_data_from_slice(b"not an ELF");
+    broken.set_executable(true);
+    let accounts = HashMap::from([
+        (
+            payer.pubkey(),
+            AccountSharedData::new(LAMPORTS_PER_SOL, 0, &system_program::id()),
+        ),
+        (broken_id, broken),
+    ]);
+
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(accounts.clone())).build();
+    let noop_id = Pubkey::new_unique();
+    rollup_c
+        .add_program_from_file(
+            noop_id,
+            Path::new(NOOP_PROGRAM_PATH),
+            LoaderKind::Upgradeable,
+        )
+        .unwrap();
+    let results =
+        rollup_c.process_rollup_transfers(&[invoke(&payer, broken_id), invoke(&payer, noop_id)]);
+
+    assert_eq!(results[0].status, TxStatus::NotProcessed);
+    assert_eq!(
+        results[0].error,
+        Some(TransactionError::InvalidProgramForExecution)
+    );
+    assert!(
+        results[0].result.contains(&broken_id.to_string()),
+        "{}",
+        results[0].result
+    );
+    assert!(results[1].is_success(), "{}", results[1].result);
+
+    // Without the verification, the runtime fails the transaction without naming the
+    // program.
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(accounts))
+        .config(RollUpChannelConfig {
+            verify_programs: false,
+            ..RollUpChannelConfig::default()
+        })
+        .build();
+    let results = rollup_c.process_rollup_transfers(&[invoke(&payer, broken_id)]);
+    assert!(!results[0].is_success());
+    assert!(!results[0].result.contains(&broken_id.to_string()));
+}
+
+#[test]
+fn broken_elfs_are_rejected_only_when_asked() {
+    let payer = Keypair::new();
+    let accounts = HashMap::from([(
+        payer.pubkey(),
+        AccountSharedData::new(LAMPORTS_PER_SOL, 0, &system_program::id()),
+    )]);
+    let program_id = Pubkey::new_unique();
+
+    // By default the ELF executes, like programs deployed before the check existed.
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(accounts.clone())).build();
+    rollup_c
+        .add_program(program_id, MISALIGNED_TEXT_PROGRAM, LoaderKind::BpfLoader)
+        .unwrap();
+    let results = rollup_c.process_rollup_transfers(&[invoke(&payer, program_id)]);
+    assert!(results[0].is_success(), "{}", results[0].result);
+
+    let strict = RollUpChannelConfig {
+        reject_broken_elfs: true,
+        ..RollUpChannelConfig::default()
+    };
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(accounts.clone()))
+        .config(strict.clone())
+        .build();
+    assert!(matches!(
+        rollup_c.add_program(program_id, MISALIGNED_TEXT_PROGRAM, LoaderKind::BpfLoader),
+        Err(ProgramLoadError::InvalidElf { program_id: id, .. }) if id == program_id
+    ));
+
+    // Without the verification, the runtime refuses to load it.
+    let mut program = AccountSharedData::new(LAMPORTS_PER_SOL, 0, &bpf_loader::id());
+    program.set_data_from_slice(MISALIGNED_TEXT_PROGRAM);
+    program.set_executable(true);
+    let mut accounts = accounts;
+    accounts.insert(program_id, program);
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(accounts))
+        .config(RollUpChannelConfig {
+            verify_programs: false,
+            ..strict
+        })
+        .build();
+    let results = rollup_c.process_rollup_transfers(&[invoke(&payer, program_id)]);
+    assert!(!results[0].is_success());
+}
@@ -0,0 +1,45 @@
+use std::collections::HashMap;
+
+use solana_client_ext::*;
+
+use solana_sdk::{
+    message::Message, pubkey::Pubkey, signature::Keypair, signer::Signer, system_instruction,
+    transaction::Transaction,
+};
+
+fn transfer(payer: &Keypair, to: &Pubkey) -> Transaction {
+    let ix = system_instruction::transfer(&payer.pubkey(), to, 1);
+    Transaction::new(
+        &[payer],
+        Message::new(&[ix], Some(&payer.pubkey())),
+        Default::default(),
+    )
+}
+
+#[test]
+fn analyze_locks_reports_conflicts_and_groups() {
+    let alice = Keypair::new();
+    let bob = Keypair::new();
+    let shared = Pubkey::new_unique();
+
+    let txs = vec![
+        transfer(&alice, &shared),
+        transfer(&bob, &Pubkey::new_unique()),
+        transfer(&bob, &shared),
+    ];
+
+    let rollup_c = RollUpChannel::new_offline(vec![], HashMap::new());
+    let analysis = rollup_c.analyze_locks(&txs);
+
+    assert_eq!(analysis.locks.len(), 3);
+    assert!(analysis.locks[0].writable.contains(&shared));
+    assert!(analysis.locks[0]
+        .readonly
+        .contains(&solana_sdk::system_program::id()));
+
+    // 0 and 2 both write `shared`, 1 and 2 both write bob; 0 and 1 are independent.
+    assert_eq!(analysis.conflicts, vec![(0, 2), (1, 2)]);
+    assert!(analysis.conflicting(2, 0));
+    assert!(!analysis.conflicting(0, 1));
+    assert_eq!(analysis.groups, vec![vec![0, 1], vec![2]]);
+}
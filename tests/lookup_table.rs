@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+use solana_client_ext::*;
+use solana_sdk::{
+    account::AccountSharedData,
+    address_lookup_table::{self, instruction, state::AddressLookupTable},
+    message::Message,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_program,
+    transaction::Transaction,
+};
+
+#[test]
+fn lookup_tables_are_created_and_extended_locally() {
+    let payer = Keypair::new();
+    let accounts = HashMap::from([(
+        payer.pubkey(),
+        AccountSharedData::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id()),
+    )]);
+
+    // Offline, the SlotHashes sysvar holds the slot before the processor's, slot 0.
+    let (create, table) = instruction::create_lookup_table(payer.pubkey(), payer.pubkey(), 0);
+    let addresses = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+    let extend = instruction::extend_lookup_table(
+        table,
+        payer.pubkey(),
+        Some(payer.pubkey()),
+        addresses.clone(),
+    );
+    let tx = Transaction::new(
+        &[&payer],
+        Message::new(&[create, extend], Some(&payer.pubkey())),
+        Default::default(),
+    );
+
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(accounts))
+        .config(RollUpChannelConfig {
+            record_account_data: true,
+            ..RollUpChannelConfig::default()
+        })
+        .build();
+    let results = rollup_c.process_rollup_transfers(&[tx]);
+
+    assert!(results[0].is_success(), "{}", results[0].result);
+    // Both instructions, and the system program CPIs funding and allocating the table.
+    assert!(
+        (1_500..10_000).contains(&results[0].cu),
+        "{}",
+        results[0].cu
+    );
+    assert_eq!(
+        results[0].programs_invoked,
+        [address_lookup_table::program::id()]
+    );
+
+    let change = results[0]
+        .account_changes
+        .iter()
+        .find(|change| change.pubkey == table)
+        .unwrap();
+    assert_eq!(change.kind, AccountChangeKind::Created);
+    assert_eq!(change.new_owner, address_lookup_table::program::id());
+    let data = change.new_data.as_deref().unwrap();
+    let table = AddressLookupTable::deserialize(data).unwrap();
+    assert_eq!(table.meta.authority, Some(payer.pubkey()));
+    assert_eq!(&*table.addresses, addresses.as_slice());
+}
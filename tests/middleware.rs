@@ -0,0 +1,104 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::rc::Rc;
+
+use solana_client_ext::*;
+
+use solana_sdk::{
+    account::{AccountSharedData, ReadableAccount},
+    message::Message,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_instruction, system_program,
+    transaction::{SanitizedTransaction, Transaction},
+};
+use solana_svm::transaction_processing_callback::TransactionProcessingCallback;
+
+/// Rejects any transaction writing to a denylisted account.
+struct Denylist(Pubkey);
+
+impl RollUpMiddleware for Denylist {
+    fn before(&self, _index: usize, transaction: &SanitizedTransaction) -> ControlFlow<Reject> {
+        if transaction
+            .message()
+            .account_keys()
+            .iter()
+            .any(|key| *key == self.0)
+        {
+            return ControlFlow::Break(Reject::new("denylisted account"));
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Records every hook invocation.
+struct Recorder(Rc<RefCell<Vec<String>>>);
+
+impl RollUpMiddleware for Recorder {
+    fn before(&self, index: usize, _transaction: &SanitizedTransaction) -> ControlFlow<Reject> {
+        self.0.borrow_mut().push(format!("before {}", index));
+        ControlFlow::Continue(())
+    }
+
+    fn after(&self, index: usize, result: &ReturnStruct) {
+        self.0
+            .borrow_mut()
+            .push(format!("after {} {}", index, result.is_success()));
+    }
+}
+
+#[test]
+fn middleware_rejects_and_skips_commit() {
+    let payer = Keypair::new();
+    let allowed = Pubkey::new_unique();
+    let denied = Pubkey::new_unique();
+
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        payer.pubkey(),
+        AccountSharedData::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id()),
+    );
+
+    let txs = [allowed, denied].map(|to| {
+        let ix = system_instruction::transfer(&payer.pubkey(), &to, LAMPORTS_PER_SOL);
+        Transaction::new(
+            &[&payer],
+            Message::new(&[ix], Some(&payer.pubkey())),
+            Default::default(),
+        )
+    });
+
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let mut rollup_c = RollUpChannel::new_offline(vec![], accounts);
+    rollup_c
+        .add_middleware(Denylist(denied))
+        .add_middleware(Recorder(Rc::clone(&log)));
+
+    let blocks = rollup_c.process_blocks(&[txs.to_vec()]);
+    let results = &blocks[0].results;
+
+    assert!(results[0].is_success(), "{}", results[0].result);
+    assert!(!results[1].is_success());
+    assert_eq!(results[1].status, TxStatus::Cancelled);
+    assert!(results[1].result.contains("denylisted account"));
+
+    // The rejecting middleware short-circuits the ones registered after it.
+    assert_eq!(
+        *log.borrow(),
+        vec!["before 0", "after 0 true", "after 1 false"]
+    );
+
+    let loader = rollup_c.account_loader();
+    assert!(loader.get_account_shared_data(&allowed).is_some());
+    assert!(loader.get_account_shared_data(&denied).is_none());
+    assert_eq!(
+        loader
+            .get_account_shared_data(&payer.pubkey())
+            .unwrap()
+            .lamports(),
+        9 * LAMPORTS_PER_SOL - 5_000
+    );
+}
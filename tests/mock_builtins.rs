@@ -0,0 +1,160 @@
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use common::{funded_channel, single_ix_tx};
+use solana_client_ext::*;
+use solana_program_runtime::declare_process_instruction;
+use solana_sdk::{
+    instruction::{Instruction, InstructionError},
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    transaction::{Transaction, TransactionError},
+};
+
+/// Compute units the succeeding mock consumes.
+const SUCCEEDS_CU: u64 = 1_000;
+
+/// Compute units the failing mock consumes before failing.
+const FAILS_CU: u64 = 500;
+
+/// Custom error the failing mock fails with.
+const MOCK_ERROR: u32 = 42;
+
+declare_process_instruction!(Succeeds, SUCCEEDS_CU, |_invoke_context| { Ok(()) });
+
+declare_process_instruction!(Fails, FAILS_CU, |_invoke_context| {
+    Err(InstructionError::Custom(MOCK_ERROR))
+});
+
+/// Times [`Counted`] was executed.
+static COUNTED_INVOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+declare_process_instruction!(Counted, SUCCEEDS_CU, |_invoke_context| {
+    COUNTED_INVOCATIONS.fetch_add(1, Ordering::Relaxed);
+    Ok(())
+});
+
+/// A channel paying with `payer` on which `succeeds` and `fails` are mock programs.
+fn channel(
+    payer: &Pubkey,
+    succeeds: Pubkey,
+    fails: Pubkey,
+    batch_cu_limit: Option<u64>,
+) -> RollUpChannel<'static> {
+    funded_channel(
+        payer,
+        RollUpChannelConfig {
+            builtins: vec![
+                Builtin::new(succeeds, "succeeds", Succeeds::vm),
+                Builtin::new(fails, "fails", Fails::vm),
+            ],
+            batch_cu_limit,
+            ..RollUpChannelConfig::default()
+        },
+    )
+}
+
+/// Transactions invoking each of `program_ids` in turn, each made unique by its index.
+fn invoking(payer: &Keypair, program_ids: &[Pubkey]) -> Vec<Transaction> {
+    program_ids
+        .iter()
+        .enumerate()
+        .map(|(i, program_id)| {
+            single_ix_tx(
+                payer,
+                Instruction::new_with_bytes(*program_id, &[i as u8], vec![]),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn failed_transactions_dont_stop_the_batch() {
+    let payer = Keypair::new();
+    let (succeeds, fails) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let rollup_c = channel(&payer.pubkey(), succeeds, fails, None);
+
+    let results =
+        rollup_c.process_rollup_transfers(&invoking(&payer, &[succeeds, fails, succeeds]));
+    let statuses: Vec<_> = results.iter().map(|result| result.status).collect();
+    assert_eq!(
+        statuses,
+        [
+            TxStatus::ExecutedOk,
+            TxStatus::ExecutedErr,
+            TxStatus::ExecutedOk
+        ]
+    );
+    let units: Vec<_> = results.iter().map(|result| result.cu).collect();
+    assert_eq!(units, [SUCCEEDS_CU, FAILS_CU, SUCCEEDS_CU]);
+    assert_eq!(
+        results[1].error,
+        Some(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(MOCK_ERROR)
+        ))
+    );
+    // The failed transaction still pays its fee.
+    assert_eq!(results[1].fee_lamports, results[0].fee_lamports);
+}
+
+#[test]
+fn block_cutoff_counts_failed_transactions() {
+    let payer = Keypair::new();
+    let (succeeds, fails) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let rollup_c = channel(
+        &payer.pubkey(),
+        succeeds,
+        fails,
+        Some(2 * SUCCEEDS_CU + FAILS_CU),
+    );
+
+    // 1_000 + 500 + 1_000 units fit the limit exactly; the last transaction doesn't.
+    let block = invoking(&payer, &[succeeds, fails, succeeds, succeeds]);
+    let blocks = rollup_c.process_blocks(&[block]);
+    assert_eq!(blocks[0].cutoff, Some(3));
+    assert_eq!(blocks[0].total_cu, 2 * SUCCEEDS_CU + FAILS_CU);
+    assert_eq!(blocks[0].results[1].status, TxStatus::ExecutedErr);
+    assert_eq!(blocks[0].results[3].status, TxStatus::Cancelled);
+}
+
+#[test]
+fn transactions_past_the_cutoff_are_not_executed() {
+    let payer = Keypair::new();
+    let (succeeds, counted) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let rollup_c = funded_channel(
+        &payer.pubkey(),
+        RollUpChannelConfig {
+            builtins: vec![
+                Builtin::new(succeeds, "succeeds", Succeeds::vm),
+                Builtin::new(counted, "counted", Counted::vm),
+            ],
+            batch_cu_limit: Some(SUCCEEDS_CU),
+            ..RollUpChannelConfig::default()
+        },
+    );
+
+    let block = invoking(&payer, &[succeeds, counted, counted, counted]);
+    let blocks = rollup_c.process_blocks(&[block]);
+    assert_eq!(blocks[0].cutoff, Some(1));
+    assert_eq!(blocks[0].total_cu, SUCCEEDS_CU);
+    for result in &blocks[0].results[1..] {
+        assert_eq!(result.status, TxStatus::Cancelled);
+        assert_eq!(
+            result.error,
+            Some(TransactionError::WouldExceedMaxBlockCostLimit)
+        );
+    }
+    // Only the transaction crossing the limit ran, to measure its units, and its
+    // execution isn't reported.
+    assert_eq!(COUNTED_INVOCATIONS.load(Ordering::Relaxed), 1);
+    let timed: Vec<_> = blocks[0]
+        .timings
+        .programs
+        .iter()
+        .map(|program| program.program_id)
+        .collect();
+    assert_eq!(timed, [succeeds]);
+}
@@ -0,0 +1,904 @@
+use std::collections::HashMap;
+
+use solana_client_ext::*;
+
+use solana_sdk::{
+    account::AccountSharedData,
+    instruction::InstructionError,
+    message::Message,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_instruction, system_program,
+    transaction::{Transaction, TransactionError},
+};
+
+#[test]
+fn offline_transfer() {
+    let payer = Keypair::new();
+    let recipient = Pubkey::new_unique();
+
+    // Only the fee payer has to be supplied: the system program is registered as a builtin.
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        payer.pubkey(),
+        AccountSharedData::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id()),
+    );
+
+    let transfer_ix = system_instruction::transfer(&payer.pubkey(), &recipient, LAMPORTS_PER_SOL);
+    let msg = Message::new(&[transfer_ix], Some(&payer.pubkey()));
+    let tx = Transaction::new(&[&payer], msg, Default::default());
+
+    let rollup_c = RollUpChannel::new_offline(tx.message.account_keys.clone(), accounts);
+    let results = rollup_c.process_rollup_transfers(&[tx]);
+
+    assert_eq!(results.len(), 1);
+    assert!(results[0].is_success(), "{}", results[0].result);
+    assert!(results[0].cu > 0);
+    // One signature, no prioritization fee.
+    assert_eq!(results[0].fee_lamports, 5000);
+}
+
+#[test]
+fn offline_missing_fee_payer() {
+    let payer = Keypair::new();
+
+    let transfer_ix =
+        system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), LAMPORTS_PER_SOL);
+    let msg = Message::new(&[transfer_ix], Some(&payer.pubkey()));
+    let tx = Transaction::new(&[&payer], msg, Default::default());
+
+    // The payer isn't in the static map, so it is simply missing.
+    let rollup_c = RollUpChannel::new_offline(tx.message.account_keys.clone(), HashMap::new());
+    let results = rollup_c.process_rollup_transfers(&[tx]);
+
+    assert_eq!(results.len(), 1);
+    assert!(!results[0].is_success());
+    assert_eq!(results[0].status, TxStatus::NotProcessed);
+    assert_eq!(results[0].error, Some(TransactionError::AccountNotFound));
+}
+
+#[test]
+fn offline_latest_blockhash_is_rejected() {
+    let payer = Keypair::new();
+    let transfer_ix =
+        system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), LAMPORTS_PER_SOL);
+    let msg = Message::new(&[transfer_ix], Some(&payer.pubkey()));
+    let tx = Transaction::new(&[&payer], msg, Default::default());
+
+    let config = RollUpChannelConfig {
+        blockhash: BlockhashSource::Latest,
+        ..RollUpChannelConfig::default()
+    };
+    let rollup_c = RollUpChannel::with_account_source(
+        tx.message.account_keys.clone(),
+        AccountSource::Static(HashMap::new()),
+        config,
+    );
+    let results = rollup_c.process_rollup_transfers(&[tx.clone()]);
+
+    assert_eq!(results.len(), 1);
+    assert!(!results[0].is_success());
+    assert!(results[0].result.contains("RPC-backed"));
+    assert_eq!(results[0].signature, Some(tx.signatures[0]));
+    assert_eq!(results[0].context, None);
+}
+
+#[test]
+fn offline_results_record_their_context() {
+    use solana_sdk::hash::Hash;
+
+    let payer = Keypair::new();
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        payer.pubkey(),
+        AccountSharedData::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id()),
+    );
+    let blockhash = Hash::new_unique();
+    let transfer_ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1);
+    let msg = Message::new(&[transfer_ix], Some(&payer.pubkey()));
+    let tx = Transaction::new(&[&payer], msg, blockhash);
+
+    let config = RollUpChannelConfig {
+        blockhash: BlockhashSource::Explicit(blockhash),
+        ..RollUpChannelConfig::default()
+    };
+    let rollup_c =
+        RollUpChannel::with_account_source(vec![], AccountSource::Static(accounts), config);
+    let results = rollup_c.process_rollup_transfers(&[tx]);
+
+    assert!(results[0].is_success(), "{}", results[0].result);
+    assert_eq!(
+        results[0].context,
+        Some(SimulationContext {
+            // Static sources don't report slots.
+            slot: None,
+            blockhash,
+            feature_set_hash: SimulationContext::feature_set_hash(
+                &agave_feature_set::FeatureSet::all_enabled()
+            ),
+        })
+    );
+}
+
+#[test]
+fn offline_blocks_see_committed_state() {
+    let payer = Keypair::new();
+    let intermediate = Keypair::new();
+    let recipient = Pubkey::new_unique();
+
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        payer.pubkey(),
+        AccountSharedData::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id()),
+    );
+
+    // Block 1 funds `intermediate`, block 2 spends from it.
+    let fund_ix = system_instruction::transfer(
+        &payer.pubkey(),
+        &intermediate.pubkey(),
+        2 * LAMPORTS_PER_SOL,
+    );
+    let fund_tx = Transaction::new(
+        &[&payer],
+        Message::new(&[fund_ix], Some(&payer.pubkey())),
+        Default::default(),
+    );
+    let spend_ix =
+        system_instruction::transfer(&intermediate.pubkey(), &recipient, LAMPORTS_PER_SOL);
+    let spend_tx = Transaction::new(
+        &[&payer, &intermediate],
+        Message::new(&[spend_ix], Some(&payer.pubkey())),
+        Default::default(),
+    );
+
+    let rollup_c = RollUpChannel::new_offline(vec![], accounts);
+
+    // Spending before funding fails, but must not prevent later blocks from executing.
+    let blocks = rollup_c.process_blocks(&[vec![spend_tx.clone()], vec![fund_tx], vec![spend_tx]]);
+
+    assert_eq!(blocks.len(), 3);
+    assert_eq!(blocks[0].slot + 1, blocks[1].slot);
+    assert_eq!(blocks[1].slot + 1, blocks[2].slot);
+    assert!(!blocks[0].results[0].is_success());
+    assert!(
+        blocks[1].results[0].is_success(),
+        "{}",
+        blocks[1].results[0].result
+    );
+    assert!(
+        blocks[2].results[0].is_success(),
+        "{}",
+        blocks[2].results[0].result
+    );
+    assert!(blocks[1].total_cu > 0);
+    assert!(blocks[1].total_fees > 0);
+}
+
+#[test]
+fn offline_duplicate_transactions() {
+    let payer = Keypair::new();
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        payer.pubkey(),
+        AccountSharedData::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id()),
+    );
+
+    let transfer_ix =
+        system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), LAMPORTS_PER_SOL);
+    let msg = Message::new(&[transfer_ix], Some(&payer.pubkey()));
+    let tx = Transaction::new(&[&payer], msg, Default::default());
+
+    let rollup_c = RollUpChannel::new_offline(vec![], accounts.clone());
+    let results = rollup_c.process_rollup_transfers(&[tx.clone(), tx.clone()]);
+    assert!(results[0].is_success(), "{}", results[0].result);
+    assert!(!results[1].is_success());
+    assert!(results[1].result.contains("already been processed"));
+
+    // Replaying is allowed once deduplication is disabled.
+    let config = RollUpChannelConfig {
+        reject_duplicate_transactions: false,
+        ..RollUpChannelConfig::default()
+    };
+    let rollup_c =
+        RollUpChannel::with_account_source(vec![], AccountSource::Static(accounts), config);
+    let results = rollup_c.process_rollup_transfers(&[tx.clone(), tx]);
+    assert!(results.iter().all(|result| result.is_success()));
+}
+
+#[test]
+fn offline_fee_payer_would_become_rent_paying() {
+    let payer = Keypair::new();
+    let rent = solana_sdk::rent::Rent::default();
+
+    // Rent-exempt before the fee, rent-paying after it.
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        payer.pubkey(),
+        AccountSharedData::new(rent.minimum_balance(0) + 4_000, 0, &system_program::id()),
+    );
+
+    let transfer_ix = system_instruction::transfer(&payer.pubkey(), &payer.pubkey(), 0);
+    let msg = Message::new(&[transfer_ix], Some(&payer.pubkey()));
+    let tx = Transaction::new(&[&payer], msg, Default::default());
+
+    let rollup_c = RollUpChannel::new_offline(vec![], accounts);
+    let results = rollup_c.process_rollup_transfers(&[tx]);
+
+    assert!(!results[0].is_success());
+    assert!(
+        results[0].result.contains("insufficient funds for rent"),
+        "{}",
+        results[0].result
+    );
+    assert_eq!(
+        results[0].error,
+        Some(TransactionError::InsufficientFundsForRent { account_index: 0 })
+    );
+}
+
+#[test]
+fn offline_nonce_fee_payer_keeps_rent_exempt_minimum() {
+    use solana_sdk::nonce::state::{Data, DurableNonce, State, Versions};
+
+    let payer = Keypair::new();
+    let rent = solana_sdk::rent::Rent::default();
+    let nonce_state = Versions::new(State::Initialized(Data::new(
+        payer.pubkey(),
+        DurableNonce::from_blockhash(&Default::default()),
+        5_000,
+    )));
+
+    // A nonce account with data can only spend what's above its rent-exempt minimum.
+    let data_len = State::size();
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        payer.pubkey(),
+        AccountSharedData::new_data_with_space(
+            rent.minimum_balance(data_len) + 4_000,
+            &nonce_state,
+            data_len,
+            &system_program::id(),
+        )
+        .unwrap(),
+    );
+
+    let transfer_ix = system_instruction::transfer(&payer.pubkey(), &payer.pubkey(), 0);
+    let msg = Message::new(&[transfer_ix], Some(&payer.pubkey()));
+    let tx = Transaction::new(&[&payer], msg, Default::default());
+
+    let rollup_c = RollUpChannel::new_offline(vec![], accounts);
+    let results = rollup_c.process_rollup_transfers(&[tx]);
+
+    assert!(!results[0].is_success());
+    assert!(
+        results[0].result.contains("Insufficient funds for fee"),
+        "{}",
+        results[0].result
+    );
+    assert_eq!(
+        results[0].error,
+        Some(TransactionError::InsufficientFundsForFee)
+    );
+}
+
+#[test]
+fn offline_block_cu_limit_cuts_remaining_transactions() {
+    use solana_svm::transaction_processing_callback::TransactionProcessingCallback;
+
+    let payer = Keypair::new();
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        payer.pubkey(),
+        AccountSharedData::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id()),
+    );
+
+    let recipients: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+    let txs: Vec<Transaction> = recipients
+        .iter()
+        .map(|to| {
+            let ix = system_instruction::transfer(&payer.pubkey(), to, LAMPORTS_PER_SOL);
+            Transaction::new(
+                &[&payer],
+                Message::new(&[ix], Some(&payer.pubkey())),
+                Default::default(),
+            )
+        })
+        .collect();
+
+    // Measure a single transfer, then allow room for two and a half of them.
+    let unbounded = RollUpChannel::new_offline(vec![], accounts.clone());
+    let transfer_cu = unbounded.process_rollup_transfers(&txs[..1])[0].cu;
+    assert!(transfer_cu > 0);
+
+    let config = RollUpChannelConfig {
+        batch_cu_limit: Some(transfer_cu * 5 / 2),
+        ..RollUpChannelConfig::default()
+    };
+    let rollup_c =
+        RollUpChannel::with_account_source(vec![], AccountSource::Static(accounts), config);
+    let blocks = rollup_c.process_blocks(&[txs]);
+
+    assert_eq!(blocks[0].cutoff, Some(2));
+    assert_eq!(blocks[0].total_cu, 2 * transfer_cu);
+    assert_eq!(blocks[0].total_fees, 2 * 5_000);
+    assert!(blocks[0].results[1].is_success());
+    assert!(!blocks[0].results[2].is_success());
+    assert_eq!(blocks[0].results[2].status, TxStatus::Cancelled);
+    assert!(blocks[0].results[2].result.contains("Block Cost Limit"));
+
+    let loader = rollup_c.account_loader();
+    assert!(loader.get_account_shared_data(&recipients[1]).is_some());
+    assert!(loader.get_account_shared_data(&recipients[2]).is_none());
+}
+
+#[test]
+fn offline_batch_prefetches_accounts() {
+    let keypairs: Vec<Keypair> = (0..6).map(|_| Keypair::new()).collect();
+    let mut accounts = HashMap::new();
+    for keypair in &keypairs {
+        accounts.insert(
+            keypair.pubkey(),
+            AccountSharedData::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id()),
+        );
+    }
+
+    let txs: Vec<Transaction> = keypairs
+        .chunks(2)
+        .map(|pair| {
+            let ix = system_instruction::transfer(
+                &pair[0].pubkey(),
+                &pair[1].pubkey(),
+                LAMPORTS_PER_SOL,
+            );
+            Transaction::new(
+                &[&pair[0]],
+                Message::new(&[ix], Some(&pair[0].pubkey())),
+                Default::default(),
+            )
+        })
+        .collect();
+
+    let single = RollUpChannel::new_offline(vec![], accounts.clone());
+    assert!(single.process_rollup_transfers(&txs[..1])[0].is_success());
+
+    let batch = RollUpChannel::new_offline(vec![], accounts);
+    let results = batch.process_rollup_transfers(&txs);
+    assert!(results.iter().all(|result| result.is_success()));
+
+    // Every account the batch references is requested at once, after the sysvars, so
+    // tripling the batch doesn't add a single per-key request.
+    let single_stats = single.account_loader().fetch_stats();
+    let batch_stats = batch.account_loader().fetch_stats();
+    assert_eq!(batch_stats.batch_requests, 2);
+    assert_eq!(batch_stats.single_requests, single_stats.single_requests);
+}
+
+#[test]
+fn offline_batch_records_accessed_accounts() {
+    let payer = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        payer.pubkey(),
+        AccountSharedData::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id()),
+    );
+
+    let transfer_ix = system_instruction::transfer(&payer.pubkey(), &recipient, LAMPORTS_PER_SOL);
+    let msg = Message::new(&[transfer_ix], Some(&payer.pubkey()));
+    let tx = Transaction::new(&[&payer], msg, Default::default());
+
+    let rollup_c = RollUpChannel::new_offline(tx.message.account_keys.clone(), accounts);
+    let results = rollup_c.process_rollup_transfers(&[tx]);
+    assert!(results[0].is_success(), "{}", results[0].result);
+
+    let accessed = rollup_c.account_loader().accessed_accounts();
+    for key in [payer.pubkey(), recipient, system_program::id()] {
+        assert!(accessed.reads.contains(&key), "{} wasn't read", key);
+    }
+    assert_eq!(
+        accessed.writes,
+        [payer.pubkey(), recipient].into_iter().collect()
+    );
+
+    rollup_c.account_loader().reset_accessed_accounts();
+    assert_eq!(
+        rollup_c.account_loader().accessed_accounts(),
+        AccessedAccounts::default()
+    );
+}
+
+#[test]
+fn offline_logs_are_recorded_on_demand() {
+    let payer = Keypair::new();
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        payer.pubkey(),
+        AccountSharedData::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id()),
+    );
+
+    // The second transfer overdraws the payer, so its logs describe the failure.
+    let transactions = [LAMPORTS_PER_SOL, 100 * LAMPORTS_PER_SOL].map(|lamports| {
+        let transfer_ix =
+            system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), lamports);
+        let msg = Message::new(&[transfer_ix], Some(&payer.pubkey()));
+        Transaction::new(&[&payer], msg, Default::default())
+    });
+
+    let keys = transactions[0].message.account_keys.clone();
+    let results = RollUpChannel::new_offline(keys.clone(), accounts.clone())
+        .process_rollup_transfers(&transactions);
+    assert!(results.iter().all(|result| result.logs.is_none()));
+    assert!(results
+        .iter()
+        .all(|result| result.inner_instructions.is_none()));
+
+    let config = RollUpChannelConfig {
+        record_trace: true,
+        ..RollUpChannelConfig::default()
+    };
+    let results = RollUpChannel::with_account_source(keys, AccountSource::Static(accounts), config)
+        .process_rollup_transfers(&transactions);
+    assert!(results[0].is_success(), "{}", results[0].result);
+    assert_eq!(results[0].status, TxStatus::ExecutedOk);
+    assert_eq!(results[0].error, None);
+    // The failed transfer still paid its fee.
+    assert_eq!(results[1].status, TxStatus::ExecutedErr);
+    assert_eq!(results[1].fee_lamports, 5000);
+    assert_eq!(
+        results[1].error,
+        Some(TransactionError::InstructionError(
+            0,
+            // SystemError::ResultWithNegativeLamports
+            InstructionError::Custom(1)
+        ))
+    );
+    for result in &results {
+        // The system program invokes nothing.
+        assert_eq!(result.inner_instructions, Some(vec![]));
+        assert_eq!(result.programs_invoked, vec![system_program::id()]);
+        let logs = result.logs.as_ref().expect("logs are recorded");
+        assert!(logs[0].starts_with("Program 11111111111111111111111111111111 invoke"));
+    }
+    assert!(!results[1].result.contains("Logs:"));
+    assert!(results[1]
+        .logs
+        .as_ref()
+        .unwrap()
+        .iter()
+        .any(|line| line.contains("insufficient lamports")));
+}
+
+#[test]
+fn offline_custom_errors_are_decoded() {
+    let payer = Keypair::new();
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        payer.pubkey(),
+        AccountSharedData::new(LAMPORTS_PER_SOL, 0, &system_program::id()),
+    );
+
+    // An overdraw, behind a compute budget instruction so it isn't the first one.
+    let transfer_ix = system_instruction::transfer(
+        &payer.pubkey(),
+        &Pubkey::new_unique(),
+        100 * LAMPORTS_PER_SOL,
+    );
+    let msg = Message::new(
+        &[
+            solana_sdk::compute_budget::ComputeBudgetInstruction::set_compute_unit_limit(10_000),
+            transfer_ix,
+        ],
+        Some(&payer.pubkey()),
+    );
+    let tx = Transaction::new(&[&payer], msg, Default::default());
+
+    let unnamed = RollUpChannel::new_offline(vec![], accounts.clone())
+        .process_rollup_transfers(&[tx.clone()]);
+    let decoded = unnamed[0].decoded_error.clone().expect("custom error");
+    assert_eq!(decoded.instruction_index, 1);
+    assert_eq!(decoded.program_id, system_program::id());
+    assert_eq!(decoded.code, 1);
+    assert_eq!(decoded.error_name, None);
+    assert_eq!(
+        decoded.to_string(),
+        format!("{}: custom program error: 0x1", system_program::id())
+    );
+
+    let decoder = ErrorDecoder::new().register(
+        system_program::id(),
+        "System",
+        [
+            (0, "AccountAlreadyInUse"),
+            (1, "ResultWithNegativeLamports"),
+        ],
+    );
+    let named = RollUpChannel::builder(AccountSource::Static(accounts))
+        .error_decoder(decoder)
+        .build()
+        .process_rollup_transfers(&[tx]);
+    let decoded = named[0].decoded_error.as_ref().expect("custom error");
+    assert_eq!(decoded.to_string(), "System::ResultWithNegativeLamports");
+    assert!(named[0]
+        .to_string()
+        .contains("decoded: System::ResultWithNegativeLamports"));
+}
+
+#[test]
+fn offline_balances_follow_the_batch() {
+    let payer = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        payer.pubkey(),
+        AccountSharedData::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id()),
+    );
+
+    // The second transfer sees the first one's debit; the third overdraws the payer.
+    let transactions = [
+        LAMPORTS_PER_SOL,
+        2 * LAMPORTS_PER_SOL,
+        100 * LAMPORTS_PER_SOL,
+    ]
+    .map(|lamports| {
+        let transfer_ix = system_instruction::transfer(&payer.pubkey(), &recipient, lamports);
+        let msg = Message::new(&[transfer_ix], Some(&payer.pubkey()));
+        Transaction::new(&[&payer], msg, Default::default())
+    });
+
+    let rollup_c = RollUpChannel::new_offline(vec![], accounts);
+    let results = rollup_c.process_rollup_transfers(&transactions);
+    assert!(results[1].is_success(), "{}", results[1].result);
+    assert!(!results[2].is_success());
+
+    let fee = 5000;
+    let balances: Vec<_> = results
+        .iter()
+        .map(|result| {
+            (
+                result.pre_balances[..2].to_vec(),
+                result.post_balances[..2].to_vec(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        balances,
+        [
+            (
+                vec![10 * LAMPORTS_PER_SOL, 0],
+                vec![9 * LAMPORTS_PER_SOL - fee, LAMPORTS_PER_SOL]
+            ),
+            (
+                vec![9 * LAMPORTS_PER_SOL - fee, LAMPORTS_PER_SOL],
+                vec![7 * LAMPORTS_PER_SOL - 2 * fee, 3 * LAMPORTS_PER_SOL]
+            ),
+            // Only the fee debit of the failed transfer remains.
+            (
+                vec![7 * LAMPORTS_PER_SOL - 2 * fee, 3 * LAMPORTS_PER_SOL],
+                vec![7 * LAMPORTS_PER_SOL - 3 * fee, 3 * LAMPORTS_PER_SOL]
+            ),
+        ]
+    );
+    for result in &results {
+        assert_eq!(result.pre_balances.len(), 3);
+        assert_eq!(result.post_balances.len(), 3);
+    }
+
+    let changes = |result: &ReturnStruct| {
+        result
+            .account_changes
+            .iter()
+            .map(|change| (change.pubkey, change.kind))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        changes(&results[0]),
+        [
+            (payer.pubkey(), AccountChangeKind::Modified),
+            (recipient, AccountChangeKind::Created)
+        ]
+    );
+    assert_eq!(
+        changes(&results[1]),
+        [
+            (payer.pubkey(), AccountChangeKind::Modified),
+            (recipient, AccountChangeKind::Modified)
+        ]
+    );
+    assert_eq!(
+        changes(&results[2]),
+        [(payer.pubkey(), AccountChangeKind::Modified)]
+    );
+}
+
+#[test]
+fn offline_untouched_writable_accounts_are_reported() {
+    let payer = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        payer.pubkey(),
+        AccountSharedData::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id()),
+    );
+
+    // An empty transfer leaves its writable recipient as it was; an overdraw fails.
+    let transactions = [0, 100 * LAMPORTS_PER_SOL].map(|lamports| {
+        let transfer_ix = system_instruction::transfer(&payer.pubkey(), &recipient, lamports);
+        let msg = Message::new(&[transfer_ix], Some(&payer.pubkey()));
+        Transaction::new(&[&payer], msg, Default::default())
+    });
+
+    let rollup_c = RollUpChannel::new_offline(vec![], accounts);
+    let results = rollup_c.process_rollup_transfers(&transactions);
+    assert!(results[0].is_success(), "{}", results[0].result);
+    assert_eq!(results[0].writable_accounts_modified, [payer.pubkey()]);
+
+    // The failed transfer still charged the payer, but reports nothing.
+    assert!(!results[1].is_success());
+    assert_eq!(results[1].account_changes.len(), 1);
+    assert!(results[1].writable_accounts_modified.is_empty());
+}
+
+#[test]
+fn offline_account_changes_carry_data_on_demand() {
+    let payer = Keypair::new();
+    let created = Keypair::new();
+    let owner = Pubkey::new_unique();
+    let rent = solana_sdk::rent::Rent::default();
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        payer.pubkey(),
+        AccountSharedData::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id()),
+    );
+
+    let create_ix = system_instruction::create_account(
+        &payer.pubkey(),
+        &created.pubkey(),
+        rent.minimum_balance(8),
+        8,
+        &owner,
+    );
+    let msg = Message::new(&[create_ix], Some(&payer.pubkey()));
+    let tx = Transaction::new(&[&payer, &created], msg, Default::default());
+
+    let results = RollUpChannel::new_offline(vec![], accounts.clone())
+        .process_rollup_transfers(&[tx.clone()]);
+    assert!(results[0].is_success(), "{}", results[0].result);
+    let change = &results[0].account_changes[1];
+    assert_eq!(change.pubkey, created.pubkey());
+    assert_eq!(change.kind, AccountChangeKind::Created);
+    assert_eq!(
+        (change.old_lamports, change.new_lamports),
+        (0, rent.minimum_balance(8))
+    );
+    assert_eq!((change.old_data_len, change.new_data_len), (0, 8));
+    assert_eq!(
+        (change.old_owner, change.new_owner),
+        (Pubkey::default(), owner)
+    );
+    assert_eq!(change.new_data_hash, solana_sdk::hash::hash(&[0; 8]));
+    assert_eq!((&change.old_data, &change.new_data), (&None, &None));
+
+    let config = RollUpChannelConfig {
+        record_account_data: true,
+        ..RollUpChannelConfig::default()
+    };
+    let results =
+        RollUpChannel::with_account_source(vec![], AccountSource::Static(accounts), config)
+            .process_rollup_transfers(&[tx]);
+    let change = &results[0].account_changes[1];
+    assert_eq!(change.old_data, Some(vec![]));
+    assert_eq!(change.new_data, Some(vec![0; 8]));
+}
+
+#[test]
+fn offline_results_identify_their_transaction() {
+    let payer = Keypair::new();
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        payer.pubkey(),
+        AccountSharedData::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id()),
+    );
+
+    // A signed transfer, an unsigned one and one whose fee payer doesn't exist.
+    let transfer = |from: &Pubkey| {
+        let transfer_ix = system_instruction::transfer(from, &Pubkey::new_unique(), 1);
+        Message::new(&[transfer_ix], Some(from))
+    };
+    let stranger = Keypair::new();
+    let transactions = [
+        Transaction::new(&[&payer], transfer(&payer.pubkey()), Default::default()),
+        Transaction::new_unsigned(transfer(&payer.pubkey())),
+        Transaction::new(
+            &[&stranger],
+            transfer(&stranger.pubkey()),
+            Default::default(),
+        ),
+    ];
+
+    let rollup_c = RollUpChannel::new_offline(vec![], accounts);
+    let results = rollup_c.process_rollup_transfers(&transactions);
+    assert!(!results[2].is_success());
+
+    let identities: Vec<_> = results
+        .iter()
+        .map(|result| (result.signature, result.message_hash))
+        .collect();
+    assert_eq!(
+        identities,
+        [
+            (
+                Some(transactions[0].signatures[0]),
+                transactions[0].message.hash()
+            ),
+            (None, transactions[1].message.hash()),
+            (
+                Some(transactions[2].signatures[0]),
+                transactions[2].message.hash()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn offline_execution_time_is_recorded_on_demand() {
+    let payer = Keypair::new();
+    let spender = Keypair::new();
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        payer.pubkey(),
+        AccountSharedData::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id()),
+    );
+
+    // The spender can only pay back what the first transfer gave it.
+    let fund_ix =
+        system_instruction::transfer(&payer.pubkey(), &spender.pubkey(), LAMPORTS_PER_SOL);
+    let spend_ix =
+        system_instruction::transfer(&spender.pubkey(), &payer.pubkey(), LAMPORTS_PER_SOL / 2);
+    let transactions = [
+        Transaction::new(
+            &[&payer],
+            Message::new(&[fund_ix], Some(&payer.pubkey())),
+            Default::default(),
+        ),
+        Transaction::new(
+            &[&payer, &spender],
+            Message::new(&[spend_ix], Some(&payer.pubkey())),
+            Default::default(),
+        ),
+    ];
+
+    let results = RollUpChannel::new_offline(vec![], accounts.clone())
+        .process_rollup_transfers(&transactions);
+    assert!(results.iter().all(|result| result.execution_time.is_none()));
+    assert_eq!(BatchSummary::from_results(&results).execution_time, None);
+
+    let config = RollUpChannelConfig {
+        record_execution_time: true,
+        ..RollUpChannelConfig::default()
+    };
+    let timed = RollUpChannel::with_account_source(vec![], AccountSource::Static(accounts), config)
+        .process_rollup_transfers(&transactions);
+    for (result, untimed) in timed.iter().zip(&results) {
+        assert!(result.is_success(), "{}", result.result);
+        assert!(result.execution_time.is_some());
+        assert_eq!(result.post_balances, untimed.post_balances);
+    }
+    assert_eq!(
+        BatchSummary::from_results(&timed).execution_time,
+        Some(
+            timed
+                .iter()
+                .filter_map(|result| result.execution_time)
+                .sum()
+        )
+    );
+}
+
+#[test]
+fn offline_loaded_accounts_are_reported_for_executed_transactions() {
+    let payer = Keypair::new();
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        payer.pubkey(),
+        AccountSharedData::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id()),
+    );
+
+    // A transfer, and an instruction to a program that doesn't exist, which fails to load.
+    let transfer_ix =
+        system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), LAMPORTS_PER_SOL);
+    let missing_program_ix =
+        solana_sdk::instruction::Instruction::new_with_bytes(Pubkey::new_unique(), &[], vec![]);
+    let transactions = [transfer_ix, missing_program_ix].map(|ix| {
+        let msg = Message::new(&[ix], Some(&payer.pubkey()));
+        Transaction::new(&[&payer], msg, Default::default())
+    });
+
+    let rollup_c = RollUpChannel::new_offline(vec![], accounts);
+    let results = rollup_c.process_rollup_transfers(&transactions);
+
+    assert!(results[0].is_success(), "{}", results[0].result);
+    // Without recording, the top-level programs are reported.
+    assert_eq!(results[0].programs_invoked, vec![system_program::id()]);
+    // The payer, the recipient and the system program.
+    assert_eq!(results[0].loaded_accounts_count, 3);
+    assert!(results[0].loaded_accounts_data_size > 0);
+    // Rent fee collection is disabled by the channel's feature set.
+    assert_eq!(results[0].rent_collected, 0);
+    assert!(results[0].rent_debits.is_empty());
+
+    assert_eq!(
+        results[1].error,
+        Some(TransactionError::ProgramAccountNotFound)
+    );
+    assert_eq!(results[1].status, TxStatus::FeesOnly);
+    assert_eq!(results[1].fee_lamports, 5000);
+    assert_eq!(results[1].loaded_accounts_count, 0);
+    assert_eq!(results[1].loaded_accounts_data_size, 0);
+    assert_eq!(results[1].rent_collected, 0);
+    assert!(results[1].programs_invoked.is_empty());
+}
+
+#[test]
+fn offline_headroom_follows_the_requested_limit() {
+    let payer = Keypair::new();
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        payer.pubkey(),
+        AccountSharedData::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id()),
+    );
+
+    let limit_ix =
+        solana_sdk::compute_budget::ComputeBudgetInstruction::set_compute_unit_limit(50_000);
+    let transfer_ix =
+        system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), LAMPORTS_PER_SOL);
+    let msg = Message::new(&[limit_ix, transfer_ix], Some(&payer.pubkey()));
+    let tx = Transaction::new(&[&payer], msg, Default::default());
+
+    let rollup_c = RollUpChannel::new_offline(vec![], accounts);
+    let results = rollup_c.process_rollup_transfers(&[tx]);
+
+    assert!(results[0].is_success(), "{}", results[0].result);
+    assert_eq!(results[0].requested_cu_limit, Some(50_000));
+    assert_eq!(results[0].headroom(), Some(50_000 - results[0].cu));
+}
+
+#[test]
+fn offline_requested_limits_are_enforced() {
+    let payer = Keypair::new();
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        payer.pubkey(),
+        AccountSharedData::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id()),
+    );
+    let transfer = |limit| {
+        let msg = Message::new(
+            &[
+                solana_sdk::compute_budget::ComputeBudgetInstruction::set_compute_unit_limit(limit),
+                system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1),
+            ],
+            Some(&payer.pubkey()),
+        );
+        Transaction::new(&[&payer], msg, Default::default())
+    };
+
+    // The compute budget and system programs each charge 150 units.
+    let rollup_c = RollUpChannel::new_offline(vec![], accounts);
+    let results = rollup_c.process_rollup_transfers(&[transfer(200), transfer(1_000)]);
+
+    assert_eq!(results[0].status, TxStatus::ExecutedErr);
+    assert_eq!(
+        results[0].error,
+        Some(TransactionError::InstructionError(
+            1,
+            InstructionError::ComputationalBudgetExceeded
+        ))
+    );
+    assert!(results[0].hit_compute_limit);
+    assert_eq!(results[0].requested_cu_limit, Some(200));
+
+    assert!(results[1].is_success(), "{}", results[1].result);
+    assert!(!results[1].hit_compute_limit);
+    assert_eq!(results[1].cu, 300);
+}
@@ -0,0 +1,114 @@
+mod common;
+
+use common::{funded_channel, single_ix_tx};
+use solana_client_ext::*;
+use solana_sdk::{
+    ed25519_program,
+    instruction::{Instruction, InstructionError},
+    message::Message,
+    native_loader,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    transaction::{Transaction, TransactionError},
+};
+
+/// Where the signature offsets struct ends in an ed25519 instruction's data, after the
+/// signature count, a padding byte and the seven `u16` offsets.
+const ED25519_DATA_START: u16 = 16;
+
+/// An ed25519 precompile instruction verifying `signature` of `message` by `signer`,
+/// every offset pointing into the instruction itself.
+fn ed25519_instruction(signer: &Pubkey, signature: &[u8], message: &[u8]) -> Instruction {
+    let public_key_offset = ED25519_DATA_START;
+    let signature_offset = public_key_offset + 32;
+    let message_offset = signature_offset + 64;
+    let mut data = vec![1, 0];
+    for offset in [
+        signature_offset,
+        u16::MAX,
+        public_key_offset,
+        u16::MAX,
+        message_offset,
+        message.len() as u16,
+        u16::MAX,
+    ] {
+        data.extend_from_slice(&offset.to_le_bytes());
+    }
+    data.extend_from_slice(signer.as_ref());
+    data.extend_from_slice(signature);
+    data.extend_from_slice(message);
+    Instruction::new_with_bytes(ed25519_program::id(), &data, Vec::new())
+}
+
+/// A channel serving a funded `payer` and the ed25519 program's account, verifying
+/// transactions as configured.
+fn channel(payer: &Pubkey, sanitization: SanitizationMode) -> RollUpChannel<'static> {
+    let rollup_c = funded_channel(
+        payer,
+        RollUpChannelConfig {
+            sanitization,
+            ..RollUpChannelConfig::default()
+        },
+    );
+    rollup_c.account_loader().insert_override(
+        ed25519_program::id(),
+        native_loader::create_loadable_account_for_test("ed25519_program"),
+    );
+    rollup_c
+}
+
+/// A transaction paid by `payer` carrying an ed25519 instruction checking the signature
+/// of `signed` against `verified`.
+fn precompile_transaction(payer: &Keypair, signed: &[u8], verified: &[u8]) -> Transaction {
+    let signer = Keypair::new();
+    let signature = signer.sign_message(signed);
+    let instruction = ed25519_instruction(&signer.pubkey(), signature.as_ref(), verified);
+    single_ix_tx(payer, instruction)
+}
+
+#[test]
+fn full_checks_accept_a_valid_precompile_signature() {
+    let payer = Keypair::new();
+    let rollup_c = channel(&payer.pubkey(), SanitizationMode::FullChecks);
+
+    let tx = precompile_transaction(&payer, b"rollup", b"rollup");
+    let results = rollup_c.process_rollup_transfers(&[tx]);
+    assert!(results[0].is_success(), "{}", results[0].result);
+}
+
+#[test]
+fn full_checks_reject_an_invalid_precompile_signature_before_execution() {
+    let payer = Keypair::new();
+    let rollup_c = channel(&payer.pubkey(), SanitizationMode::FullChecks);
+
+    let tx = precompile_transaction(&payer, b"rollup", b"tampered");
+    let results = rollup_c.process_rollup_transfers(&[tx]);
+    assert_eq!(results[0].status, TxStatus::NotProcessed);
+    assert!(matches!(
+        results[0].error,
+        Some(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(_)
+        ))
+    ));
+    assert_eq!(results[0].fee_lamports, 0);
+}
+
+#[test]
+fn full_checks_reject_invalid_transaction_signatures() {
+    let payer = Keypair::new();
+    let transfer =
+        solana_sdk::system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1);
+    let tx = Transaction::new_unsigned(Message::new(&[transfer], Some(&payer.pubkey())));
+
+    // Unsigned transactions are simulated unless signatures are verified.
+    let results = channel(&payer.pubkey(), SanitizationMode::SkipVerification)
+        .process_rollup_transfers(&[tx.clone()]);
+    assert!(results[0].is_success(), "{}", results[0].result);
+
+    let results =
+        channel(&payer.pubkey(), SanitizationMode::FullChecks).process_rollup_transfers(&[tx]);
+    assert_eq!(results[0].status, TxStatus::NotProcessed);
+    assert_eq!(results[0].error, Some(TransactionError::SignatureFailure));
+}
@@ -0,0 +1,188 @@
+use std::sync::Arc;
+
+use agave_feature_set::{blake3_syscall_enabled, FeatureSet};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use serde_json::{json, Value};
+use solana_client::client_error::Result as ClientResult;
+use solana_client::rpc_client::{RpcClient, RpcClientConfig};
+use solana_client::rpc_request::RpcRequest;
+use solana_client::rpc_sender::{RpcSender, RpcTransportStats};
+use solana_client_ext::*;
+use solana_sdk::{
+    account::{create_account_shared_data_for_test, ReadableAccount},
+    epoch_schedule::EpochSchedule,
+    feature,
+    hash::Hash,
+    pubkey::Pubkey,
+    rent::Rent,
+    rent_collector::RentCollector,
+    sysvar,
+};
+
+/// Slot `blake3_syscall_enabled` was activated in on the fake cluster.
+const ACTIVATION_SLOT: u64 = 5;
+
+/// Epoch the fake cluster is in.
+const EPOCH: u64 = 7;
+
+/// Stake of the fake cluster's current and delinquent vote accounts.
+const STAKES: [u64; 3] = [1_000, 2_000, 500];
+
+fn cluster_blockhash() -> Hash {
+    Hash::new_from_array([7; 32])
+}
+
+fn cluster_rent() -> Rent {
+    Rent {
+        lamports_per_byte_year: Rent::default().lamports_per_byte_year * 2,
+        ..Rent::default()
+    }
+}
+
+fn cluster_epoch_schedule() -> EpochSchedule {
+    EpochSchedule::custom(64, 64, false)
+}
+
+fn vote_account(activated_stake: u64) -> Value {
+    json!({
+        "votePubkey": Pubkey::new_unique().to_string(),
+        "nodePubkey": Pubkey::new_unique().to_string(),
+        "activatedStake": activated_stake,
+        "commission": 0,
+        "epochVoteAccount": true,
+        "epochCredits": [],
+        "lastVote": 0,
+        "rootSlot": 0,
+    })
+}
+
+/// An RPC transport serving a cluster at [`EPOCH`], with [`cluster_blockhash`],
+/// [`cluster_rent`], [`cluster_epoch_schedule`], vote accounts staking [`STAKES`], and
+/// `blake3_syscall_enabled` as its only active feature.
+struct ClusterSender;
+
+impl ClusterSender {
+    fn account(&self, key: &Value) -> Value {
+        let key = key.as_str().unwrap();
+        let (data, owner) = if key == sysvar::rent::id().to_string() {
+            let rent = create_account_shared_data_for_test(&cluster_rent());
+            (rent.data().to_vec(), sysvar::id())
+        } else if key == blake3_syscall_enabled::id().to_string() {
+            let mut data = vec![1];
+            data.extend_from_slice(&ACTIVATION_SLOT.to_le_bytes());
+            (data, feature::id())
+        } else {
+            return Value::Null;
+        };
+        json!({
+            "lamports": 1,
+            "data": [BASE64_STANDARD.encode(&data), "base64"],
+            "owner": owner.to_string(),
+            "executable": false,
+            "rentEpoch": 0,
+            "space": data.len(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl RpcSender for ClusterSender {
+    async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+        let value = match request {
+            RpcRequest::GetMultipleAccounts => params[0]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|key| self.account(key))
+                .collect(),
+            RpcRequest::GetAccountInfo => self.account(&params[0]),
+            RpcRequest::GetLatestBlockhash => json!({
+                "blockhash": cluster_blockhash().to_string(),
+                "lastValidBlockHeight": 100,
+            }),
+            RpcRequest::GetVoteAccounts => {
+                return Ok(json!({
+                    "current": [vote_account(STAKES[0]), vote_account(STAKES[1])],
+                    "delinquent": [vote_account(STAKES[2])],
+                }))
+            }
+            RpcRequest::GetEpochInfo => {
+                return Ok(json!({
+                    "epoch": EPOCH,
+                    "slotIndex": 0,
+                    "slotsInEpoch": 64,
+                    "absoluteSlot": 10,
+                    "blockHeight": 10,
+                }))
+            }
+            RpcRequest::GetEpochSchedule => {
+                return Ok(serde_json::to_value(cluster_epoch_schedule()).unwrap())
+            }
+            RpcRequest::GetVersion => return Ok(json!({ "solana-core": "2.2.2" })),
+            _ => Value::Null,
+        };
+        Ok(json!({ "context": { "slot": 10 }, "value": value }))
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        RpcTransportStats::default()
+    }
+
+    fn url(&self) -> String {
+        "cluster".to_string()
+    }
+}
+
+#[test]
+fn deterministic_preset_matches_the_defaults() {
+    let builder = ProcessingEnvironmentBuilder::deterministic();
+    let environment = builder.build();
+    assert_eq!(environment.blockhash, Hash::default());
+    assert_eq!(environment.blockhash_lamports_per_signature, 5000);
+    assert_eq!(environment.fee_lamports_per_signature, 5000);
+    assert_eq!(environment.epoch_total_stake, 0);
+    assert_eq!(*environment.feature_set, FeatureSet::all_enabled());
+    assert!(environment.rent_collector.is_some());
+    assert_eq!(builder, ProcessingEnvironmentBuilder::default());
+}
+
+#[test]
+fn setters_override_the_preset() {
+    let feature_set = Arc::new(FeatureSet::default());
+    let builder = ProcessingEnvironmentBuilder::deterministic()
+        .blockhash(cluster_blockhash())
+        .blockhash_lamports_per_signature(10)
+        .epoch_total_stake(42)
+        .feature_set(Arc::clone(&feature_set))
+        .fee_lamports_per_signature(20);
+    let environment = builder.build();
+    assert_eq!(environment.blockhash, cluster_blockhash());
+    assert_eq!(environment.blockhash_lamports_per_signature, 10);
+    assert_eq!(environment.fee_lamports_per_signature, 20);
+    assert_eq!(environment.epoch_total_stake, 42);
+    assert!(Arc::ptr_eq(&environment.feature_set, &feature_set));
+}
+
+#[test]
+fn cluster_preset_mirrors_the_cluster() {
+    let rpc_client = RpcClient::new_sender(ClusterSender, RpcClientConfig::default());
+
+    let builder = ProcessingEnvironmentBuilder::from_cluster(&rpc_client).unwrap();
+    let mut feature_set = FeatureSet::default();
+    feature_set.activate(&blake3_syscall_enabled::id(), ACTIVATION_SLOT);
+    let expected = ProcessingEnvironmentBuilder::deterministic()
+        .blockhash(cluster_blockhash())
+        .epoch_total_stake(STAKES.iter().sum())
+        .feature_set(Arc::new(feature_set))
+        .rent_collector(RentCollector {
+            epoch: EPOCH,
+            epoch_schedule: cluster_epoch_schedule(),
+            rent: cluster_rent(),
+            ..RentCollector::default()
+        });
+    assert_eq!(builder, expected);
+
+    let environment = builder.build();
+    assert_eq!(environment.blockhash_lamports_per_signature, 5000);
+    assert_eq!(environment.fee_lamports_per_signature, 5000);
+}
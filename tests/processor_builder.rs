@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use agave_feature_set::{migrate_address_lookup_table_program_to_core_bpf, FeatureSet};
+use solana_client_ext::*;
+use solana_program_runtime::declare_process_instruction;
+use solana_sdk::{
+    account::{AccountSharedData, ReadableAccount},
+    address_lookup_table,
+    instruction::Instruction,
+    message::Message,
+    native_loader,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_program,
+    transaction::Transaction,
+};
+use solana_svm::transaction_processing_callback::TransactionProcessingCallback;
+
+declare_process_instruction!(Noop, 100, |_invoke_context| Ok(()));
+
+#[test]
+fn configured_builtins_are_executed() {
+    let payer = Keypair::new();
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        payer.pubkey(),
+        AccountSharedData::new(LAMPORTS_PER_SOL, 0, &system_program::id()),
+    );
+    let noop_id = Pubkey::new_unique();
+    let tx = Transaction::new(
+        &[&payer],
+        Message::new(
+            &[Instruction::new_with_bytes(noop_id, &[], vec![])],
+            Some(&payer.pubkey()),
+        ),
+        Default::default(),
+    );
+
+    let results = RollUpChannel::new_offline(vec![], accounts.clone())
+        .process_rollup_transfers(&[tx.clone()]);
+    assert!(!results[0].is_success());
+
+    let config = RollUpChannelConfig {
+        builtins: vec![Builtin::new(noop_id, "noop", Noop::vm)],
+        ..RollUpChannelConfig::default()
+    };
+    let results =
+        RollUpChannel::with_account_source(vec![], AccountSource::Static(accounts), config)
+            .process_rollup_transfers(&[tx]);
+    assert!(results[0].is_success(), "{}", results[0].result);
+    assert_eq!(results[0].cu, 100);
+    assert_eq!(results[0].programs_invoked, [noop_id]);
+}
+
+#[test]
+fn default_builtins_can_be_left_out() {
+    let fork_graph = Arc::new(RwLock::new(ForkRollUpGraph::default()));
+    let noop_id = Pubkey::new_unique();
+
+    let loader = RollUpAccountLoader::with_source(AccountSource::Static(HashMap::new()));
+    ProcessorBuilder::new(Arc::clone(&fork_graph))
+        .slot(5)
+        .without_default_builtins()
+        .add_builtin(noop_id, "noop", Noop::vm)
+        .build(&loader);
+    let noop = loader.get_account_shared_data(&noop_id).unwrap();
+    assert!(noop.executable());
+    assert_eq!(noop.owner(), &native_loader::id());
+    assert!(loader
+        .get_account_shared_data(&system_program::id())
+        .is_none());
+
+    let loader = RollUpAccountLoader::with_source(AccountSource::Static(HashMap::new()));
+    ProcessorBuilder::new(fork_graph).build(&loader);
+    for builtin in Builtin::defaults() {
+        assert!(loader
+            .get_account_shared_data(&builtin.program_id)
+            .is_some());
+    }
+}
+
+#[test]
+fn runtime_environment_flags_are_applied() {
+    let fork_graph = Arc::new(RwLock::new(ForkRollUpGraph::default()));
+    let loader = RollUpAccountLoader::with_source(AccountSource::Static(HashMap::new()));
+
+    let processor = ProcessorBuilder::new(Arc::clone(&fork_graph)).build(&loader);
+    let program_cache = processor.program_cache.read().unwrap();
+    let config = program_cache.environments.program_runtime_v1.get_config();
+    assert!(!config.reject_broken_elfs);
+    assert!(!config.enable_instruction_tracing);
+    drop(program_cache);
+
+    let processor = ProcessorBuilder::new(fork_graph)
+        .reject_broken_elfs(true)
+        .debugging_features(true)
+        .build(&loader);
+    let program_cache = processor.program_cache.read().unwrap();
+    let config = program_cache.environments.program_runtime_v1.get_config();
+    assert!(config.reject_broken_elfs);
+    assert!(config.enable_instruction_tracing);
+}
+
+#[test]
+fn migrated_builtins_follow_the_feature_set() {
+    let fork_graph = Arc::new(RwLock::new(ForkRollUpGraph::default()));
+    let lookup_table_program = address_lookup_table::program::id();
+    let registered = |feature_set: Option<FeatureSet>| {
+        let loader = RollUpAccountLoader::with_source(AccountSource::Static(HashMap::new()));
+        let mut builder = ProcessorBuilder::new(Arc::clone(&fork_graph));
+        if let Some(feature_set) = feature_set {
+            builder = builder.feature_set(Arc::new(feature_set));
+        }
+        let processor = builder.build(&loader);
+        let builtin = processor
+            .builtin_program_ids
+            .read()
+            .unwrap()
+            .contains(&lookup_table_program);
+        assert_eq!(
+            builtin,
+            loader
+                .get_account_shared_data(&lookup_table_program)
+                .is_some()
+        );
+        builtin
+    };
+
+    // Every builtin is registered unless a feature set is given.
+    assert!(registered(None));
+
+    // Once migrated, the program is left to be loaded from its core BPF account.
+    assert!(!registered(Some(FeatureSet::all_enabled())));
+
+    let mut feature_set = FeatureSet::all_enabled();
+    feature_set.deactivate(&migrate_address_lookup_table_program_to_core_bpf::id());
+    assert!(registered(Some(feature_set)));
+}
@@ -0,0 +1,207 @@
+mod common;
+
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, RwLock};
+
+use agave_feature_set::FeatureSet;
+use common::{funded_channel, single_ix_tx, NOOP_PROGRAM};
+use solana_client_ext::*;
+use solana_sdk::{
+    account::{AccountSharedData, WritableAccount},
+    bpf_loader,
+    instruction::Instruction,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::Keypair,
+    signer::Signer,
+    system_program,
+    transaction::Transaction,
+};
+use solana_svm::transaction_processor::{
+    TransactionProcessingConfig, TransactionProcessingEnvironment,
+};
+
+/// Invokes each of `program_ids` in its own block, twice over, on a channel whose program
+/// cache is bounded by `limits`.
+fn invoke_twice(program_ids: &[Pubkey], limits: ProgramCacheLimits) -> ProgramCacheStats {
+    let payer = Keypair::new();
+    let rollup_c = funded_channel(
+        &payer.pubkey(),
+        RollUpChannelConfig {
+            program_cache_limits: limits,
+            ..RollUpChannelConfig::default()
+        },
+    );
+    for program_id in program_ids {
+        rollup_c
+            .add_program(*program_id, NOOP_PROGRAM, LoaderKind::BpfLoader)
+            .unwrap();
+    }
+
+    let blocks: Vec<Vec<Transaction>> = program_ids
+        .iter()
+        .chain(program_ids)
+        .enumerate()
+        .map(|(i, program_id)| {
+            // A distinct instruction per block, so no transaction is a duplicate.
+            let ix = Instruction::new_with_bytes(*program_id, &[i as u8], vec![]);
+            vec![single_ix_tx(&payer, ix)]
+        })
+        .collect();
+    for block in rollup_c.process_blocks(&blocks) {
+        assert!(block.results[0].is_success(), "{}", block.results[0].result);
+    }
+    rollup_c.program_cache_stats()
+}
+
+#[test]
+fn evicted_programs_are_recompiled_on_demand() {
+    let program_ids: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+
+    let unbounded = invoke_twice(&program_ids, ProgramCacheLimits::default());
+    assert_eq!(unbounded.entries, 4);
+    assert_eq!(unbounded.bytes, 4 * NOOP_PROGRAM.len() as u64);
+    assert_eq!(unbounded.misses, 4);
+    assert_eq!(unbounded.evictions, 0);
+
+    // Every program is invoked again after the others pushed it out.
+    let bounded = invoke_twice(
+        &program_ids,
+        ProgramCacheLimits {
+            max_entries: Some(2),
+            max_bytes: None,
+        },
+    );
+    assert_eq!(bounded.entries, 2);
+    assert!(bounded.evictions >= 2, "{:?}", bounded);
+    assert!(bounded.misses > unbounded.misses, "{:?}", bounded);
+
+    let by_size = invoke_twice(
+        &program_ids,
+        ProgramCacheLimits {
+            max_entries: None,
+            max_bytes: Some(NOOP_PROGRAM.len() as u64),
+        },
+    );
+    assert_eq!(by_size.entries, 1);
+    assert!(by_size.misses > unbounded.misses, "{:?}", by_size);
+}
+
+#[test]
+fn warmed_up_programs_are_not_compiled_again() {
+    let payer = Keypair::new();
+    let program_id = Pubkey::new_unique();
+    let not_a_program = Pubkey::new_unique();
+    let mut program = AccountSharedData::new(LAMPORTS_PER_SOL, 0, &bpf_loader::id());
+    program.set_data_from_slice(NOOP_PROGRAM);
+    program.set_executable(true);
+    let system_account = AccountSharedData::new(LAMPORTS_PER_SOL, 0, &system_program::id());
+    let loader = RollUpAccountLoader::with_source(AccountSource::Static(HashMap::from([
+        (payer.pubkey(), system_account.clone()),
+        (not_a_program, system_account),
+        (program_id, program),
+    ])));
+    let fork_graph = Arc::new(RwLock::new(ForkRollUpGraph::with_slots([1])));
+    let processor = ProcessorBuilder::new(Arc::clone(&fork_graph))
+        .slot(1)
+        .build(&loader);
+    populate_sysvars(&processor, &loader, &SysvarSource::Fetch);
+
+    let missing = Pubkey::new_unique();
+    let warm_ups = warm_up_programs(
+        &processor,
+        &loader,
+        &[program_id, system_program::id(), missing, not_a_program],
+    );
+    let results: Vec<_> = warm_ups.into_iter().map(|warm_up| warm_up.result).collect();
+    assert_eq!(
+        results,
+        [
+            Ok(()),
+            Ok(()),
+            Err(ProgramLoadError::NotFound {
+                program_id: missing
+            }),
+            Err(ProgramLoadError::NotAProgram {
+                program_id: not_a_program
+            }),
+        ]
+    );
+
+    // The batch finds the program compiled: the cache records no miss for it.
+    let misses = processor
+        .program_cache
+        .read()
+        .unwrap()
+        .stats
+        .misses
+        .load(Ordering::Relaxed);
+    let ix = Instruction::new_with_bytes(program_id, &[], vec![]);
+    let tx = single_ix_tx(&payer, ix);
+    let sanitized = sanitize_transactions(&[tx]);
+    let feature_set = Arc::new(FeatureSet::all_enabled());
+    let rent = Rent::default();
+    let check_results =
+        check_transactions(&sanitized, &loader, &CheckConfig::new(&rent, &feature_set));
+    let output = processor.load_and_execute_sanitized_transactions(
+        &loader,
+        &sanitized,
+        check_results,
+        &TransactionProcessingEnvironment {
+            feature_set,
+            ..TransactionProcessingEnvironment::default()
+        },
+        &TransactionProcessingConfig::default(),
+    );
+    let processed = output.processing_results[0].as_ref().unwrap();
+    assert!(processed.status().is_ok(), "{:?}", processed.status());
+    let program_cache = processor.program_cache.read().unwrap();
+    assert_eq!(program_cache.stats.misses.load(Ordering::Relaxed), misses);
+}
+
+#[test]
+fn channel_warm_up_spares_the_first_batch_compiling() {
+    let run = |warm_up: bool| {
+        let payer = Keypair::new();
+        let program_id = Pubkey::new_unique();
+        let rollup_c = funded_channel(&payer.pubkey(), RollUpChannelConfig::default());
+        rollup_c
+            .add_program(program_id, NOOP_PROGRAM, LoaderKind::BpfLoader)
+            .unwrap();
+        if warm_up {
+            let warm_ups = rollup_c.warm_up(&[program_id]);
+            assert_eq!(warm_ups[0].result, Ok(()));
+        }
+
+        let ix = Instruction::new_with_bytes(program_id, &[], vec![]);
+        let tx = single_ix_tx(&payer, ix);
+        let results = rollup_c.process_rollup_transfers(&[tx]);
+        assert!(results[0].is_success(), "{}", results[0].result);
+        rollup_c.program_cache_stats()
+    };
+
+    assert_eq!(run(true).misses, 0);
+    assert_eq!(run(false).misses, 1);
+}
+
+#[test]
+fn compiled_programs_carry_over_between_calls() {
+    let payer = Keypair::new();
+    let program_id = Pubkey::new_unique();
+    let rollup_c = funded_channel(&payer.pubkey(), RollUpChannelConfig::default());
+    rollup_c
+        .add_program(program_id, NOOP_PROGRAM, LoaderKind::BpfLoader)
+        .unwrap();
+
+    for i in 0..2u8 {
+        let ix = Instruction::new_with_bytes(program_id, &[i], vec![]);
+        let tx = single_ix_tx(&payer, ix);
+        let results = rollup_c.process_rollup_transfers(&[tx]);
+        assert!(results[0].is_success(), "{}", results[0].result);
+    }
+    let stats = rollup_c.program_cache_stats();
+    assert_eq!(stats.misses, 1);
+    assert_eq!(stats.entries, 1);
+}
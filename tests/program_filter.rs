@@ -0,0 +1,120 @@
+mod common;
+
+use std::collections::HashSet;
+
+use common::{funded_channel, single_ix_tx};
+use solana_client_ext::*;
+use solana_program_runtime::declare_process_instruction;
+use solana_sdk::{
+    compute_budget::ComputeBudgetInstruction,
+    instruction::{AccountMeta, Instruction},
+    message::Message,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_instruction, system_program,
+    transaction::{Transaction, TransactionError},
+};
+
+/// The mock program [`Caller`] invokes through CPI.
+const CALLEE: Pubkey = Pubkey::new_from_array([9; 32]);
+
+declare_process_instruction!(Callee, 1_000, |_invoke_context| { Ok(()) });
+
+declare_process_instruction!(Caller, 1_000, |invoke_context| {
+    let instruction = Instruction::new_with_bytes(CALLEE, &[], vec![]);
+    invoke_context.native_invoke(instruction.into(), &[])
+});
+
+/// A channel paying with `payer`, on which `caller` is a mock program invoking
+/// [`CALLEE`], filtering programs with `program_filter`.
+fn channel(
+    payer: &Pubkey,
+    caller: Pubkey,
+    program_filter: ProgramFilter,
+) -> RollUpChannel<'static> {
+    funded_channel(
+        payer,
+        RollUpChannelConfig {
+            builtins: vec![
+                Builtin::new(caller, "caller", Caller::vm),
+                Builtin::new(CALLEE, "callee", Callee::vm),
+            ],
+            program_filter,
+            ..RollUpChannelConfig::default()
+        },
+    )
+}
+
+#[test]
+fn denied_programs_are_not_executed() {
+    let payer = Keypair::new();
+    let transfer = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1);
+    let rollup_c = channel(
+        &payer.pubkey(),
+        Pubkey::new_unique(),
+        ProgramFilter::Deny(HashSet::from([system_program::id()])),
+    );
+
+    let results = rollup_c.process_rollup_transfers(&[single_ix_tx(&payer, transfer)]);
+    assert_eq!(results[0].status, TxStatus::NotProcessed);
+    assert_eq!(
+        results[0].error,
+        Some(TransactionError::InvalidProgramForExecution)
+    );
+    assert_eq!(results[0].denied_program, Some(system_program::id()));
+    assert!(
+        results[0].result.contains("denied by the program filter"),
+        "{}",
+        results[0].result
+    );
+    assert_eq!(results[0].fee_lamports, 0);
+}
+
+#[test]
+fn allowlists_refuse_every_other_program() {
+    let payer = Keypair::new();
+    let transfer = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1);
+    let rollup_c = channel(
+        &payer.pubkey(),
+        Pubkey::new_unique(),
+        ProgramFilter::Allow(HashSet::from([system_program::id()])),
+    );
+
+    let allowed = single_ix_tx(&payer, transfer.clone());
+    let results = rollup_c.process_rollup_transfers(&[allowed]);
+    assert!(results[0].is_success(), "{}", results[0].result);
+    assert_eq!(results[0].denied_program, None);
+
+    let limit = ComputeBudgetInstruction::set_compute_unit_limit(10_000);
+    let refused = Transaction::new(
+        &[&payer],
+        Message::new(&[limit, transfer], Some(&payer.pubkey())),
+        Default::default(),
+    );
+    let results = rollup_c.process_rollup_transfers(&[refused]);
+    assert_eq!(results[0].status, TxStatus::NotProcessed);
+    assert_eq!(
+        results[0].denied_program,
+        Some(solana_sdk::compute_budget::id())
+    );
+}
+
+#[test]
+fn denied_programs_invoked_through_cpi_are_flagged() {
+    let payer = Keypair::new();
+    let caller = Pubkey::new_unique();
+    let rollup_c = channel(
+        &payer.pubkey(),
+        caller,
+        ProgramFilter::Deny(HashSet::from([CALLEE])),
+    );
+
+    // The callee must be one of the caller's accounts for it to be invoked.
+    let call =
+        Instruction::new_with_bytes(caller, &[], vec![AccountMeta::new_readonly(CALLEE, false)]);
+    let results = rollup_c.process_rollup_transfers(&[single_ix_tx(&payer, call)]);
+    assert_eq!(results[0].status, TxStatus::ExecutedOk);
+    assert_eq!(results[0].programs_invoked, [caller, CALLEE]);
+    assert_eq!(results[0].denied_program, Some(CALLEE));
+}
@@ -0,0 +1,178 @@
+use std::sync::{Arc, Mutex};
+
+use base64::prelude::{Engine, BASE64_STANDARD};
+use serde_json::{json, Value};
+use solana_client::client_error::Result as ClientResult;
+use solana_client::rpc_client::{RpcClient, RpcClientConfig};
+use solana_client::rpc_request::RpcRequest;
+use solana_client::rpc_sender::{RpcSender, RpcTransportStats};
+use solana_client_ext::*;
+use solana_sdk::{
+    account::{create_account_shared_data_for_test, ReadableAccount},
+    epoch_schedule::EpochSchedule,
+    message::Message,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::Keypair,
+    signer::Signer,
+    system_instruction, system_program, sysvar,
+    transaction::{Transaction, TransactionError},
+};
+
+/// Rent on the fake cluster: twice the default price.
+fn cluster_rent() -> Rent {
+    Rent {
+        lamports_per_byte_year: Rent::default().lamports_per_byte_year * 2,
+        ..Rent::default()
+    }
+}
+
+/// Lamports of the fee payer: rent exempt under both rents, but not once it paid the fee
+/// under the cluster's.
+fn payer_lamports() -> u64 {
+    cluster_rent().minimum_balance(0) + 1_000
+}
+
+/// An RPC transport serving a cluster with [`cluster_rent`], a custom epoch schedule, and
+/// the `funded` system account holding [`payer_lamports`]. Every other account is
+/// missing.
+#[derive(Clone, Default)]
+struct RentSender {
+    requests: Arc<Mutex<Vec<String>>>,
+    funded: Option<Pubkey>,
+}
+
+impl RentSender {
+    fn account(&self, key: &Value) -> Value {
+        let key = key.as_str().unwrap();
+        let (lamports, data, owner) = if key == sysvar::rent::id().to_string() {
+            let rent = create_account_shared_data_for_test(&cluster_rent());
+            (rent.lamports(), rent.data().to_vec(), sysvar::id())
+        } else if Some(key) == self.funded.map(|pubkey| pubkey.to_string()).as_deref() {
+            (payer_lamports(), Vec::new(), system_program::id())
+        } else {
+            return Value::Null;
+        };
+        json!({
+            "lamports": lamports,
+            "data": [BASE64_STANDARD.encode(&data), "base64"],
+            "owner": owner.to_string(),
+            "executable": false,
+            "rentEpoch": 0,
+            "space": data.len(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl RpcSender for RentSender {
+    async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+        self.requests.lock().unwrap().push(request.to_string());
+        let value = match request {
+            RpcRequest::GetMultipleAccounts => params[0]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|key| self.account(key))
+                .collect(),
+            RpcRequest::GetAccountInfo => self.account(&params[0]),
+            RpcRequest::GetEpochSchedule => {
+                return Ok(serde_json::to_value(EpochSchedule::custom(64, 64, false)).unwrap())
+            }
+            RpcRequest::GetVersion => return Ok(json!({ "solana-core": "2.2.2" })),
+            _ => Value::Null,
+        };
+        Ok(json!({ "context": { "slot": 10 }, "value": value }))
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        RpcTransportStats::default()
+    }
+
+    fn url(&self) -> String {
+        "rent".to_string()
+    }
+}
+
+fn transfer(payer: &Keypair) -> Transaction {
+    let ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 0);
+    let message = Message::new(&[ix], Some(&payer.pubkey()));
+    Transaction::new(&[payer], message, Default::default())
+}
+
+#[test]
+fn pre_check_uses_the_cluster_rent() {
+    let payer = Keypair::new();
+    let sender = RentSender {
+        funded: Some(payer.pubkey()),
+        ..RentSender::default()
+    };
+    let rpc_client = RpcClient::new_sender(sender.clone(), RpcClientConfig::default());
+
+    // The default rent keeps the payer exempt.
+    let rollup_c = RollUpChannel::builder(AccountSource::Rpc(&rpc_client)).build();
+    let results = rollup_c.process_rollup_transfers(&[transfer(&payer)]);
+    assert!(results[0].is_success(), "{}", results[0].result);
+
+    let rollup_c = RollUpChannel::builder(AccountSource::Rpc(&rpc_client))
+        .config(RollUpChannelConfig {
+            rent_source: RentSource::FromCluster,
+            ..RollUpChannelConfig::default()
+        })
+        .build();
+    for _ in 0..2 {
+        let results = rollup_c.process_rollup_transfers(&[transfer(&payer)]);
+        assert_eq!(
+            results[0].error,
+            Some(TransactionError::InsufficientFundsForRent { account_index: 0 })
+        );
+    }
+
+    // The parameters are fetched once per channel.
+    let requests = sender.requests.lock().unwrap();
+    let epoch_schedule_requests = requests
+        .iter()
+        .filter(|request| *request == "getEpochSchedule")
+        .count();
+    assert_eq!(epoch_schedule_requests, 1);
+}
+
+#[test]
+fn explicit_rent_overrides_the_default() {
+    let payer = Keypair::new();
+    let rpc_client = RpcClient::new_sender(
+        RentSender {
+            funded: Some(payer.pubkey()),
+            ..RentSender::default()
+        },
+        RpcClientConfig::default(),
+    );
+    let rollup_c = RollUpChannel::builder(AccountSource::Rpc(&rpc_client))
+        .config(RollUpChannelConfig {
+            rent: cluster_rent(),
+            ..RollUpChannelConfig::default()
+        })
+        .build();
+    let results = rollup_c.process_rollup_transfers(&[transfer(&payer)]);
+    assert_eq!(
+        results[0].error,
+        Some(TransactionError::InsufficientFundsForRent { account_index: 0 })
+    );
+}
+
+#[test]
+fn cluster_rent_requires_an_rpc_source() {
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(Default::default()))
+        .config(RollUpChannelConfig {
+            rent_source: RentSource::FromCluster,
+            ..RollUpChannelConfig::default()
+        })
+        .build();
+    let results = rollup_c.process_rollup_transfers(&[transfer(&Keypair::new())]);
+    assert!(results[0].infrastructure_error);
+    assert!(
+        results[0].result.contains("RPC-backed"),
+        "{}",
+        results[0].result
+    );
+}
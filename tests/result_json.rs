@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use serde_json::Value;
+use solana_client_ext::*;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::{CompiledInstruction, InstructionError};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::TransactionError;
+
+/// Golden files: a failing change to them is a schema change, and should bump
+/// `RESULT_JSON_SCHEMA_VERSION` unless it only adds fields.
+const GOLDEN: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/json");
+
+fn golden(name: &str) -> Value {
+    let text = std::fs::read_to_string(format!("{}/{}", GOLDEN, name)).unwrap();
+    serde_json::from_str(&text).unwrap()
+}
+
+/// A failed result with every field set, to fixed values.
+fn failed_result() -> ReturnStruct {
+    let program_id = Pubkey::new_from_array([1; 32]);
+    let payer = Pubkey::new_from_array([8; 32]);
+    let error = TransactionError::InstructionError(1, InstructionError::Custom(6001));
+
+    let mut result = ReturnStruct::transaction_failure(
+        error.clone(),
+        format!("Transaction 0 failed with error: {}", error),
+    );
+    result.status = TxStatus::ExecutedErr;
+    result.signature = Some(Signature::from([7; 64]));
+    result.message_hash = Hash::new_from_array([2; 32]);
+    result.cu = 4213;
+    result.fee_lamports = 5000;
+    result.fee_charged = true;
+    result.decoded_error = Some(DecodedError {
+        instruction_index: 1,
+        program_id,
+        code: 6001,
+        program_name: Some("MyProgram".to_string()),
+        error_name: Some("SlippageExceeded".to_string()),
+    });
+    result.logs = Some(vec![
+        format!("Program {} invoke [1]", program_id),
+        format!(
+            "Program {} failed: custom program error: 0x1771",
+            program_id
+        ),
+    ]);
+    result.return_data = Some((program_id, vec![1, 2, 3]));
+    result.inner_instructions = Some(vec![InnerInstructionGroup {
+        index: 1,
+        instructions: vec![InvokedInstruction {
+            instruction: CompiledInstruction::new_from_raw_parts(2, vec![9, 9], vec![0, 1]),
+            stack_height: 2,
+        }],
+    }]);
+    result.programs_invoked = vec![program_id, Pubkey::new_from_array([6; 32])];
+    result.loaded_accounts_count = 4;
+    result.loaded_accounts_data_size = 1024;
+    result.pre_balances = vec![10_000_000, 0];
+    result.post_balances = vec![9_995_000, 0];
+    result.account_changes = vec![AccountChange {
+        pubkey: payer,
+        kind: AccountChangeKind::Modified,
+        old_lamports: 10_000_000,
+        new_lamports: 9_995_000,
+        old_data_len: 0,
+        new_data_len: 0,
+        old_owner: Pubkey::default(),
+        new_owner: Pubkey::default(),
+        old_data_hash: Hash::new_from_array([5; 32]),
+        new_data_hash: Hash::new_from_array([5; 32]),
+        old_data: Some(vec![]),
+        new_data: Some(vec![]),
+    }];
+    result.writable_accounts_modified = vec![payer];
+    result.requested_cu_limit = Some(200_000);
+    result.execution_time = Some(Duration::from_micros(1500));
+    result.context_slot = Some(42);
+    result.context = Some(SimulationContext {
+        slot: Some(42),
+        blockhash: Hash::new_from_array([3; 32]),
+        feature_set_hash: Hash::new_from_array([4; 32]),
+    });
+    result
+}
+
+#[test]
+fn result_json_matches_the_golden_file() {
+    let result = failed_result();
+    assert_eq!(result.to_json(), golden("failed_result.json"));
+
+    let pretty: Value = serde_json::from_str(&result.to_json_pretty()).unwrap();
+    assert_eq!(pretty, result.to_json());
+}
+
+#[test]
+fn results_json_matches_the_golden_file() {
+    let results = [ReturnStruct::success_with_fee(150, 5000)];
+    assert_eq!(results_to_json(&results), golden("results.json"));
+}
@@ -1,20 +1,255 @@
 use solana_client_ext::*;
 
 use crate::ReturnStruct;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::TransactionError;
+use std::time::Duration;
 
 #[test]
 fn test_return_struct() {
     // Test ReturnStruct helper methods
     let success_result = ReturnStruct::success(5000);
-    assert_eq!(success_result.success, true);
+    assert_eq!(success_result.is_success(), true);
+    assert_eq!(success_result.status, TxStatus::ExecutedOk);
     assert_eq!(success_result.cu, 5000);
+    assert_eq!(success_result.fee_lamports, 0);
+
+    assert_eq!(success_result.headroom(), None);
+    let mut limited = ReturnStruct::success(5000);
+    limited.requested_cu_limit = Some(12_000);
+    assert_eq!(limited.headroom(), Some(7000));
+    limited.cu = 15_000;
+    assert_eq!(limited.headroom(), Some(0));
+
+    let charged = ReturnStruct::success_with_fee(5000, 10_000);
+    assert!(charged.is_success());
+    assert_eq!(charged.cu, 5000);
+    assert_eq!(charged.fee_lamports, 10_000);
 
     let failure_result = ReturnStruct::failure("Test error message");
-    assert_eq!(failure_result.success, false);
+    assert_eq!(failure_result.is_success(), false);
+    assert_eq!(failure_result.status, TxStatus::NotProcessed);
+    assert!(!failure_result.status.was_charged());
     assert_eq!(failure_result.cu, 0);
     assert_eq!(failure_result.result, "Test error message");
+    assert_eq!(failure_result.error, None);
+
+    let typed_failure =
+        ReturnStruct::transaction_failure(TransactionError::AccountNotFound, "Test error message");
+    assert_eq!(typed_failure.is_success(), false);
+    assert_eq!(typed_failure.result, "Test error message");
+    assert_eq!(typed_failure.error, Some(TransactionError::AccountNotFound));
+
+    let mut returned = ReturnStruct::success(5000);
+    assert_eq!(returned.return_data_json(), None);
+    let program_id = Pubkey::new_unique();
+    returned.return_data = Some((program_id, vec![1, 2, 3]));
+    assert_eq!(
+        returned.return_data_json(),
+        Some(serde_json::json!({
+            "programId": program_id.to_string(),
+            "data": ["AQID", "base64"],
+        }))
+    );
 
     let no_results = ReturnStruct::no_results();
-    assert_eq!(no_results.success, false);
+    assert_eq!(no_results.is_success(), false);
     assert_eq!(no_results.result, "No transaction results returned");
 }
+
+#[test]
+fn test_batch_summary_loaded_totals() {
+    let mut first = ReturnStruct::success(1000);
+    first.loaded_accounts_count = 3;
+    first.loaded_accounts_data_size = 1024;
+    let mut second = ReturnStruct::failure("boom");
+    second.loaded_accounts_count = 1;
+    second.loaded_accounts_data_size = 128;
+
+    let summary = BatchSummary::from_results(&[first, second]);
+    assert_eq!(summary.loaded_accounts_count, 4);
+    assert_eq!(summary.loaded_accounts_data_size, 1152);
+
+    assert_eq!(BatchSummary::from_results(&[]), BatchSummary::default());
+}
+
+#[test]
+fn test_batch_summary_totals() {
+    let first = ReturnStruct::success_with_fee(1000, 5000);
+    let mut second = ReturnStruct::failure("boom");
+    second.cu = 300;
+    second.fee_lamports = 5000;
+    let mut third = ReturnStruct::success_with_fee(2000, 10_000);
+    third.execution_time = Some(Duration::from_millis(3));
+    let fourth = ReturnStruct::failure("never processed");
+
+    let summary = BatchSummary::from_results(&[first, second, third, fourth]);
+    assert_eq!(summary.transactions, 4);
+    assert_eq!(summary.succeeded, 2);
+    assert_eq!(summary.failed, 2);
+    assert_eq!(summary.failed_indexes, vec![1, 3]);
+    assert_eq!(summary.total_cu, 3300);
+    assert_eq!(summary.average_cu(), 825);
+    assert_eq!(summary.total_fees, 20_000);
+    assert_eq!(summary.execution_time, Some(Duration::from_millis(3)));
+
+    assert_eq!(BatchSummary::default().average_cu(), 0);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_return_struct_serde_round_trip() {
+    let mut result = ReturnStruct::transaction_failure(
+        TransactionError::InsufficientFundsForFee,
+        "Transaction 0 failed",
+    );
+    result.logs = Some(vec!["Program log: hello".to_string()]);
+    result.return_data = Some((Pubkey::new_unique(), vec![1, 2, 3]));
+    result.trace = Some(ExecutionTrace::default());
+    result.requested_cu_limit = Some(200_000);
+
+    let json = serde_json::to_string(&result).unwrap();
+    assert_eq!(serde_json::from_str::<ReturnStruct>(&json).unwrap(), result);
+
+    let summary = BatchSummary::from_results(&[result]);
+    let json = serde_json::to_string(&summary).unwrap();
+    assert_eq!(
+        serde_json::from_str::<BatchSummary>(&json).unwrap(),
+        summary
+    );
+}
+
+#[test]
+fn test_return_struct_display() {
+    let succeeded = ReturnStruct::success_with_fee(4213, 5000);
+    assert_eq!(succeeded.to_string(), "ok, 4,213 CU, fee 5,000 lamports");
+
+    let mut failed = ReturnStruct::transaction_failure(
+        TransactionError::InsufficientFundsForFee,
+        "Transaction 0 failed: Insufficient funds for fee",
+    );
+    failed.fee_lamports = 1_005_000;
+    failed.logs = Some((1..=7).map(|line| format!("line {}", line)).collect());
+    assert_eq!(
+        failed.to_string(),
+        "failed, 0 CU, fee 1,005,000 lamports\n  \
+         Transaction 0 failed: Insufficient funds for fee\n  \
+         error: InsufficientFundsForFee\n  \
+         logs (last 5 of 7):\n    line 3\n    line 4\n    line 5\n    line 6\n    line 7"
+    );
+    assert!(format!("{:#}", failed).ends_with(
+        "logs:\n    line 1\n    line 2\n    line 3\n    line 4\n    line 5\n    line 6\n    line 7"
+    ));
+}
+
+#[test]
+fn test_return_struct_render_failure() {
+    use solana_sdk::instruction::InstructionError;
+
+    assert_eq!(ReturnStruct::success(150).render_failure(), None);
+
+    let mut failed = ReturnStruct::transaction_failure(
+        TransactionError::InstructionError(1, InstructionError::Custom(1)),
+        "Transaction 0 failed with error: custom program error: 0x1",
+    );
+    assert_eq!(
+        failed.render_failure().unwrap(),
+        "Transaction 0 failed with error: custom program error: 0x1\n\
+         error: InstructionError(1, Custom(1))"
+    );
+
+    failed.logs = Some(
+        [
+            "Program ComputeBudget111111111111111111111111111111 invoke [1]",
+            "Program ComputeBudget111111111111111111111111111111 success",
+            "Program 11111111111111111111111111111111 invoke [1]",
+            "Transfer: insufficient lamports 1000, need 2000",
+            "Program 11111111111111111111111111111111 failed: custom program error: 0x1",
+        ]
+        .map(String::from)
+        .to_vec(),
+    );
+    let rendered = "Transaction 0 failed with error: custom program error: 0x1\n\
+                    error: InstructionError(1, Custom(1))\n\
+                    instructions:\n  \
+                    #0 ComputeBudget111111111111111111111111111111 success\n\
+                    > #1 11111111111111111111111111111111 failed: custom program error: 0x1\n\
+                    >     Transfer: insufficient lamports 1000, need 2000";
+    assert_eq!(failed.render_failure().unwrap(), rendered);
+    assert!(format!("{:#}", failed).ends_with(&rendered.replace('\n', "\n  ")));
+}
+
+#[test]
+fn test_return_struct_to_rpc_simulation_result() {
+    let mut succeeded = ReturnStruct::success(4213);
+    succeeded.logs = Some(vec!["Program log: hello".to_string()]);
+    succeeded.return_data = Some((Pubkey::new_unique(), vec![1, 2, 3]));
+    succeeded.inner_instructions = Some(vec![]);
+
+    let simulated = succeeded.to_rpc_simulation_result().unwrap();
+    assert_eq!(simulated.err, None);
+    assert_eq!(simulated.units_consumed, Some(4213));
+    assert_eq!(simulated.logs, succeeded.logs);
+    assert_eq!(simulated.accounts, None);
+    assert_eq!(simulated.inner_instructions, Some(vec![]));
+    let json = serde_json::to_value(&simulated).unwrap();
+    assert_eq!(
+        Some(json["returnData"].clone()),
+        succeeded.return_data_json()
+    );
+
+    let failed = ReturnStruct::from_processing_error(0, &TransactionError::AccountNotFound);
+    let simulated = failed.to_rpc_simulation_result().unwrap();
+    assert_eq!(simulated.err, Some(TransactionError::AccountNotFound));
+    assert_eq!(simulated.logs, None);
+
+    // A middleware rejection has no runtime equivalent.
+    assert!(ReturnStruct::failure("rejected by middleware")
+        .to_rpc_simulation_result()
+        .is_none());
+}
+
+#[test]
+fn test_return_struct_from_fees_only_transaction() {
+    use solana_sdk::account::AccountSharedData;
+    use solana_sdk::fee::FeeDetails;
+    use solana_svm::rollback_accounts::RollbackAccounts;
+    use solana_svm::transaction_processing_result::{FeesOnlyTransaction, ProcessedTransaction};
+
+    let processed = ProcessedTransaction::FeesOnly(Box::new(FeesOnlyTransaction {
+        load_error: TransactionError::ProgramAccountNotFound,
+        rollback_accounts: RollbackAccounts::FeePayerOnly {
+            fee_payer_account: AccountSharedData::default(),
+        },
+        fee_details: FeeDetails::new(5000, 1000),
+    }));
+
+    let result =
+        ReturnStruct::from_processed_transaction(3, &processed, ConversionOptions::default());
+    assert!(!result.is_success());
+    assert_eq!(result.status, TxStatus::FeesOnly);
+    assert!(result.status.was_charged());
+    assert_eq!(result.error, Some(TransactionError::ProgramAccountNotFound));
+    assert_eq!(result.fee_lamports, 6000);
+    assert_eq!(result.cu, 0);
+    assert_eq!(result.loaded_accounts_count, 0);
+    assert_eq!(result.logs, None);
+    assert_eq!(
+        result.result,
+        "Transaction 3 failed with error: Attempt to load a program that does not exist. \
+         Only fees were charged."
+    );
+}
+
+#[test]
+fn test_return_struct_from_processing_error() {
+    let result = ReturnStruct::from_processing_error(1, &TransactionError::AlreadyProcessed);
+    assert!(!result.is_success());
+    assert_eq!(result.status, TxStatus::NotProcessed);
+    assert_eq!(result.error, Some(TransactionError::AlreadyProcessed));
+    assert_eq!(result.fee_lamports, 0);
+    assert_eq!(
+        result.result,
+        "Transaction 1 failed: This transaction has already been processed"
+    );
+}
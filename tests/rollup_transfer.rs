@@ -31,7 +31,10 @@ fn cu() {
     for (i, result) in results.iter().enumerate() {
         println!(
             "Transaction {}: Success={}, CU={}, Result: {}",
-            i, result.success, result.cu, result.result
+            i,
+            result.is_success(),
+            result.cu,
+            result.result
         );
     }
 
@@ -84,11 +87,14 @@ fn test_failed_transaction() {
     for (i, result) in results.iter().enumerate() {
         println!(
             "Transaction {}: Success={}, CU={}, Result: {}",
-            i, result.success, result.cu, result.result
+            i,
+            result.is_success(),
+            result.cu,
+            result.result
         );
 
         // Verify that the transaction failed
-        assert!(!result.success, "Transaction should have failed");
+        assert!(!result.is_success(), "Transaction should have failed");
 
         // The error message should contain information about the failure
         assert!(
@@ -0,0 +1,582 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use base64::prelude::{Engine, BASE64_STANDARD};
+use serde_json::{json, Value};
+use solana_client::client_error::Result as ClientResult;
+use solana_client::rpc_client::{RpcClient, RpcClientConfig};
+use solana_client::rpc_filter::RpcFilterType;
+use solana_client::rpc_request::{RpcError, RpcRequest, RpcResponseErrorData};
+use solana_client::rpc_sender::{RpcSender, RpcTransportStats};
+use solana_client_ext::*;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::message::Message;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Keypair;
+use solana_sdk::signer::Signer;
+use solana_sdk::system_instruction;
+use solana_sdk::system_program;
+use solana_sdk::transaction::{SanitizedTransaction, Transaction};
+use solana_svm::transaction_processing_callback::TransactionProcessingCallback;
+
+/// An RPC transport that records every request and reports every account as missing,
+/// except the `existing` ones: system accounts holding `lamports`, at least 1, and
+/// `data_len` zeroed bytes.
+///
+/// The first request is answered at slot 10 and later ones at their `minContextSlot`, or
+/// slot 9 without one. A lagging sender fails every request asking for a minimum slot,
+/// and the first `throttled` requests are rate-limited. A sender rejecting zstd fails
+/// every request asking for base64+zstd data. `getProgramAccounts` returns every
+/// existing account, unless the sender refuses it.
+#[derive(Clone, Default)]
+struct RecordingSender {
+    requests: Arc<Mutex<Vec<(String, Value)>>>,
+    lagging: bool,
+    throttled: usize,
+    rejects_zstd: bool,
+    refuses_program_accounts: bool,
+    existing: Vec<Pubkey>,
+    lamports: u64,
+    data_len: usize,
+}
+
+impl RecordingSender {
+    fn account(&self, key: &Value) -> Value {
+        let existing = self
+            .existing
+            .iter()
+            .any(|pubkey| key.as_str() == Some(pubkey.to_string().as_str()));
+        if !existing {
+            return Value::Null;
+        }
+        json!({
+            "lamports": self.lamports.max(1),
+            "data": [BASE64_STANDARD.encode(vec![0; self.data_len]), "base64"],
+            "owner": system_program::id().to_string(),
+            "executable": false,
+            "rentEpoch": 0,
+            "space": self.data_len,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl RpcSender for RecordingSender {
+    async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+        let slot = {
+            let mut requests = self.requests.lock().unwrap();
+            requests.push((request.to_string(), params.clone()));
+            if requests.len() <= self.throttled {
+                return Err(RpcError::RpcResponseError {
+                    code: 429,
+                    message: "Too many requests".to_string(),
+                    data: RpcResponseErrorData::Empty,
+                }
+                .into());
+            }
+            match params[1]["minContextSlot"].as_u64() {
+                _ if requests.len() == 1 => 10,
+                Some(min_context_slot) => min_context_slot,
+                None => 9,
+            }
+        };
+        if self.rejects_zstd && params[1]["encoding"] == "base64+zstd" {
+            return Err(RpcError::RpcResponseError {
+                code: -32602,
+                message: "Invalid params: unsupported encoding: base64+zstd".to_string(),
+                data: RpcResponseErrorData::Empty,
+            }
+            .into());
+        }
+        if self.refuses_program_accounts && request == RpcRequest::GetProgramAccounts {
+            return Err(RpcError::RpcResponseError {
+                code: -32010,
+                message: "excluded from account secondary indexes".to_string(),
+                data: RpcResponseErrorData::Empty,
+            }
+            .into());
+        }
+        if self.lagging && !params[1]["minContextSlot"].is_null() {
+            return Err(RpcError::RpcResponseError {
+                code: -32016,
+                message: "Minimum context slot has not been reached".to_string(),
+                data: RpcResponseErrorData::Empty,
+            }
+            .into());
+        }
+        let value = match request {
+            RpcRequest::GetMultipleAccounts => params[0]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|key| self.account(key))
+                .collect(),
+            RpcRequest::GetAccountInfo => self.account(&params[0]),
+            RpcRequest::GetProgramAccounts => self
+                .existing
+                .iter()
+                .map(|pubkey| {
+                    json!({
+                        "pubkey": pubkey.to_string(),
+                        "account": self.account(&json!(pubkey.to_string())),
+                    })
+                })
+                .collect(),
+            RpcRequest::GetVersion => return Ok(json!({ "solana-core": "2.2.2" })),
+            _ => Value::Null,
+        };
+        Ok(json!({ "context": { "slot": slot }, "value": value }))
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        RpcTransportStats::default()
+    }
+
+    fn url(&self) -> String {
+        "recording".to_string()
+    }
+}
+
+#[test]
+fn loader_commitment_flows_into_requests() {
+    let sender = RecordingSender::default();
+    let rpc_client = RpcClient::new_sender(
+        sender.clone(),
+        RpcClientConfig::with_commitment(CommitmentConfig::confirmed()),
+    );
+
+    let key = Pubkey::new_unique();
+    let loader =
+        RollUpAccountLoader::new(&rpc_client).with_commitment(CommitmentConfig::finalized());
+    assert!(loader.prefetch(&[key]).missing.contains(&key));
+    assert_eq!(loader.load(&key), Ok(None));
+
+    let requests = sender.requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].0, "getMultipleAccounts");
+    assert_eq!(requests[1].0, "getAccountInfo");
+    for (_, params) in requests.iter() {
+        assert_eq!(params[1]["commitment"], "finalized");
+    }
+}
+
+fn unique_keys(count: usize) -> Vec<Pubkey> {
+    (0..count).map(|_| Pubkey::new_unique()).collect()
+}
+
+#[test]
+fn prefetch_reads_later_requests_at_first_slot() {
+    let sender = RecordingSender::default();
+    let rpc_client = RpcClient::new_sender(sender.clone(), RpcClientConfig::default());
+
+    let report = RollUpAccountLoader::new(&rpc_client).prefetch(&unique_keys(150));
+    assert_eq!(report.missing.len(), 150);
+
+    let requests = sender.requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert!(requests[0].1[1]["minContextSlot"].is_null());
+    assert_eq!(requests[1].1[1]["minContextSlot"], 10);
+    assert_eq!(report.context_slot, Some(10));
+}
+
+#[test]
+fn strict_prefetch_fails_keys_the_node_is_behind_on() {
+    let sender = RecordingSender {
+        lagging: true,
+        ..RecordingSender::default()
+    };
+    let rpc_client = RpcClient::new_sender(sender.clone(), RpcClientConfig::default());
+    let loader = RollUpAccountLoader::new(&rpc_client).with_slot_consistency(SlotConsistency {
+        deadline: Duration::ZERO,
+        strict: true,
+    });
+
+    let keys = unique_keys(150);
+    let report = loader.prefetch(&keys);
+    assert_eq!(report.missing, keys[..100]);
+    assert_eq!(report.failed.len(), 50);
+    assert_eq!(
+        report.failed[0],
+        AccountLoadError::Inconsistent {
+            pubkey: keys[100],
+            slot: 10
+        }
+    );
+    assert_eq!(report.context_slot, Some(10));
+    assert_eq!(loader.stats().rpc_errors, 1);
+}
+
+#[test]
+fn best_effort_prefetch_gives_up_on_consistency() {
+    let sender = RecordingSender {
+        lagging: true,
+        ..RecordingSender::default()
+    };
+    let rpc_client = RpcClient::new_sender(sender.clone(), RpcClientConfig::default());
+    let loader = RollUpAccountLoader::new(&rpc_client).with_slot_consistency(SlotConsistency {
+        deadline: Duration::ZERO,
+        strict: false,
+    });
+
+    let report = loader.prefetch(&unique_keys(150));
+    assert_eq!(report.missing.len(), 150);
+    assert!(report.failed.is_empty());
+    // The last request was answered at slot 9, behind the first one.
+    assert_eq!(report.context_slot, None);
+
+    let requests = sender.requests.lock().unwrap();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[1].1[1]["minContextSlot"], 10);
+    assert!(requests[2].1[1]["minContextSlot"].is_null());
+}
+
+#[test]
+fn data_slicing_fetches_readonly_accounts_without_data() {
+    let sender = RecordingSender::default();
+    let rpc_client = RpcClient::new_sender(sender.clone(), RpcClientConfig::default());
+    let loader = RollUpAccountLoader::new(&rpc_client).with_data_slicing(true);
+
+    let payer = Pubkey::new_unique();
+    let program_id = Pubkey::new_unique();
+    let readonly = Pubkey::new_unique();
+    let needed = Pubkey::new_unique();
+    loader.require_full_data(needed);
+    let instruction = Instruction::new_with_bytes(
+        program_id,
+        &[],
+        vec![
+            AccountMeta::new_readonly(readonly, false),
+            AccountMeta::new_readonly(needed, false),
+        ],
+    );
+    let transaction = SanitizedTransaction::from_transaction_for_tests(
+        Transaction::new_with_payer(&[instruction], Some(&payer)),
+    );
+    assert_eq!(
+        loader.prefetch_transactions(&[transaction]).missing.len(),
+        4
+    );
+
+    let requests = sender.requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].1[0], json!([readonly.to_string()]));
+    assert_eq!(
+        requests[0].1[1]["dataSlice"],
+        json!({ "offset": 0, "length": 0 })
+    );
+    let full: Vec<&str> = requests[1].1[0]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|key| key.as_str().unwrap())
+        .collect();
+    assert_eq!(full.len(), 3);
+    for key in [payer, program_id, needed] {
+        assert!(full.contains(&key.to_string().as_str()));
+    }
+    assert!(requests[1].1[1]["dataSlice"].is_null());
+}
+
+#[test]
+fn owner_checks_are_answered_from_the_cache() {
+    let existing = Pubkey::new_unique();
+    let sender = RecordingSender {
+        existing: vec![existing],
+        ..RecordingSender::default()
+    };
+    let rpc_client = RpcClient::new_sender(sender.clone(), RpcClientConfig::default());
+    let loader = RollUpAccountLoader::new(&rpc_client);
+
+    let owners = [Pubkey::new_unique(), system_program::id()];
+    assert_eq!(loader.account_matches_owners(&existing, &owners), Some(1));
+    assert_eq!(loader.account_matches_owners(&existing, &owners), Some(1));
+    assert!(loader.get_account_shared_data(&existing).is_some());
+    assert_eq!(sender.requests.lock().unwrap().len(), 1);
+
+    let prefetched = Pubkey::new_unique();
+    let sender = RecordingSender {
+        existing: vec![prefetched],
+        ..RecordingSender::default()
+    };
+    let rpc_client = RpcClient::new_sender(sender.clone(), RpcClientConfig::default());
+    let loader = RollUpAccountLoader::new(&rpc_client);
+    loader.prefetch(&[prefetched]);
+    assert_eq!(loader.account_matches_owners(&prefetched, &owners), Some(1));
+    assert_eq!(sender.requests.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn throttled_requests_are_retried_after_a_backoff() {
+    let sender = RecordingSender {
+        throttled: 2,
+        ..RecordingSender::default()
+    };
+    let rpc_client = RpcClient::new_sender(sender.clone(), RpcClientConfig::default());
+    let limits = RequestLimits {
+        throttle_backoff: Duration::from_millis(1),
+        ..RequestLimits::default()
+    };
+    let loader = RollUpAccountLoader::new(&rpc_client).with_request_limits(limits);
+
+    assert_eq!(loader.load(&Pubkey::new_unique()), Ok(None));
+    assert_eq!(sender.requests.lock().unwrap().len(), 3);
+    assert_eq!(loader.stats().throttled, 2);
+    assert_eq!(loader.stats().rpc_errors, 0);
+
+    // Out of retries, the request fails like any other.
+    let sender = RecordingSender {
+        throttled: 2,
+        ..RecordingSender::default()
+    };
+    let rpc_client = RpcClient::new_sender(sender.clone(), RpcClientConfig::default());
+    let loader = RollUpAccountLoader::new(&rpc_client).with_request_limits(RequestLimits {
+        max_throttle_retries: 1,
+        ..limits
+    });
+    assert!(matches!(
+        loader.load(&Pubkey::new_unique()),
+        Err(AccountLoadError::Transport { .. })
+    ));
+    assert_eq!(loader.stats().throttled, 2);
+}
+
+#[test]
+fn requests_are_paced() {
+    let sender = RecordingSender::default();
+    let rpc_client = RpcClient::new_sender(sender.clone(), RpcClientConfig::default());
+    let loader = RollUpAccountLoader::new(&rpc_client).with_request_limits(RequestLimits {
+        requests_per_second: Some(20),
+        ..RequestLimits::default()
+    });
+
+    let started = Instant::now();
+    for _ in 0..3 {
+        loader.load(&Pubkey::new_unique()).unwrap();
+    }
+    assert!(started.elapsed() >= Duration::from_millis(100));
+}
+
+#[test]
+fn zstd_is_only_requested_where_it_pays_off() {
+    let encodings = |data_len| {
+        let existing = Pubkey::new_unique();
+        let sender = RecordingSender {
+            existing: vec![existing],
+            data_len,
+            ..RecordingSender::default()
+        };
+        let rpc_client = RpcClient::new_sender(sender.clone(), RpcClientConfig::default());
+        let loader = RollUpAccountLoader::new(&rpc_client).with_ttl(Duration::ZERO);
+        assert!(loader.load(&existing).unwrap().is_some());
+        assert!(loader.load(&existing).unwrap().is_some());
+        let encodings: Vec<Value> = sender
+            .requests
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(_, params)| params[1]["encoding"].clone())
+            .collect();
+        encodings
+    };
+
+    // Never fetched, an account is assumed small; once known to be large, it's compressed.
+    assert_eq!(encodings(16), [json!("base64"), json!("base64")]);
+    assert_eq!(encodings(4096), [json!("base64"), json!("base64+zstd")]);
+
+    let sender = RecordingSender::default();
+    let rpc_client = RpcClient::new_sender(sender.clone(), RpcClientConfig::default());
+    let loader = RollUpAccountLoader::new(&rpc_client).with_data_encoding(DataEncoding::Base64);
+    loader.prefetch(&unique_keys(2));
+    assert_eq!(
+        sender.requests.lock().unwrap()[0].1[1]["encoding"],
+        "base64"
+    );
+}
+
+#[test]
+fn nodes_without_zstd_are_asked_for_base64() {
+    let sender = RecordingSender {
+        rejects_zstd: true,
+        ..RecordingSender::default()
+    };
+    let rpc_client = RpcClient::new_sender(sender.clone(), RpcClientConfig::default());
+    let loader = RollUpAccountLoader::new(&rpc_client).with_data_encoding(DataEncoding::Base64Zstd);
+
+    assert_eq!(loader.load(&Pubkey::new_unique()), Ok(None));
+    assert!(loader.prefetch(&unique_keys(2)).failed.is_empty());
+    let encodings: Vec<Value> = sender
+        .requests
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(_, params)| params[1]["encoding"].clone())
+        .collect();
+    assert_eq!(
+        encodings,
+        [json!("base64+zstd"), json!("base64"), json!("base64")]
+    );
+    assert_eq!(loader.stats().rpc_errors, 0);
+    assert_eq!(loader.stats().compression_saved_bytes, 0);
+}
+
+#[test]
+fn program_accounts_are_prefetched_in_one_request() {
+    let existing = unique_keys(2);
+    let sender = RecordingSender {
+        existing: existing.clone(),
+        ..RecordingSender::default()
+    };
+    let rpc_client = RpcClient::new_sender(sender.clone(), RpcClientConfig::default());
+    let loader = RollUpAccountLoader::new(&rpc_client);
+
+    let program_id = system_program::id();
+    let fetched = loader
+        .prefetch_program_accounts(&program_id, vec![RpcFilterType::DataSize(0)])
+        .unwrap();
+    assert_eq!(fetched, existing);
+    for pubkey in &existing {
+        assert!(loader.load(pubkey).unwrap().is_some());
+    }
+
+    let requests = sender.requests.lock().unwrap();
+    let queries: Vec<&Value> = requests
+        .iter()
+        .filter(|(method, _)| method == "getProgramAccounts")
+        .map(|(_, params)| params)
+        .collect();
+    assert_eq!(queries.len(), 1);
+    assert_eq!(queries[0][0], program_id.to_string());
+    assert_eq!(queries[0][1]["filters"], json!([{ "dataSize": 0 }]));
+    assert!(requests
+        .iter()
+        .all(|(method, _)| method != "getAccountInfo"));
+}
+
+#[test]
+fn oversized_or_refused_program_queries_cache_nothing() {
+    let existing = unique_keys(3);
+    let sender = RecordingSender {
+        existing: existing.clone(),
+        ..RecordingSender::default()
+    };
+    let rpc_client = RpcClient::new_sender(sender, RpcClientConfig::default());
+    let loader = RollUpAccountLoader::new(&rpc_client).with_max_program_accounts(2);
+    let program_id = system_program::id();
+    assert_eq!(
+        loader.prefetch_program_accounts(&program_id, Vec::new()),
+        Err(ProgramAccountsError::TooManyAccounts {
+            program_id,
+            count: 3,
+            limit: 2,
+        })
+    );
+    assert!(loader.cache().is_empty());
+
+    let sender = RecordingSender {
+        existing,
+        refuses_program_accounts: true,
+        ..RecordingSender::default()
+    };
+    let rpc_client = RpcClient::new_sender(sender, RpcClientConfig::default());
+    let loader = RollUpAccountLoader::new(&rpc_client);
+    let err = loader
+        .prefetch_program_accounts(&program_id, Vec::new())
+        .unwrap_err();
+    assert!(matches!(err, ProgramAccountsError::Refused { .. }));
+    assert!(err.to_string().contains("secondary indexes"));
+    assert!(loader.cache().is_empty());
+    assert_eq!(loader.stats().rpc_errors, 1);
+
+    let loader = RollUpAccountLoader::with_source(AccountSource::Static(Default::default()));
+    assert_eq!(
+        loader.prefetch_program_accounts(&program_id, Vec::new()),
+        Err(ProgramAccountsError::Unsupported(program_id))
+    );
+}
+
+#[test]
+fn optimizing_refuses_a_present_limit_below_the_estimate() {
+    let payer = Keypair::new();
+    let sender = RecordingSender {
+        existing: vec![payer.pubkey()],
+        lamports: 10 * LAMPORTS_PER_SOL,
+        ..RecordingSender::default()
+    };
+    let rpc_client = RpcClient::new_sender(sender, RpcClientConfig::default());
+    let recipient = Pubkey::new_unique();
+    let transfer = |limit| {
+        Transaction::new_unsigned(Message::new(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(limit),
+                system_instruction::transfer(&payer.pubkey(), &recipient, LAMPORTS_PER_SOL),
+            ],
+            Some(&payer.pubkey()),
+        ))
+    };
+
+    // The estimate is made without the limit, which would otherwise truncate it.
+    let mut tx = transfer(100);
+    let err = rpc_client
+        .optimize_compute_units_unsigned_tx(&mut tx, &[&payer])
+        .unwrap_err();
+    match err.downcast_ref::<SolanaClientExtError>() {
+        Some(SolanaClientExtError::ComputeUnitLimitTooLow { limit, estimate }) => {
+            assert_eq!(*limit, 100);
+            assert!(*estimate > 100, "{}", estimate);
+        }
+        _ => panic!("unexpected error: {}", err),
+    }
+    assert_eq!(tx, transfer(100));
+
+    // A sufficient limit is replaced by the buffered estimate, not duplicated.
+    let mut tx = transfer(100_000);
+    let estimate = rpc_client
+        .optimize_compute_units_unsigned_tx(&mut tx, &[&payer])
+        .unwrap();
+    assert_eq!(tx.message.instructions.len(), 2);
+    assert_eq!(
+        tx.message.instructions[0].data,
+        ComputeBudgetInstruction::set_compute_unit_limit(2 * estimate).data
+    );
+}
+
+#[test]
+fn optimizing_an_invalid_message_fails_instead_of_panicking() {
+    let payer = Keypair::new();
+    let rpc_client = RpcClient::new_mock_with_mocks(
+        "succeeds".to_string(),
+        HashMap::from([(
+            RpcRequest::SimulateTransaction,
+            json!({
+                "context": { "slot": 1 },
+                "value": { "err": null, "logs": null, "accounts": null, "unitsConsumed": 1000 },
+            }),
+        )]),
+    );
+    let mut message = Message::new(
+        &[system_instruction::transfer(
+            &payer.pubkey(),
+            &Pubkey::new_unique(),
+            1,
+        )],
+        Some(&payer.pubkey()),
+    );
+    // The program id points past the account keys.
+    message.instructions[0].program_id_index = 9;
+
+    let err = rpc_client
+        .optimize_compute_units_msg(&mut message, &[&payer])
+        .unwrap_err();
+    assert!(
+        matches!(
+            err.downcast_ref::<SolanaClientExtError>(),
+            Some(SolanaClientExtError::ComputeUnitsError(_))
+        ),
+        "unexpected error: {}",
+        err
+    );
+}
@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+
+use solana_client_ext::*;
+
+use solana_sdk::{
+    account::{AccountSharedData, ReadableAccount, WritableAccount},
+    message::Message,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_instruction, system_program,
+    transaction::Transaction,
+};
+use solana_svm::transaction_processing_callback::TransactionProcessingCallback;
+
+#[test]
+fn snapshot_accounts_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let pubkey = Pubkey::new_unique();
+    let mut account = AccountSharedData::new(42, 3, &Pubkey::new_unique());
+    account.set_data_from_slice(&[1, 2, 3]);
+    account.set_executable(true);
+    account.set_rent_epoch(7);
+    SnapshotAccounts::write_account(dir.path(), &pubkey, &account).unwrap();
+
+    let snapshot = SnapshotAccounts::new(dir.path(), NonZeroUsize::new(1).unwrap());
+    assert_eq!(snapshot.get(&pubkey).unwrap(), Some(account.clone()));
+    assert_eq!(snapshot.get(&Pubkey::new_unique()).unwrap(), None);
+
+    // Evicted from the LRU by the lookup above, then read from disk again.
+    assert_eq!(snapshot.get(&pubkey).unwrap().unwrap().rent_epoch(), 7);
+}
+
+#[test]
+fn snapshot_source_falls_back_for_missing_keys() {
+    let dir = tempfile::tempdir().unwrap();
+    let payer = Keypair::new();
+    let recipient = Keypair::new();
+    SnapshotAccounts::write_account(
+        dir.path(),
+        &payer.pubkey(),
+        &AccountSharedData::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id()),
+    )
+    .unwrap();
+
+    // The recipient isn't on disk, only in the fallback.
+    let mut fallback = HashMap::new();
+    fallback.insert(
+        recipient.pubkey(),
+        AccountSharedData::new(LAMPORTS_PER_SOL, 0, &system_program::id()),
+    );
+    let source = AccountSource::Snapshot(SnapshotAccounts::new(
+        dir.path(),
+        NonZeroUsize::new(16).unwrap(),
+    ))
+    .with_fallback(AccountSource::Static(fallback));
+
+    let ix = system_instruction::transfer(&payer.pubkey(), &recipient.pubkey(), LAMPORTS_PER_SOL);
+    let tx = Transaction::new(
+        &[&payer],
+        Message::new(&[ix], Some(&payer.pubkey())),
+        Default::default(),
+    );
+
+    let rollup_c = RollUpChannel::builder(source).build();
+    let blocks = rollup_c.process_blocks(&[vec![tx]]);
+    assert!(
+        blocks[0].results[0].is_success(),
+        "{}",
+        blocks[0].results[0].result
+    );
+    assert_eq!(
+        rollup_c
+            .account_loader()
+            .get_account_shared_data(&recipient.pubkey())
+            .unwrap()
+            .lamports(),
+        2 * LAMPORTS_PER_SOL
+    );
+}
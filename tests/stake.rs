@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use solana_client_ext::*;
+use solana_sdk::{
+    account::{AccountSharedData, WritableAccount},
+    account_utils::StateMut,
+    clock::Clock,
+    message::Message,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::Keypair,
+    signer::Signer,
+    stake::{
+        self,
+        state::{Authorized, Lockup, StakeStateV2},
+    },
+    system_program,
+    transaction::Transaction,
+    vote::state::{VoteInit, VoteState, VoteStateVersions},
+};
+
+/// A vote account of a freshly created validator.
+fn vote_account() -> AccountSharedData {
+    let vote_state = VoteState::new(
+        &VoteInit {
+            node_pubkey: Pubkey::new_unique(),
+            authorized_voter: Pubkey::new_unique(),
+            authorized_withdrawer: Pubkey::new_unique(),
+            commission: 0,
+        },
+        &Clock::default(),
+    );
+    AccountSharedData::new_data_with_space(
+        LAMPORTS_PER_SOL,
+        &VoteStateVersions::new_current(vote_state),
+        VoteState::size_of(),
+        &solana_sdk::vote::program::id(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn stake_is_initialized_and_delegated_locally() {
+    let payer = Keypair::new();
+    let stake_address = Pubkey::new_unique();
+    let vote_address = Pubkey::new_unique();
+    // Rent exemption, plus more than the minimum delegation.
+    let stake_lamports =
+        Rent::default().minimum_balance(StakeStateV2::size_of()) + 2 * LAMPORTS_PER_SOL;
+    let accounts = HashMap::from([
+        (
+            payer.pubkey(),
+            AccountSharedData::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id()),
+        ),
+        (
+            stake_address,
+            AccountSharedData::new(
+                stake_lamports,
+                StakeStateV2::size_of(),
+                &stake::program::id(),
+            ),
+        ),
+        (vote_address, vote_account()),
+    ]);
+
+    let tx = Transaction::new(
+        &[&payer],
+        Message::new(
+            &[
+                stake::instruction::initialize(
+                    &stake_address,
+                    &Authorized::auto(&payer.pubkey()),
+                    &Lockup::default(),
+                ),
+                stake::instruction::delegate_stake(&stake_address, &payer.pubkey(), &vote_address),
+            ],
+            Some(&payer.pubkey()),
+        ),
+        Default::default(),
+    );
+
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(accounts))
+        .config(RollUpChannelConfig {
+            record_account_data: true,
+            ..RollUpChannelConfig::default()
+        })
+        .build();
+    let results = rollup_c.process_rollup_transfers(&[tx]);
+
+    assert!(results[0].is_success(), "{}", results[0].result);
+    assert!(results[0].cu > 0);
+    assert_eq!(results[0].programs_invoked, [stake::program::id()]);
+
+    let change = results[0]
+        .account_changes
+        .iter()
+        .find(|change| change.pubkey == stake_address)
+        .unwrap();
+    assert_eq!(change.kind, AccountChangeKind::Modified);
+    let mut stake_account = AccountSharedData::new(stake_lamports, 0, &stake::program::id());
+    stake_account.set_data_from_slice(change.new_data.as_deref().unwrap());
+    match stake_account.state().unwrap() {
+        StakeStateV2::Stake(meta, stake, _) => {
+            assert_eq!(meta.authorized.staker, payer.pubkey());
+            assert_eq!(stake.delegation.voter_pubkey, vote_address);
+            assert_eq!(
+                stake.delegation.stake,
+                stake_lamports - meta.rent_exempt_reserve
+            );
+        }
+        state => panic!("unexpected stake state: {:?}", state),
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use solana_client_ext::*;
+use solana_sdk::{
+    account::{create_account_shared_data_for_test, from_account, AccountSharedData},
+    clock::Clock,
+    instruction::Instruction,
+    message::Message,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_program, sysvar,
+    transaction::Transaction,
+};
+
+/// A minimal SBPFv0 program returning the `unix_timestamp` it reads with
+/// `sol_get_clock_sysvar` as its exit code, so any nonzero timestamp fails it with that
+/// custom error.
+const CLOCK_PROGRAM: &[u8] = include_bytes!("fixtures/clock.so");
+
+/// 0x6553f100, small enough to be returned as a custom error.
+const UNIX_TIMESTAMP: i64 = 1_700_000_000;
+
+#[test]
+fn clock_program_observes_the_configured_timestamp() {
+    let payer = Keypair::new();
+    let source_clock = Clock {
+        unix_timestamp: 5,
+        ..Clock::default()
+    };
+    let accounts = HashMap::from([
+        (
+            payer.pubkey(),
+            AccountSharedData::new(LAMPORTS_PER_SOL, 0, &system_program::id()),
+        ),
+        (
+            sysvar::clock::id(),
+            create_account_shared_data_for_test(&source_clock),
+        ),
+    ]);
+    let clock = Clock {
+        slot: 1,
+        epoch: 1,
+        unix_timestamp: UNIX_TIMESTAMP,
+        ..Clock::default()
+    };
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(accounts))
+        .config(RollUpChannelConfig {
+            sysvars: SysvarSource::Explicit(SysvarDefaults {
+                clock: clock.clone(),
+                ..SysvarDefaults::default()
+            }),
+            ..RollUpChannelConfig::default()
+        })
+        .build();
+    let program_id = Pubkey::new_unique();
+    rollup_c
+        .add_program(program_id, CLOCK_PROGRAM, LoaderKind::BpfLoader)
+        .unwrap();
+
+    let tx = Transaction::new(
+        &[&payer],
+        Message::new(
+            &[Instruction::new_with_bytes(program_id, &[], vec![])],
+            Some(&payer.pubkey()),
+        ),
+        Default::default(),
+    );
+    let results = rollup_c.process_rollup_transfers(&[tx]);
+    assert!(!results[0].is_success());
+    assert!(
+        results[0]
+            .result
+            .contains("custom program error: 0x6553f100"),
+        "{}",
+        results[0].result
+    );
+
+    // The Clock account agrees with the syscall, replacing the source's.
+    let account = rollup_c
+        .account_loader()
+        .load(&sysvar::clock::id())
+        .unwrap()
+        .unwrap();
+    assert_eq!(from_account::<Clock, _>(&account), Some(clock));
+}
+
+#[test]
+fn fetched_sysvars_are_requested_at_once() {
+    let accounts = HashMap::from([(
+        sysvar::clock::id(),
+        create_account_shared_data_for_test(&Clock {
+            unix_timestamp: UNIX_TIMESTAMP,
+            ..Clock::default()
+        }),
+    )]);
+    let rollup_c = RollUpChannel::new_offline(vec![], accounts);
+    rollup_c.process_rollup_transfers(&[]);
+
+    // The sysvars are fetched in a single request; the missing ones are synthesized.
+    assert_eq!(rollup_c.account_loader().fetch_stats().batch_requests, 1);
+    let clock = rollup_c
+        .account_loader()
+        .load(&sysvar::clock::id())
+        .unwrap()
+        .and_then(|account| from_account::<Clock, _>(&account))
+        .unwrap();
+    assert_eq!(clock.unix_timestamp, UNIX_TIMESTAMP);
+    assert!(rollup_c
+        .account_loader()
+        .load(&sysvar::rent::id())
+        .unwrap()
+        .is_some());
+}
@@ -0,0 +1,318 @@
+mod common;
+
+use std::collections::HashMap;
+
+use common::NOOP_PROGRAM;
+use solana_client_ext::*;
+
+use solana_sdk::{
+    account::{from_account, AccountSharedData, ReadableAccount, WritableAccount},
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    clock::{Clock, Slot},
+    instruction::Instruction,
+    message::Message,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_program, sysvar,
+    transaction::Transaction,
+};
+
+/// A minimal SBPFv0 program returning `sol_get_epoch_stake` of the vote account at
+/// [`STAKED_VOTE_ACCOUNT`] as its exit code, so any stake fails it with that custom error.
+const EPOCH_STAKE_PROGRAM: &[u8] = include_bytes!("fixtures/epoch_stake.so");
+
+/// The vote address hardcoded in [`EPOCH_STAKE_PROGRAM`].
+const STAKED_VOTE_ACCOUNT: Pubkey = Pubkey::new_from_array([7; 32]);
+
+/// Builds the program and ProgramData accounts of an upgradeable program deployed at slot 0.
+fn upgradeable_program(
+    program_id: &Pubkey,
+    elf: &[u8],
+    upgrade_authority_address: Option<Pubkey>,
+) -> [(Pubkey, AccountSharedData); 2] {
+    let (programdata_address, _) =
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+
+    let mut program = AccountSharedData::new_data(
+        LAMPORTS_PER_SOL,
+        &UpgradeableLoaderState::Program {
+            programdata_address,
+        },
+        &bpf_loader_upgradeable::id(),
+    )
+    .unwrap();
+    program.set_executable(true);
+
+    let metadata_size = UpgradeableLoaderState::size_of_programdata_metadata();
+    let mut programdata = AccountSharedData::new_data_with_space(
+        LAMPORTS_PER_SOL,
+        &UpgradeableLoaderState::ProgramData {
+            slot: 0,
+            upgrade_authority_address,
+        },
+        metadata_size + elf.len(),
+        &bpf_loader_upgradeable::id(),
+    )
+    .unwrap();
+    programdata.data_as_mut_slice()[metadata_size..].copy_from_slice(elf);
+
+    [(*program_id, program), (programdata_address, programdata)]
+}
+
+/// Accounts funding `payer` and holding the noop program under `program_id`, deployed
+/// at `slot`.
+fn noop_deployed_at(
+    payer: &Pubkey,
+    program_id: &Pubkey,
+    slot: Slot,
+) -> HashMap<Pubkey, AccountSharedData> {
+    let mut accounts: HashMap<_, _> = upgradeable_program(program_id, NOOP_PROGRAM, None).into();
+    // ProgramData starts with its variant index (u32) and deployment slot (u64).
+    let programdata = accounts
+        .iter_mut()
+        .find(|(key, _)| *key != program_id)
+        .unwrap()
+        .1;
+    programdata.data_as_mut_slice()[4..12].copy_from_slice(&slot.to_le_bytes());
+    accounts.insert(
+        *payer,
+        AccountSharedData::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id()),
+    );
+    accounts
+}
+
+#[test]
+fn upgradeable_program_executes() {
+    let payer = Keypair::new();
+    let program_id = Pubkey::new_unique();
+
+    let mut accounts: HashMap<_, _> = upgradeable_program(&program_id, NOOP_PROGRAM, None).into();
+    accounts.insert(
+        payer.pubkey(),
+        AccountSharedData::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id()),
+    );
+
+    let ix = Instruction::new_with_bytes(program_id, &[], vec![]);
+    let tx = Transaction::new(
+        &[&payer],
+        Message::new(&[ix], Some(&payer.pubkey())),
+        Default::default(),
+    );
+
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(accounts))
+        .config(RollUpChannelConfig {
+            record_return_data: true,
+            ..RollUpChannelConfig::default()
+        })
+        .build();
+    let results = rollup_c.process_rollup_transfers(&[tx]);
+
+    assert!(results[0].is_success(), "{}", results[0].result);
+    assert!(results[0].cu > 0);
+    // The program sets no return data, which isn't reported as an empty one.
+    assert_eq!(results[0].return_data, None);
+}
+
+#[test]
+fn upgrade_authority_is_set_locally() {
+    let authority = Keypair::new();
+    let new_authority = Pubkey::new_unique();
+    let program_id = Pubkey::new_unique();
+
+    let mut accounts: HashMap<_, _> =
+        upgradeable_program(&program_id, NOOP_PROGRAM, Some(authority.pubkey())).into();
+    let programdata_address = *accounts.keys().find(|key| **key != program_id).unwrap();
+    accounts.insert(
+        authority.pubkey(),
+        AccountSharedData::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id()),
+    );
+
+    let ix = bpf_loader_upgradeable::set_upgrade_authority(
+        &program_id,
+        &authority.pubkey(),
+        Some(&new_authority),
+    );
+    let tx = Transaction::new(
+        &[&authority],
+        Message::new(&[ix], Some(&authority.pubkey())),
+        Default::default(),
+    );
+
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(accounts))
+        .config(RollUpChannelConfig {
+            record_account_data: true,
+            ..RollUpChannelConfig::default()
+        })
+        .build();
+    let results = rollup_c.process_rollup_transfers(&[tx]);
+
+    assert!(results[0].is_success(), "{}", results[0].result);
+    assert!(results[0].cu > 0);
+    assert_eq!(results[0].programs_invoked, [bpf_loader_upgradeable::id()]);
+
+    let [_, (_, expected)] = upgradeable_program(&program_id, NOOP_PROGRAM, Some(new_authority));
+    let change = results[0]
+        .account_changes
+        .iter()
+        .find(|change| change.pubkey == programdata_address)
+        .unwrap();
+    assert_eq!(change.kind, AccountChangeKind::Modified);
+    assert_eq!(change.new_data.as_deref(), Some(expected.data()));
+}
+
+#[test]
+fn deployments_are_visible_from_the_next_slot() {
+    let payer = Keypair::new();
+    let program_id = Pubkey::new_unique();
+
+    let accounts = noop_deployed_at(&payer.pubkey(), &program_id, 10);
+    let tx = Transaction::new(
+        &[&payer],
+        Message::new(
+            &[Instruction::new_with_bytes(program_id, &[], vec![])],
+            Some(&payer.pubkey()),
+        ),
+        Default::default(),
+    );
+
+    let run_at = |slot| {
+        RollUpChannel::builder(AccountSource::Static(accounts.clone()))
+            .config(RollUpChannelConfig {
+                slot,
+                ..RollUpChannelConfig::default()
+            })
+            .build()
+            .process_rollup_transfers(&[tx.clone()])
+            .remove(0)
+    };
+    assert!(!run_at(10).is_success());
+    let result = run_at(11);
+    assert!(result.is_success(), "{}", result.result);
+
+    // Blocks advance from the configured slot.
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(accounts.clone()))
+        .config(RollUpChannelConfig {
+            slot: 10,
+            ..RollUpChannelConfig::default()
+        })
+        .build();
+    let blocks = rollup_c.process_blocks(&[vec![tx.clone()], vec![tx]]);
+    assert_eq!(blocks[0].slot, 10);
+    assert!(!blocks[0].results[0].is_success());
+    assert_eq!(blocks[1].slot, 11);
+    assert!(
+        blocks[1].results[0].is_success(),
+        "{}",
+        blocks[1].results[0].result
+    );
+}
+
+#[test]
+fn prefetch_fetches_programdata_with_its_program() {
+    let program_id = Pubkey::new_unique();
+    let accounts: HashMap<_, _> = upgradeable_program(&program_id, NOOP_PROGRAM, None).into();
+    let programdata_address = *accounts.keys().find(|key| **key != program_id).unwrap();
+
+    let loader = RollUpAccountLoader::with_source(AccountSource::Static(accounts));
+    let report = loader.prefetch(&[program_id]);
+    assert_eq!(report.fetched, vec![program_id, programdata_address]);
+    assert_eq!(loader.fetch_stats().batch_requests, 2);
+
+    // The pair is served from the cache, and invalidated together.
+    loader.load(&programdata_address).unwrap();
+    assert_eq!(loader.fetch_stats().single_requests, 0);
+    loader.invalidate(&program_id);
+    assert_eq!(loader.cache().len(), 0);
+}
+
+#[test]
+fn programs_read_configured_epoch_stakes() {
+    let payer = Keypair::new();
+    let program_id = Pubkey::new_unique();
+
+    let mut accounts: HashMap<_, _> =
+        upgradeable_program(&program_id, EPOCH_STAKE_PROGRAM, None).into();
+    accounts.insert(
+        payer.pubkey(),
+        AccountSharedData::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id()),
+    );
+    let ix = Instruction::new_with_bytes(program_id, &[], vec![]);
+    let tx = Transaction::new(
+        &[&payer],
+        Message::new(&[ix], Some(&payer.pubkey())),
+        Default::default(),
+    );
+
+    // Without stakes, the vote account has none and the program succeeds.
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(accounts.clone())).build();
+    let results = rollup_c.process_rollup_transfers(&[tx.clone()]);
+    assert!(results[0].is_success(), "{}", results[0].result);
+
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(accounts))
+        .epoch_stakes(HashMap::from([
+            (STAKED_VOTE_ACCOUNT, 42),
+            (Pubkey::new_unique(), 8),
+        ]))
+        .build();
+    let results = rollup_c.process_rollup_transfers(&[tx]);
+    assert!(!results[0].is_success());
+    assert!(
+        results[0].result.contains("custom program error: 0x2a"),
+        "{}",
+        results[0].result
+    );
+    assert_eq!(rollup_c.account_loader().epoch_total_stake(), 50);
+}
+
+#[test]
+fn advancing_the_slot_reveals_deployments() {
+    let payer = Keypair::new();
+    let program_id = Pubkey::new_unique();
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(noop_deployed_at(
+        &payer.pubkey(),
+        &program_id,
+        10,
+    )))
+    .config(RollUpChannelConfig {
+        slot: 10,
+        ..RollUpChannelConfig::default()
+    })
+    .build();
+    let invoke = |data: u8| {
+        let tx = Transaction::new(
+            &[&payer],
+            Message::new(
+                &[Instruction::new_with_bytes(program_id, &[data], vec![])],
+                Some(&payer.pubkey()),
+            ),
+            Default::default(),
+        );
+        rollup_c.process_rollup_transfers(&[tx]).remove(0)
+    };
+
+    // Deployed in the slot the channel is at: not invokable yet, on every call.
+    assert_eq!(rollup_c.slot(), 10);
+    assert!(!invoke(0).is_success());
+    assert!(!invoke(1).is_success());
+
+    assert_eq!(rollup_c.advance_slot(), 11);
+    assert_eq!(rollup_c.slot(), 11);
+    let result = invoke(2);
+    assert!(result.is_success(), "{}", result.result);
+
+    // The Clock follows the channel.
+    let clock = rollup_c
+        .account_loader()
+        .load(&sysvar::clock::id())
+        .unwrap()
+        .and_then(|account| from_account::<Clock, _>(&account))
+        .unwrap();
+    assert_eq!(clock.slot, 11);
+
+    // Blocks start from the advanced slot.
+    let blocks = rollup_c.process_blocks(&[vec![], vec![]]);
+    assert_eq!(blocks[0].slot, 11);
+    assert_eq!(blocks[1].slot, 12);
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use solana_client_ext::*;
+use solana_sdk::{
+    account::{AccountSharedData, WritableAccount},
+    account_utils::StateMut,
+    clock::Clock,
+    message::Message,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_program,
+    transaction::Transaction,
+    vote::{
+        self,
+        state::{VoteAuthorize, VoteInit, VoteState, VoteStateVersions},
+    },
+};
+
+#[test]
+fn vote_authorities_are_changed_locally() {
+    let withdrawer = Keypair::new();
+    let new_withdrawer = Pubkey::new_unique();
+    let vote_address = Pubkey::new_unique();
+
+    let vote_state = VoteState::new(
+        &VoteInit {
+            node_pubkey: Pubkey::new_unique(),
+            authorized_voter: Pubkey::new_unique(),
+            authorized_withdrawer: withdrawer.pubkey(),
+            commission: 0,
+        },
+        &Clock::default(),
+    );
+    let vote_account = AccountSharedData::new_data_with_space(
+        LAMPORTS_PER_SOL,
+        &VoteStateVersions::new_current(vote_state),
+        VoteState::size_of(),
+        &vote::program::id(),
+    )
+    .unwrap();
+    let accounts = HashMap::from([
+        (
+            withdrawer.pubkey(),
+            AccountSharedData::new(LAMPORTS_PER_SOL, 0, &system_program::id()),
+        ),
+        (vote_address, vote_account),
+    ]);
+
+    let tx = Transaction::new(
+        &[&withdrawer],
+        Message::new(
+            &[vote::instruction::authorize(
+                &vote_address,
+                &withdrawer.pubkey(),
+                &new_withdrawer,
+                VoteAuthorize::Withdrawer,
+            )],
+            Some(&withdrawer.pubkey()),
+        ),
+        Default::default(),
+    );
+
+    // The vote program isn't registered by default.
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(accounts.clone())).build();
+    let results = rollup_c.process_rollup_transfers(&[tx.clone()]);
+    assert!(!results[0].is_success());
+
+    let rollup_c = RollUpChannel::builder(AccountSource::Static(accounts))
+        .config(RollUpChannelConfig {
+            record_account_data: true,
+            builtins: vec![Builtin::vote(), Builtin::config()],
+            ..RollUpChannelConfig::default()
+        })
+        .build();
+    let results = rollup_c.process_rollup_transfers(&[tx]);
+    assert!(results[0].is_success(), "{}", results[0].result);
+    assert!(results[0].cu > 0);
+
+    let change = results[0]
+        .account_changes
+        .iter()
+        .find(|change| change.pubkey == vote_address)
+        .unwrap();
+    let mut vote_account = AccountSharedData::new(LAMPORTS_PER_SOL, 0, &vote::program::id());
+    vote_account.set_data_from_slice(change.new_data.as_deref().unwrap());
+    let vote_state = StateMut::<VoteStateVersions>::state(&vote_account)
+        .unwrap()
+        .convert_to_current();
+    assert_eq!(vote_state.authorized_withdrawer, new_withdrawer);
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use common::{funded_channel, single_ix_tx};
+use solana_client_ext::*;
+use solana_sdk::{
+    instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer,
+    transaction::TransactionError,
+};
+
+/// A channel paying with `payer`, registering `builtins`.
+fn channel(payer: &Pubkey, builtins: Vec<Builtin>) -> RollUpChannel<'static> {
+    funded_channel(
+        payer,
+        RollUpChannelConfig {
+            builtins,
+            ..RollUpChannelConfig::default()
+        },
+    )
+}
+
+#[test]
+fn unregistered_proof_program_is_reported() {
+    let payer = Keypair::new();
+    let rollup_c = channel(&payer.pubkey(), Vec::new());
+
+    let verify =
+        Instruction::new_with_bytes(solana_sdk_ids::zk_elgamal_proof_program::id(), &[4], vec![]);
+    let results = rollup_c.process_rollup_transfers(&[single_ix_tx(&payer, verify)]);
+    assert_eq!(results[0].status, TxStatus::NotProcessed);
+    assert_eq!(
+        results[0].error,
+        Some(TransactionError::InvalidProgramForExecution)
+    );
+    assert!(
+        results[0].result.contains("zk proof program not enabled"),
+        "{}",
+        results[0].result
+    );
+}
+
+#[cfg(feature = "zk-elgamal-proof")]
+#[test]
+fn proofs_are_verified_locally() {
+    use solana_zk_sdk::encryption::elgamal::ElGamalKeypair;
+    use solana_zk_sdk::zk_elgamal_proof_program::instruction::ProofInstruction;
+    use solana_zk_sdk::zk_elgamal_proof_program::proof_data::PubkeyValidityProofData;
+
+    let payer = Keypair::new();
+    let rollup_c = channel(&payer.pubkey(), vec![Builtin::zk_elgamal_proof()]);
+
+    let proof = PubkeyValidityProofData::new(&ElGamalKeypair::new_rand()).unwrap();
+    let verify = ProofInstruction::VerifyPubkeyValidity.encode_verify_proof(None, &proof);
+    let results = rollup_c.process_rollup_transfers(&[single_ix_tx(&payer, verify.clone())]);
+    assert!(results[0].is_success(), "{}", results[0].result);
+    // Verifying a public key validity proof costs a few thousand compute units.
+    assert!(
+        (1_000..20_000).contains(&results[0].cu),
+        "{}",
+        results[0].cu
+    );
+
+    // A tampered proof executes, and fails verification.
+    let mut tampered = verify;
+    *tampered.data.last_mut().unwrap() ^= 1;
+    let results = rollup_c.process_rollup_transfers(&[single_ix_tx(&payer, tampered)]);
+    assert_eq!(results[0].status, TxStatus::ExecutedErr);
+}